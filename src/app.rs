@@ -1,14 +1,23 @@
 //! Application state and main loop.
 
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use ratatui::crossterm::event::KeyEvent;
 use strum::AsRefStr;
 
-use crate::color::Theme;
-use crate::editor::History;
+use crate::color::{
+    Rgb, Theme, calculate_conservation, get_consensus_char, get_consensus_char_with_case,
+};
+use crate::editor::{History, UndoTreeRow};
+use crate::external::hmmer::{HmmSummary, build_hmm};
 use crate::history::InputHistory;
-use crate::stockholm::{Alignment, SequenceType};
-use crate::structure::StructureCache;
+use crate::stockholm::{Alignment, FileAnnotation, Profile, SequenceType, short_id, writer};
+use crate::structure::{
+    CompensatoryChange, StructDiffClass, StructureCache, analyze_compensatory, compare_structures,
+    is_valid_pair, ungap_structure,
+};
 
 /// Braille spinner frames used while a background clustering job runs.
 const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
@@ -22,6 +31,8 @@ pub struct SearchState {
     pub matches: Vec<(usize, usize, usize)>,
     /// Current match index in matches.
     pub match_index: Option<usize>,
+    /// Matches are SS_cons column spans from `:/s` (row is always 0).
+    pub in_structure: bool,
     /// Search history.
     pub history: InputHistory,
 }
@@ -37,6 +48,7 @@ impl SearchState {
         self.pattern.clear();
         self.matches.clear();
         self.match_index = None;
+        self.in_structure = false;
     }
 
     /// Check if there's an active search with results.
@@ -47,7 +59,7 @@ impl SearchState {
     /// Check if a position is part of a search match.
     /// Returns Some(true) if it's the current match, Some(false) if it's another match, None if not a match.
     pub fn is_match(&self, row: usize, col: usize) -> Option<bool> {
-        if !self.has_matches() {
+        if !self.has_matches() || self.in_structure {
             return None;
         }
 
@@ -59,6 +71,18 @@ impl SearchState {
 
         None
     }
+
+    /// Check if an SS_cons column is part of a structure match, like [`Self::is_match`].
+    pub fn is_structure_match(&self, col: usize) -> Option<bool> {
+        if !self.has_matches() || !self.in_structure {
+            return None;
+        }
+
+        self.matches
+            .iter()
+            .position(|&(_, start_col, end_col)| (start_col..end_col).contains(&col))
+            .map(|idx| self.match_index == Some(idx))
+    }
 }
 
 /// State for tab completion in command mode.
@@ -105,6 +129,12 @@ pub enum ColorScheme {
     Compensatory,
     /// Color by per-residue posterior probability (#=GR PP).
     PP,
+    /// Color by per-residue solvent accessibility (#=GR ACC).
+    Accessibility,
+    /// Color the ID column by `#=GS CLADE`.
+    Clade,
+    /// Color paired residues by the pair they form (AU, GC, GU, mismatch).
+    PairType,
 }
 
 impl ColorScheme {
@@ -116,6 +146,54 @@ impl ColorScheme {
             "conservation" | "cons" => Some(ColorScheme::Conservation),
             "compensatory" | "comp" => Some(ColorScheme::Compensatory),
             "pp" | "probability" => Some(ColorScheme::PP),
+            "accessibility" | "acc" => Some(ColorScheme::Accessibility),
+            "clade" => Some(ColorScheme::Clade),
+            "pairtype" | "pairs" => Some(ColorScheme::PairType),
+            _ => None,
+        }
+    }
+}
+
+/// An annotation bar drawn below the sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationKind {
+    /// `#=GC SS_cons` (shown whenever present).
+    SsCons,
+    /// `#=GC RF` (`:rf`).
+    Rf,
+    /// `#=GC PP_cons` (`:ppcons`).
+    PpCons,
+    /// Consensus sequence (`:consensus`).
+    Consensus,
+    /// Conservation histogram (`:conservation`).
+    Conservation,
+    /// `#=GC HELIX` helix numbers (`:helix-bar`).
+    Helix,
+    /// `#=GC ANCHORS` landmark labels (`:anchorbar`).
+    Anchors,
+}
+
+impl AnnotationKind {
+    /// Default top-to-bottom order of the annotation bars.
+    pub const DEFAULT_ORDER: [AnnotationKind; 7] = [
+        AnnotationKind::SsCons,
+        AnnotationKind::Rf,
+        AnnotationKind::PpCons,
+        AnnotationKind::Consensus,
+        AnnotationKind::Conservation,
+        AnnotationKind::Helix,
+        AnnotationKind::Anchors,
+    ];
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "ss" | "ss_cons" => Some(AnnotationKind::SsCons),
+            "rf" => Some(AnnotationKind::Rf),
+            "pp" | "pp_cons" => Some(AnnotationKind::PpCons),
+            "cons" | "consensus" => Some(AnnotationKind::Consensus),
+            "conservation" | "conserv" => Some(AnnotationKind::Conservation),
+            "helix" => Some(AnnotationKind::Helix),
+            "anchors" => Some(AnnotationKind::Anchors),
             _ => None,
         }
     }
@@ -138,6 +216,16 @@ pub enum ActivePane {
     Secondary,
 }
 
+/// Numbering shown on the ruler (`:ruler-mode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RulerMode {
+    /// Alignment column numbers.
+    #[default]
+    Alignment,
+    /// Reference positions, counting only `#=GC RF` match columns.
+    Reference,
+}
+
 /// Terminal color theme (detected at startup).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TerminalTheme {
@@ -148,6 +236,422 @@ pub enum TerminalTheme {
     Dark,
 }
 
+/// Read-only report popups opened by analysis commands.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Report {
+    /// Hairpin loops of SS_cons (`:show-loops`).
+    HairpinLoops,
+    /// Alignment summary statistics and the time taken to compute them (`:stats`).
+    Stats(AlignmentStats, std::time::Duration),
+    /// Pairwise identity distribution (`:pid-hist`).
+    PidHistogram(PidHistogram),
+    /// Gap pattern summary (`:gapstats`).
+    GapStats(GapStats),
+    /// Mutual information between column pairs (`:show-covariation`).
+    Covariation(CovariationReport),
+    /// `#=GS` annotations as a sequence-by-tag table (`:gsview`).
+    SequenceAnnotations(GsTable),
+    /// Per-sequence insertions relative to RF as `(RF position, length)`
+    /// lists, in sequence order (`:show-insertions`).
+    Insertions(Vec<(String, Vec<(usize, usize)>)>),
+    /// SS_cons base pairs with pairing statistics (`:show-pairs`).
+    ///
+    /// `report_scroll` is the selected row; Enter jumps to its left column.
+    BasePairs(Vec<BasePairStats>),
+    /// Per-sequence GC fraction, highest first (`:show-gc`).
+    ///
+    /// `report_scroll` is the selected row; Enter jumps to its sequence.
+    GcContent(Vec<(String, f64)>),
+    /// Per-sequence gap fraction, most gapped first (`:show-gap-seqs`).
+    ///
+    /// `report_scroll` is the selected row; Enter jumps to its sequence.
+    GapFractions(Vec<(String, f64)>),
+    /// Per-sequence ungapped length, longest first (`:show-lengths`).
+    ///
+    /// `report_scroll` is the selected row; Enter jumps to its sequence.
+    SequenceLengths(Vec<(String, usize)>),
+    /// Characters outside the sequence type's alphabet as `(sequence ID,
+    /// column, character)`, sorted by ID (`:check-alphabet`).
+    AlphabetIssues(Vec<(String, usize, char)>),
+    /// Estimated memory use (`:show-memory`).
+    Memory(MemoryStats),
+    /// Inconsistencies in the alignment data (`:diagnostic`).
+    ///
+    /// `report_scroll` is the selected row; Enter jumps to its sequence
+    /// and/or column.
+    Diagnostics(Vec<DiagnosticMessage>),
+    /// One `#=GC` annotation shown in full (`:show-gc <tag>`).
+    ///
+    /// `selected` is the highlighted column; Enter moves the cursor there.
+    ColumnAnnotation {
+        tag: String,
+        data: String,
+        selected: usize,
+    },
+    /// Changed SS_cons pairs relative to the reference sequence, sorted by
+    /// change type and then column (`:show-comp`).
+    ///
+    /// `report_scroll` is the selected row; Enter jumps to its sequence and
+    /// left column.
+    Compensatory(Vec<CompensatoryRecord>),
+    /// Pairwise identity heatmap in clustered order (`:show-pid`).
+    IdentityMatrix(IdentityMatrix),
+    /// Conservation at RF match-state columns as `(column, conservation)`,
+    /// with the mean over insertion columns if there are any (`:show-rf-cons`).
+    RfConservation(Vec<(usize, f64)>, Option<f64>),
+    /// The undo history as a depth-first listing of its states (`:undo-tree`).
+    UndoTree(Vec<UndoTreeRow>),
+}
+
+/// Screen layout of the primary pane's sequence rows, recorded while drawing
+/// so a mouse position can be mapped back to an alignment cell.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceGrid {
+    /// Screen column where the ID column starts.
+    pub id_x: u16,
+    /// Screen column of the first rendered alignment column.
+    pub x: u16,
+    /// Screen row of the first scrolling sequence row.
+    pub y: u16,
+    /// Display row shown at `y`.
+    pub first_row: usize,
+    /// Number of scrolling sequence rows shown.
+    pub rows: usize,
+    /// Screen rows per sequence (two with a `:show-gr` track).
+    pub rows_per_seq: usize,
+    /// Alignment column under each screen column.
+    pub columns: Vec<usize>,
+    /// Pinned header row, drawn two screen rows above `y`.
+    pub header_row: Option<usize>,
+}
+
+impl SequenceGrid {
+    /// Display row drawn at screen row `y`, if any.
+    fn row_at(&self, y: u16) -> Option<usize> {
+        if let Some(header) = self.header_row
+            && y + 2 == self.y
+        {
+            return Some(header);
+        }
+        let offset = y.checked_sub(self.y)? as usize / self.rows_per_seq.max(1);
+        (offset < self.rows).then_some(self.first_row + offset)
+    }
+}
+
+/// `#=GS` annotations laid out as a table (`:gsview`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GsTable {
+    /// Annotation tags, in order of first appearance.
+    pub tags: Vec<String>,
+    /// Sequence ID and one cell per tag; repeated tags are joined with `; `.
+    pub rows: Vec<(String, Vec<String>)>,
+}
+
+/// Pairing statistics for one SS_cons base pair (`:show-pairs`).
+///
+/// Frequencies are fractions of all sequences and sum to 1.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BasePairStats {
+    pub helix_id: usize,
+    /// 5' column (0-based).
+    pub left: usize,
+    /// 3' column (0-based).
+    pub right: usize,
+    /// Watson-Crick pairs (A-U, A-T, G-C).
+    pub watson_crick: f64,
+    /// G-U and G-T wobble pairs.
+    pub wobble: f64,
+    /// Two residues that cannot pair.
+    pub mismatch: f64,
+    /// A gap on either side.
+    pub gap: f64,
+}
+
+/// One SS_cons pair of one sequence that differs from the reference
+/// sequence (`:show-comp`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompensatoryRecord {
+    pub seq_id: String,
+    /// 5' column (0-based).
+    pub left: usize,
+    /// 3' column (0-based).
+    pub right: usize,
+    pub change: CompensatoryChange,
+}
+
+/// One inconsistency found by `:diagnostic`, with where to jump to fix it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticMessage {
+    pub message: String,
+    /// Sequence the issue belongs to, if any.
+    pub seq_id: Option<String>,
+    /// Column (0-based) the issue is at, if any.
+    pub col: Option<usize>,
+}
+
+/// Gap pattern summary for quality control (`:gapstats`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GapStats {
+    /// Per-sequence gap fraction: (min, max, mean, median).
+    pub seq_gap_fraction: (f64, f64, f64, f64),
+    /// Mean length of runs of consecutive gaps (over all sequences).
+    pub mean_run_length: f64,
+    /// Longest run of consecutive gaps.
+    pub max_run_length: usize,
+    /// Sequences without any gaps.
+    pub ungapped_seqs: usize,
+    /// Sequences whose gaps are all at the ends.
+    pub terminal_only_seqs: usize,
+    /// Sequences with at least one gap between residues.
+    pub internal_gap_seqs: usize,
+    /// Fraction of sequences gapped at each column.
+    pub column_gap_fraction: Vec<f64>,
+}
+
+/// Which cells a visual selection covers relative to its anchor/cursor rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionKind {
+    /// Cells inside the rectangle.
+    Block,
+    /// Cells outside the rectangle (toggled with `Ctrl+I`).
+    Inverted,
+}
+
+/// Covariation of one helix in every sequence (the `H` overlay).
+#[derive(Debug, Clone, Default)]
+pub struct HelixCovariation {
+    /// Helix the scores are for.
+    pub helix_id: usize,
+    /// Fraction of the helix's base pairs with a compatible (compensatory)
+    /// change in each sequence, by actual row; `None` for the reference.
+    pub scores: Vec<Option<f64>>,
+}
+
+/// Number of column pairs listed by `:show-covariation`.
+const COVARIATION_TOP_PAIRS: usize = 5;
+
+/// Mutual information between column pairs (`:show-covariation`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CovariationReport {
+    /// Bits for every column pair, upper triangle only.
+    pub matrix: Vec<Vec<f64>>,
+    /// The highest-scoring pairs as `(bits, left, right)`, best first.
+    pub top_pairs: Vec<(f64, usize, usize)>,
+}
+
+impl CovariationReport {
+    /// Rank the covarying pairs of `matrix` once, so redraws only read them.
+    pub fn new(matrix: Vec<Vec<f64>>) -> Self {
+        let mut pairs: Vec<(f64, usize, usize)> = matrix
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .skip(i + 1)
+                    .map(move |(j, &mi)| (mi, i, j))
+            })
+            .filter(|&(mi, _, _)| mi > 0.0)
+            .collect();
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+        pairs.truncate(COVARIATION_TOP_PAIRS);
+        Self {
+            matrix,
+            top_pairs: pairs,
+        }
+    }
+}
+
+/// Column filter on a `#=GC` annotation (`:col-filter <tag> <pattern>`).
+#[derive(Debug, Clone)]
+pub struct ColumnFilter {
+    /// Annotation tag, e.g. `RF`.
+    pub tag: String,
+    /// Regex matched against the annotation character of each column.
+    pub regex: regex::Regex,
+}
+
+/// Number of `:highlight` slots.
+pub const HIGHLIGHT_SLOTS: usize = 9;
+
+/// A motif highlighted in a fixed color (`:highlight <n> <pattern> <color>`).
+#[derive(Debug, Clone)]
+pub struct MotifHighlight {
+    /// Pattern, normalized like search patterns (uppercase, T→U).
+    pub pattern: String,
+    /// Background color for matching residues.
+    pub color: Rgb,
+}
+
+/// `:delete-seqs` asks for `!` when more sequences than this would be removed.
+const DELETE_CONFIRM_THRESHOLD: usize = 10;
+
+/// `:show-covariation` asks for `!` on alignments wider than this.
+const COVARIATION_CONFIRM_WIDTH: usize = 500;
+
+/// Least mutual information (bits) for `:infer-ss` to propose a pair.
+const INFER_SS_MIN_MI: f64 = 0.5;
+/// Shortest and longest column distance between pairs proposed by `:infer-ss`.
+const INFER_SS_SPAN: std::ops::RangeInclusive<usize> = 4..=100;
+
+/// `:write-per-col` asks for `!` on alignments wider than this.
+const PER_COLUMN_CONFIRM_WIDTH: usize = 1000;
+
+/// `#=GS` tag holding a sequence's strand, `+` or `-` (`:set-strand`).
+const STRAND_TAG: &str = "STRAND";
+
+/// Number of random pairs sampled by `:pid-hist` for large alignments.
+const PID_SAMPLE_PAIRS: usize = 200;
+/// Alignments with more sequences than this are sampled by `:pid-hist`.
+const PID_SAMPLE_THRESHOLD: usize = 200;
+
+/// Most sequences shown in the `:show-pid` matrix.
+pub const IDENTITY_MATRIX_MAX: usize = 50;
+
+/// Background tint used by `:shade` when no color is given.
+pub const DEFAULT_SHADE_COLOR: Rgb = Rgb::new(255, 236, 139);
+
+/// Default event loop poll interval in milliseconds.
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 100;
+/// Longest poll interval accepted by `--poll-interval` and `:set poll_interval=`.
+pub const MAX_POLL_INTERVAL_MS: u64 = 1000;
+/// How often `:watch` checks the file's modification time.
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Distribution of pairwise percent identities (`:pid-hist`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PidHistogram {
+    /// Pair counts in 10% bins (`bins[9]` is 90–100%).
+    pub bins: [usize; 10],
+    /// Number of pairs compared.
+    pub pairs: usize,
+    /// True if pairs were randomly sampled rather than exhaustive.
+    pub sampled: bool,
+    /// Mean identity (%).
+    pub mean: f64,
+    /// Median identity (%).
+    pub median: f64,
+    /// Lowest identity (%).
+    pub min: f64,
+    /// Highest identity (%).
+    pub max: f64,
+}
+
+/// Pairwise identities in clustered order (`:show-pid`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IdentityMatrix {
+    /// Sequence IDs, in `cluster_order`.
+    pub ids: Vec<String>,
+    /// Percent identity for each pair of `ids`; `None` if the pair shares
+    /// no residue columns.
+    pub pids: Vec<Vec<Option<f64>>>,
+    /// Number of sequences in the alignment, which may exceed `ids.len()`.
+    pub total: usize,
+}
+
+/// Summary statistics for the whole alignment (`:stats`).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AlignmentStats {
+    /// Number of sequences.
+    pub num_sequences: usize,
+    /// Alignment width in columns.
+    pub width: usize,
+    /// Mean most-common-residue frequency over columns with any residue.
+    pub mean_conservation: f64,
+    /// Mean Shannon entropy (bits) over columns with any residue.
+    pub mean_entropy: f64,
+    /// Columns containing only gaps.
+    pub gap_only_columns: usize,
+    /// Columns where every sequence has the same residue (no gaps).
+    pub conserved_columns: usize,
+    /// Base pairs in SS_cons.
+    pub base_pairs: usize,
+    /// Percentage of all positions that are gaps.
+    pub gap_percent: f64,
+    /// Percentage of residues that are G or C (`None` for protein).
+    pub gc_percent: Option<f64>,
+    /// Number of distinct sequences.
+    pub unique_sequences: usize,
+}
+
+/// Estimated memory use in bytes (`:show-memory`).
+///
+/// Sequence data shared between the alignment and undo snapshots is counted
+/// once, under `sequences`; `undo_history` holds only what the snapshots add.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MemoryStats {
+    /// Sequence residues and IDs.
+    pub sequences: usize,
+    /// `#=GC` annotations.
+    pub column_annotations: usize,
+    /// `#=GR` annotations.
+    pub residue_annotations: usize,
+    /// `#=GS` annotations.
+    pub sequence_annotations: usize,
+    /// Undo states not shared with the current alignment.
+    pub undo_history: usize,
+    /// Number of saved undo states.
+    pub undo_states: usize,
+    /// Cluster order and dendrogram rows. The distance matrix is built on
+    /// demand and freed after clustering, so it is not included.
+    pub clustering: usize,
+    /// Visible columns cache.
+    pub visible_columns: usize,
+}
+
+impl MemoryStats {
+    /// Sum of all categories.
+    pub fn total(&self) -> usize {
+        self.sequences
+            + self.column_annotations
+            + self.residue_annotations
+            + self.sequence_annotations
+            + self.undo_history
+            + self.clustering
+            + self.visible_columns
+    }
+}
+
+/// Estimated bytes of `alignment`'s sequences and annotations as
+/// `(sequences, #=GC, #=GR, #=GS)`, skipping sequences already in `seen`.
+fn alignment_memory(
+    alignment: &Alignment,
+    seen: &mut std::collections::HashSet<*const crate::stockholm::Sequence>,
+) -> (usize, usize, usize, usize) {
+    let sequences = alignment
+        .sequences
+        .iter()
+        .filter(|seq| seen.insert(std::rc::Rc::as_ptr(seq)))
+        .map(|seq| {
+            std::mem::size_of::<crate::stockholm::Sequence>()
+                + seq.len() * std::mem::size_of::<char>()
+                + seq.id.len()
+        })
+        .sum();
+    let column = alignment
+        .column_annotations
+        .iter()
+        .map(|ann| ann.tag.len() + ann.data.len())
+        .sum();
+    let residue = alignment
+        .residue_annotations
+        .iter()
+        .flat_map(|(id, anns)| {
+            anns.iter()
+                .map(move |ann| id.len() + ann.tag.len() + ann.data.len())
+        })
+        .sum();
+    let sequence = alignment
+        .sequence_annotations
+        .iter()
+        .flat_map(|(id, anns)| {
+            anns.iter()
+                .map(move |ann| id.len() + ann.tag.len() + ann.value.len())
+        })
+        .sum();
+    (sequences, column, residue, sequence)
+}
+
 /// A clustering computation running on a background thread.
 ///
 /// `:cluster` on large alignments can take seconds; running it off the UI thread
@@ -159,6 +663,23 @@ pub struct ClusteringJob {
     pub seq_count: usize,
     /// Current spinner frame index.
     pub spinner: usize,
+    /// Started by `auto_cluster` on load rather than by `:cluster`.
+    pub auto: bool,
+}
+
+impl ClusteringJob {
+    /// Status line while the job runs, with the current spinner frame.
+    fn progress_status(&self) -> String {
+        let verb = if self.auto {
+            "Auto-clustering"
+        } else {
+            "Clustering"
+        };
+        format!(
+            "{} {verb} {} sequences…",
+            SPINNER_FRAMES[self.spinner], self.seq_count
+        )
+    }
 }
 
 /// Application state.
@@ -170,6 +691,17 @@ pub struct App {
     pub file_path: Option<PathBuf>,
     /// Structure cache.
     pub structure_cache: StructureCache,
+    /// Consensus characters per column, filled lazily by the consensus bar.
+    pub(crate) consensus_cache: RefCell<Option<Vec<char>>>,
+    /// Content hash shown by `:info`, filled lazily and cleared by edits.
+    pub(crate) alignment_hash_cache: RefCell<Option<String>>,
+    /// Last `H` overlay scores with the reference row they were computed
+    /// against, filled lazily and cleared by edits.
+    pub(crate) helix_covariation_cache: RefCell<Option<(usize, HelixCovariation)>>,
+    /// Sequence area of the primary pane as last drawn.
+    pub(crate) sequence_grid: RefCell<Option<SequenceGrid>>,
+    /// Last mouse position reported by the terminal, as `(column, row)`.
+    pub(crate) mouse_pos: Option<(u16, u16)>,
 
     // === Public - User configuration ===
     /// Gap character.
@@ -178,18 +710,34 @@ pub struct App {
     pub gap_chars: Vec<char>,
     /// Color scheme.
     pub color_scheme: ColorScheme,
+    /// The scheme was chosen with `:color`/`--color`; loading a file keeps it.
+    pub color_scheme_explicit: bool,
     /// Show help overlay.
     pub show_help: bool,
     /// Scroll offset (in lines) for the help overlay.
     pub(crate) help_scroll: u16,
     /// Show position ruler at top.
     pub show_ruler: bool,
+    /// Add a ruler row with the reference sequence's ungapped coordinates
+    /// (`:ref-ruler`).
+    pub ref_ungapped_ruler: bool,
     /// Show row numbers.
     pub show_row_numbers: bool,
     /// Show short IDs (strip coordinate suffix like /10000-20000).
     pub show_short_ids: bool,
-    /// Reference sequence index for compensatory coloring.
-    pub reference_seq: usize,
+    /// Prefix IDs with their `#=GS STRAND` as `[+]`/`[-]` (`:strand-display`).
+    pub show_strand: bool,
+    /// Cap the ID column at `compact_id_width` characters (`:compact`).
+    pub compact_ids: bool,
+    /// ID column width in compact mode (`:set compact_id_width=`).
+    pub compact_id_width: usize,
+    /// Continue IDs longer than `id_wrap_width` on a second row (`:wrapids`).
+    pub wrap_ids: bool,
+    /// ID column width when wrapping IDs (`:set id_wrap_width=`).
+    pub id_wrap_width: usize,
+    /// ID of the reference sequence for compensatory coloring (`:color-ref`);
+    /// the first sequence when unset.
+    pub reference_id: Option<String>,
     /// Split screen mode (None = single pane).
     pub split_mode: Option<SplitMode>,
     /// Which pane is active in split mode.
@@ -213,6 +761,8 @@ pub struct App {
     pub(crate) viewport_row: usize,
     /// Viewport offset (column).
     pub(crate) viewport_col: usize,
+    /// Sequence columns shown at the last draw (0 before the first draw).
+    pub(crate) viewport_width: usize,
     /// Current editor mode.
     pub(crate) mode: Mode,
     /// Command history.
@@ -223,10 +773,22 @@ pub struct App {
     pub(crate) completion: Option<CompletionState>,
     /// Status message.
     pub(crate) status_message: Option<String>,
+    /// Whether `status_message` reports a failed command ([`App::set_error`]).
+    pub(crate) status_is_error: bool,
     /// Undo/redo history.
     pub(crate) history: History,
     /// Numeric count buffer for vim-style count prefixes (e.g., 50|).
     pub(crate) count_buffer: String,
+    /// Keys recorded so far while recording a macro (`q<reg>` ... `q`).
+    pub(crate) macro_recording: Option<Vec<KeyEvent>>,
+    /// Register the macro being recorded is stored in.
+    pub(crate) macro_register: char,
+    /// Recorded macros by register, played back with `@<reg>`.
+    pub(crate) macros: HashMap<char, Vec<KeyEvent>>,
+    /// Register played last, for `@@`.
+    pub(crate) last_macro: Option<char>,
+    /// Whether a macro is being played back; its keys are not recorded.
+    pub(crate) macro_playing: bool,
     /// Secondary pane viewport row.
     pub(crate) secondary_viewport_row: usize,
     /// Secondary pane viewport column.
@@ -243,10 +805,18 @@ pub struct App {
     pub(crate) secondary_cursor_row: usize,
     /// Secondary pane cursor column.
     pub(crate) secondary_cursor_col: usize,
+    /// Comparison view (`:compare`): the secondary pane shows a second file and
+    /// follows the active pane's rows by sequence ID.
+    pub(crate) compare_mode: bool,
+    /// Scroll the inactive split pane with the active one (`:link-panes`):
+    /// columns in a vertical split, rows in a horizontal split.
+    pub linked_panes: bool,
 
     // === Visual selection state ===
     /// Selection anchor point (row, col) - set when entering visual mode.
     pub(crate) selection_anchor: Option<(usize, usize)>,
+    /// Whether the selection covers the anchor/cursor rectangle or its complement.
+    pub(crate) selection_kind: SelectionKind,
     /// Clipboard for yanked content (can be block or complete sequences with annotations).
     pub(crate) clipboard: Option<Alignment>,
     /// Whether the clipboard contains line-wise yanked sequences (vs block).
@@ -258,16 +828,29 @@ pub struct App {
     pub(crate) cluster_order: Option<Vec<usize>>,
     /// Pre-computed ASCII tree characters for each display row.
     pub(crate) cluster_tree: Option<Vec<String>>,
+    /// Dendrogram behind `cluster_order`, for `:write-tree`.
+    pub(crate) cluster_dendrogram: Option<crate::clustering::ClusterTree>,
     /// Pre-computed ASCII tree characters for collapsed view (one per group).
     pub(crate) collapsed_tree: Option<Vec<String>>,
     /// Width of the tree column in characters.
     pub(crate) tree_width: usize,
+    /// Maximum dendrogram width (`:set tree_width=`); `usize::MAX` means uncapped.
+    pub tree_max_width: usize,
     /// Whether to show the dendrogram tree visualization.
     pub(crate) show_tree: bool,
+    /// Cluster sequences whenever a file is loaded (`--auto-cluster`, `:set auto_cluster=`).
+    pub auto_cluster: bool,
+    /// Fewest sequences for which `auto_cluster` kicks in (`:set auto_cluster_min=`).
+    pub auto_cluster_min: usize,
     /// Group order when clustering with collapse (maps display_row -> group_index).
     pub(crate) cluster_group_order: Option<Vec<usize>>,
     /// In-progress background clustering job, if any.
     pub(crate) clustering_job: Option<ClusteringJob>,
+    /// Run `auto_cluster` as a background job; set once the TUI is up, so the
+    /// CLI and tests still cluster synchronously.
+    pub(crate) background_clustering: bool,
+    /// Rfam accession queued by `:fetch`, downloaded after the next draw.
+    pub(crate) pending_fetch: Option<String>,
     /// Terminal color theme (detected at startup).
     pub terminal_theme: TerminalTheme,
     /// UI theme colors.
@@ -288,14 +871,58 @@ pub struct App {
     pub show_rf_bar: bool,
     /// Show PP_cons (posterior probability consensus) bar.
     pub show_pp_cons: bool,
+    /// Show the `#=GC HELIX` bar (`:helix-bar`).
+    pub show_helix_bar: bool,
+    /// Show the `#=GC ANCHORS` bar (`:anchorbar`).
+    pub show_anchor_bar: bool,
     /// Conservation threshold for uppercase in consensus (0.0-1.0).
     pub consensus_threshold: f64,
+    /// PP_cons values below this are drawn as gaps in the PP_cons bar (0.0-1.0).
+    pub pp_cons_threshold: f64,
+    /// Top-to-bottom order of the annotation bars (`:set annotation_order=`).
+    pub annotation_order: Vec<AnnotationKind>,
+    /// Pseudocount added to each residue count in exported profiles.
+    pub pwm_pseudocount: f64,
+    /// Columns per block for `:w-interleaved` (`:set block_width=`).
+    pub block_width: usize,
+    /// Align sequence and annotation data columns when saving with `:w`
+    /// (`:set write_pretty=`).
+    pub write_pretty: bool,
+    /// Character that marks leading and trailing gaps (`:trim-ends`, `:set trim_char=`).
+    pub trim_char: char,
+    /// How long the event loop waits for input before redrawing
+    /// (`--poll-interval`, `:set poll_interval=`).
+    pub poll_interval: std::time::Duration,
+    /// Reload the file when it changes on disk (`:watch`).
+    pub watch_mode: bool,
+    /// Modification time of `file_path` when it was last loaded or saved.
+    file_mtime: Option<std::time::SystemTime>,
+    /// When `:watch` last checked the file.
+    watch_checked_at: std::time::Instant,
 
     // === Info overlay ===
     /// Show file info overlay.
     pub show_info: bool,
     /// Scroll offset (in lines) for the info overlay.
     pub(crate) info_scroll: u16,
+    /// Show the residue distribution of the cursor column (`gP`).
+    pub show_column_profile: bool,
+    /// Report popup currently shown, if any.
+    pub report: Option<Report>,
+    /// Scroll offset (in lines) for the report popup.
+    pub(crate) report_scroll: u16,
+    /// Horizontal scroll offset of wide report tables (`:gsview`).
+    pub(crate) report_hscroll: u16,
+
+    // === Command history popup (`:history`) ===
+    /// Show the command history popup.
+    pub show_history_popup: bool,
+    /// Highlighted entry, counted in the filtered most-recent-first list.
+    pub history_selection: usize,
+    /// Substring that history entries must contain.
+    pub history_filter: String,
+    /// Keys edit `history_filter` (after `/`) instead of moving the selection.
+    pub history_filter_editing: bool,
 
     // === Multiple alignments ===
     /// All alignments loaded from the current file (a Stockholm file may hold
@@ -312,14 +939,61 @@ pub struct App {
     /// Detected sequence type (RNA, DNA, or Protein).
     pub sequence_type: SequenceType,
 
+    /// Underline the cursor column in annotation bars and tint it in the
+    /// sequence area (`:cursor-col-highlight`).
+    pub highlight_cursor_column: bool,
+
     // === Gap column state ===
     /// Highlight columns that contain only gaps.
     pub highlight_gap_columns: bool,
+    /// Tint columns where RF has a gap (insertions relative to RF, `:rf-gaps`).
+    pub highlight_rf_insertions: bool,
     /// Hide columns that contain only gaps from display.
     pub hide_gap_columns: bool,
+    /// Tint columns with more than one distinct residue.
+    pub highlight_variable_cols: bool,
+    /// Underline the cursor sequence's residues that differ from the column
+    /// consensus (`:diffcons`).
+    pub highlight_consensus_diff: bool,
+    /// Color the cursor's helix by per-sequence covariation (`H`).
+    pub show_helix_covariation: bool,
+    /// `#=GR` tag drawn as a track under every sequence (`:show-gr`).
+    pub show_gr_tag: Option<String>,
+    /// Motif highlights by slot; lower slots win where matches overlap.
+    pub motif_highlights: [Option<MotifHighlight>; HIGHLIGHT_SLOTS],
+    /// Custom colors for whole columns (`:set-color-at`), replacing the scheme color.
+    pub column_color_overrides: HashMap<usize, Rgb>,
+    /// Background shading of column ranges (`:shade`), as 0-based inclusive
+    /// `(start, end, color)`; later regions win where they overlap.
+    pub shaded_regions: Vec<(usize, usize, Rgb)>,
+    /// Show every Nth column (`:zoom N`); 1 shows every column.
+    pub column_stride: usize,
+    /// Hide columns whose `#=GC` annotation does not match (`:col-filter`).
+    pub column_filter: Option<ColumnFilter>,
+    /// Only show columns in `[start, end)` (`:slice`).
+    pub column_slice: Option<(usize, usize)>,
+    /// Number the ruler from the start of the slice (`:set slice_relative_ruler=`).
+    pub slice_relative_ruler: bool,
+    /// Number the ruler by alignment column or RF reference position.
+    pub ruler_mode: RulerMode,
     /// Precomputed list of visible (non-empty) column indices.
-    /// Only populated when hide_gap_columns is true.
+    /// Only populated when hide_gap_columns, column_filter or column_slice is set.
     pub(crate) visible_columns: Vec<usize>,
+
+    // === Structure comparison ===
+    /// Per-column comparison of a sequence's `#=GR SS` against SS_cons (`:compare-ss`).
+    pub(crate) structure_diff: Option<Vec<StructDiffClass>>,
+    /// ID of the sequence that `structure_diff` was computed for.
+    pub(crate) structure_diff_id: String,
+
+    // === Sticky header ===
+    /// ID of the sequence kept above the scrolling rows (`:pin-header`).
+    pub pinned_header_id: Option<String>,
+
+    // === Pairwise comparison ===
+    /// ID of the sequence pinned with `C`; other rows are colored by their
+    /// similarity to it.
+    pub pinned_comparison_id: Option<String>,
 }
 
 impl Default for App {
@@ -332,25 +1006,44 @@ impl Default for App {
             cursor_col: 0,
             viewport_row: 0,
             viewport_col: 0,
+            viewport_width: 0,
             mode: Mode::Normal,
             line_input: tui_input::Input::default(),
             command_history: InputHistory::new(),
             search: SearchState::new(),
             completion: None,
             status_message: None,
+            status_is_error: false,
             gap_char: '.',
             gap_chars: vec!['.', '-', '_', '~', ':'],
             color_scheme: ColorScheme::None,
+            color_scheme_explicit: false,
             structure_cache: StructureCache::new(),
+            consensus_cache: RefCell::new(None),
+            alignment_hash_cache: RefCell::new(None),
+            helix_covariation_cache: RefCell::new(None),
+            sequence_grid: RefCell::new(None),
+            mouse_pos: None,
             history: History::new(),
             should_quit: false,
             show_help: false,
             help_scroll: 0,
             show_ruler: true,
+            ref_ungapped_ruler: false,
             show_row_numbers: true,
             show_short_ids: false,
-            reference_seq: 0,
+            show_strand: false,
+            compact_ids: false,
+            compact_id_width: 8,
+            wrap_ids: false,
+            id_wrap_width: 30,
+            reference_id: None,
             count_buffer: String::new(),
+            macro_recording: None,
+            macro_register: 'q',
+            macros: HashMap::new(),
+            last_macro: None,
+            macro_playing: false,
             split_mode: None,
             active_pane: ActivePane::Primary,
             secondary_viewport_row: 0,
@@ -360,16 +1053,25 @@ impl Default for App {
             secondary_modified: false,
             secondary_cursor_row: 0,
             secondary_cursor_col: 0,
+            compare_mode: false,
+            linked_panes: false,
             selection_anchor: None,
+            selection_kind: SelectionKind::Block,
             clipboard: None,
             clipboard_is_linewise: false,
             cluster_order: None,
             cluster_tree: None,
+            cluster_dendrogram: None,
             collapsed_tree: None,
             tree_width: 0,
+            tree_max_width: crate::clustering::DEFAULT_TREE_MAX_WIDTH,
             show_tree: false,
+            auto_cluster: false,
+            auto_cluster_min: 0,
             cluster_group_order: None,
             clustering_job: None,
+            background_clustering: false,
+            pending_fetch: None,
             terminal_theme: TerminalTheme::Dark,
             theme: Theme::default(),
             collapse_identical: false,
@@ -378,17 +1080,55 @@ impl Default for App {
             show_conservation_bar: false,
             show_rf_bar: false,
             show_pp_cons: false,
+            show_helix_bar: false,
+            show_anchor_bar: false,
+            annotation_order: AnnotationKind::DEFAULT_ORDER.to_vec(),
             consensus_threshold: 0.7,
+            pp_cons_threshold: 0.0,
+            pwm_pseudocount: 0.0,
+            block_width: 60,
+            write_pretty: false,
+            trim_char: '~',
+            poll_interval: std::time::Duration::from_millis(DEFAULT_POLL_INTERVAL_MS),
+            watch_mode: false,
+            file_mtime: None,
+            watch_checked_at: std::time::Instant::now(),
             show_info: false,
+            show_column_profile: false,
             info_scroll: 0,
+            report: None,
+            report_scroll: 0,
+            report_hscroll: 0,
             alignments: Vec::new(),
             current_alignment: 0,
+            show_history_popup: false,
+            history_selection: 0,
+            history_filter: String::new(),
+            history_filter_editing: false,
             show_msa_picker: false,
             msa_picker_selection: 0,
             sequence_type: SequenceType::RNA,
+            highlight_cursor_column: true,
             highlight_gap_columns: false,
+            highlight_rf_insertions: false,
+            highlight_variable_cols: false,
+            highlight_consensus_diff: false,
+            show_helix_covariation: false,
+            show_gr_tag: None,
+            motif_highlights: Default::default(),
+            column_color_overrides: HashMap::new(),
+            shaded_regions: Vec::new(),
             hide_gap_columns: false,
+            column_stride: 1,
+            column_filter: None,
+            column_slice: None,
+            slice_relative_ruler: false,
+            ruler_mode: RulerMode::default(),
             visible_columns: Vec::new(),
+            structure_diff: None,
+            structure_diff_id: String::new(),
+            pinned_header_id: None,
+            pinned_comparison_id: None,
         }
     }
 }
@@ -400,16 +1140,15 @@ impl App {
     }
 
     /// Load an alignment from a file.
+    ///
+    /// Vienna files (`.vienna`, `.db`) are detected by extension; everything
+    /// else is parsed as Stockholm.
     pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
-        let alignments = crate::stockholm::parser::parse_all_file(path)
-            .map_err(|e| format!("Failed to parse file: {e}"))?;
-
-        if alignments.is_empty() {
-            return Err("No alignments found in file".to_string());
-        }
+        let alignments = read_alignments(path)?;
 
         self.alignments = alignments;
         self.file_path = Some(path.to_path_buf());
+        self.file_mtime = modified_time(path);
         self.activate_alignment(0);
 
         let count = self.alignments.len();
@@ -429,9 +1168,219 @@ impl App {
                 self.alignment.ss_cons().is_some()
             ));
         }
+
+        let mut warnings = self.alignment.parse_warnings.join("; ");
+        if !self.alignment.duplicate_ids().is_empty() {
+            warnings.push_str("; :dedup-ids removes them");
+        }
+        if !self.alignment.is_rectangular() {
+            warnings.push_str("; :diagnostic lists them");
+        }
+        if !warnings.is_empty()
+            && let Some(status) = self.status_message.as_mut()
+        {
+            status.push_str(&format!(" — warning: {warnings}"));
+        }
+
+        let num_seqs = self.alignment.num_sequences();
+        if self.auto_cluster && num_seqs > 1 && num_seqs >= self.auto_cluster_min {
+            if self.background_clustering {
+                self.spawn_clustering(true);
+            } else {
+                self.cluster_sequences();
+                if let Some(status) = self.status_message.as_mut() {
+                    status.push_str(&format!(" — auto-clustered {num_seqs} sequences"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// With `:watch` on, reload the file if its modification time changed.
+    ///
+    /// Checks at most once per [`WATCH_INTERVAL`]. The cursor, viewport,
+    /// collapsing and clustering survive the reload; unsaved edits are never
+    /// discarded, so a modified buffer only gets a warning.
+    pub fn check_watched_file(&mut self) {
+        if !self.watch_mode || self.watch_checked_at.elapsed() < WATCH_INTERVAL {
+            return;
+        }
+        self.watch_checked_at = std::time::Instant::now();
+        let Some(path) = self.file_path.clone() else {
+            return;
+        };
+        let mtime = modified_time(&path);
+        if mtime.is_none() || mtime == self.file_mtime {
+            return;
+        }
+        if self.modified {
+            self.file_mtime = mtime;
+            self.set_status("File changed on disk; keeping unsaved changes (:w overwrites it)");
+            return;
+        }
+        self.reload_file(&path);
+    }
+
+    /// Load `path` again, keeping the view where it was.
+    fn reload_file(&mut self, path: &Path) {
+        let (cursor, viewport) = (
+            (self.cursor_row, self.cursor_col),
+            (self.viewport_row, self.viewport_col),
+        );
+        let index = self.current_alignment;
+        let (collapsed, clustered, show_tree) = (
+            self.collapse_identical,
+            self.cluster_order.is_some(),
+            self.show_tree,
+        );
+
+        if let Err(e) = self.load_file(path) {
+            self.set_status(format!("Reload failed: {e}"));
+            return;
+        }
+        if index < self.alignments.len() && index != 0 {
+            self.activate_alignment(index);
+        }
+        self.collapse_identical = collapsed;
+        if clustered {
+            // `auto_cluster` may already have clustered the new contents.
+            if self.cluster_order.is_none() && !self.is_clustering() {
+                self.cluster_sequences();
+            }
+            self.show_tree = show_tree;
+        }
+        (self.cursor_row, self.cursor_col) = cursor;
+        (self.viewport_row, self.viewport_col) = viewport;
+        self.clamp_cursor();
+        self.set_status(format!("File changed, reloaded {}", path.display()));
+    }
+
+    /// Open a second file side by side with the current one (`:compare`).
+    ///
+    /// The file is shown in a vertical split; the first alignment in it is used.
+    pub fn open_compare(&mut self, path: &Path) -> Result<(), String> {
+        if self.secondary_alignment.is_some() && self.secondary_modified {
+            return Err("Secondary pane has unsaved changes".to_string());
+        }
+        let alignment = read_alignments(path)?.swap_remove(0);
+        let num_seqs = alignment.num_sequences();
+
+        if self.active_pane == ActivePane::Secondary {
+            self.switch_pane();
+        }
+        self.secondary_alignment = Some(alignment);
+        self.secondary_file_path = Some(path.to_path_buf());
+        self.secondary_modified = false;
+        self.secondary_viewport_row = 0;
+        self.secondary_viewport_col = 0;
+        self.secondary_cursor_row = 0;
+        self.secondary_cursor_col = 0;
+        self.split_mode = Some(SplitMode::Vertical);
+        self.compare_mode = true;
+        self.sync_compare_viewport();
+
+        self.set_status(format!(
+            "Comparing with {} ({num_seqs} seqs)",
+            path.display()
+        ));
         Ok(())
     }
 
+    /// Insert the columns of the alignment in `path` before `col`.
+    fn insert_aligned_command(&mut self, path: &str, col: usize) {
+        let inserted = read_alignments(Path::new(path)).and_then(|mut alignments| {
+            let other = alignments.swap_remove(0);
+            let columns = other.width();
+            self.insert_aligned_columns(other, col).map(|()| columns)
+        });
+        match inserted {
+            Ok(columns) => self.set_status(format!(
+                "Inserted {columns} column(s) from {path} at column {}",
+                col + 1
+            )),
+            Err(e) => self.set_status(e),
+        }
+    }
+
+    /// Split the alignment into two files with the right one starting at `col`.
+    fn split_at_command(&mut self, col: usize, left: &str, right: &str) {
+        match self.split_at_column(col, Path::new(left), Path::new(right)) {
+            Ok(()) => self.set_status(format!(
+                "Wrote columns 1-{col} to {left} and {}-{} to {right}",
+                col + 1,
+                self.alignment.width()
+            )),
+            Err(e) => self.set_status(e),
+        }
+    }
+
+    /// Leave the comparison view, closing the second file (`:compare-off`).
+    pub fn close_compare(&mut self) {
+        if !self.compare_mode {
+            self.set_status("Not in compare mode");
+            return;
+        }
+        if self.active_pane == ActivePane::Secondary {
+            self.switch_pane();
+        }
+        self.secondary_alignment = None;
+        self.secondary_file_path = None;
+        self.secondary_modified = false;
+        self.split_mode = None;
+        self.compare_mode = false;
+        self.set_status("Compare mode off");
+    }
+
+    /// In compare mode, scroll the inactive pane so its top row shows the same
+    /// sequence ID as the active pane (or the same row index if the ID is absent).
+    pub fn sync_compare_viewport(&mut self) {
+        if !self.compare_mode {
+            return;
+        }
+        let Some(secondary) = self.secondary_alignment.as_ref() else {
+            return;
+        };
+
+        match self.active_pane {
+            ActivePane::Primary => {
+                let top = self.display_to_actual_row(self.viewport_row);
+                let row = self
+                    .alignment
+                    .sequences
+                    .get(top)
+                    .and_then(|seq| secondary.sequences.iter().position(|s| s.id == seq.id))
+                    .unwrap_or(self.viewport_row);
+                self.secondary_viewport_row = row.min(secondary.num_sequences().saturating_sub(1));
+            }
+            ActivePane::Secondary => {
+                let row = secondary
+                    .sequences
+                    .get(self.viewport_row)
+                    .and_then(|seq| {
+                        (0..self.visible_sequence_count()).find(|&d| {
+                            self.alignment.sequences[self.display_to_actual_row(d)].id == seq.id
+                        })
+                    })
+                    .unwrap_or(self.viewport_row);
+                self.secondary_viewport_row =
+                    row.min(self.visible_sequence_count().saturating_sub(1));
+            }
+        }
+    }
+
+    /// With `:link-panes`, copy the active pane's scroll position to the
+    /// inactive pane: the column in a vertical split, the row in a horizontal one.
+    pub fn sync_linked_viewport(&mut self) {
+        if !self.linked_panes {
+            return;
+        }
+        match self.split_mode {
+            Some(SplitMode::Vertical) => self.secondary_viewport_col = self.viewport_col,
+            Some(SplitMode::Horizontal) => self.secondary_viewport_row = self.viewport_row,
+            None => {}
+        }
+    }
+
     /// Make `alignments[index]` the active alignment, resetting view/edit state.
     ///
     /// Does not preserve edits to the previously active alignment; callers that
@@ -446,15 +1395,27 @@ impl App {
         self.viewport_row = 0;
         self.viewport_col = 0;
         self.history.clear();
+        self.invalidate_consensus();
+        *self.alignment_hash_cache.get_mut() = None;
+        *self.helix_covariation_cache.get_mut() = None;
 
         // Reset collapse and clustering state for the new alignment.
         self.collapse_identical = false;
         self.collapse_groups.clear();
         self.cluster_order = None;
         self.cluster_tree = None;
+        self.cluster_dendrogram = None;
         self.collapsed_tree = None;
         self.cluster_group_order = None;
+        // A job still running was for the previous contents
+        self.clustering_job = None;
         self.show_tree = false;
+        self.reset_column_state();
+
+        // Pins name sequences of the previous alignment
+        self.pinned_header_id = None;
+        self.pinned_comparison_id = None;
+        self.reference_id = None;
 
         // Update structure cache (warn on parse errors)
         if let Some(ss) = self.alignment.ss_cons()
@@ -549,6 +1510,57 @@ impl App {
         self.select_alignment(self.msa_picker_selection);
     }
 
+    /// Open the command history popup with no filter.
+    pub fn open_command_history(&mut self) {
+        if self.command_history.entries().is_empty() {
+            self.set_status("No command history");
+            return;
+        }
+        self.history_selection = 0;
+        self.history_filter.clear();
+        self.history_filter_editing = false;
+        self.show_history_popup = true;
+    }
+
+    /// Command history entries containing `history_filter`, most recent first,
+    /// with their 1-based history numbers.
+    pub fn history_matches(&self) -> Vec<(usize, &str)> {
+        self.command_history
+            .entries()
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, entry)| entry.contains(self.history_filter.as_str()))
+            .map(|(i, entry)| (i + 1, entry.as_str()))
+            .collect()
+    }
+
+    /// Move the history popup highlight by `delta`, clamping to range.
+    pub fn history_move(&mut self, delta: isize) {
+        let len = self.history_matches().len();
+        if len == 0 {
+            return;
+        }
+        let new = (self.history_selection as isize)
+            .saturating_add(delta)
+            .clamp(0, len as isize - 1);
+        self.history_selection = new as usize;
+    }
+
+    /// Close the history popup and run the highlighted entry.
+    pub fn history_confirm(&mut self) {
+        self.show_history_popup = false;
+        let Some(command) = self
+            .history_matches()
+            .get(self.history_selection)
+            .map(|(_, entry)| entry.to_string())
+        else {
+            return;
+        };
+        self.line_input = tui_input::Input::new(command);
+        self.execute_command();
+    }
+
     /// Commit the active working copy back into the in-memory alignment list so
     /// that a save reflects edits to the current alignment.
     fn commit_active_alignment(&mut self) {
@@ -564,6 +1576,7 @@ impl App {
     pub fn save_file(&mut self) -> Result<(), String> {
         let path = self.file_path.as_ref().ok_or("No file path set")?.clone();
         self.write_all_to(&path)?;
+        self.file_mtime = modified_time(&path);
         self.modified = false;
         self.set_status(format!("Saved {}", path.display()));
         Ok(())
@@ -572,20 +1585,80 @@ impl App {
     /// Save the alignment(s) to a new file.
     pub fn save_file_as(&mut self, path: PathBuf) -> Result<(), String> {
         self.write_all_to(&path)?;
+        self.file_mtime = modified_time(&path);
         self.file_path = Some(path.clone());
         self.modified = false;
         self.set_status(format!("Saved {}", path.display()));
         Ok(())
     }
 
+    /// Write the active alignment to `path` in the format its extension names:
+    /// FASTA (`.fa`, `.fasta`, `.afa`), PHYLIP (`.phy`, `.phylip`), Vienna
+    /// (`.vienna`, `.db`) or Stockholm otherwise. A trailing `.gz` is ignored
+    /// when detecting it.
+    pub fn export_alignment(&self, path: &Path) -> Result<(), String> {
+        let stem = match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("gz") => {
+                Path::new(path.file_stem().unwrap_or_default())
+            }
+            _ => path,
+        };
+        let ext = stem
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let result = match ext.as_str() {
+            "fa" | "fasta" | "afa" => {
+                let records: Vec<(String, String)> = self
+                    .alignment
+                    .sequences
+                    .iter()
+                    .map(|seq| (seq.id.clone(), seq.data()))
+                    .collect();
+                writer::write_fasta(&records, path)
+            }
+            "phy" | "phylip" => writer::write_phylip(&self.alignment, path),
+            "vienna" | "db" => writer::write_vienna(&self.alignment, path),
+            _ => writer::write_file(&self.alignment, path),
+        };
+        result.map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Run one transform command (as typed after `:`, e.g. `t2u`) outside the
+    /// TUI, for `--transform`. Errors if the command is not a transform or
+    /// reports a failure.
+    pub fn apply_transform(&mut self, command: &str) -> Result<(), String> {
+        let command = command.trim().trim_start_matches(':');
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        self.status_is_error = false;
+        if !self.execute_transform_command(&parts) {
+            return Err(format!("Not a transform command: {command}"));
+        }
+        if self.status_is_error {
+            return Err(self.status_message.take().unwrap_or_default());
+        }
+        Ok(())
+    }
+
     /// Write the full set of loaded alignments (with current edits committed) to
     /// `path`, falling back to just the active alignment if the list is empty.
+    /// Vienna paths (`.vienna`, `.db`) get the active alignment in Vienna format.
     fn write_all_to(&mut self, path: &Path) -> Result<(), String> {
         self.commit_active_alignment();
-        let result = if self.alignments.is_empty() {
-            crate::stockholm::writer::write_file(&self.alignment, path)
+        let result = if crate::stockholm::vienna::is_vienna_path(path) {
+            if self.alignments.len() > 1 {
+                return Err(format!(
+                    "Vienna files hold one alignment; use :w {}.sto to keep all {}",
+                    path.with_extension("").display(),
+                    self.alignments.len()
+                ));
+            }
+            writer::write_vienna(&self.alignment, path)
+        } else if self.alignments.is_empty() {
+            crate::stockholm::writer::write_file_with(&self.alignment, path, self.write_pretty)
         } else {
-            crate::stockholm::writer::write_all_file(&self.alignments, path)
+            crate::stockholm::writer::write_all_file(&self.alignments, path, self.write_pretty)
         };
         result.map_err(|e| format!("Failed to save file: {e}"))
     }
@@ -599,8 +1672,12 @@ impl App {
                 .secondary_file_path
                 .as_ref()
                 .ok_or("No file path set for secondary pane")?;
-            crate::stockholm::writer::write_file(secondary, path)
-                .map_err(|e| format!("Failed to save file: {e}"))?;
+            if crate::stockholm::vienna::is_vienna_path(path) {
+                writer::write_vienna(secondary, path)
+            } else {
+                writer::write_file_with(secondary, path, self.write_pretty)
+            }
+            .map_err(|e| format!("Failed to save file: {e}"))?;
             self.secondary_modified = false;
             self.set_status(format!("Saved {}", path.display()));
             Ok(())
@@ -614,7 +1691,7 @@ impl App {
         if self.active_pane == ActivePane::Secondary
             && let Some(ref secondary) = self.secondary_alignment
         {
-            crate::stockholm::writer::write_file(secondary, &path)
+            crate::stockholm::writer::write_file_with(secondary, &path, self.write_pretty)
                 .map_err(|e| format!("Failed to save file: {e}"))?;
             self.secondary_file_path = Some(path.clone());
             self.secondary_modified = false;
@@ -628,6 +1705,14 @@ impl App {
     /// Set a status message.
     pub fn set_status(&mut self, message: impl Into<String>) {
         self.status_message = Some(message.into());
+        self.status_is_error = false;
+    }
+
+    /// Set a status message reporting that a command failed, which makes
+    /// [`App::apply_transform`] return it as an error.
+    pub fn set_error(&mut self, message: impl Into<String>) {
+        self.set_status(message);
+        self.status_is_error = true;
     }
 
     /// Clear the status message.
@@ -662,37 +1747,39 @@ impl App {
         }
     }
 
-    /// Move cursor left.
+    /// Move cursor left (by `column_stride` columns when zoomed out).
     pub fn cursor_left(&mut self) {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
+        let step = self.column_stride;
+        if self.columns_hidden() {
             // Find previous visible column
             if let Some(display_col) = self.actual_to_display_col(self.cursor_col)
-                && display_col > 0
+                && display_col >= step
             {
-                self.cursor_col = self.display_to_actual_col(display_col - 1);
+                self.cursor_col = self.display_to_actual_col(display_col - step);
             }
-        } else if self.cursor_col > 0 {
-            self.cursor_col -= 1;
+        } else if self.cursor_col >= step {
+            self.cursor_col -= step;
         }
     }
 
-    /// Move cursor right.
+    /// Move cursor right (by `column_stride` columns when zoomed out).
     pub fn cursor_right(&mut self) {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
+        let step = self.column_stride;
+        if self.columns_hidden() {
             // Find next visible column
             if let Some(display_col) = self.actual_to_display_col(self.cursor_col)
-                && display_col < self.visible_columns.len().saturating_sub(1)
+                && display_col + step < self.visible_columns.len()
             {
-                self.cursor_col = self.display_to_actual_col(display_col + 1);
+                self.cursor_col = self.display_to_actual_col(display_col + step);
             }
-        } else if self.cursor_col < self.alignment.width().saturating_sub(1) {
-            self.cursor_col += 1;
+        } else if self.cursor_col + step < self.alignment.width() {
+            self.cursor_col += step;
         }
     }
 
     /// Move cursor to start of line.
     pub fn cursor_line_start(&mut self) {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
+        if self.columns_hidden() {
             // Go to first visible column
             self.cursor_col = self.display_to_actual_col(0);
         } else {
@@ -702,7 +1789,7 @@ impl App {
 
     /// Move cursor to end of line.
     pub fn cursor_line_end(&mut self) {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
+        if self.columns_hidden() {
             // Go to last visible column
             self.cursor_col =
                 self.display_to_actual_col(self.visible_columns.len().saturating_sub(1));
@@ -730,7 +1817,7 @@ impl App {
 
     /// Jump to a specific column (1-indexed, like vim).
     pub fn goto_column(&mut self, col: usize) {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
+        if self.columns_hidden() {
             // When hiding, col refers to visible column index
             let max_display_col = self.visible_columns.len().saturating_sub(1);
             let target_display = col.saturating_sub(1).min(max_display_col);
@@ -772,6 +1859,48 @@ impl App {
         self.count_buffer.clear();
     }
 
+    /// Start recording keys into macro `register` (`q<reg>`).
+    pub fn start_macro_recording(&mut self, register: char) {
+        self.macro_recording = Some(Vec::new());
+        self.macro_register = register;
+        self.set_status(format!("Recording @{register}"));
+    }
+
+    /// Stop recording and store the macro, replacing the register's old one.
+    pub fn stop_macro_recording(&mut self) {
+        if let Some(keys) = self.macro_recording.take() {
+            let register = self.macro_register;
+            self.set_status(format!("Recorded @{register} ({} keys)", keys.len()));
+            self.macros.insert(register, keys);
+        }
+    }
+
+    /// Append `key` to the macro being recorded, unless it comes from a
+    /// macro being played back.
+    pub fn record_key(&mut self, key: KeyEvent) {
+        if !self.macro_playing
+            && let Some(keys) = self.macro_recording.as_mut()
+        {
+            keys.push(key);
+        }
+    }
+
+    /// Keys of macro `register` (`@` for the last one played), remembering it
+    /// for `@@`.
+    pub fn macro_keys(&mut self, register: char) -> Result<Vec<KeyEvent>, String> {
+        let register = match register {
+            '@' => self.last_macro.ok_or("No previous macro")?,
+            register => register,
+        };
+        let keys = self
+            .macros
+            .get(&register)
+            .cloned()
+            .ok_or_else(|| format!("Macro @{register} is empty"))?;
+        self.last_macro = Some(register);
+        Ok(keys)
+    }
+
     /// Page down.
     pub fn page_down(&mut self, page_size: usize) {
         let max_row = self.visible_sequence_count().saturating_sub(1);
@@ -795,7 +1924,7 @@ impl App {
 
     /// Scroll right.
     pub fn scroll_right(&mut self, amount: usize) {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
+        if self.columns_hidden() {
             // Move by visible columns
             if let Some(display_col) = self.actual_to_display_col(self.cursor_col) {
                 let max_display = self.visible_columns.len().saturating_sub(1);
@@ -810,7 +1939,7 @@ impl App {
 
     /// Scroll left.
     pub fn scroll_left(&mut self, amount: usize) {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
+        if self.columns_hidden() {
             // Move by visible columns
             if let Some(display_col) = self.actual_to_display_col(self.cursor_col) {
                 let new_display = display_col.saturating_sub(amount);
@@ -837,6 +1966,8 @@ impl App {
     pub fn enter_normal_mode(&mut self) {
         self.mode = Mode::Normal;
         self.line_input = tui_input::Input::default();
+        self.selection_anchor = None;
+        self.selection_kind = SelectionKind::Block;
     }
 
     /// Enter search mode.
@@ -849,71 +1980,285 @@ impl App {
     pub fn enter_visual_mode(&mut self) {
         self.mode = Mode::Visual;
         self.selection_anchor = Some((self.cursor_row, self.cursor_col));
+        self.selection_kind = SelectionKind::Block;
     }
 
     /// Enter visual line selection mode (selects whole rows).
     pub fn enter_visual_line_mode(&mut self) {
         self.mode = Mode::VisualLine;
         self.selection_anchor = Some((self.cursor_row, self.cursor_col));
+        self.selection_kind = SelectionKind::Block;
     }
 
     /// Exit visual mode without taking action.
     pub fn exit_visual_mode(&mut self) {
         self.mode = Mode::Normal;
         self.selection_anchor = None;
+        self.selection_kind = SelectionKind::Block;
     }
 
-    /// Get the bounds of the current selection (`min_row`, `min_col`, `max_row`, `max_col`).
-    /// Returns None if not in visual mode or no anchor set.
-    /// For VisualLine mode, returns full row width (0 to alignment.width()-1).
-    pub fn get_selection_bounds(&self) -> Option<(usize, usize, usize, usize)> {
-        let (anchor_row, anchor_col) = self.selection_anchor?;
-        let min_row = anchor_row.min(self.cursor_row);
-        let max_row = anchor_row.max(self.cursor_row);
+    /// Actual row of the first sequence named `id`.
+    fn row_of_id(&self, id: &str) -> Option<usize> {
+        self.alignment.sequences.iter().position(|seq| seq.id == id)
+    }
 
-        // VisualLine mode selects entire rows
-        if self.mode == Mode::VisualLine {
-            let min_col = 0;
-            let max_col = self.alignment.width().saturating_sub(1);
-            Some((min_row, min_col, max_row, max_col))
-        } else {
-            let min_col = anchor_col.min(self.cursor_col);
-            let max_col = anchor_col.max(self.cursor_col);
-            Some((min_row, min_col, max_row, max_col))
-        }
+    /// Actual row of the pinned header sequence (`:pin-header`).
+    pub fn pinned_header_row(&self) -> Option<usize> {
+        self.row_of_id(self.pinned_header_id.as_deref()?)
     }
 
-    /// Check if a cell is within the current selection.
-    pub fn is_selected(&self, row: usize, col: usize) -> bool {
-        if self.mode != Mode::Visual && self.mode != Mode::VisualLine {
-            return false;
-        }
-        if let Some((min_row, min_col, max_row, max_col)) = self.get_selection_bounds() {
-            row >= min_row && row <= max_row && col >= min_col && col <= max_col
-        } else {
-            false
-        }
+    /// Actual row of the sequence pinned for pairwise comparison (`C`).
+    pub fn pinned_comparison_row(&self) -> Option<usize> {
+        self.row_of_id(self.pinned_comparison_id.as_deref()?)
     }
 
-    /// Get selection dimensions as a string for status bar.
-    pub fn selection_info(&self) -> Option<String> {
-        if self.mode != Mode::Visual && self.mode != Mode::VisualLine {
-            return None;
+    /// Actual row of the reference sequence (`:color-ref`): the first
+    /// sequence when none is set or it is gone.
+    pub fn reference_row(&self) -> usize {
+        self.reference_id
+            .as_deref()
+            .and_then(|id| self.row_of_id(id))
+            .unwrap_or(0)
+    }
+
+    /// Display row of the pinned header sequence, if it is currently shown.
+    ///
+    /// A pinned sequence hidden inside a collapsed group has no display row.
+    pub fn pinned_header_display_row(&self) -> Option<usize> {
+        let pinned = self.pinned_header_row()?;
+        (0..self.visible_sequence_count()).find(|&row| self.display_to_actual_row(row) == pinned)
+    }
+
+    /// Pin the cursor sequence for pairwise comparison, or unpin it.
+    pub fn toggle_pinned_comparison(&mut self) {
+        if self.pinned_comparison_id.take().is_some() {
+            self.set_status("Pairwise comparison off");
+            return;
+        }
+        let actual_row = self.display_to_actual_row(self.cursor_row);
+        if let Some(seq) = self.alignment.sequences.get(actual_row) {
+            let id = seq.id.clone();
+            self.set_status(format!("Comparing against {id} (C or Esc to exit)"));
+            self.pinned_comparison_id = Some(id);
+        }
+    }
+
+    /// Set the reference sequence for compensatory coloring.
+    ///
+    /// With no target the cursor sequence is used; otherwise the target is a
+    /// 1-indexed row number or a sequence ID.
+    pub fn set_reference_sequence(&mut self, target: Option<&str>) -> Result<(), String> {
+        let actual_row = match target {
+            None => self.display_to_actual_row(self.cursor_row),
+            Some(target) => match target.parse::<usize>() {
+                Ok(row) if (1..=self.visible_sequence_count()).contains(&row) => {
+                    self.display_to_actual_row(row - 1)
+                }
+                Ok(row) => return Err(format!("Row out of range: {row}")),
+                Err(_) => self
+                    .alignment
+                    .sequences
+                    .iter()
+                    .position(|seq| seq.id == target)
+                    .ok_or_else(|| format!("No sequence with ID: {target}"))?,
+            },
+        };
+        let Some(seq) = self.alignment.sequences.get(actual_row) else {
+            return Err("No sequence to use as reference".to_string());
+        };
+        self.reference_id = Some(seq.id.clone());
+        Ok(())
+    }
+
+    /// Toggle the helix covariation overlay of the compensatory scheme.
+    pub fn toggle_helix_covariation(&mut self) {
+        if self.color_scheme != ColorScheme::Compensatory {
+            self.set_status("Helix covariation needs :color compensatory");
+            return;
+        }
+        self.show_helix_covariation = !self.show_helix_covariation;
+        self.set_status(format!(
+            "Helix covariation: {}",
+            if self.show_helix_covariation {
+                "on"
+            } else {
+                "off"
+            }
+        ));
+    }
+
+    /// Score every sequence by the fraction of pairs in `helix_id` that carry
+    /// a compatible change relative to the reference sequence.
+    pub fn compute_helix_covariation(&self, helix_id: usize) -> HelixCovariation {
+        let pairs: Vec<usize> = self
+            .structure_cache
+            .pairs()
+            .iter()
+            .filter(|pair| pair.helix_id == helix_id)
+            .map(|pair| pair.left)
+            .collect();
+        let reference_row = self.reference_row();
+        let reference = self
+            .alignment
+            .sequences
+            .get(reference_row)
+            .map(|seq| seq.data());
+
+        let scores = self
+            .alignment
+            .sequences
+            .iter()
+            .enumerate()
+            .map(|(row, seq)| {
+                let reference = reference.as_deref().filter(|_| row != reference_row)?;
+                if pairs.is_empty() {
+                    return Some(0.0);
+                }
+                let data = seq.data();
+                let compensatory = pairs
+                    .iter()
+                    .filter(|&&col| {
+                        matches!(
+                            analyze_compensatory(
+                                reference,
+                                &data,
+                                col,
+                                &self.structure_cache,
+                                &self.gap_chars
+                            ),
+                            CompensatoryChange::SingleCompatible
+                                | CompensatoryChange::DoubleCompatible
+                        )
+                    })
+                    .count();
+                Some(compensatory as f64 / pairs.len() as f64)
+            })
+            .collect();
+
+        HelixCovariation { helix_id, scores }
+    }
+
+    /// Covariation of the helix under the cursor, if the `H` overlay applies.
+    ///
+    /// Scores are cached until the next edit, so moving within a helix
+    /// doesn't rescore every sequence on each frame.
+    pub fn cursor_helix_covariation(&self) -> Option<HelixCovariation> {
+        if !self.show_helix_covariation || self.color_scheme != ColorScheme::Compensatory {
+            return None;
+        }
+        let helix_id = self.structure_cache.get_helix(self.cursor_col)?;
+        let reference_row = self.reference_row();
+        if let Some((row, cov)) = self.helix_covariation_cache.borrow().as_ref()
+            && *row == reference_row
+            && cov.helix_id == helix_id
+        {
+            return Some(cov.clone());
+        }
+        let cov = self.compute_helix_covariation(helix_id);
+        *self.helix_covariation_cache.borrow_mut() = Some((reference_row, cov.clone()));
+        Some(cov)
+    }
+
+    /// Toggle between selecting the block and everything outside it.
+    pub fn invert_selection(&mut self) {
+        if self.selection_anchor.is_none() {
+            return;
+        }
+        self.selection_kind = match self.selection_kind {
+            SelectionKind::Block => SelectionKind::Inverted,
+            SelectionKind::Inverted => SelectionKind::Block,
+        };
+    }
+
+    /// Get the bounds of the current selection (`min_row`, `min_col`, `max_row`, `max_col`).
+    /// Returns None if not in visual mode or no anchor set.
+    /// For VisualLine mode, returns full row width (0 to alignment.width()-1).
+    pub fn get_selection_bounds(&self) -> Option<(usize, usize, usize, usize)> {
+        let (anchor_row, anchor_col) = self.selection_anchor?;
+        let min_row = anchor_row.min(self.cursor_row);
+        let max_row = anchor_row.max(self.cursor_row);
+
+        // VisualLine mode selects entire rows
+        if self.mode == Mode::VisualLine {
+            let min_col = 0;
+            let max_col = self.alignment.width().saturating_sub(1);
+            Some((min_row, min_col, max_row, max_col))
+        } else {
+            let min_col = anchor_col.min(self.cursor_col);
+            let max_col = anchor_col.max(self.cursor_col);
+            Some((min_row, min_col, max_row, max_col))
+        }
+    }
+
+    /// Check if a cell is within the current selection.
+    pub fn is_selected(&self, row: usize, col: usize) -> bool {
+        if self.mode != Mode::Visual && self.mode != Mode::VisualLine {
+            return false;
+        }
+        if let Some((min_row, min_col, max_row, max_col)) = self.get_selection_bounds() {
+            let inside = row >= min_row && row <= max_row && col >= min_col && col <= max_col;
+            inside != (self.selection_kind == SelectionKind::Inverted)
+        } else {
+            false
+        }
+    }
+
+    /// Check if any cell of a display row is within the current selection.
+    pub fn is_row_selected(&self, row: usize) -> bool {
+        let Some((min_row, min_col, max_row, max_col)) = self.get_selection_bounds() else {
+            return false;
+        };
+        let in_rows = row >= min_row && row <= max_row;
+        match self.selection_kind {
+            SelectionKind::Block => in_rows,
+            SelectionKind::Inverted => {
+                let spans_width = min_col == 0 && max_col + 1 >= self.alignment.width();
+                !in_rows || !spans_width
+            }
+        }
+    }
+
+    /// Display rows covered by a line-wise selection, honoring inversion.
+    pub(crate) fn selected_rows(&self) -> Vec<usize> {
+        let Some((min_row, _, max_row, _)) = self.get_selection_bounds() else {
+            return Vec::new();
+        };
+        match self.selection_kind {
+            SelectionKind::Block => (min_row..=max_row).collect(),
+            SelectionKind::Inverted => (0..self.visible_sequence_count())
+                .filter(|row| !(min_row..=max_row).contains(row))
+                .collect(),
+        }
+    }
+
+    /// Get selection dimensions as a string for status bar.
+    pub fn selection_info(&self) -> Option<String> {
+        if self.mode != Mode::Visual && self.mode != Mode::VisualLine {
+            return None;
         }
         let (min_row, min_col, max_row, max_col) = self.get_selection_bounds()?;
         let rows = max_row - min_row + 1;
         let cols = max_col - min_col + 1;
+        let prefix = match self.selection_kind {
+            SelectionKind::Block => "",
+            SelectionKind::Inverted => "inverted ",
+        };
         if self.mode == Mode::VisualLine {
-            Some(format!("{rows} lines"))
+            Some(format!("{prefix}{rows} lines"))
         } else {
-            Some(format!("{rows}x{cols}"))
+            Some(format!("{prefix}{rows}x{cols}"))
         }
     }
 
     /// Yank (copy) the selected content to clipboard.
     /// In Visual mode, yanks a rectangular block of characters.
     /// In VisualLine mode, yanks complete sequences with all annotations.
+    /// An inverted block selection is not rectangular and cannot be yanked.
     pub fn yank_selection(&mut self) {
+        if self.mode == Mode::Visual && self.selection_kind == SelectionKind::Inverted {
+            self.set_status("Cannot yank an inverted block selection (use V for rows)");
+            return;
+        }
         if self.mode == Mode::VisualLine {
             self.yank_sequences();
         } else {
@@ -953,9 +2298,11 @@ impl App {
 
     /// Yank complete sequences with all annotations (VisualLine mode).
     fn yank_sequences(&mut self) {
-        let Some((min_row, _, max_row, _)) = self.get_selection_bounds() else {
+        let rows = self.selected_rows();
+        if rows.is_empty() {
+            self.set_status("No sequences selected");
             return;
-        };
+        }
 
         let mut sub_alignment = Alignment::new();
 
@@ -966,7 +2313,7 @@ impl App {
         sub_alignment.column_annotations = self.alignment.column_annotations.clone();
 
         // Collect selected sequences with their annotations
-        for display_row in min_row..=max_row {
+        for display_row in rows {
             let actual_row = self.display_to_actual_row(display_row);
             if let Some(seq) = self.alignment.sequences.get(actual_row) {
                 // Clone the sequence
@@ -1011,6 +2358,29 @@ impl App {
         self.history
             .save(&self.alignment, self.cursor_row, self.cursor_col);
 
+        if self.selection_kind == SelectionKind::Inverted {
+            // Replace everything outside the block with gaps
+            let mut cells = 0;
+            for display_row in 0..self.visible_sequence_count() {
+                let actual_row = self.display_to_actual_row(display_row);
+                let selected: Vec<usize> = (0..self.alignment.width())
+                    .filter(|&col| self.is_selected(display_row, col))
+                    .collect();
+                if let Some(seq_rc) = self.alignment.sequences.get_mut(actual_row) {
+                    let seq = std::rc::Rc::make_mut(seq_rc);
+                    let len = seq.len();
+                    for &col in selected.iter().filter(|&&col| col < len) {
+                        seq.set(col, self.gap_char);
+                        cells += 1;
+                    }
+                }
+            }
+            self.mark_modified();
+            self.exit_visual_mode();
+            self.set_status(format!("Deleted {cells} cell(s) outside the block"));
+            return;
+        }
+
         // Replace selected region with gaps
         for row in min_row..=max_row {
             if let Some(seq_rc) = self.alignment.sequences.get_mut(row) {
@@ -1025,7 +2395,7 @@ impl App {
 
         let rows = max_row - min_row + 1;
         let cols = max_col - min_col + 1;
-        self.modified = true;
+        self.mark_modified();
         self.exit_visual_mode();
         self.set_status(format!("Deleted {rows}x{cols} block"));
     }
@@ -1077,7 +2447,7 @@ impl App {
 
         let rows = clipboard.sequences.len();
         let cols = clipboard.width();
-        self.modified = true;
+        self.mark_modified();
         self.set_status(format!("Pasted {rows}x{cols} block"));
     }
 
@@ -1116,7 +2486,7 @@ impl App {
             }
         }
 
-        self.modified = true;
+        self.mark_modified();
 
         // Recompute collapse groups if collapse is enabled
         self.precompute_collapse_groups();
@@ -1145,6 +2515,7 @@ impl App {
         self.search.history.push(self.search.pattern.clone());
 
         self.search.matches = self.find_matches(&self.search.pattern.clone());
+        self.search.in_structure = false;
 
         if self.search.matches.is_empty() {
             self.set_status("Pattern not found (ignoring gaps)");
@@ -1175,7 +2546,7 @@ impl App {
         }
 
         // Find first match strictly after current cursor position
-        let cursor_pos = (self.cursor_row, self.cursor_col);
+        let cursor_pos = self.search_cursor_pos();
         let next_idx = self
             .search
             .matches
@@ -1197,7 +2568,7 @@ impl App {
         }
 
         // Find last match strictly before current cursor position
-        let cursor_pos = (self.cursor_row, self.cursor_col);
+        let cursor_pos = self.search_cursor_pos();
         let prev_idx = self
             .search
             .matches
@@ -1209,6 +2580,60 @@ impl App {
         self.jump_to_current_match();
     }
 
+    /// Cursor position comparable with search match starts.
+    ///
+    /// Structure matches are column spans, so only the column counts.
+    fn search_cursor_pos(&self) -> (usize, usize) {
+        if self.search.in_structure {
+            (0, self.cursor_col)
+        } else {
+            (self.cursor_row, self.cursor_col)
+        }
+    }
+
+    /// Search SS_cons for a bracket motif such as `<<<...>>>` (`:/s`).
+    ///
+    /// `.` in the motif matches any column; any opening bracket matches any
+    /// opening bracket, likewise for closing ones, and other characters must
+    /// match exactly. Matches replace the current search results, so `n`/`N`
+    /// step through them. Returns the number of matches.
+    pub fn search_structure(&mut self, motif: &str) -> Result<usize, String> {
+        let ss = self.alignment.ss_cons().ok_or("No SS_cons annotation")?;
+        let motif: Vec<char> = motif.chars().collect();
+        if motif.is_empty() {
+            return Err("Empty structure pattern".to_string());
+        }
+
+        let ss: Vec<char> = ss.chars().collect();
+        let matches: Vec<(usize, usize, usize)> = ss
+            .windows(motif.len())
+            .enumerate()
+            .filter(|(_, window)| {
+                window
+                    .iter()
+                    .zip(&motif)
+                    .all(|(&s, &m)| structure_chars_match(s, m))
+            })
+            .map(|(start, _)| (0, start, start + motif.len()))
+            .collect();
+
+        self.search.pattern = motif.iter().collect();
+        self.search.in_structure = true;
+        self.search.matches = matches;
+        self.search.match_index = None;
+        if !self.search.matches.is_empty() {
+            let first = self
+                .search
+                .matches
+                .iter()
+                .position(|&(_, start_col, _)| start_col >= self.cursor_col)
+                .unwrap_or(0);
+            self.search.match_index = Some(first);
+            self.jump_to_current_match();
+        }
+        Ok(self.search.matches.len())
+    }
+
     /// Find all matches of a pattern in the alignment.
     /// Case-insensitive, U/T tolerant (RNA/DNA equivalent), and ignores gap characters.
     /// Returns (row, start_col, end_col) where end_col is exclusive.
@@ -1222,27 +2647,140 @@ impl App {
         }
 
         for (row, seq) in self.alignment.sequences.iter().enumerate() {
-            let seq_chars: Vec<char> = seq.chars().to_vec();
-
-            // Try matching starting at each position
-            let mut col = 0;
-            while col < seq_chars.len() {
-                if let Some(end_col) = self.try_match_at(&seq_chars, col, &pattern_chars) {
-                    matches.push((row, col, end_col));
-                    // Move past the first non-gap character to find overlapping matches
-                    col += 1;
-                    while col < seq_chars.len() && self.gap_chars.contains(&seq_chars[col]) {
-                        col += 1;
-                    }
-                } else {
+            for (start_col, end_col) in self.find_matches_in_row(seq.chars(), &pattern_chars) {
+                matches.push((row, start_col, end_col));
+            }
+        }
+
+        matches
+    }
+
+    /// Find all matches of a normalized pattern in one sequence, ignoring gaps.
+    /// Returns (start_col, end_col) where end_col is exclusive.
+    fn find_matches_in_row(&self, seq_chars: &[char], pattern: &[char]) -> Vec<(usize, usize)> {
+        let mut matches = Vec::new();
+
+        // Try matching starting at each position
+        let mut col = 0;
+        while col < seq_chars.len() {
+            if let Some(end_col) = self.try_match_at(seq_chars, col, pattern) {
+                matches.push((col, end_col));
+                // Move past the first non-gap character to find overlapping matches
+                col += 1;
+                while col < seq_chars.len() && self.gap_chars.contains(&seq_chars[col]) {
                     col += 1;
                 }
+            } else {
+                col += 1;
             }
         }
 
         matches
     }
 
+    /// Set `:highlight` slot `slot` (1-based) to `pattern` in `color`.
+    pub fn set_motif_highlight(
+        &mut self,
+        slot: usize,
+        pattern: &str,
+        color: &str,
+    ) -> Result<(), String> {
+        if !(1..=HIGHLIGHT_SLOTS).contains(&slot) {
+            return Err(format!("Highlight slot must be 1-{HIGHLIGHT_SLOTS}"));
+        }
+        let color = Rgb::from_hex(color).ok_or_else(|| format!("Invalid color: {color}"))?;
+        let pattern = Self::normalize_for_search(pattern);
+        if pattern.is_empty() {
+            return Err("Empty pattern".to_string());
+        }
+        self.motif_highlights[slot - 1] = Some(MotifHighlight { pattern, color });
+        Ok(())
+    }
+
+    /// Clear `:highlight` slot `slot` (1-based).
+    pub fn clear_motif_highlight(&mut self, slot: usize) -> Result<(), String> {
+        match self.motif_highlights.get_mut(slot.wrapping_sub(1)) {
+            Some(entry @ Some(_)) => {
+                *entry = None;
+                Ok(())
+            }
+            Some(None) => Err(format!("Highlight slot {slot} is empty")),
+            None => Err(format!("Highlight slot must be 1-{HIGHLIGHT_SLOTS}")),
+        }
+    }
+
+    /// Color column `col` (1-based) with `color` in every sequence.
+    pub fn set_column_color(&mut self, col: usize, color: &str) -> Result<(), String> {
+        let width = self.alignment.width();
+        if !(1..=width).contains(&col) {
+            return Err(format!("Column must be 1-{width}"));
+        }
+        let color = Rgb::from_hex(color).ok_or_else(|| format!("Invalid color: {color}"))?;
+        self.column_color_overrides.insert(col - 1, color);
+        Ok(())
+    }
+
+    /// Shade columns `first`-`last` (1-based, inclusive) with `color`, or
+    /// [`DEFAULT_SHADE_COLOR`]. Shading the same range again replaces its color.
+    pub fn shade_columns(
+        &mut self,
+        first: usize,
+        last: usize,
+        color: Option<&str>,
+    ) -> Result<(), String> {
+        let width = self.alignment.width();
+        if first == 0 || last < first || last > width {
+            return Err(format!("Range must be within 1-{width}"));
+        }
+        let color = match color {
+            Some(hex) => Rgb::from_hex(hex).ok_or_else(|| format!("Invalid color: {hex}"))?,
+            None => DEFAULT_SHADE_COLOR,
+        };
+        self.shaded_regions
+            .retain(|&(start, end, _)| (start, end) != (first - 1, last - 1));
+        self.shaded_regions.push((first - 1, last - 1, color));
+        Ok(())
+    }
+
+    /// Remove the shading of exactly columns `first`-`last` (1-based).
+    /// Returns false if that range was not shaded.
+    pub fn unshade_columns(&mut self, first: usize, last: usize) -> bool {
+        let before = self.shaded_regions.len();
+        self.shaded_regions
+            .retain(|&(start, end, _)| (start + 1, end + 1) != (first, last));
+        self.shaded_regions.len() < before
+    }
+
+    /// Shade color of column `col`, if it lies in a `:shade` region.
+    pub fn shade_color(&self, col: usize) -> Option<Rgb> {
+        self.shaded_regions
+            .iter()
+            .rev()
+            .find(|&&(start, end, _)| (start..=end).contains(&col))
+            .map(|&(_, _, color)| color)
+    }
+
+    /// Motif highlight color for each column of `seq_chars`.
+    ///
+    /// Computed per row at render time so highlights follow edits. Where
+    /// matches from several slots overlap, the lowest slot number wins.
+    pub fn motif_highlight_colors(&self, seq_chars: &[char]) -> Vec<Option<Rgb>> {
+        let mut colors = Vec::new();
+        // Paint from the highest slot down so lower slots overwrite
+        for highlight in self.motif_highlights.iter().rev().flatten() {
+            let pattern: Vec<char> = highlight.pattern.chars().collect();
+            for (start, end) in self.find_matches_in_row(seq_chars, &pattern) {
+                if colors.is_empty() {
+                    colors = vec![None; seq_chars.len()];
+                }
+                for color in &mut colors[start..end] {
+                    *color = Some(highlight.color);
+                }
+            }
+        }
+        colors
+    }
+
     /// Try to match pattern starting at given column, skipping gaps.
     /// Returns the end column (exclusive) if match found, None otherwise.
     fn try_match_at(&self, seq: &[char], start_col: usize, pattern: &[char]) -> Option<usize> {
@@ -1295,8 +2833,16 @@ impl App {
         if let Some(idx) = self.search.match_index
             && let Some(&(row, start_col, _end_col)) = self.search.matches.get(idx)
         {
-            self.cursor_row = row;
             self.cursor_col = start_col;
+            if self.search.in_structure {
+                self.set_status(format!(
+                    "Structure match {}/{}",
+                    idx + 1,
+                    self.search.matches.len()
+                ));
+                return;
+            }
+            self.cursor_row = row;
             self.set_status(format!(
                 "Match {}/{} (ignoring gaps)",
                 idx + 1,
@@ -1319,21 +2865,28 @@ impl App {
         self.command_history.push(command.clone());
 
         let parts: Vec<&str> = command.split_whitespace().collect();
+        self.dispatch_command(&parts, &command);
+
+        // A visual selection carried into command mode only applies to this command.
+        self.selection_anchor = None;
+        self.selection_kind = SelectionKind::Block;
+    }
 
-        // Try each command category in order
-        if self.execute_file_command(&parts, &command) {
+    /// Run a parsed command through each command category in order.
+    fn dispatch_command(&mut self, parts: &[&str], command: &str) {
+        if self.execute_file_command(parts, command) {
             return;
         }
-        if self.execute_display_command(&parts) {
+        if self.execute_display_command(parts) {
             return;
         }
-        if self.execute_transform_command(&parts) {
+        if self.execute_transform_command(parts) {
             return;
         }
-        if self.execute_clustering_command(&parts) {
+        if self.execute_clustering_command(parts) {
             return;
         }
-        if self.execute_export_command(&parts) {
+        if self.execute_export_command(parts) {
             return;
         }
 
@@ -1411,6 +2964,25 @@ impl App {
                 }
                 true
             }
+            [cmd @ ("fetch" | "fetch!"), accession] => {
+                use crate::external::rfam::{RfamError, normalize_accession};
+                match normalize_accession(accession) {
+                    Ok(_) if !cfg!(feature = "network") => {
+                        self.set_status(RfamError::NetworkUnsupported.to_string())
+                    }
+                    // The download replaces the open alignments
+                    Ok(_) if self.modified && *cmd == "fetch" => {
+                        self.set_status("No write since last change (use :fetch! to force)")
+                    }
+                    Ok(accession) => {
+                        // Downloaded by `run_pending_fetch` once this status is drawn
+                        self.set_status(format!("Fetching {accession}..."));
+                        self.pending_fetch = Some(accession);
+                    }
+                    Err(e) => self.set_status(e.to_string()),
+                }
+                true
+            }
             ["msa" | "aln" | "alignment"] => {
                 // No argument: open the interactive picker, or report status.
                 if self.alignments.len() <= 1 {
@@ -1431,6 +3003,163 @@ impl App {
                 }
                 true
             }
+            ["compare", path] => {
+                if let Err(e) = self.open_compare(Path::new(path)) {
+                    self.set_status(e);
+                }
+                true
+            }
+            ["split-at", left, right] => {
+                self.split_at_command(self.cursor_col, left, right);
+                true
+            }
+            ["split-at", col, left, right] => {
+                match col.parse::<usize>() {
+                    Ok(col) if col > 0 => self.split_at_command(col - 1, left, right),
+                    _ => self.set_status(format!("Invalid column: {col}")),
+                }
+                true
+            }
+            ["insert-aligned", path] => {
+                self.insert_aligned_command(path, self.cursor_col);
+                true
+            }
+            ["insert-aligned", path, "at", col] => {
+                match col.parse::<usize>() {
+                    Ok(col) if col > 0 => self.insert_aligned_command(path, col - 1),
+                    _ => self.set_status(format!("Invalid column: {col}")),
+                }
+                true
+            }
+            ["merge", path] => {
+                let merged = read_alignments(Path::new(path)).and_then(|mut alignments| {
+                    let other = alignments.swap_remove(0);
+                    let columns = other.width();
+                    self.merge_alignment(other).map(|()| columns)
+                });
+                match merged {
+                    Ok(columns) => self.set_status(format!(
+                        "Merged {columns} column(s) from {path} (now {} columns)",
+                        self.alignment.width()
+                    )),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["compare-off"] => {
+                self.close_compare();
+                true
+            }
+            ["write-tree", path] => {
+                match self.write_tree(Path::new(path)) {
+                    Ok(n) => self.set_status(format!("Wrote tree ({n} leaves) to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-per-seq" | "write-per-seq!", dir]
+            | ["write-per-seq" | "write-per-seq!", dir, _] => {
+                let format = parts.get(2).copied().unwrap_or("ungapped");
+                let overwrite = parts[0].ends_with('!');
+                match self.write_sequences_individually(Path::new(dir), format, overwrite) {
+                    Ok(n) => self.set_status(format!("Wrote {n} FASTA file(s) to {dir}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-per-col", _] if self.alignment.width() > PER_COLUMN_CONFIRM_WIDTH => {
+                self.set_status(format!(
+                    "This writes {} files; use :write-per-col! to continue",
+                    self.alignment.width()
+                ));
+                true
+            }
+            ["write-per-col" | "write-per-col!", dir] => {
+                match self.write_columns_individually(Path::new(dir)) {
+                    Ok(n) => self.set_status(format!("Wrote {n} column file(s) to {dir}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-both" | "write-clustal-phy", stem] => {
+                let stem = Path::new(stem);
+                let aln = stem.with_extension("aln");
+                let phy = stem.with_extension("phy");
+                let result = writer::write_clustal(&self.alignment, &aln, &self.gap_chars)
+                    .and_then(|()| writer::write_phylip(&self.alignment, &phy));
+                match result {
+                    Ok(()) => {
+                        self.set_status(format!("Wrote {} and {}", aln.display(), phy.display()))
+                    }
+                    Err(e) => self.set_status(format!("Failed to write {}: {e}", stem.display())),
+                }
+                true
+            }
+            ["write-hmm", path] => {
+                match self.write_hmm(Path::new(path)) {
+                    Ok(summary) => self.set_status(format!(
+                        "Wrote {path}: {} sequences (effective {:.2}), {} of {} columns as match states",
+                        summary.nseq, summary.eff_nseq, summary.mlen, summary.alen
+                    )),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-md", path] => {
+                let num_cols = if self.viewport_width == 0 {
+                    self.visible_column_count()
+                } else {
+                    self.viewport_width
+                };
+                match self.write_markdown_view(Path::new(path), self.viewport_col, num_cols) {
+                    Ok(()) => self.set_status(format!("Wrote view to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-dot", threshold, path] => {
+                let result = threshold
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|t| t.is_finite() && *t >= 0.0)
+                    .ok_or_else(|| format!("Invalid distance threshold: {threshold}"))
+                    .and_then(|t| self.write_distance_graph(t, Path::new(path)));
+                match result {
+                    Ok(()) => self.set_status(format!("Wrote distance graph to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["import-features", path, tag] => {
+                match self.import_features_from_file(Path::new(path), tag) {
+                    Ok(n) => self.set_status(format!("#=GC {tag}: {n} column(s) annotated")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["import-anchors", path] => {
+                match self.import_anchors(Path::new(path)) {
+                    Ok(n) => {
+                        self.show_anchor_bar = true;
+                        self.set_status(format!("#=GC ANCHORS: {n} anchor(s)"));
+                    }
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["import-coaxial", path] => {
+                match self.load_coaxial_stacking(Path::new(path)) {
+                    Ok(n) => self.set_status(format!("#=GC COAXIAL: {n} stack(s)")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["import-tree", path] => {
+                if let Err(e) = self.order_by_tree(Path::new(path)) {
+                    self.set_status(e);
+                }
+                true
+            }
             ["noh" | "nohlsearch"] => {
                 self.clear_search();
                 true
@@ -1481,6 +3210,16 @@ impl App {
                 ));
                 true
             }
+            ["ref-ruler"] => {
+                self.ref_ungapped_ruler = !self.ref_ungapped_ruler;
+                let status = match self.alignment.sequences.get(self.reference_row()) {
+                    Some(seq) if self.ref_ungapped_ruler => format!("on ({})", seq.id),
+                    _ if self.ref_ungapped_ruler => "on".to_string(),
+                    _ => "off".to_string(),
+                };
+                self.set_status(format!("Reference ruler: {status}"));
+                true
+            }
             ["rownum"] => {
                 self.show_row_numbers = !self.show_row_numbers;
                 self.set_status(format!(
@@ -1497,16 +3236,40 @@ impl App {
                 ));
                 true
             }
-            ["consensus"] => {
-                self.show_consensus = !self.show_consensus;
+            ["strand-display"] => {
+                self.show_strand = !self.show_strand;
                 self.set_status(format!(
-                    "Consensus bar: {}",
-                    if self.show_consensus { "on" } else { "off" }
+                    "Strand display: {}",
+                    if self.show_strand { "on" } else { "off" }
                 ));
                 true
             }
-            ["conservation"] | ["consbar"] => {
-                self.show_conservation_bar = !self.show_conservation_bar;
+            ["compact"] => {
+                self.compact_ids = !self.compact_ids;
+                self.set_status(format!(
+                    "Compact IDs: {}",
+                    if self.compact_ids { "on" } else { "off" }
+                ));
+                true
+            }
+            ["wrapids"] => {
+                self.wrap_ids = !self.wrap_ids;
+                self.set_status(format!(
+                    "Wrap IDs: {}",
+                    if self.wrap_ids { "on" } else { "off" }
+                ));
+                true
+            }
+            ["consensus"] => {
+                self.show_consensus = !self.show_consensus;
+                self.set_status(format!(
+                    "Consensus bar: {}",
+                    if self.show_consensus { "on" } else { "off" }
+                ));
+                true
+            }
+            ["conservation"] | ["consbar"] => {
+                self.show_conservation_bar = !self.show_conservation_bar;
                 self.set_status(format!(
                     "Conservation bar: {}",
                     if self.show_conservation_bar {
@@ -1525,6 +3288,29 @@ impl App {
                 ));
                 true
             }
+            ["annotate-helices"] => {
+                match self.annotate_helices() {
+                    Ok(n) => self.set_status(format!("#=GC HELIX: {n} helices numbered")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["helix-bar"] => {
+                self.show_helix_bar = !self.show_helix_bar;
+                self.set_status(format!(
+                    "HELIX bar: {}",
+                    if self.show_helix_bar { "on" } else { "off" }
+                ));
+                true
+            }
+            ["anchorbar"] => {
+                self.show_anchor_bar = !self.show_anchor_bar;
+                self.set_status(format!(
+                    "ANCHORS bar: {}",
+                    if self.show_anchor_bar { "on" } else { "off" }
+                ));
+                true
+            }
             ["ppcons"] | ["pp_cons"] => {
                 self.show_pp_cons = !self.show_pp_cons;
                 self.set_status(format!(
@@ -1533,11 +3319,280 @@ impl App {
                 ));
                 true
             }
+            ["set-gf", tag, value @ ..] if !value.is_empty() => {
+                let value = value.join(" ");
+                self.set_file_annotation(tag, &value);
+                self.set_status(format!("#=GF {tag} {value}"));
+                true
+            }
+            ["delete-gf", tag] => {
+                match self.delete_file_annotation(tag) {
+                    Ok(n) => self.set_status(format!("Removed {n} #=GF {tag} line(s)")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["show-gr", "off"] => {
+                self.show_gr_tag = None;
+                self.set_status("#=GR track off");
+                true
+            }
+            ["show-gr", tag] => {
+                match self.set_gr_track(Some(tag)) {
+                    Ok(n) => self.set_status(format!("#=GR {tag} track: {n} sequence(s)")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
             ["info"] => {
                 self.show_info = !self.show_info;
                 self.info_scroll = 0;
                 true
             }
+            ["column-profile"] => {
+                self.show_column_profile = true;
+                true
+            }
+            ["history"] => {
+                self.open_command_history();
+                true
+            }
+            ["gsview"] => {
+                let table = self.sequence_annotation_table();
+                if table.rows.is_empty() {
+                    self.set_status("No #=GS annotations");
+                } else {
+                    self.report = Some(Report::SequenceAnnotations(table));
+                    self.report_scroll = 0;
+                    self.report_hscroll = 0;
+                }
+                true
+            }
+            ["show-comp" | "show-compensatory"] => {
+                if self.structure_cache.pairs().is_empty() {
+                    self.set_status("No SS_cons base pairs");
+                    return true;
+                }
+                let records = self.find_all_compensatory();
+                if records.is_empty() {
+                    self.set_status("Every pair matches the reference sequence");
+                } else {
+                    self.report = Some(Report::Compensatory(records));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-pairs"] => {
+                let pairs = self.list_base_pairs();
+                if pairs.is_empty() {
+                    self.set_status("No SS_cons base pairs");
+                } else {
+                    self.report = Some(Report::BasePairs(pairs));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-gc"] => {
+                if self.alignment.sequences.is_empty() {
+                    self.set_status("No sequences");
+                } else {
+                    let gc = self.compute_sequence_gc();
+                    self.report = Some(Report::GcContent(gc));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-gc" | "show-column-annotation", tag] => {
+                let found = self
+                    .alignment
+                    .column_annotations
+                    .iter()
+                    .find(|a| a.tag == *tag);
+                match found {
+                    Some(ann) => {
+                        self.report = Some(Report::ColumnAnnotation {
+                            tag: ann.tag.clone(),
+                            data: ann.data.clone(),
+                            selected: self
+                                .cursor_col
+                                .min(ann.data.chars().count().saturating_sub(1)),
+                        });
+                        self.report_scroll = 0;
+                    }
+                    None if self.alignment.column_annotations.is_empty() => {
+                        self.set_status("No #=GC annotations");
+                    }
+                    None => {
+                        let mut tags: Vec<&str> = self
+                            .alignment
+                            .column_annotations
+                            .iter()
+                            .map(|a| a.tag.as_str())
+                            .collect();
+                        tags.sort_unstable();
+                        self.set_status(format!("No #=GC {tag}; available: {}", tags.join(", ")));
+                    }
+                }
+                true
+            }
+            ["show-gap-seqs" | "show-gaps-by-seq"] => {
+                if self.alignment.sequences.is_empty() {
+                    self.set_status("No sequences");
+                } else {
+                    let gaps = self.compute_gap_fractions();
+                    self.report = Some(Report::GapFractions(gaps));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-rf-cons" | "show-rf-conservation"] => {
+                if self.alignment.rf().is_none() {
+                    self.set_status("No RF annotation");
+                    return true;
+                }
+                let columns = self.compute_rf_conservation();
+                if columns.is_empty() {
+                    self.set_status("No match-state RF columns");
+                } else {
+                    let insert_mean = self.compute_insert_conservation();
+                    self.report = Some(Report::RfConservation(columns, insert_mean));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-lengths" | "show-length-dist"] => {
+                if self.alignment.sequences.is_empty() {
+                    self.set_status("No sequences");
+                } else {
+                    let mut lengths = self.compute_ungapped_lengths();
+                    lengths.sort_by_key(|l| std::cmp::Reverse(l.1));
+                    self.report = Some(Report::SequenceLengths(lengths));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["check-alphabet"] => {
+                let issues = self.check_alphabet();
+                if issues.is_empty() {
+                    self.set_status(format!(
+                        "0 unexpected characters found ({})",
+                        self.sequence_type.as_str()
+                    ));
+                } else {
+                    self.report = Some(Report::AlphabetIssues(issues));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-memory"] => {
+                self.report = Some(Report::Memory(self.estimate_memory_usage()));
+                self.report_scroll = 0;
+                true
+            }
+            ["diagnostic"] => {
+                let issues = self.run_diagnostics();
+                if issues.is_empty() {
+                    self.set_status("No inconsistencies found");
+                } else {
+                    self.report = Some(Report::Diagnostics(issues));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-insertions"] => {
+                if self.alignment.rf().is_none() {
+                    self.set_status("No RF annotation");
+                    return true;
+                }
+                let mut stats = self.compute_insertion_stats();
+                let rows: Vec<(String, Vec<(usize, usize)>)> = self
+                    .alignment
+                    .sequences
+                    .iter()
+                    .filter_map(|seq| stats.remove(&seq.id).map(|ins| (seq.id.clone(), ins)))
+                    .collect();
+                if rows.is_empty() {
+                    self.set_status("No insertions relative to RF");
+                } else {
+                    self.report = Some(Report::Insertions(rows));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["undo-tree"] => {
+                if self.history.can_undo() || self.history.can_redo() {
+                    self.report = Some(Report::UndoTree(self.history.tree_rows()));
+                    self.report_scroll = 0;
+                } else {
+                    self.set_status("Undo history is empty");
+                }
+                true
+            }
+            ["show-loops"] => {
+                if self.alignment.ss_cons().is_some() {
+                    self.report = Some(Report::HairpinLoops);
+                    self.report_scroll = 0;
+                } else {
+                    self.set_status("No SS_cons annotation");
+                }
+                true
+            }
+            ["stats"] => {
+                let start = std::time::Instant::now();
+                let stats = self.compute_alignment_stats();
+                self.report = Some(Report::Stats(stats, start.elapsed()));
+                self.report_scroll = 0;
+                true
+            }
+            ["pid-hist"] => {
+                match self.compute_pid_histogram() {
+                    Some(hist) => {
+                        self.report = Some(Report::PidHistogram(hist));
+                        self.report_scroll = 0;
+                    }
+                    None => self.set_status("Need at least two sequences with residues"),
+                }
+                true
+            }
+            ["gapstats"] => {
+                if self.alignment.sequences.is_empty() {
+                    self.set_status("No sequences");
+                } else {
+                    self.report = Some(Report::GapStats(self.compute_gap_stats()));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["show-covariation"] if self.alignment.width() > COVARIATION_CONFIRM_WIDTH => {
+                self.set_status(format!(
+                    "{} columns is slow to compare pairwise; use :show-covariation! to continue",
+                    self.alignment.width()
+                ));
+                true
+            }
+            ["show-covariation" | "show-covariation!"] => {
+                if self.alignment.sequences.is_empty() {
+                    self.set_status("No sequences");
+                } else {
+                    self.report = Some(Report::Covariation(CovariationReport::new(
+                        self.compute_covariation_matrix(),
+                    )));
+                    self.report_scroll = 0;
+                }
+                true
+            }
+            ["cursor-col-highlight"] => {
+                self.highlight_cursor_column = !self.highlight_cursor_column;
+                self.set_status(format!(
+                    "Cursor column highlighting: {}",
+                    if self.highlight_cursor_column {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ));
+                true
+            }
             ["gapcols"] | ["gapcol"] => {
                 self.highlight_gap_columns = !self.highlight_gap_columns;
                 self.set_status(format!(
@@ -1550,14 +3605,268 @@ impl App {
                 ));
                 true
             }
+            ["rf-gaps" | "show-rf-gaps"] => {
+                if self.alignment.rf().is_none() && !self.highlight_rf_insertions {
+                    self.set_status("No RF annotation");
+                    return true;
+                }
+                self.highlight_rf_insertions = !self.highlight_rf_insertions;
+                self.set_status(format!(
+                    "RF insertion highlighting: {}",
+                    if self.highlight_rf_insertions {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ));
+                true
+            }
+            ["recompute-consensus"] => {
+                self.invalidate_consensus();
+                let columns = self.consensus_chars().len();
+                self.set_status(format!("Consensus recomputed ({columns} columns)"));
+                true
+            }
+            ["consensus-threshold"] => {
+                self.set_status(format!(
+                    "Consensus threshold: {:.2}",
+                    self.consensus_threshold
+                ));
+                true
+            }
+            ["consensus-threshold", value] => {
+                if let Err(e) = self.set_consensus_threshold(value) {
+                    self.set_status(e);
+                }
+                true
+            }
+            ["ppbar-threshold"] => {
+                self.set_status(format!("PP_cons threshold: {:.2}", self.pp_cons_threshold));
+                true
+            }
+            ["ppbar-threshold", value] => {
+                if let Err(e) = self.set_pp_cons_threshold(value) {
+                    self.set_status(e);
+                }
+                true
+            }
+            ["highlight", slot, pattern, color] => {
+                let result = slot
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid highlight slot: {slot}"))
+                    .and_then(|n| self.set_motif_highlight(n, pattern, color));
+                match result {
+                    Ok(()) => self.set_status(format!("Highlight {slot}: {pattern}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["clear-highlight", slot] => {
+                let result = slot
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid highlight slot: {slot}"))
+                    .and_then(|n| self.clear_motif_highlight(n));
+                match result {
+                    Ok(()) => self.set_status(format!("Cleared highlight {slot}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["set-color-at", col, color] => {
+                let result = col
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid column: {col}"))
+                    .and_then(|n| self.set_column_color(n, color));
+                match result {
+                    Ok(()) => self.set_status(format!("Column {col}: {color}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["clear-color-at", col] => {
+                let removed = col
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|n| self.column_color_overrides.remove(&n));
+                match removed {
+                    Some(_) => self.set_status(format!("Cleared color of column {col}")),
+                    None => self.set_status(format!("No custom color at column {col}")),
+                }
+                true
+            }
+            ["clear-color-all"] => {
+                let count = self.column_color_overrides.len();
+                self.column_color_overrides.clear();
+                self.set_status(format!("Cleared {count} column color(s)"));
+                true
+            }
+            ["shade" | "unshade", range, ..] if parse_column_range(range).is_none() => {
+                self.set_status(format!("Invalid range: {range} (expected N-M)"));
+                true
+            }
+            ["shade", range] | ["shade", range, _] => {
+                let (first, last) = parse_column_range(range).unwrap_or_default();
+                match self.shade_columns(first, last, parts.get(2).copied()) {
+                    Ok(()) => self.set_status(format!("Shaded columns {first}-{last}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["unshade", range] => {
+                let (first, last) = parse_column_range(range).unwrap_or_default();
+                if self.unshade_columns(first, last) {
+                    self.set_status(format!("Removed shading of columns {first}-{last}"));
+                } else {
+                    self.set_status(format!("Columns {first}-{last} are not shaded"));
+                }
+                true
+            }
+            ["unshade-all"] => {
+                let count = self.shaded_regions.len();
+                self.shaded_regions.clear();
+                self.set_status(format!("Removed {count} shaded region(s)"));
+                true
+            }
+            ["clear-highlight"] => {
+                self.motif_highlights = Default::default();
+                self.set_status("Cleared all highlights");
+                true
+            }
+            ["varcols"] | ["varcol"] => {
+                self.highlight_variable_cols = !self.highlight_variable_cols;
+                self.set_status(format!(
+                    "Variable column highlighting: {}",
+                    if self.highlight_variable_cols {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ));
+                true
+            }
+            ["diffcons"] => {
+                self.highlight_consensus_diff = !self.highlight_consensus_diff;
+                self.set_status(format!(
+                    "Consensus difference highlighting: {}",
+                    if self.highlight_consensus_diff {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                ));
+                true
+            }
+            ["pin-header"] => {
+                let actual_row = self.display_to_actual_row(self.cursor_row);
+                if let Some(seq) = self.alignment.sequences.get(actual_row) {
+                    let id = seq.id.clone();
+                    self.set_status(format!("Pinned {id} as header"));
+                    self.pinned_header_id = Some(id);
+                }
+                true
+            }
+            ["color-ref"] | ["color-ref", _] => {
+                match self.set_reference_sequence(parts.get(1).copied()) {
+                    Ok(()) => {
+                        let id = self.alignment.sequences[self.reference_row()].id.clone();
+                        self.set_status(format!("Reference sequence: {id}"));
+                    }
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["/s" | "search-structure", motif] => {
+                match self.search_structure(motif) {
+                    Ok(0) => self.set_status(format!("Structure pattern not found: {motif}")),
+                    // The status already shows the current match
+                    Ok(_) => {}
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["unpin-header"] => {
+                self.pinned_header_id = None;
+                self.set_status("Header unpinned");
+                true
+            }
+            ["zoom"] => {
+                self.set_status(format!("Zoom: every {} column(s)", self.column_stride));
+                true
+            }
+            ["zoom", value] => {
+                let stride = match *value {
+                    "off" => Some(1),
+                    _ => value.parse::<usize>().ok().filter(|&n| n > 0),
+                };
+                match stride {
+                    Some(stride) => {
+                        self.column_stride = stride;
+                        if stride == 1 {
+                            self.set_status("Zoom off");
+                        } else {
+                            self.set_status(format!("Zoom: every {stride} columns"));
+                        }
+                    }
+                    None => self.set_status(format!("Invalid zoom: {value}")),
+                }
+                true
+            }
+            ["col-filter", tag, pattern] => {
+                match self.set_column_filter(tag, pattern) {
+                    Ok(n) => {
+                        self.set_status(format!("Column filter {tag} ~ '{pattern}': {n} column(s)"))
+                    }
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["slice", "off"] => {
+                self.clear_column_slice();
+                self.set_status("Slice off");
+                true
+            }
+            ["slice", range] => {
+                let parsed = range
+                    .split_once('-')
+                    .and_then(|(a, b)| Some((a.parse::<usize>().ok()?, b.parse::<usize>().ok()?)))
+                    .filter(|&(a, _)| a > 0);
+                match parsed {
+                    Some((first, last)) => match self.set_column_slice(first - 1, last) {
+                        Ok(n) => self.set_status(format!("Slice {first}-{last}: {n} column(s)")),
+                        Err(e) => self.set_status(e),
+                    },
+                    None => self.set_status(format!("Invalid slice: {range} (expected N-M)")),
+                }
+                true
+            }
+            ["col-filter-off"] => {
+                self.clear_column_filter();
+                self.set_status("Column filter off");
+                true
+            }
+            ["ruler-mode", mode] => {
+                match *mode {
+                    "ref" | "reference" if self.alignment.rf().is_none() => {
+                        self.set_status("No RF annotation");
+                    }
+                    "ref" | "reference" => {
+                        self.ruler_mode = RulerMode::Reference;
+                        self.set_status("Ruler: RF reference positions");
+                    }
+                    "aln" | "alignment" => {
+                        self.ruler_mode = RulerMode::Alignment;
+                        self.set_status("Ruler: alignment columns");
+                    }
+                    _ => self.set_status(format!("Unknown ruler mode: {mode} (use ref or aln)")),
+                }
+                true
+            }
             ["hidegaps"] | ["hidegap"] => {
                 self.hide_gap_columns = !self.hide_gap_columns;
                 self.precompute_visible_columns();
                 // Ensure cursor is on a visible column
-                if self.hide_gap_columns
-                    && !self.visible_columns.is_empty()
-                    && self.actual_to_display_col(self.cursor_col).is_none()
-                {
+                if self.columns_hidden() && self.actual_to_display_col(self.cursor_col).is_none() {
                     // Snap to nearest visible column
                     self.cursor_col = self.visible_columns.first().copied().unwrap_or(0);
                 }
@@ -1567,9 +3876,16 @@ impl App {
                 ));
                 true
             }
+            ["auto-color"] => {
+                self.color_scheme_explicit = false;
+                self.auto_color();
+                self.set_status(format!("Color scheme: {}", self.color_scheme.as_ref()));
+                true
+            }
             ["color", scheme] => {
                 if let Some(s) = ColorScheme::from_str(scheme) {
                     self.color_scheme = s;
+                    self.color_scheme_explicit = true;
                     self.set_status(format!("Color scheme: {}", s.as_ref()));
                 } else {
                     self.set_status(format!("Unknown color scheme: {scheme}"));
@@ -1588,6 +3904,25 @@ impl App {
                 self.execute_set_command(setting);
                 true
             }
+            ["compare-ss"] => {
+                if self.structure_diff.take().is_some() {
+                    self.set_status("Structure comparison: off");
+                } else if let Err(e) = self.compare_structure() {
+                    self.set_status(e);
+                } else {
+                    let differing = self
+                        .structure_diff
+                        .iter()
+                        .flatten()
+                        .filter(|&&c| c != StructDiffClass::Agree)
+                        .count();
+                    self.set_status(format!(
+                        "Structure comparison: {} ({differing} differing column(s))",
+                        self.structure_diff_id
+                    ));
+                }
+                true
+            }
             ["split" | "sp"] => {
                 self.horizontal_split();
                 true
@@ -1600,6 +3935,31 @@ impl App {
                 self.close_split();
                 true
             }
+            ["watch"] => {
+                self.watch_mode = !self.watch_mode;
+                self.file_mtime = self.file_path.as_deref().and_then(modified_time);
+                self.set_status(match (self.watch_mode, &self.file_path) {
+                    (true, Some(path)) => format!("Watching {} for changes", path.display()),
+                    (true, None) => "Watch on (no file loaded)".to_string(),
+                    (false, _) => "Watch off".to_string(),
+                });
+                true
+            }
+            ["link-panes"] => {
+                self.linked_panes = true;
+                self.sync_linked_viewport();
+                self.set_status(match self.split_mode {
+                    Some(SplitMode::Vertical) => "Panes linked: columns scroll together",
+                    Some(SplitMode::Horizontal) => "Panes linked: rows scroll together",
+                    None => "Panes linked (takes effect in split mode)",
+                });
+                true
+            }
+            ["unlink-panes"] => {
+                self.linked_panes = false;
+                self.set_status("Panes unlinked");
+                true
+            }
             _ => false,
         }
     }
@@ -1639,19 +3999,227 @@ impl App {
                 "gap" => {
                     if let Some(c) = value.chars().next() {
                         self.gap_char = c;
+                        self.invalidate_consensus();
                         self.set_status(format!("Gap character: '{c}'"));
                     }
                 }
-                _ => {
-                    self.set_status(format!("Unknown setting: {key}"));
+                "consensus_threshold" => {
+                    if let Err(e) = self.set_consensus_threshold(value) {
+                        self.set_status(e);
+                    }
                 }
-            }
+                "pp_threshold" => {
+                    if let Err(e) = self.set_pp_cons_threshold(value) {
+                        self.set_status(e);
+                    }
+                }
+                "annotation_order" => {
+                    if let Err(e) = self.set_annotation_order(value) {
+                        self.set_status(e);
+                    }
+                }
+                "trim_char" => match value.chars().next() {
+                    Some(c) if value.chars().count() == 1 && !c.is_alphanumeric() => {
+                        self.trim_char = c;
+                        // Trimmed ends must still count as gaps everywhere else
+                        if self.gap_chars.contains(&c) {
+                            self.set_status(format!("Trim character: '{c}'"));
+                        } else {
+                            self.gap_chars.push(c);
+                            self.invalidate_consensus();
+                            *self.helix_covariation_cache.get_mut() = None;
+                            self.set_status(format!("Trim character: '{c}' (added as a gap)"));
+                        }
+                    }
+                    _ => self.set_status(format!("Invalid trim character: {value}")),
+                },
+                "compact_id_width" => match value.parse::<usize>() {
+                    Ok(width) if width >= 2 => {
+                        self.compact_id_width = width;
+                        self.set_status(format!("Compact ID width: {width}"));
+                    }
+                    _ => self.set_status(format!("Invalid compact ID width: {value}")),
+                },
+                "id_wrap_width" => match value.parse::<usize>() {
+                    Ok(width) if width >= 2 => {
+                        self.id_wrap_width = width;
+                        self.set_status(format!("ID wrap width: {width}"));
+                    }
+                    _ => self.set_status(format!("Invalid ID wrap width: {value}")),
+                },
+                "poll_interval" => match value.trim_end_matches("ms").parse::<u64>() {
+                    Ok(ms) if (1..=MAX_POLL_INTERVAL_MS).contains(&ms) => {
+                        self.poll_interval = std::time::Duration::from_millis(ms);
+                        self.set_status(format!("Poll interval: {ms}ms"));
+                    }
+                    _ => self.set_status(format!(
+                        "Invalid poll interval: {value} (1-{MAX_POLL_INTERVAL_MS} ms)"
+                    )),
+                },
+                "block_width" => match value.parse::<usize>() {
+                    Ok(width) if width > 0 => {
+                        self.block_width = width;
+                        self.set_status(format!("Block width: {width}"));
+                    }
+                    _ => self.set_status(format!("Invalid block width: {value}")),
+                },
+                "write_pretty" => match value.parse::<bool>() {
+                    Ok(pretty) => {
+                        self.write_pretty = pretty;
+                        self.set_status(format!("Aligned columns on save: {pretty}"));
+                    }
+                    _ => self.set_status(format!("Invalid write_pretty: {value}")),
+                },
+                "slice_relative_ruler" => match value.parse::<bool>() {
+                    Ok(relative) => {
+                        self.slice_relative_ruler = relative;
+                        self.set_status(format!("Slice-relative ruler: {relative}"));
+                    }
+                    _ => self.set_status(format!("Invalid slice_relative_ruler: {value}")),
+                },
+                "auto_cluster" => match value.parse::<bool>() {
+                    Ok(auto) => {
+                        self.auto_cluster = auto;
+                        self.set_status(format!("Auto-cluster on load: {auto}"));
+                    }
+                    _ => self.set_status(format!("Invalid auto_cluster: {value}")),
+                },
+                "auto_cluster_min" => match value.parse::<usize>() {
+                    Ok(min) => {
+                        self.auto_cluster_min = min;
+                        self.set_status(format!("Auto-cluster minimum: {min} sequences"));
+                    }
+                    _ => self.set_status(format!("Invalid auto_cluster_min: {value}")),
+                },
+                "pwm_pseudocount" => match value.parse::<f64>() {
+                    Ok(pseudocount) if pseudocount >= 0.0 => {
+                        self.pwm_pseudocount = pseudocount;
+                        self.set_status(format!("Profile pseudocount: {pseudocount}"));
+                    }
+                    _ => self.set_status(format!("Invalid pseudocount: {value}")),
+                },
+                "tree_width" => {
+                    let width = match value {
+                        "auto" => Some(usize::MAX),
+                        _ => value.parse::<usize>().ok().filter(|&w| w > 0),
+                    };
+                    let Some(width) = width else {
+                        self.set_status(format!("Invalid tree width: {value}"));
+                        return;
+                    };
+                    self.tree_max_width = width;
+                    // The tree is drawn at clustering time, so rebuild it.
+                    if self.cluster_tree.is_some() && !self.is_clustering() {
+                        self.start_clustering();
+                    } else {
+                        self.set_status(format!("Tree width: {value}"));
+                    }
+                }
+                _ => {
+                    self.set_status(format!("Unknown setting: {key}"));
+                }
+            }
         }
     }
 
+    /// Set the conservation level above which consensus residues are uppercase.
+    fn set_consensus_threshold(&mut self, value: &str) -> Result<(), String> {
+        let threshold = value
+            .parse::<f64>()
+            .ok()
+            .filter(|t| (0.0..=1.0).contains(t))
+            .ok_or_else(|| format!("Consensus threshold must be between 0.0 and 1.0: {value}"))?;
+        self.consensus_threshold = threshold;
+        self.invalidate_consensus();
+        self.set_status(format!("Consensus threshold: {threshold:.2}"));
+        Ok(())
+    }
+
+    /// Set the posterior probability below which the PP_cons bar shows a gap.
+    fn set_pp_cons_threshold(&mut self, value: &str) -> Result<(), String> {
+        let threshold = value
+            .parse::<f64>()
+            .ok()
+            .filter(|t| (0.0..=1.0).contains(t))
+            .ok_or_else(|| format!("PP_cons threshold must be between 0.0 and 1.0: {value}"))?;
+        self.pp_cons_threshold = threshold;
+        self.set_status(format!("PP_cons threshold: {threshold:.2}"));
+        Ok(())
+    }
+
+    /// Reorder the annotation bars from a comma-separated list such as `SS,cons,RF`.
+    ///
+    /// Bars that are not listed keep their default relative order after the
+    /// listed ones.
+    fn set_annotation_order(&mut self, value: &str) -> Result<(), String> {
+        let mut order = Vec::new();
+        for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let kind = AnnotationKind::from_str(name)
+                .ok_or_else(|| format!("Unknown annotation bar: {name}"))?;
+            if order.contains(&kind) {
+                return Err(format!("Annotation bar listed twice: {name}"));
+            }
+            order.push(kind);
+        }
+        if order.is_empty() {
+            return Err(
+                "Usage: :set annotation_order=SS,RF,PP,cons,conservation,helix,anchors".to_string(),
+            );
+        }
+        for kind in AnnotationKind::DEFAULT_ORDER {
+            if !order.contains(&kind) {
+                order.push(kind);
+            }
+        }
+        self.annotation_order = order;
+        self.set_status(format!("Annotation order: {value}"));
+        Ok(())
+    }
+
+    /// Annotation bars to draw for `alignment`, in the configured order.
+    ///
+    /// Skips bars that are toggled off or whose annotation is missing.
+    pub fn visible_annotations(&self, alignment: &Alignment) -> Vec<AnnotationKind> {
+        self.annotation_order
+            .iter()
+            .copied()
+            .filter(|kind| match kind {
+                AnnotationKind::SsCons => alignment.ss_cons().is_some(),
+                AnnotationKind::Rf => self.show_rf_bar && alignment.rf().is_some(),
+                AnnotationKind::PpCons => self.show_pp_cons && alignment.pp_cons().is_some(),
+                AnnotationKind::Consensus => self.show_consensus,
+                AnnotationKind::Conservation => self.show_conservation_bar,
+                AnnotationKind::Helix => self.show_helix_bar && alignment.helix().is_some(),
+                AnnotationKind::Anchors => self.show_anchor_bar && alignment.anchors().is_some(),
+            })
+            .collect()
+    }
+
     /// Execute alignment transformation commands. Returns true if handled.
     fn execute_transform_command(&mut self, parts: &[&str]) -> bool {
         match parts {
+            ["set-strand", strand @ ("+" | "-")] => {
+                let strand = if *strand == "+" { '+' } else { '-' };
+                match self.set_strand(strand) {
+                    Ok(id) => self.set_status(format!("Strand of {id}: {strand}")),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["set-strand", ..] => {
+                self.set_error("Usage: :set-strand +|-");
+                true
+            }
+            ["insert-cols", count] => {
+                match count.parse::<usize>() {
+                    Ok(n) if n > 0 => {
+                        self.insert_gap_column_range(self.cursor_col, n);
+                        self.set_status(format!("Inserted {n} gap column(s)"));
+                    }
+                    _ => self.set_error(format!("Invalid column count: {count}")),
+                }
+                true
+            }
             ["upper" | "uppercase"] => {
                 self.uppercase_alignment();
                 self.set_status("Converted to uppercase");
@@ -1684,10 +4252,344 @@ impl App {
                 self.trim();
                 true
             }
+            ["gap-mask"] | ["gap-mask", _] => {
+                let marker = match parts.get(1) {
+                    None => Some('|'),
+                    Some(arg) => {
+                        let mut chars = arg.chars();
+                        chars.next().filter(|_| chars.next().is_none())
+                    }
+                };
+                match marker {
+                    Some(marker) if self.gap_chars.contains(&marker) => {
+                        self.set_error(format!("'{marker}' is a gap character"));
+                    }
+                    Some(marker) => match self.mask_gap_columns(marker) {
+                        0 => self.set_status("No gap-only columns"),
+                        n => self
+                            .set_status(format!("Masked {n} gap-only column(s) with '{marker}'")),
+                    },
+                    None => self.set_error("Usage: :gap-mask [char]"),
+                }
+                true
+            }
+            ["interp-gaps"] | ["interp-gaps", _] => {
+                let fill = match parts.get(1) {
+                    None => Some(self.ambiguous_residue()),
+                    Some(arg) => {
+                        let mut chars = arg.chars();
+                        chars.next().filter(|_| chars.next().is_none())
+                    }
+                };
+                let Some(fill) = fill else {
+                    self.set_error("Usage: :interp-gaps [char]");
+                    return true;
+                };
+                let row = self.display_to_actual_row(self.cursor_row);
+                match self.interpolate_gaps(row, fill) {
+                    Ok(0) => self.set_status("No internal gaps in this sequence"),
+                    Ok(n) => self.set_status(format!("Filled {n} internal gap(s) with '{fill}'")),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["convert-gaps", to] => {
+                let mut chars = to.chars();
+                match chars.next().filter(|_| chars.next().is_none()) {
+                    Some(to) if self.gap_chars.contains(&to) => match self.convert_all_gaps(to) {
+                        0 => self.set_status(format!("All gaps are already '{to}'")),
+                        n => self.set_status(format!("Converted {n} gap(s) to '{to}'")),
+                    },
+                    Some(to) => self.set_error(format!("'{to}' is not a gap character")),
+                    None => self.set_error("Usage: :convert-gaps <char>"),
+                }
+                true
+            }
+            ["pp-filter", threshold] => {
+                match threshold.parse::<f64>() {
+                    Ok(t) if (0.0..=1.0).contains(&t) => match self.filter_by_pp(t) {
+                        0 => self.set_status(format!("No residues with PP below {t}")),
+                        n => self.set_status(format!("Masked {n} residue(s) with PP below {t}")),
+                    },
+                    _ => self.set_error(format!("Invalid PP threshold: {threshold} (0.0-1.0)")),
+                }
+                true
+            }
+            ["trim-seqs"] => {
+                let count = self.unmasked_terminal_gaps();
+                if count == 0 {
+                    self.set_status("No terminal gaps to mask");
+                } else {
+                    self.set_error(format!(
+                        "{count} terminal gap(s) would become '{}'; use :trim-seqs! to mask them",
+                        self.trim_char
+                    ));
+                }
+                true
+            }
+            ["trim-seqs!" | "trim-ends"] => {
+                match self.mask_terminal_gaps() {
+                    0 => self.set_status("No terminal gaps to mask"),
+                    n => self.set_status(format!("Masked terminal gaps in {n} sequence(s)")),
+                }
+                true
+            }
+            [command @ ("sort-gs" | "sort-gs-r"), tag] => {
+                match self.sort_sequences_by_gs(tag, *command == "sort-gs-r") {
+                    Ok(()) => self.set_status(format!("Sorted sequences by #=GS {tag}")),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["randomize" | "shuffle"] => {
+                self.randomize_sequence_order(None);
+                true
+            }
+            ["randomize" | "shuffle", seed] => {
+                match seed.parse::<u64>() {
+                    Ok(seed) => {
+                        self.randomize_sequence_order(Some(seed));
+                    }
+                    Err(_) => self.set_error(format!("Invalid seed: {seed}")),
+                }
+                true
+            }
+            ["add-ss", structure] => {
+                match self.set_ss_cons(structure) {
+                    Ok(pairs) => self.set_status(format!("SS_cons set ({pairs} base pairs)")),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["add-gr", tag, data] => {
+                let row = self.display_to_actual_row(self.cursor_row);
+                match self.alignment.sequences.get(row).map(|s| s.id.clone()) {
+                    Some(id) => self.add_gr_command(&id, tag, data),
+                    None => self.set_error("No sequence at cursor"),
+                }
+                true
+            }
+            ["add-gr", seq_id, tag, data] => {
+                self.add_gr_command(seq_id, tag, data);
+                true
+            }
+            ["infer-ss"] | ["infer-ss", _] => {
+                let min_mi = match parts.get(1) {
+                    None => Some(INFER_SS_MIN_MI),
+                    Some(bits) => bits.parse::<f64>().ok().filter(|&b| b > 0.0),
+                };
+                let Some(min_mi) = min_mi else {
+                    self.set_error("Usage: :infer-ss [min MI bits]");
+                    return true;
+                };
+                match self.infer_ss_from_covariation(min_mi) {
+                    Ok(pairs) => self.set_status(format!(
+                        "Inferred SS_cons from covariation ({pairs} base pairs)"
+                    )),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["clear-ss"] => {
+                if let Err(e) = self.clear_ss_cons() {
+                    self.set_error(e);
+                } else {
+                    self.set_status("SS_cons removed");
+                }
+                true
+            }
+            ["delete-seqs", pattern] => {
+                self.delete_seqs_command(pattern, false);
+                true
+            }
+            ["delete-seqs!", pattern] => {
+                self.delete_seqs_command(pattern, true);
+                true
+            }
+            ["complement-ss"] => {
+                match self.complement_ss() {
+                    Ok(()) => self.set_status("SS_cons replaced by its reverse-strand structure"),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["reverse-cols"] => {
+                self.reverse_alignment_columns();
+                self.set_status(format!("Reversed {} column(s)", self.alignment.width()));
+                true
+            }
+            ["dedup"] => {
+                match self.deduplicate_sequences() {
+                    0 => self.set_status("No duplicate sequences"),
+                    n => self.set_status(format!("Removed {n} duplicate sequences")),
+                }
+                true
+            }
+            ["dedup-ids"] => {
+                match self.remove_duplicate_ids() {
+                    0 => self.set_status("No duplicate sequence IDs"),
+                    n => self.set_status(format!("Removed {n} sequence(s) with duplicate IDs")),
+                }
+                true
+            }
+            ["align-to-ref"] => {
+                match self.align_to_reference() {
+                    Ok(0) => self.set_status("All sequences already start with the reference"),
+                    Ok(n) => {
+                        self.set_status(format!("Aligned {n} sequence(s) to the reference start"))
+                    }
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["align-hmm", path] => {
+                match self.align_to_hmm_profile(Path::new(path)) {
+                    Ok(n) => self.set_status(format!(
+                        "Aligned {n} sequence(s) to {path} ({} columns)",
+                        self.alignment.width()
+                    )),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["consensus-seq"] => {
+                match self.add_consensus_sequence() {
+                    Ok(true) => self.set_status("Added CONSENSUS sequence"),
+                    Ok(false) => self.set_status("Updated CONSENSUS sequence"),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["rf-to-seq"] => {
+                match self.add_rf_as_sequence() {
+                    Ok(true) => self.set_status("Added RF_REFERENCE sequence"),
+                    Ok(false) => self.set_status("Updated RF_REFERENCE sequence"),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["import-hmm-cons", path] => {
+                match self.import_hmm_consensus(Path::new(path)) {
+                    Ok((length, true)) => self.set_status(format!(
+                        "Added {length}-column HMM consensus and set SS_cons from {path}"
+                    )),
+                    Ok((length, false)) => {
+                        self.set_status(format!("Added {length}-column HMM consensus from {path}"))
+                    }
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["ungap-rf"] => {
+                match self.remove_insertion_columns() {
+                    Ok(0) => self.set_status("No insertion columns relative to RF"),
+                    Ok(n) => self.set_status(format!("Removed {n} insertion column(s)")),
+                    Err(e) => self.set_error(e),
+                }
+                true
+            }
+            ["fold-region" | "fold-selection"] => {
+                let range = self
+                    .get_selection_bounds()
+                    .map(|(_, min_col, _, max_col)| (min_col, max_col))
+                    .or_else(|| self.current_helix_range());
+                match range {
+                    Some((start, end)) => match self.fold_sequence_region(start, end) {
+                        Ok(n) => self.set_status(format!(
+                            "Folded columns {}-{}: {n} base pair(s)",
+                            start + 1,
+                            end + 1
+                        )),
+                        Err(e) => self.set_error(e),
+                    },
+                    None => self.set_error("No selection or helix at cursor"),
+                }
+                true
+            }
             _ => false,
         }
     }
 
+    /// Show the `#=GR <tag>` annotation under each sequence, or hide it with `None`.
+    ///
+    /// Returns the number of sequences that carry the tag.
+    pub fn set_gr_track(&mut self, tag: Option<&str>) -> Result<usize, String> {
+        let Some(tag) = tag else {
+            self.show_gr_tag = None;
+            return Ok(0);
+        };
+        let count = self
+            .alignment
+            .residue_annotations
+            .values()
+            .filter(|anns| anns.iter().any(|a| a.tag == tag))
+            .count();
+        if count == 0 {
+            return Err(format!("No #=GR {tag} annotations"));
+        }
+        self.show_gr_tag = Some(tag.to_string());
+        Ok(count)
+    }
+
+    /// Screen rows per sequence: two while a `:show-gr` track is shown or
+    /// IDs are wrapped.
+    pub(crate) fn rows_per_sequence(&self) -> usize {
+        if self.show_gr_tag.is_some() || self.wrap_ids {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Data of the `:show-gr` track for `seq_id`, if it has one.
+    pub(crate) fn gr_track(&self, seq_id: &str) -> Option<&str> {
+        let tag = self.show_gr_tag.as_deref()?;
+        self.alignment
+            .residue_annotations
+            .get(seq_id)?
+            .iter()
+            .find(|a| a.tag == tag)
+            .map(|a| a.data.as_str())
+    }
+
+    /// Run `:add-gr` for `seq_id` and report the result.
+    fn add_gr_command(&mut self, seq_id: &str, tag: &str, data: &str) {
+        match self.add_residue_annotation(seq_id, tag, data) {
+            Ok(()) => self.set_status(format!("Added #=GR {seq_id} {tag}")),
+            Err(e) => self.set_error(e),
+        }
+    }
+
+    /// Run `:delete-seqs`, asking for `!` before removing many sequences.
+    ///
+    /// A leading `~` makes the pattern a regular expression.
+    fn delete_seqs_command(&mut self, pattern: &str, force: bool) {
+        let (pattern, regex) = match pattern.strip_prefix('~') {
+            Some(re) => (re, true),
+            None => (pattern, false),
+        };
+        if !force {
+            match self.sequences_matching(pattern, regex) {
+                Ok(matches) if matches.len() > DELETE_CONFIRM_THRESHOLD => {
+                    self.set_error(format!(
+                        "{} sequences match; use :delete-seqs! to delete them",
+                        matches.len()
+                    ));
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    self.set_error(e);
+                    return;
+                }
+            }
+        }
+        match self.delete_sequences_by_pattern(pattern, regex) {
+            Ok(n) => self.set_status(format!("Deleted {n} sequence(s) matching '{pattern}'")),
+            Err(e) => self.set_error(e),
+        }
+    }
+
     /// Execute clustering-related commands. Returns true if handled.
     fn execute_clustering_command(&mut self, parts: &[&str]) -> bool {
         // Clustering is not supported in secondary pane with its own alignment
@@ -1727,6 +4629,49 @@ impl App {
                 self.toggle_collapse_identical();
                 true
             }
+            ["number-seqs"] | ["number-seqs", _] => {
+                match parse_cut_distance(parts.get(1)).and_then(|d| self.cluster_member_ids(d)) {
+                    Ok(ids) => self.set_status(format!(
+                        "{} sequence(s) would be renamed groupN_memberM; use :number-seqs! to rename them",
+                        ids.len()
+                    )),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-by-cluster", dir] | ["write-by-cluster", dir, _] => {
+                match parse_cut_distance(parts.get(2))
+                    .and_then(|d| self.write_fasta_by_cluster(Path::new(dir), d))
+                {
+                    Ok(groups) => {
+                        self.set_status(format!("Wrote {groups} cluster FASTA file(s) to {dir}"))
+                    }
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["number-seqs!"] | ["number-seqs!", _] => {
+                match parse_cut_distance(parts.get(1))
+                    .and_then(|d| self.number_sequences_by_cluster(d))
+                {
+                    Ok(groups) => self.set_status(format!(
+                        "Renamed {} sequence(s) into {groups} cluster group(s)",
+                        self.alignment.sequences.len()
+                    )),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["show-pid" | "show-identity-matrix"] => {
+                match self.compute_identity_matrix() {
+                    Some(matrix) => {
+                        self.report = Some(Report::IdentityMatrix(matrix));
+                        self.report_scroll = 0;
+                    }
+                    None => self.set_status("Not clustered; run :cluster first"),
+                }
+                true
+            }
             _ => false,
         }
     }
@@ -1741,24 +4686,369 @@ impl App {
                 }
                 true
             }
+            ["write-svg" | "write-structure-svg", path] => {
+                match self.write_structure_svg(Path::new(path)) {
+                    Ok(n) => self.set_status(format!("Wrote arc diagram ({n} pairs) to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-r2r", path] => {
+                let options = crate::external::r2r::R2rOptions {
+                    shaded: self.shaded_regions.clone(),
+                    helix_colors: self.color_scheme == ColorScheme::Structure,
+                    keep_all_pairs: true,
+                };
+                match crate::external::r2r::write_r2r(&self.alignment, Path::new(path), &options) {
+                    Ok(()) => self.set_status(format!("Wrote R2R input to {path}")),
+                    Err(e) => self.set_status(format!("R2R export failed: {e}")),
+                }
+                true
+            }
+            ["write-html", path] => {
+                match self.write_html_view(Path::new(path)) {
+                    Ok(()) => self.set_status(format!("Exported HTML to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["extract-stemloop", path] => {
+                let result = self
+                    .current_helix_range()
+                    .ok_or_else(|| "Cursor is not on a paired column".to_string())
+                    .and_then(|(left, right)| self.extract_stem_loop(left, right, Path::new(path)));
+                match result {
+                    Ok(n) => self.set_status(format!("Wrote {n}-column stem-loop to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-ct", path] => {
+                let result = self.cursor_structure().and_then(|(id, seq, ss)| {
+                    crate::external::ct::write_ct(&seq, &ss, &id, Path::new(path))
+                        .map_err(|e| e.to_string())
+                });
+                match result {
+                    Ok(()) => self.set_status(format!("Wrote CT to {path}")),
+                    Err(e) => self.set_status(format!("CT export failed: {e}")),
+                }
+                true
+            }
+            ["write-bpseq", path] => {
+                let result = self.cursor_structure().and_then(|(_, seq, ss)| {
+                    crate::external::bpseq::write_bpseq(&seq, &ss, Path::new(path))
+                        .map_err(|e| e.to_string())
+                });
+                match result {
+                    Ok(()) => self.set_status(format!("Wrote BPSEQ to {path}")),
+                    Err(e) => self.set_status(format!("BPSEQ export failed: {e}")),
+                }
+                true
+            }
+            ["write-fasta-coords" | "write-fasta-ungapped-coords", path] => {
+                match self.write_fasta_with_coords(Path::new(path)) {
+                    Ok(()) => self.set_status(format!("Wrote FASTA with coordinates to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-cons-bed", threshold, path] => {
+                match threshold.parse::<f64>() {
+                    Ok(t) if (0.0..=1.0).contains(&t) => {
+                        match self.write_conservation_bed(Path::new(path), t) {
+                            Ok(()) => self.set_status(format!("Wrote conservation BED to {path}")),
+                            Err(e) => self.set_status(e),
+                        }
+                    }
+                    _ => self.set_status(format!("Invalid threshold (0-1): {threshold}")),
+                }
+                true
+            }
+            ["write-ungapped" | "wu", path] => {
+                match self.write_ungapped_fasta(Path::new(path)) {
+                    Ok(n) => self.set_status(format!("Wrote {n} ungapped sequence(s) to {path}")),
+                    Err(e) => self.set_status(e),
+                }
+                true
+            }
+            ["write-pretty", path] => {
+                match writer::write_file_with(&self.alignment, Path::new(path), true) {
+                    Ok(()) => self.set_status(format!("Wrote {path} with aligned columns")),
+                    Err(e) => self.set_status(format!("Failed to write {path}: {e}")),
+                }
+                true
+            }
+            ["w-interleaved", path] => {
+                self.write_interleaved_command(path, self.block_width);
+                true
+            }
+            ["w-interleaved!", path, block_width] => {
+                match block_width.parse::<usize>() {
+                    Ok(width) if width > 0 => self.write_interleaved_command(path, width),
+                    _ => self.set_status(format!("Invalid block width: {block_width}")),
+                }
+                true
+            }
+            [
+                format @ ("write-profile-jaspar" | "write-profile-transfac"),
+                path,
+            ] => {
+                let profile = self.compute_profile();
+                let name = self.profile_name();
+                let (label, result) = if *format == "write-profile-jaspar" {
+                    let result = writer::write_jaspar(&profile, &name, Path::new(path));
+                    ("JASPAR", result)
+                } else {
+                    let result = writer::write_transfac(&profile, &name, Path::new(path));
+                    ("TRANSFAC", result)
+                };
+                match result {
+                    Ok(()) => self.set_status(format!(
+                        "Wrote {label} profile ({} columns) to {path}",
+                        profile.columns.len()
+                    )),
+                    Err(e) => self.set_status(format!("{label} export failed: {e}")),
+                }
+                true
+            }
             _ => false,
         }
     }
 
-    /// Toggle help display.
-    pub fn toggle_help(&mut self) {
-        self.show_help = !self.show_help;
-        self.help_scroll = 0;
-    }
-
-    /// Enable horizontal split (top/bottom panes).
-    /// If clipboard contains line-wise yanked sequences, create secondary alignment with that data.
-    pub fn horizontal_split(&mut self) {
-        self.do_split(SplitMode::Horizontal);
+    /// Write every sequence with its gaps removed to `path` as FASTA.
+    ///
+    /// `/start-end` suffixes are updated to the ungapped length. Sequences
+    /// that are all gaps are skipped; returns the number written.
+    pub fn write_ungapped_fasta(&self, path: &Path) -> Result<usize, String> {
+        let records: Vec<(String, String)> = self
+            .alignment
+            .sequences
+            .iter()
+            .filter_map(|seq| {
+                let residues: String = seq
+                    .chars()
+                    .iter()
+                    .filter(|&&c| !self.gap_chars.contains(&c))
+                    .collect();
+                let len = residues.chars().count();
+                if len == 0 {
+                    return None;
+                }
+                let id = ungapped_id(&seq.id, len);
+                if sequence_strand(&self.alignment, &seq.id) == Some('-') {
+                    let rna = self.sequence_type == SequenceType::RNA;
+                    Some((swap_id_range(&id), reverse_complement(&residues, rna)))
+                } else {
+                    Some((id, residues))
+                }
+            })
+            .collect();
+        writer::write_fasta(&records, path)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(records.len())
     }
 
-    /// Enable vertical split (left/right panes).
-    /// If clipboard contains line-wise yanked sequences, create secondary alignment with that data.
+    /// Record the cursor sequence's strand as `#=GS STRAND`, returning its ID.
+    pub fn set_strand(&mut self, strand: char) -> Result<String, String> {
+        if self.sequence_type == SequenceType::Protein {
+            return Err("Strands only apply to nucleotide sequences".to_string());
+        }
+        let row = self.display_to_actual_row(self.cursor_row);
+        let id = self
+            .alignment
+            .sequences
+            .get(row)
+            .map(|seq| seq.id.clone())
+            .ok_or("No sequence at cursor")?;
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        self.alignment
+            .set_sequence_annotation(&id, STRAND_TAG, strand.to_string());
+        self.mark_modified();
+        Ok(id)
+    }
+
+    /// Write the ungapped residues of the shown columns (the `:slice` range,
+    /// else every column) to `path` as FASTA, with `/start-end` suffixes
+    /// recomputed for that region: the start skips the residues left of the
+    /// slice and the end counts the residues written. Sequences with no
+    /// residues in the region are skipped.
+    pub fn write_fasta_with_coords(&self, path: &Path) -> Result<(), String> {
+        let (first, last) = self.column_slice.unwrap_or((0, self.alignment.width()));
+        let is_residue = |c: &&char| !self.gap_chars.contains(c);
+        let records: Vec<(String, String)> = self
+            .alignment
+            .sequences
+            .iter()
+            .filter_map(|seq| {
+                let chars = seq.chars();
+                let first = first.min(chars.len());
+                let skipped = chars[..first].iter().filter(is_residue).count();
+                let residues: String = chars[first..last.clamp(first, chars.len())]
+                    .iter()
+                    .filter(is_residue)
+                    .collect();
+                let len = residues.chars().count();
+                (len > 0).then(|| (region_id(&seq.id, skipped, len), residues))
+            })
+            .collect();
+        if records.is_empty() {
+            return Err("No residues in the shown columns".to_string());
+        }
+        writer::write_fasta(&records, path)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Write per-column conservation over RF match columns (see
+    /// [`App::rf_match_columns`]) to `path` as BED, in RF coordinates.
+    ///
+    /// Consecutive match positions on the same side of `threshold` are merged
+    /// into one interval, named `conserved` (at or above) or `variable`
+    /// (below), with the mean conservation scaled to 0-1000 as the score. The
+    /// chromosome is the `#=GF ID` of the alignment, or `RF` without one.
+    pub fn write_conservation_bed(&self, path: &Path, threshold: f64) -> Result<(), String> {
+        let conservation = self.compute_rf_conservation();
+        if conservation.is_empty() {
+            return Err("No RF match columns (needs #=GC RF)".to_string());
+        }
+        let chrom = self.alignment.get_file_annotation("ID").unwrap_or("RF");
+
+        let mut out = String::new();
+        let mut start = 0;
+        for end in 1..=conservation.len() {
+            let conserved = conservation[start].1 >= threshold;
+            if end < conservation.len() && (conservation[end].1 >= threshold) == conserved {
+                continue;
+            }
+            let run = &conservation[start..end];
+            let mean = run.iter().map(|(_, c)| c).sum::<f64>() / run.len() as f64;
+            out.push_str(&format!(
+                "{chrom}\t{start}\t{end}\t{}\t{}\n",
+                if conserved { "conserved" } else { "variable" },
+                (mean * 1000.0).round() as u32
+            ));
+            start = end;
+        }
+
+        std::fs::write(path, out).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Write each sequence to its own FASTA file `<dir>/<id>.fa`, creating
+    /// `dir` if needed.
+    ///
+    /// `format` is `ungapped` (residues only, with the ID updated as for
+    /// `:write-ungapped`) or `aligned` (gaps kept). `/`, `\\` and `:` in IDs
+    /// become `_`, and repeated names get a `_2`, `_3`... suffix. Existing files
+    /// are only replaced with `overwrite`. Returns the number of files written.
+    pub fn write_sequences_individually(
+        &self,
+        dir: &Path,
+        format: &str,
+        overwrite: bool,
+    ) -> Result<usize, String> {
+        let aligned = match format {
+            "ungapped" => false,
+            "aligned" => true,
+            _ => {
+                return Err(format!(
+                    "Unknown format '{format}' (expected ungapped or aligned)"
+                ));
+            }
+        };
+
+        let mut used = std::collections::HashSet::new();
+        let mut files = Vec::new();
+        for seq in &self.alignment.sequences {
+            let record = if aligned {
+                (seq.id.clone(), seq.data())
+            } else {
+                let residues: String = seq
+                    .chars()
+                    .iter()
+                    .filter(|&&c| !self.gap_chars.contains(&c))
+                    .collect();
+                let len = residues.chars().count();
+                if len == 0 {
+                    continue;
+                }
+                (ungapped_id(&seq.id, len), residues)
+            };
+            let base = seq.id.replace(['/', '\\', ':'], "_");
+            let mut name = base.clone();
+            let mut n = 1;
+            while !used.insert(name.clone()) {
+                n += 1;
+                name = format!("{base}_{n}");
+            }
+            files.push((dir.join(format!("{name}.fa")), record));
+        }
+
+        if !overwrite {
+            let existing = files.iter().filter(|(path, _)| path.exists()).count();
+            if existing > 0 {
+                return Err(format!(
+                    "{existing} file(s) already exist in {}; use :write-per-seq! to overwrite",
+                    dir.display()
+                ));
+            }
+        }
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        for (path, record) in &files {
+            writer::write_fasta(std::slice::from_ref(record), path)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        }
+        Ok(files.len())
+    }
+
+    /// Write each column to its own FASTA file `<dir>/col_<N>.fa` (1-based,
+    /// zero-padded), creating `dir` if needed. Each record is one residue;
+    /// sequences with a gap in the column are left out. Returns the number of
+    /// files written.
+    pub fn write_columns_individually(&self, dir: &Path) -> Result<usize, String> {
+        let width = self.alignment.width();
+        let digits = width.to_string().len();
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        for col in 0..width {
+            let records: Vec<(String, String)> = self
+                .alignment
+                .sequences
+                .iter()
+                .filter_map(|seq| {
+                    let c = seq.get(col)?;
+                    (!self.gap_chars.contains(&c)).then(|| (seq.id.clone(), c.to_string()))
+                })
+                .collect();
+            let path = dir.join(format!("col_{:0digits$}.fa", col + 1));
+            writer::write_fasta(&records, &path)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        }
+        Ok(width)
+    }
+
+    /// Write the alignment to `path` in blocks of `block_width` columns.
+    fn write_interleaved_command(&mut self, path: &str, block_width: usize) {
+        match writer::write_interleaved_file(&self.alignment, Path::new(path), block_width) {
+            Ok(()) => self.set_status(format!("Wrote {path} in blocks of {block_width} columns")),
+            Err(e) => self.set_status(format!("Failed to write {path}: {e}")),
+        }
+    }
+
+    /// Toggle help display.
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+        self.help_scroll = 0;
+    }
+
+    /// Enable horizontal split (top/bottom panes).
+    /// If clipboard contains line-wise yanked sequences, create secondary alignment with that data.
+    pub fn horizontal_split(&mut self) {
+        self.do_split(SplitMode::Horizontal);
+    }
+
+    /// Enable vertical split (left/right panes).
+    /// If clipboard contains line-wise yanked sequences, create secondary alignment with that data.
     pub fn vertical_split(&mut self) {
         self.do_split(SplitMode::Vertical);
     }
@@ -1871,6 +5161,7 @@ impl App {
         // Close the split mode entirely if no secondary alignment remains
         if self.secondary_alignment.is_none() {
             self.split_mode = None;
+            self.compare_mode = false;
         }
         self.set_status("Split closed");
     }
@@ -1889,6 +5180,7 @@ impl App {
         }
         if self.secondary_alignment.is_none() {
             self.split_mode = None;
+            self.compare_mode = false;
         }
         self.set_status("Split closed");
     }
@@ -2001,6 +5293,72 @@ impl App {
     /// Mark the alignment as modified.
     pub fn mark_modified(&mut self) {
         self.modified = true;
+        self.invalidate_consensus();
+        *self.alignment_hash_cache.get_mut() = None;
+        *self.helix_covariation_cache.get_mut() = None;
+        // The :compare-ss overlay describes the old columns
+        self.structure_diff = None;
+    }
+
+    /// Maximum ID column width, if `:compact` or `:wrapids` is on.
+    pub fn id_width_cap(&self) -> Option<usize> {
+        if self.compact_ids {
+            Some(self.compact_id_width)
+        } else {
+            self.wrap_ids.then_some(self.id_wrap_width)
+        }
+    }
+
+    /// Display row and alignment column under the mouse, if it is over a sequence.
+    pub fn mouse_cell(&self) -> Option<(usize, usize)> {
+        let (x, y) = self.mouse_pos?;
+        let grid = self.sequence_grid.borrow();
+        let grid = grid.as_ref()?;
+        let col = *grid.columns.get(x.checked_sub(grid.x)? as usize)?;
+        Some((grid.row_at(y)?, col))
+    }
+
+    /// Display row whose ID is under the mouse, if any.
+    pub fn mouse_id_row(&self) -> Option<usize> {
+        let (x, y) = self.mouse_pos?;
+        let grid = self.sequence_grid.borrow();
+        let grid = grid.as_ref()?;
+        // The ID column ends one cell before the separator
+        if x < grid.id_x || x + 1 >= grid.x {
+            return None;
+        }
+        grid.row_at(y)
+    }
+
+    /// Drop the cached consensus so the next render recomputes it.
+    pub fn invalidate_consensus(&mut self) {
+        *self.consensus_cache.get_mut() = None;
+    }
+
+    /// Consensus character of every column, computed on first use after an edit.
+    pub(crate) fn consensus_chars(&self) -> Ref<'_, Vec<char>> {
+        let width = self.alignment.width();
+        let stale = self
+            .consensus_cache
+            .borrow()
+            .as_ref()
+            .is_none_or(|chars| chars.len() != width);
+        if stale {
+            let chars = (0..width)
+                .map(|col| {
+                    get_consensus_char_with_case(
+                        col,
+                        &self.alignment,
+                        &self.gap_chars,
+                        self.consensus_threshold,
+                    )
+                })
+                .collect();
+            *self.consensus_cache.borrow_mut() = Some(chars);
+        }
+        Ref::map(self.consensus_cache.borrow(), |cache| {
+            cache.as_ref().expect("consensus cache was just filled")
+        })
     }
 
     /// Update the structure cache if needed.
@@ -2011,483 +5369,5590 @@ impl App {
         {
             // Structure parsing failed - show status to user
             self.set_status(format!("Warning: SS_cons parse error: {e}"));
+        } else if self.alignment.ss_cons().is_none() {
+            // e.g. after undoing :add-ss on an alignment without SS_cons
+            self.structure_cache.clear();
         }
     }
 
-    /// Ensure cursor is within bounds.
-    pub fn clamp_cursor(&mut self) {
-        let max_row = self.visible_sequence_count().saturating_sub(1);
-        self.cursor_row = self.cursor_row.min(max_row);
+    /// The cursor sequence and its structure, both with gap columns removed.
+    ///
+    /// Uses the sequence's own `#=GR SS` annotation when present, falling back
+    /// to SS_cons. Returns `(id, sequence, structure)`.
+    pub fn cursor_structure(&self) -> Result<(String, String, String), String> {
+        let row = self.display_to_actual_row(self.cursor_row);
+        let seq = self
+            .alignment
+            .sequences
+            .get(row)
+            .ok_or("No sequence at cursor")?;
+        let structure = self
+            .alignment
+            .residue_annotations
+            .get(&seq.id)
+            .and_then(|anns| anns.iter().find(|a| a.tag == "SS"))
+            .map(|a| a.data.as_str())
+            .or_else(|| self.alignment.ss_cons())
+            .ok_or_else(|| format!("No #=GR SS or SS_cons structure for {}", seq.id))?;
+
+        let (sequence, structure) = ungap_structure(seq.chars(), structure, &self.gap_chars)
+            .map_err(|e| format!("Structure parse error: {e}"))?;
+        Ok((seq.id.clone(), sequence, structure))
+    }
 
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
-            // Ensure cursor is on a visible column
-            let max_display_col = self.visible_columns.len().saturating_sub(1);
-            if self.actual_to_display_col(self.cursor_col).is_none() {
-                // Snap to nearest visible column
-                self.cursor_col = self
-                    .visible_columns
-                    .first()
-                    .copied()
-                    .unwrap_or(0)
-                    .min(self.display_to_actual_col(max_display_col));
-            }
-        } else {
-            let max_col = self.alignment.width().saturating_sub(1);
-            self.cursor_col = self.cursor_col.min(max_col);
+    /// Write the stem-loop closed by the pair `(left_col, right_col)` to `path`.
+    ///
+    /// The new alignment keeps columns `left_col..=right_col` of every sequence
+    /// and annotation; its SS_cons must still be balanced (a pseudoknot
+    /// crossing the range makes it invalid). Returns the number of columns.
+    pub fn extract_stem_loop(
+        &self,
+        left_col: usize,
+        right_col: usize,
+        path: &Path,
+    ) -> Result<usize, String> {
+        if self.structure_cache.get_pair(left_col) != Some(right_col) || left_col > right_col {
+            return Err(format!(
+                "Columns {} and {} are not an SS_cons base pair",
+                left_col + 1,
+                right_col + 1
+            ));
         }
-    }
 
-    /// Adjust viewport to keep cursor visible.
-    pub fn adjust_viewport(&mut self, visible_rows: usize, visible_cols: usize) {
-        // Vertical scrolling
-        if self.cursor_row < self.viewport_row {
-            self.viewport_row = self.cursor_row;
-        } else if self.cursor_row >= self.viewport_row + visible_rows {
-            self.viewport_row = self.cursor_row - visible_rows + 1;
+        let keep: Vec<bool> = (0..self.alignment.width())
+            .map(|col| (left_col..=right_col).contains(&col))
+            .collect();
+        let mut extracted = self.alignment.clone();
+        extracted.retain_columns(&keep);
+        let balanced = extracted
+            .ss_cons()
+            .is_some_and(|ss| StructureCache::new().update(ss).is_ok());
+        if !balanced {
+            return Err("Extracted SS_cons is unbalanced (crossing pseudoknot?)".to_string());
         }
 
-        // Horizontal scrolling
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
-            // When hiding, viewport_col is in display column space
-            if let Some(cursor_display_col) = self.actual_to_display_col(self.cursor_col) {
-                if cursor_display_col < self.viewport_col {
-                    self.viewport_col = cursor_display_col;
-                } else if cursor_display_col >= self.viewport_col + visible_cols {
-                    self.viewport_col = cursor_display_col - visible_cols + 1;
+        writer::write_file(&extracted, path)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(right_col - left_col + 1)
+    }
+
+    /// Column range spanned by the helix under the cursor (outermost pair).
+    pub fn current_helix_range(&self) -> Option<(usize, usize)> {
+        let helix = self.structure_cache.get_helix(self.cursor_col)?;
+        let pairs = self
+            .structure_cache
+            .pairs()
+            .iter()
+            .filter(|p| p.helix_id == helix);
+        let start = pairs.clone().map(|p| p.left).min()?;
+        let end = pairs.map(|p| p.right).max()?;
+        Some((start, end))
+    }
+
+    /// Hairpin loops of SS_cons as `(left_col, right_col, consensus_sequence)`.
+    ///
+    /// A hairpin is a base pair with no paired column between its two sides.
+    /// `left_col`/`right_col` are the closing pair; the sequence is the column
+    /// consensus of the enclosed loop with all-gap columns dropped.
+    pub fn extract_hairpin_loops(&self) -> Vec<(usize, usize, String)> {
+        let mut loops: Vec<(usize, usize, String)> = self
+            .structure_cache
+            .pairs()
+            .iter()
+            .filter(|p| (p.left + 1..p.right).all(|c| !self.structure_cache.is_paired(c)))
+            .map(|p| {
+                let seq = (p.left + 1..p.right)
+                    .map(|c| get_consensus_char(c, &self.alignment, &self.gap_chars))
+                    .filter(|&c| c != '.')
+                    .collect();
+                (p.left, p.right, seq)
+            })
+            .collect();
+        loops.sort_by_key(|&(left, _, _)| left);
+        loops
+    }
+
+    /// Write SS_cons as an SVG arc diagram over the column consensus.
+    /// Returns the number of base pairs drawn.
+    pub fn write_structure_svg(&self, path: &Path) -> Result<usize, String> {
+        let ss = self.alignment.ss_cons().ok_or("No SS_cons annotation")?;
+        let consensus: String = (0..self.alignment.width())
+            .map(|col| get_consensus_char(col, &self.alignment, &self.gap_chars))
+            .collect();
+        crate::svg::write_arc_diagram(ss, &consensus, path)
+            .map_err(|e| format!("SVG export failed: {e}"))?;
+        Ok(self.structure_cache.pairs().len())
+    }
+
+    /// Compute summary statistics for the alignment.
+    pub fn compute_alignment_stats(&self) -> AlignmentStats {
+        let sequences = &self.alignment.sequences;
+        let num_sequences = sequences.len();
+        let width = self.alignment.width();
+
+        let mut stats = AlignmentStats {
+            num_sequences,
+            width,
+            base_pairs: self.structure_cache.pairs().len(),
+            // Counted afresh: collapse groups are only rebuilt on load
+            unique_sequences: sequences
+                .iter()
+                .map(|seq| seq.chars())
+                .collect::<std::collections::HashSet<_>>()
+                .len(),
+            ..Default::default()
+        };
+
+        let mut counts = [0usize; 256];
+        let mut residue_columns = 0;
+        let mut total_gaps = 0;
+        let (mut gc, mut residues) = (0usize, 0usize);
+        for col in 0..width {
+            counts.fill(0);
+            let mut column_residues = 0;
+            for seq in sequences {
+                match seq.get(col) {
+                    Some(c) if !self.gap_chars.contains(&c) => {
+                        let upper = c.to_ascii_uppercase();
+                        counts[(upper as usize) & 0xFF] += 1;
+                        column_residues += 1;
+                        if matches!(upper, 'G' | 'C') {
+                            gc += 1;
+                        }
+                    }
+                    _ => total_gaps += 1,
                 }
             }
-        } else {
-            // Normal mode - viewport_col is actual column
-            if self.cursor_col < self.viewport_col {
-                self.viewport_col = self.cursor_col;
-            } else if self.cursor_col >= self.viewport_col + visible_cols {
-                self.viewport_col = self.cursor_col - visible_cols + 1;
+            residues += column_residues;
+
+            if column_residues == 0 {
+                stats.gap_only_columns += 1;
+                continue;
+            }
+            residue_columns += 1;
+
+            let total = column_residues as f64;
+            let max = counts.iter().copied().max().unwrap_or(0);
+            stats.mean_conservation += max as f64 / total;
+            stats.mean_entropy -= counts
+                .iter()
+                .filter(|&&n| n > 0)
+                .map(|&n| {
+                    let p = n as f64 / total;
+                    p * p.log2()
+                })
+                .sum::<f64>();
+            if max == num_sequences {
+                stats.conserved_columns += 1;
             }
         }
-    }
 
-    // === Clustering methods ===
+        if residue_columns > 0 {
+            stats.mean_conservation /= residue_columns as f64;
+            stats.mean_entropy /= residue_columns as f64;
+        }
+        let positions = num_sequences * width;
+        if positions > 0 {
+            stats.gap_percent = 100.0 * total_gaps as f64 / positions as f64;
+        }
+        if self.sequence_type != SequenceType::Protein && residues > 0 {
+            stats.gc_percent = Some(100.0 * gc as f64 / residues as f64);
+        }
+        stats
+    }
 
-    /// Map display row to actual sequence index.
-    /// When collapse is active, maps to representative. When clustering is active, uses cluster order.
-    pub fn display_to_actual_row(&self, display_row: usize) -> usize {
-        if self.collapse_identical && !self.collapse_groups.is_empty() {
-            // When clustering is also enabled, use group_order to find correct group
-            let group_idx = if let Some(ref group_order) = self.cluster_group_order {
-                group_order.get(display_row).copied().unwrap_or(display_row)
-            } else {
-                display_row
-            };
+    /// Histogram of pairwise percent identities.
+    ///
+    /// All N*(N-1)/2 pairs are compared unless the alignment has more than
+    /// `PID_SAMPLE_THRESHOLD` sequences, in which case a fixed-seed random sample of
+    /// `PID_SAMPLE_PAIRS` pairs is used. Returns `None` if no pair can be compared.
+    pub fn compute_pid_histogram(&self) -> Option<PidHistogram> {
+        use crate::clustering::{build_gap_lut, percent_identity};
+
+        let seqs = self.snapshot_seq_bytes();
+        let n = seqs.len();
+        if n < 2 {
+            return None;
+        }
+        let gap_lut = build_gap_lut(&self.gap_chars);
+
+        let sampled = n > PID_SAMPLE_THRESHOLD;
+        let pairs: Vec<(usize, usize)> = if sampled {
+            use rand_chacha::ChaCha8Rng;
+            use rand_chacha::rand_core::{RngCore, SeedableRng};
+
+            let mut rng = ChaCha8Rng::seed_from_u64(0);
+            (0..PID_SAMPLE_PAIRS)
+                .map(|_| {
+                    let i = (rng.next_u64() % n as u64) as usize;
+                    // Pick j != i by offsetting into the other n - 1 sequences.
+                    let offset = rng.next_u64() % (n as u64 - 1);
+                    (i, (i + 1 + offset as usize) % n)
+                })
+                .collect()
+        } else {
+            (0..n)
+                .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+                .collect()
+        };
 
-            // Get representative from the group
-            if group_idx < self.collapse_groups.len() {
-                self.collapse_groups[group_idx].0
-            } else {
-                display_row
-            }
-        } else if let Some(ref order) = self.cluster_order {
-            // Only clustering, no collapse
-            order.get(display_row).copied().unwrap_or(display_row)
+        let mut pids: Vec<f64> = pairs
+            .iter()
+            .filter_map(|&(i, j)| percent_identity(&seqs[i], &seqs[j], &gap_lut))
+            .collect();
+        if pids.is_empty() {
+            return None;
+        }
+        pids.sort_by(f64::total_cmp);
+
+        let mut hist = PidHistogram {
+            pairs: pids.len(),
+            sampled,
+            mean: pids.iter().sum::<f64>() / pids.len() as f64,
+            min: pids[0],
+            max: pids[pids.len() - 1],
+            ..Default::default()
+        };
+        let mid = pids.len() / 2;
+        hist.median = if pids.len().is_multiple_of(2) {
+            (pids[mid - 1] + pids[mid]) / 2.0
         } else {
-            // No collapse, no clustering
-            display_row
+            pids[mid]
+        };
+        for pid in pids {
+            hist.bins[((pid / 10.0) as usize).min(9)] += 1;
         }
+        Some(hist)
     }
 
-    /// Get the number of visible sequences (accounts for collapse).
-    pub fn visible_sequence_count(&self) -> usize {
-        if self.collapse_identical && !self.collapse_groups.is_empty() {
-            self.collapse_groups.len()
-        } else {
-            self.alignment.num_sequences()
+    /// Pairwise identities of the first `IDENTITY_MATRIX_MAX` sequences in
+    /// `cluster_order`, so clusters show up as blocks along the diagonal.
+    /// Returns `None` if the alignment has not been clustered.
+    pub fn compute_identity_matrix(&self) -> Option<IdentityMatrix> {
+        use crate::clustering::{build_gap_lut, percent_identity};
+
+        let order = self.cluster_order.as_ref()?;
+        let rows: Vec<usize> = order.iter().copied().take(IDENTITY_MATRIX_MAX).collect();
+        let seqs = self.snapshot_seq_bytes();
+        let gap_lut = build_gap_lut(&self.gap_chars);
+        let pids = rows
+            .iter()
+            .map(|&i| {
+                rows.iter()
+                    .map(|&j| percent_identity(&seqs[i], &seqs[j], &gap_lut))
+                    .collect()
+            })
+            .collect();
+        Some(IdentityMatrix {
+            ids: rows
+                .iter()
+                .map(|&i| self.alignment.sequences[i].id.clone())
+                .collect(),
+            pids,
+            total: self.alignment.sequences.len(),
+        })
+    }
+
+    /// Per-column residue frequencies for position weight matrix export.
+    ///
+    /// Gaps and residues outside the alphabet are ignored, and T/U count as
+    /// the alphabet's own base. `pwm_pseudocount` is added to every residue
+    /// count before normalizing; an empty column is uniform.
+    pub fn compute_profile(&self) -> Profile {
+        let alphabet = self.sequence_type.profile_alphabet();
+        let columns = (0..self.alignment.width())
+            .map(|col| {
+                let mut counts: HashMap<char, f64> = alphabet
+                    .iter()
+                    .map(|&c| (c, self.pwm_pseudocount))
+                    .collect();
+                for seq in &self.alignment.sequences {
+                    let Some(c) = seq.get(col) else {
+                        continue;
+                    };
+                    let c = match (c.to_ascii_uppercase(), self.sequence_type) {
+                        ('T', SequenceType::RNA) => 'U',
+                        ('U', SequenceType::DNA) => 'T',
+                        (c, _) => c,
+                    };
+                    if let Some(count) = counts.get_mut(&c) {
+                        *count += 1.0;
+                    }
+                }
+                let total: f64 = counts.values().sum();
+                for count in counts.values_mut() {
+                    *count = if total > 0.0 {
+                        *count / total
+                    } else {
+                        1.0 / alphabet.len() as f64
+                    };
+                }
+                counts
+            })
+            .collect();
+        Profile {
+            alphabet: alphabet.to_vec(),
+            columns,
         }
     }
 
-    /// Snapshot the alignment sequences as owned bytes for clustering.
-    /// Alignments are ASCII, so a byte representation is exact and `Send`.
-    fn snapshot_seq_bytes(&self) -> Vec<Vec<u8>> {
+    /// Name for exported matrices: the `#=GF ID`, else the file stem.
+    fn profile_name(&self) -> String {
         self.alignment
-            .sequences
-            .iter()
-            .map(|s| s.data().into_bytes())
-            .collect()
+            .get_file_annotation("ID")
+            .map(str::to_string)
+            .or_else(|| {
+                self.file_path
+                    .as_ref()
+                    .and_then(|p| p.file_stem())
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "alignment".to_string())
     }
 
-    /// Returns true when a background clustering job is in progress.
-    pub fn is_clustering(&self) -> bool {
-        self.clustering_job.is_some()
+    /// SHA-256 of the sequence content, as lowercase hex.
+    ///
+    /// Only `ID SEQUENCE` lines are hashed, sorted by ID, so sequence order,
+    /// comments and annotations do not affect the result. Cached until the
+    /// next edit, since the `:info` overlay asks for it on every redraw.
+    pub fn compute_alignment_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+        use std::fmt::Write;
+
+        if let Some(hash) = self.alignment_hash_cache.borrow().as_ref() {
+            return hash.clone();
+        }
+        let mut sequences: Vec<_> = self.alignment.sequences.iter().collect();
+        sequences.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut hasher = Sha256::new();
+        for seq in sequences {
+            hasher.update(seq.id.as_bytes());
+            hasher.update(b" ");
+            hasher.update(seq.data().as_bytes());
+            hasher.update(b"\n");
+        }
+        let hash = hasher
+            .finalize()
+            .iter()
+            .fold(String::with_capacity(64), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            });
+        *self.alignment_hash_cache.borrow_mut() = Some(hash.clone());
+        hash
     }
 
-    /// Apply a finished [`crate::clustering::ClusterResult`] to app state and clamp
-    /// the cursor. Shared by the synchronous and background clustering paths.
-    fn apply_cluster_result(&mut self, result: crate::clustering::ClusterResult) {
-        self.cluster_order = Some(result.order);
-        self.cluster_tree = Some(result.tree_lines);
-        self.collapsed_tree = result.collapsed_tree_lines;
-        self.tree_width = result.tree_width;
-        self.cluster_group_order = result.group_order;
+    /// Set `#=GF <tag>` to `value`, replacing every existing line with that tag.
+    pub fn set_file_annotation(&mut self, tag: &str, value: &str) {
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        let annotations = &mut self.alignment.file_annotations;
+        if let Some(first) = annotations.iter().position(|a| a.tag == tag) {
+            annotations[first].value = value.to_string();
+            let mut index = 0;
+            annotations.retain(|a| {
+                index += 1;
+                index - 1 == first || a.tag != tag
+            });
+        } else {
+            annotations.push(FileAnnotation {
+                tag: tag.to_string(),
+                value: value.to_string(),
+            });
+        }
+        self.mark_modified();
+    }
 
-        // Clamp cursor to valid range
-        if self.cursor_row >= self.visible_sequence_count() {
-            self.cursor_row = self.visible_sequence_count().saturating_sub(1);
+    /// Remove every `#=GF <tag>` line, returning how many were removed.
+    pub fn delete_file_annotation(&mut self, tag: &str) -> Result<usize, String> {
+        let count = self
+            .alignment
+            .file_annotations
+            .iter()
+            .filter(|a| a.tag == tag)
+            .count();
+        if count == 0 {
+            return Err(format!("No #=GF {tag} annotation"));
         }
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        self.alignment.file_annotations.retain(|a| a.tag != tag);
+        self.mark_modified();
+        Ok(count)
     }
 
-    /// Cluster sequences by similarity using hierarchical clustering, synchronously.
-    /// Uses precomputed collapse groups to avoid redundant distance calculations.
+    /// Mutual information (bits) between every pair of columns.
     ///
-    /// This blocks the caller; used for CLI startup and post-paste re-clustering
-    /// where blocking is acceptable. Interactive `:cluster` uses [`Self::start_clustering`].
-    pub fn cluster_sequences(&mut self) {
-        self.cluster_now();
+    /// Only the upper triangle (`matrix[i][j]` with `i < j`) is filled.
+    /// Residues are compared case-insensitively with T as U for nucleotides,
+    /// and sequences with a gap in either column are left out of that pair.
+    pub fn compute_covariation_matrix(&self) -> Vec<Vec<f64>> {
+        let width = self.alignment.width();
+        let mut matrix = vec![vec![0.0; width]; width];
+        for (i, j, mi) in self.column_pair_mi(width) {
+            matrix[i][j] = mi;
+        }
+        matrix
     }
 
-    /// Run the clustering computation synchronously and apply the result.
-    fn cluster_now(&mut self) {
-        if self.alignment.sequences.is_empty() {
-            return;
+    /// Mutual information (bits) of each column pair `(i, j)` with
+    /// `i < j <= i + max_span`, as computed for [`App::compute_covariation_matrix`].
+    fn column_pair_mi(&self, max_span: usize) -> Vec<(usize, usize, f64)> {
+        let width = self.alignment.width();
+
+        // Residues as small integers; gaps are None
+        let mut alphabet: Vec<char> = Vec::new();
+        let mut columns: Vec<Vec<Option<usize>>> = Vec::with_capacity(width);
+        for col in 0..width {
+            let mut column = Vec::with_capacity(self.alignment.sequences.len());
+            for seq in &self.alignment.sequences {
+                let symbol = seq
+                    .get(col)
+                    .filter(|c| !self.gap_chars.contains(c))
+                    .map(|c| match c.to_ascii_uppercase() {
+                        'T' if self.sequence_type != SequenceType::Protein => 'U',
+                        c => c,
+                    })
+                    .map(|c| match alphabet.iter().position(|&a| a == c) {
+                        Some(idx) => idx,
+                        None => {
+                            alphabet.push(c);
+                            alphabet.len() - 1
+                        }
+                    });
+                column.push(symbol);
+            }
+            columns.push(column);
         }
 
-        let seq_bytes = self.snapshot_seq_bytes();
-        let gap_lut = crate::clustering::build_gap_lut(&self.gap_chars);
+        let k = alphabet.len();
+        let mut pairs = Vec::new();
+        let mut joint = vec![0usize; k * k];
+        for (i, left) in columns.iter().enumerate() {
+            for (j, right) in columns.iter().enumerate().skip(i + 1).take(max_span) {
+                joint.fill(0);
+                let mut n = 0;
+                for pair in left.iter().zip(right) {
+                    if let (Some(a), Some(b)) = pair {
+                        joint[a * k + b] += 1;
+                        n += 1;
+                    }
+                }
+                if n == 0 {
+                    continue;
+                }
 
-        let result = crate::clustering::cluster_sequences_with_collapse(
-            &seq_bytes,
-            &gap_lut,
-            &self.collapse_groups,
-        );
-        self.apply_cluster_result(result);
+                let n = n as f64;
+                let mut p_left = vec![0.0; k];
+                let mut p_right = vec![0.0; k];
+                for (idx, &count) in joint.iter().enumerate() {
+                    p_left[idx / k] += count as f64 / n;
+                    p_right[idx % k] += count as f64 / n;
+                }
+                let mi = joint
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &count)| count > 0)
+                    .map(|(idx, &count)| {
+                        let p = count as f64 / n;
+                        p * (p / (p_left[idx / k] * p_right[idx % k])).log2()
+                    })
+                    .sum::<f64>()
+                    .max(0.0);
+                pairs.push((i, j, mi));
+            }
+        }
+        pairs
     }
 
-    /// Start clustering on a background thread, animating a spinner while it runs.
+    /// Propose SS_cons from covariation when the alignment has none (`:infer-ss`).
     ///
-    /// Snapshots owned, `Send` data (byte sequences, gap LUT, collapse groups) and
-    /// spawns a thread that computes the result and sends it over an mpsc channel.
-    pub fn start_clustering(&mut self) {
-        if self.alignment.sequences.is_empty() {
-            return;
+    /// Column pairs with mutual information of at least `min_mi` bits that
+    /// are 4 to 100 columns apart are accepted greedily, strongest first,
+    /// skipping any that reuse a column or cross an accepted pair, so the
+    /// result is always nested. Returns the number of base pairs.
+    pub fn infer_ss_from_covariation(&mut self, min_mi: f64) -> Result<usize, String> {
+        if self.alignment.ss_cons().is_some() {
+            return Err("SS_cons already present (:clear-ss removes it)".to_string());
+        }
+        if self.sequence_type == SequenceType::Protein {
+            return Err("Structure inference needs a nucleotide alignment".to_string());
         }
 
-        let seq_bytes = self.snapshot_seq_bytes();
-        let gap_lut = crate::clustering::build_gap_lut(&self.gap_chars);
-        let collapse_groups = self.collapse_groups.clone();
-        let seq_count = self.alignment.num_sequences();
+        // Only pairs within the span are scored, so wide alignments stay cheap
+        let mut candidates: Vec<(usize, usize, f64)> = self
+            .column_pair_mi(*INFER_SS_SPAN.end())
+            .into_iter()
+            .filter(|&(i, j, mi)| mi >= min_mi && INFER_SS_SPAN.contains(&(j - i)))
+            .collect();
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        let mut pairs: Vec<(usize, usize)> = Vec::new();
+        for (i, j, _) in candidates {
+            let conflicts = pairs.iter().any(|&(a, b)| {
+                let shares_column = [a, b].contains(&i) || [a, b].contains(&j);
+                let crosses = (a < i && i < b && b < j) || (i < a && a < j && j < b);
+                shares_column || crosses
+            });
+            if !conflicts {
+                pairs.push((i, j));
+            }
+        }
+        if pairs.is_empty() {
+            return Err(format!("No column pairs with MI >= {min_mi} bits"));
+        }
 
-        let (tx, rx) = std::sync::mpsc::channel();
-        std::thread::spawn(move || {
-            let result = crate::clustering::cluster_sequences_with_collapse(
-                &seq_bytes,
-                &gap_lut,
-                &collapse_groups,
-            );
-            // Ignore send errors: the receiver may have been dropped.
-            let _ = tx.send(result);
-        });
+        let mut structure = vec!['.'; self.alignment.width()];
+        for &(i, j) in &pairs {
+            structure[i] = '<';
+            structure[j] = '>';
+        }
+        self.set_ss_cons(&structure.into_iter().collect::<String>())
+    }
 
-        self.clustering_job = Some(ClusteringJob {
-            rx,
-            seq_count,
-            spinner: 0,
-        });
-        self.set_status(format!(
-            "{} Clustering {seq_count} sequences…",
-            SPINNER_FRAMES[0]
-        ));
+    /// Sequence IDs starting with `prefix` (case-insensitive), for tab completion.
+    pub fn complete_sequence_id(&self, prefix: &str) -> Vec<String> {
+        let prefix = prefix.to_lowercase();
+        self.alignment
+            .sequences
+            .iter()
+            .filter(|seq| seq.id.to_lowercase().starts_with(&prefix))
+            .map(|seq| seq.id.clone())
+            .collect()
     }
 
-    /// Poll the background clustering job (call once per event-loop iteration).
-    /// Applies the result when ready and animates the spinner while it runs.
-    pub fn poll_clustering(&mut self) {
-        let Some(job) = self.clustering_job.as_mut() else {
-            return;
+    /// Count of each character in column `col`, most frequent first.
+    ///
+    /// Residues are counted case-insensitively and all gap characters are
+    /// counted together under `gap_char`.
+    pub fn column_profile(&self, col: usize) -> Vec<(char, usize)> {
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for ch in self
+            .alignment
+            .sequences
+            .iter()
+            .filter_map(|seq| seq.get(col))
+        {
+            let ch = if self.gap_chars.contains(&ch) {
+                self.gap_char
+            } else {
+                ch.to_ascii_uppercase()
+            };
+            match counts.iter_mut().find(|(c, _)| *c == ch) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((ch, 1)),
+            }
+        }
+        // Stable sort keeps ties in order of first appearance
+        counts.sort_by_key(|c| std::cmp::Reverse(c.1));
+        counts
+    }
+
+    /// Collect the `#=GS` annotations of every sequence that has any.
+    pub fn sequence_annotation_table(&self) -> GsTable {
+        let mut table = GsTable::default();
+        for seq in &self.alignment.sequences {
+            let Some(annotations) = self.alignment.sequence_annotations.get(&seq.id) else {
+                continue;
+            };
+            for ann in annotations {
+                if !table.tags.contains(&ann.tag) {
+                    table.tags.push(ann.tag.clone());
+                }
+            }
+            table.rows.push((seq.id.clone(), Vec::new()));
+        }
+        for (id, cells) in &mut table.rows {
+            let annotations = &self.alignment.sequence_annotations[id.as_str()];
+            *cells = table
+                .tags
+                .iter()
+                .map(|tag| {
+                    annotations
+                        .iter()
+                        .filter(|a| &a.tag == tag)
+                        .map(|a| a.value.as_str())
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                })
+                .collect();
+        }
+        table
+    }
+
+    /// Insertions relative to RF for each sequence that has any.
+    ///
+    /// Each run of insert columns (see [`App::rf_match_columns`]) between two
+    /// match columns where the sequence has residues is recorded as
+    /// `(RF position, residues)`, where the position is the 1-based match
+    /// column the insertion follows (0 before the first). Empty without RF.
+    pub fn compute_insertion_stats(&self) -> HashMap<String, Vec<(usize, usize)>> {
+        let Some(match_cols) = self.rf_match_columns() else {
+            return HashMap::new();
         };
 
-        match job.rx.try_recv() {
-            Ok(result) => {
-                let seq_count = job.seq_count;
-                self.clustering_job = None;
-                self.apply_cluster_result(result);
+        let mut stats = HashMap::new();
+        for seq in &self.alignment.sequences {
+            let mut insertions = Vec::new();
+            let mut rf_pos = 0;
+            let mut run = 0;
+            for (&is_match, c) in match_cols.iter().zip(seq.chars()) {
+                if is_match {
+                    if run > 0 {
+                        insertions.push((rf_pos, run));
+                        run = 0;
+                    }
+                    rf_pos += 1;
+                } else if !self.gap_chars.contains(c) {
+                    run += 1;
+                }
+            }
+            if run > 0 {
+                insertions.push((rf_pos, run));
+            }
+            if !insertions.is_empty() {
+                stats.insert(seq.id.clone(), insertions);
+            }
+        }
+        stats
+    }
 
-                // Show the tree by default; it scrolls line-for-line with the
-                // sequences, so it's no denser per screen at large N. :tree toggles it.
-                self.show_tree = true;
-                self.set_status(format!(
-                    "Clustered {seq_count} sequences by similarity (tree visible)"
+    /// GC fraction of each sequence's residues (gaps excluded), sorted from
+    /// most to least GC-rich; sequences without residues count as 0.
+    pub fn compute_sequence_gc(&self) -> Vec<(String, f64)> {
+        let mut gc: Vec<(String, f64)> = self
+            .alignment
+            .sequences
+            .iter()
+            .map(|seq| {
+                let (residues, gc) = seq
+                    .chars()
+                    .iter()
+                    .filter(|&&c| !self.gap_chars.contains(&c))
+                    .fold((0usize, 0usize), |(residues, gc), c| {
+                        let is_gc = matches!(c.to_ascii_uppercase(), 'G' | 'C');
+                        (residues + 1, gc + is_gc as usize)
+                    });
+                let fraction = if residues == 0 {
+                    0.0
+                } else {
+                    gc as f64 / residues as f64
+                };
+                (seq.id.clone(), fraction)
+            })
+            .collect();
+        gc.sort_by(|a, b| b.1.total_cmp(&a.1));
+        gc
+    }
+
+    /// Fraction of each sequence's columns that are gaps, sorted from most to
+    /// least gapped.
+    pub fn compute_gap_fractions(&self) -> Vec<(String, f64)> {
+        let width = self.alignment.width().max(1);
+        let mut gaps: Vec<(String, f64)> = self
+            .alignment
+            .sequences
+            .iter()
+            .map(|seq| {
+                let count = seq
+                    .chars()
+                    .iter()
+                    .filter(|&&c| self.gap_chars.contains(&c))
+                    .count();
+                (seq.id.clone(), count as f64 / width as f64)
+            })
+            .collect();
+        gaps.sort_by(|a, b| b.1.total_cmp(&a.1));
+        gaps
+    }
+
+    /// Characters that are neither gaps nor residues of the detected
+    /// sequence type (ACGU, ACGT or the 20 standard amino acids, either
+    /// case), as `(sequence ID, column, character)` sorted by ID.
+    pub fn check_alphabet(&self) -> Vec<(String, usize, char)> {
+        let alphabet = self.sequence_type.profile_alphabet();
+        let mut issues: Vec<(String, usize, char)> = self
+            .alignment
+            .sequences
+            .iter()
+            .flat_map(|seq| {
+                seq.chars()
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, c)| {
+                        !self.gap_chars.contains(c) && !alphabet.contains(&c.to_ascii_uppercase())
+                    })
+                    .map(|(col, &c)| (seq.id.clone(), col, c))
+            })
+            .collect();
+        issues.sort_by(|a, b| a.0.cmp(&b.0));
+        issues
+    }
+
+    /// Check the alignment for inconsistencies: sequences, `#=GC` and `#=GR`
+    /// lines whose length differs from the alignment width, unbalanced
+    /// SS_cons brackets, gap-only sequences and SS_cons pairs at columns
+    /// where every sequence has the same character.
+    pub fn run_diagnostics(&self) -> Vec<DiagnosticMessage> {
+        let issue = |message: String, seq_id: Option<&str>, col: Option<usize>| DiagnosticMessage {
+            message,
+            seq_id: seq_id.map(str::to_string),
+            col,
+        };
+        let mut issues = Vec::new();
+        let width = self.alignment.width();
+
+        for seq in &self.alignment.sequences {
+            if seq.len() != width {
+                issues.push(issue(
+                    format!("Sequence has {} columns, expected {width}", seq.len()),
+                    Some(seq.id.as_str()),
+                    None,
                 ));
             }
-            Err(std::sync::mpsc::TryRecvError::Empty) => {
-                job.spinner = (job.spinner + 1) % SPINNER_FRAMES.len();
-                let frame = SPINNER_FRAMES[job.spinner];
-                let seq_count = job.seq_count;
-                self.set_status(format!("{frame} Clustering {seq_count} sequences…"));
+            if seq.chars().iter().all(|c| self.gap_chars.contains(c)) {
+                issues.push(issue(
+                    "Sequence contains only gaps".to_string(),
+                    Some(seq.id.as_str()),
+                    None,
+                ));
             }
-            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                self.clustering_job = None;
-                self.set_status("Clustering failed (worker thread stopped)");
+            for ann in self
+                .alignment
+                .residue_annotations
+                .get(&seq.id)
+                .into_iter()
+                .flatten()
+            {
+                let len = ann.data.chars().count();
+                if len != seq.len() {
+                    issues.push(issue(
+                        format!("#=GR {} has {len} columns, expected {}", ann.tag, seq.len()),
+                        Some(seq.id.as_str()),
+                        None,
+                    ));
+                }
+            }
+        }
+
+        for ann in &self.alignment.column_annotations {
+            let len = ann.data.chars().count();
+            if len != width {
+                issues.push(issue(
+                    format!("#=GC {} has {len} columns, expected {width}", ann.tag),
+                    None,
+                    None,
+                ));
+            }
+        }
+
+        if let Some(ss) = self.alignment.ss_cons() {
+            // A failed parse leaves the cache without pairs
+            let mut cache = StructureCache::new();
+            if let Err(e) = cache.update(ss) {
+                issues.push(issue(format!("SS_cons: {e}"), None, None));
+            }
+            // The character every sequence has in `col`, if they all agree
+            let shared_char = |col: usize| {
+                let mut chars = self.alignment.sequences.iter().map(|seq| seq.get(col));
+                let first = chars.next()??;
+                chars.all(|c| c == Some(first)).then_some(first)
+            };
+            for pair in cache.pairs() {
+                for (col, partner) in [(pair.left, pair.right), (pair.right, pair.left)] {
+                    if let Some(c) = shared_char(col) {
+                        issues.push(issue(
+                            format!(
+                                "Paired with column {} in SS_cons but '{c}' in every sequence",
+                                partner + 1
+                            ),
+                            None,
+                            Some(col),
+                        ));
+                    }
+                }
             }
         }
+
+        issues
     }
 
-    /// Physically reorder sequences to match the current cluster display order,
-    /// then clear clustering state. This "bakes in" the cluster order so that
-    /// subsequent index-based operations (like deletion) work correctly without
-    /// needing to recluster.
-    pub fn materialize_cluster_order(&mut self) {
-        if let Some(order) = self.cluster_order.take() {
-            let old_seqs = self.alignment.sequences.clone();
-            self.alignment.sequences = order
-                .into_iter()
-                .filter(|&i| i < old_seqs.len())
-                .map(|i| old_seqs[i].clone())
-                .collect();
+    /// Estimate the memory held by the alignment, undo history and caches.
+    pub fn estimate_memory_usage(&self) -> MemoryStats {
+        let mut seen = std::collections::HashSet::new();
+        let (sequences, column_annotations, residue_annotations, sequence_annotations) =
+            alignment_memory(&self.alignment, &mut seen);
+
+        let mut undo_states = 0;
+        let mut undo_history = 0;
+        for snapshot in self.history.snapshots() {
+            let (seqs, gc, gr, gs) = alignment_memory(&snapshot.alignment, &mut seen);
+            undo_history += seqs + gc + gr + gs;
+            undo_states += 1;
         }
-        self.cluster_tree = None;
-        self.collapsed_tree = None;
-        self.tree_width = 0;
-        self.show_tree = false;
-        self.cluster_group_order = None;
 
-        // Refresh collapse groups since sequence indices changed
-        if self.collapse_identical {
-            self.precompute_collapse_groups();
+        let rows = |rows: &Option<Vec<String>>| {
+            rows.iter()
+                .flatten()
+                .map(|row| std::mem::size_of::<String>() + row.len())
+                .sum::<usize>()
+        };
+        let indices = |order: &Option<Vec<usize>>| {
+            order.as_ref().map_or(0, |order| order.len()) * std::mem::size_of::<usize>()
+        };
+        let clustering = indices(&self.cluster_order)
+            + indices(&self.cluster_group_order)
+            + rows(&self.cluster_tree)
+            + rows(&self.collapsed_tree);
+
+        MemoryStats {
+            sequences,
+            column_annotations,
+            residue_annotations,
+            sequence_annotations,
+            undo_history,
+            undo_states,
+            clustering,
+            visible_columns: self.visible_columns.capacity() * std::mem::size_of::<usize>(),
+        }
+    }
+
+    /// Number of non-gap residues in each sequence, in alignment order.
+    pub fn compute_ungapped_lengths(&self) -> Vec<(String, usize)> {
+        self.alignment
+            .sequences
+            .iter()
+            .map(|seq| {
+                let len = seq
+                    .chars()
+                    .iter()
+                    .filter(|&&c| !self.gap_chars.contains(&c))
+                    .count();
+                (seq.id.clone(), len)
+            })
+            .collect()
+    }
+
+    /// Conservation at each match-state column (see [`App::rf_match_columns`]),
+    /// as `(alignment column, conservation)` in column order.
+    pub fn compute_rf_conservation(&self) -> Vec<(usize, f64)> {
+        let Some(is_match) = self.rf_match_columns() else {
+            return Vec::new();
+        };
+        is_match
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, is_match)| is_match)
+            .map(|(col, _)| {
+                let cons = calculate_conservation(col, &self.alignment, &self.gap_chars);
+                (col, cons)
+            })
+            .collect()
+    }
+
+    /// Mean conservation over insertion columns (see [`App::rf_match_columns`]),
+    /// or None if there is no RF annotation or every column is a match state.
+    fn compute_insert_conservation(&self) -> Option<f64> {
+        let values: Vec<f64> = self
+            .rf_match_columns()?
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, is_match)| !is_match)
+            .map(|(col, _)| calculate_conservation(col, &self.alignment, &self.gap_chars))
+            .collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    }
+
+    /// Move the cursor to the first displayed row showing sequence `id`.
+    ///
+    /// Returns false if no displayed row has that ID (e.g. collapsed away).
+    pub fn jump_to_sequence_id(&mut self, id: &str) -> bool {
+        let row = (0..self.visible_sequence_count()).find(|&row| {
+            self.alignment
+                .sequences
+                .get(self.display_to_actual_row(row))
+                .is_some_and(|seq| seq.id == id)
+        });
+        if let Some(row) = row {
+            self.cursor_row = row;
+        }
+        row.is_some()
+    }
+
+    /// Every SS_cons pair, in every sequence but the reference
+    /// (`reference_id`), that differs from the reference, sorted by change
+    /// type, then left column, then sequence order. Unchanged pairs are left out.
+    pub fn find_all_compensatory(&self) -> Vec<CompensatoryRecord> {
+        let reference_row = self.reference_row();
+        let Some(reference) = self.alignment.sequences.get(reference_row) else {
+            return Vec::new();
+        };
+        let ref_data = reference.data();
+
+        let mut records = Vec::new();
+        for (row, seq) in self.alignment.sequences.iter().enumerate() {
+            if row == reference_row {
+                continue;
+            }
+            let data = seq.data();
+            for pair in self.structure_cache.pairs() {
+                let change = analyze_compensatory(
+                    &ref_data,
+                    &data,
+                    pair.left,
+                    &self.structure_cache,
+                    &self.gap_chars,
+                );
+                if !matches!(
+                    change,
+                    CompensatoryChange::Unchanged | CompensatoryChange::Unpaired
+                ) {
+                    records.push(CompensatoryRecord {
+                        seq_id: seq.id.clone(),
+                        left: pair.left,
+                        right: pair.right,
+                        change,
+                    });
+                }
+            }
+        }
+        // Stable, so rows with the same change and column stay in sequence order
+        records.sort_by_key(|r| (r.change, r.left));
+        records
+    }
+
+    /// Every SS_cons base pair with how often the sequences pair there,
+    /// sorted by helix and then by left column.
+    pub fn list_base_pairs(&self) -> Vec<BasePairStats> {
+        let total = self.alignment.num_sequences();
+        let mut stats: Vec<BasePairStats> = self
+            .structure_cache
+            .pairs()
+            .iter()
+            .map(|pair| {
+                let mut counts = [0usize; 4];
+                for seq in &self.alignment.sequences {
+                    let (Some(a), Some(b)) = (seq.get(pair.left), seq.get(pair.right)) else {
+                        counts[3] += 1;
+                        continue;
+                    };
+                    let kind = if self.gap_chars.contains(&a) || self.gap_chars.contains(&b) {
+                        3
+                    } else if !is_valid_pair(a, b) {
+                        2
+                    } else if matches!(
+                        (a.to_ascii_uppercase(), b.to_ascii_uppercase()),
+                        ('G', 'U' | 'T') | ('U' | 'T', 'G')
+                    ) {
+                        1
+                    } else {
+                        0
+                    };
+                    counts[kind] += 1;
+                }
+                let freq = |n: usize| {
+                    if total == 0 {
+                        0.0
+                    } else {
+                        n as f64 / total as f64
+                    }
+                };
+                BasePairStats {
+                    helix_id: pair.helix_id,
+                    left: pair.left,
+                    right: pair.right,
+                    watson_crick: freq(counts[0]),
+                    wobble: freq(counts[1]),
+                    mismatch: freq(counts[2]),
+                    gap: freq(counts[3]),
+                }
+            })
+            .collect();
+        stats.sort_by_key(|s| (s.helix_id, s.left));
+        stats
+    }
+
+    /// Summarize gap patterns per sequence and per column.
+    pub fn compute_gap_stats(&self) -> GapStats {
+        let sequences = &self.alignment.sequences;
+        let width = self.alignment.width();
+        let mut stats = GapStats {
+            column_gap_fraction: vec![0.0; width],
+            ..Default::default()
+        };
+        if sequences.is_empty() {
+            return stats;
+        }
+
+        let mut fractions = Vec::with_capacity(sequences.len());
+        let (mut runs, mut run_total) = (0usize, 0usize);
+        for seq in sequences {
+            let chars = seq.chars();
+            let is_gap = |c: &char| self.gap_chars.contains(c);
+
+            let gaps = chars.iter().filter(|c| is_gap(c)).count();
+            fractions.push(if chars.is_empty() {
+                0.0
+            } else {
+                gaps as f64 / chars.len() as f64
+            });
+
+            // Gap runs, and whether any lies strictly between residues.
+            let first = chars.iter().position(|c| !is_gap(c));
+            let last = chars.iter().rposition(|c| !is_gap(c));
+            let mut internal = false;
+            let mut run = 0;
+            for (i, c) in chars.iter().enumerate() {
+                if is_gap(c) {
+                    run += 1;
+                    if let (Some(first), Some(last)) = (first, last) {
+                        internal |= i > first && i < last;
+                    }
+                }
+                if run > 0 && (!is_gap(c) || i + 1 == chars.len()) {
+                    runs += 1;
+                    run_total += run;
+                    stats.max_run_length = stats.max_run_length.max(run);
+                    run = 0;
+                }
+            }
+
+            if gaps == 0 {
+                stats.ungapped_seqs += 1;
+            } else if internal {
+                stats.internal_gap_seqs += 1;
+            } else {
+                stats.terminal_only_seqs += 1;
+            }
+
+            for (col, c) in chars.iter().enumerate().take(width) {
+                if is_gap(c) {
+                    stats.column_gap_fraction[col] += 1.0;
+                }
+            }
+        }
+
+        let n = sequences.len() as f64;
+        for fraction in &mut stats.column_gap_fraction {
+            *fraction /= n;
+        }
+        if runs > 0 {
+            stats.mean_run_length = run_total as f64 / runs as f64;
+        }
+
+        fractions.sort_by(f64::total_cmp);
+        let mid = fractions.len() / 2;
+        let median = if fractions.len().is_multiple_of(2) {
+            (fractions[mid - 1] + fractions[mid]) / 2.0
+        } else {
+            fractions[mid]
+        };
+        stats.seq_gap_fraction = (
+            fractions[0],
+            fractions[fractions.len() - 1],
+            fractions.iter().sum::<f64>() / n,
+            median,
+        );
+        stats
+    }
+
+    /// Compare the cursor sequence's `#=GR SS` annotation against SS_cons.
+    ///
+    /// The per-column result is stored in `structure_diff`; while set, the
+    /// renderer colors that sequence by agreement instead of the color scheme.
+    pub fn compare_structure(&mut self) -> Result<(), String> {
+        let row = self.display_to_actual_row(self.cursor_row);
+        let seq = self
+            .alignment
+            .sequences
+            .get(row)
+            .ok_or("No sequence at cursor")?;
+        let ss_cons = self.alignment.ss_cons().ok_or("No SS_cons annotation")?;
+        let seq_ss = self
+            .alignment
+            .residue_annotations
+            .get(&seq.id)
+            .and_then(|anns| anns.iter().find(|a| a.tag == "SS"))
+            .map(|a| a.data.as_str())
+            .ok_or_else(|| format!("No #=GR SS annotation for {}", seq.id))?;
+
+        let diff = compare_structures(ss_cons, seq_ss)
+            .map_err(|e| format!("Structure parse error: {e}"))?;
+        self.structure_diff_id = seq.id.clone();
+        self.structure_diff = Some(diff);
+        Ok(())
+    }
+
+    /// Ensure cursor is within bounds.
+    pub fn clamp_cursor(&mut self) {
+        let max_row = self.visible_sequence_count().saturating_sub(1);
+        self.cursor_row = self.cursor_row.min(max_row);
+
+        if self.columns_hidden() {
+            // Ensure cursor is on a visible column
+            let max_display_col = self.visible_columns.len().saturating_sub(1);
+            if self.actual_to_display_col(self.cursor_col).is_none() {
+                // Snap to nearest visible column
+                self.cursor_col = self
+                    .visible_columns
+                    .first()
+                    .copied()
+                    .unwrap_or(0)
+                    .min(self.display_to_actual_col(max_display_col));
+            }
+        } else {
+            let max_col = self.alignment.width().saturating_sub(1);
+            self.cursor_col = self.cursor_col.min(max_col);
+        }
+    }
+
+    /// Adjust viewport to keep cursor visible.
+    pub fn adjust_viewport(&mut self, visible_rows: usize, visible_cols: usize) {
+        // Vertical scrolling
+        if self.cursor_row < self.viewport_row {
+            self.viewport_row = self.cursor_row;
+        } else if self.cursor_row >= self.viewport_row + visible_rows {
+            self.viewport_row = self.cursor_row - visible_rows + 1;
+        }
+
+        // Horizontal scrolling (each screen cell spans column_stride columns when zoomed)
+        self.viewport_width = visible_cols;
+        let span = visible_cols * self.column_stride;
+        if self.columns_hidden() {
+            // When hiding, viewport_col is in display column space
+            if let Some(cursor_display_col) = self.actual_to_display_col(self.cursor_col) {
+                if cursor_display_col < self.viewport_col {
+                    self.viewport_col = cursor_display_col;
+                } else if cursor_display_col >= self.viewport_col + span {
+                    self.viewport_col = cursor_display_col - span + 1;
+                }
+            }
+        } else {
+            // Normal mode - viewport_col is actual column
+            if self.cursor_col < self.viewport_col {
+                self.viewport_col = self.cursor_col;
+            } else if self.cursor_col >= self.viewport_col + span {
+                self.viewport_col = self.cursor_col - span + 1;
+            }
         }
     }
 
-    /// Disable clustering and restore original order.
-    pub fn uncluster(&mut self) {
-        self.cluster_order = None;
-        self.cluster_tree = None;
-        self.collapsed_tree = None;
-        self.tree_width = 0;
-        self.show_tree = false;
-        self.cluster_group_order = None;
+    // === Clustering methods ===
+
+    /// Map display row to actual sequence index.
+    /// When collapse is active, maps to representative. When clustering is active, uses cluster order.
+    pub fn display_to_actual_row(&self, display_row: usize) -> usize {
+        if self.collapse_identical && !self.collapse_groups.is_empty() {
+            // When clustering is also enabled, use group_order to find correct group
+            let group_idx = if let Some(ref group_order) = self.cluster_group_order {
+                group_order.get(display_row).copied().unwrap_or(display_row)
+            } else {
+                display_row
+            };
+
+            // Get representative from the group
+            if group_idx < self.collapse_groups.len() {
+                self.collapse_groups[group_idx].0
+            } else {
+                display_row
+            }
+        } else if let Some(ref order) = self.cluster_order {
+            // Only clustering, no collapse
+            order.get(display_row).copied().unwrap_or(display_row)
+        } else {
+            // No collapse, no clustering
+            display_row
+        }
+    }
+
+    /// Get the number of visible sequences (accounts for collapse).
+    pub fn visible_sequence_count(&self) -> usize {
+        if self.collapse_identical && !self.collapse_groups.is_empty() {
+            self.collapse_groups.len()
+        } else {
+            self.alignment.num_sequences()
+        }
+    }
+
+    /// Snapshot the alignment sequences as owned bytes for clustering.
+    /// Alignments are ASCII, so a byte representation is exact and `Send`.
+    fn snapshot_seq_bytes(&self) -> Vec<Vec<u8>> {
+        self.alignment
+            .sequences
+            .iter()
+            .map(|s| s.data().into_bytes())
+            .collect()
+    }
+
+    /// Returns true when a background clustering job is in progress.
+    pub fn is_clustering(&self) -> bool {
+        self.clustering_job.is_some()
+    }
+
+    /// Apply a finished [`crate::clustering::ClusterResult`] to app state and clamp
+    /// the cursor. Shared by the synchronous and background clustering paths.
+    fn apply_cluster_result(&mut self, result: crate::clustering::ClusterResult) {
+        self.cluster_order = Some(result.order);
+        self.cluster_tree = Some(result.tree_lines);
+        self.cluster_dendrogram = Some(result.dendrogram);
+        self.collapsed_tree = result.collapsed_tree_lines;
+        self.tree_width = result.tree_width;
+        self.cluster_group_order = result.group_order;
+
+        // Clamp cursor to valid range
+        if self.cursor_row >= self.visible_sequence_count() {
+            self.cursor_row = self.visible_sequence_count().saturating_sub(1);
+        }
+    }
+
+    /// Cluster sequences by similarity using hierarchical clustering, synchronously.
+    /// Uses precomputed collapse groups to avoid redundant distance calculations.
+    ///
+    /// This blocks the caller; used for CLI startup and post-paste re-clustering
+    /// where blocking is acceptable. Interactive `:cluster` uses [`Self::start_clustering`].
+    pub fn cluster_sequences(&mut self) {
+        self.cluster_now();
+    }
+
+    /// Run the clustering computation synchronously and apply the result.
+    fn cluster_now(&mut self) {
+        if self.alignment.sequences.is_empty() {
+            return;
+        }
+
+        let seq_bytes = self.snapshot_seq_bytes();
+        let gap_lut = crate::clustering::build_gap_lut(&self.gap_chars);
+
+        let result = crate::clustering::cluster_sequences_with_collapse(
+            &seq_bytes,
+            &gap_lut,
+            &self.collapse_groups,
+            self.tree_max_width,
+        );
+        self.apply_cluster_result(result);
+    }
+
+    /// Start clustering on a background thread, animating a spinner while it runs.
+    ///
+    /// Snapshots owned, `Send` data (byte sequences, gap LUT, collapse groups) and
+    /// spawns a thread that computes the result and sends it over an mpsc channel.
+    pub fn start_clustering(&mut self) {
+        self.spawn_clustering(false);
+    }
+
+    /// Start a background clustering job; `auto` marks one started on load.
+    fn spawn_clustering(&mut self, auto: bool) {
+        if self.alignment.sequences.is_empty() {
+            return;
+        }
+
+        let seq_bytes = self.snapshot_seq_bytes();
+        let gap_lut = crate::clustering::build_gap_lut(&self.gap_chars);
+        let collapse_groups = self.collapse_groups.clone();
+        let seq_count = self.alignment.num_sequences();
+        let max_tree_width = self.tree_max_width;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let result = crate::clustering::cluster_sequences_with_collapse(
+                &seq_bytes,
+                &gap_lut,
+                &collapse_groups,
+                max_tree_width,
+            );
+            // Ignore send errors: the receiver may have been dropped.
+            let _ = tx.send(result);
+        });
+
+        let job = ClusteringJob {
+            rx,
+            seq_count,
+            spinner: 0,
+            auto,
+        };
+        self.set_status(job.progress_status());
+        self.clustering_job = Some(job);
+    }
+
+    /// Download the Rfam seed alignment queued by `:fetch`, if any, and open it.
+    ///
+    /// Blocks until the request finishes; the main loop calls this right after
+    /// drawing so the "Fetching..." status is visible meanwhile.
+    pub fn run_pending_fetch(&mut self) {
+        let Some(accession) = self.pending_fetch.take() else {
+            return;
+        };
+        match crate::external::rfam::fetch_rfam_seed(&accession) {
+            Ok(alignment) => {
+                self.alignments = vec![alignment];
+                self.file_path = None;
+                self.activate_alignment(0);
+                self.auto_configure_display();
+                self.set_status(format!(
+                    "Fetched {accession} seed ({} seqs); :w <path> to save",
+                    self.alignment.num_sequences()
+                ));
+            }
+            Err(e) => self.set_status(format!("Fetching {accession} failed: {e}")),
+        }
+    }
+
+    /// Poll the background clustering job (call once per event-loop iteration).
+    /// Applies the result when ready and animates the spinner while it runs.
+    pub fn poll_clustering(&mut self) {
+        let Some(job) = self.clustering_job.as_mut() else {
+            return;
+        };
+
+        match job.rx.try_recv() {
+            Ok(result) => {
+                let (seq_count, auto) = (job.seq_count, job.auto);
+                self.clustering_job = None;
+                self.apply_cluster_result(result);
+
+                if auto {
+                    // Like synchronous auto-clustering, leave the tree to --tree
+                    self.set_status(format!("Auto-clustered {seq_count} sequences"));
+                } else {
+                    // Show the tree by default; it scrolls line-for-line with the
+                    // sequences, so it's no denser per screen at large N. :tree toggles it.
+                    self.show_tree = true;
+                    self.set_status(format!(
+                        "Clustered {seq_count} sequences by similarity (tree visible)"
+                    ));
+                }
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => {
+                job.spinner = (job.spinner + 1) % SPINNER_FRAMES.len();
+                let status = job.progress_status();
+                self.set_status(status);
+            }
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.clustering_job = None;
+                self.set_status("Clustering failed (worker thread stopped)");
+            }
+        }
+    }
+
+    /// New `group<N>_member<M>` IDs for every sequence, in sequence order.
+    ///
+    /// Groups come from cutting the clustering tree at `max_distance` (see
+    /// [`crate::clustering::ClusterTree::cut`]); groups and members are numbered from 1
+    /// in the clustered display order.
+    pub fn cluster_member_ids(&self, max_distance: Option<f64>) -> Result<Vec<String>, String> {
+        Ok(self
+            .cluster_group_numbers(max_distance)?
+            .into_iter()
+            .map(|(group, member)| format!("group{group}_member{member}"))
+            .collect())
+    }
+
+    /// `(group, member)` numbers for every sequence, in sequence order, as
+    /// used by [`App::cluster_member_ids`].
+    fn cluster_group_numbers(
+        &self,
+        max_distance: Option<f64>,
+    ) -> Result<Vec<(usize, usize)>, String> {
+        let (Some(order), Some(tree)) = (&self.cluster_order, &self.cluster_dendrogram) else {
+            return Err("No clustering tree (use :cluster first)".to_string());
+        };
+        let groups = tree.cut(self.alignment.sequences.len(), max_distance);
+
+        let mut numbers: HashMap<usize, (usize, usize)> = HashMap::new();
+        let mut assigned = vec![(0, 0); groups.len()];
+        for &row in order {
+            let next_group = numbers.len() + 1;
+            let (group, members) = numbers.entry(groups[row]).or_insert((next_group, 0));
+            *members += 1;
+            assigned[row] = (*group, *members);
+        }
+        Ok(assigned)
+    }
+
+    /// Write each cluster group to `<dir>/cluster_<N>.fa` (aligned, in display
+    /// order) and the group of every sequence to `<dir>/clusters.tsv`,
+    /// creating `dir` if needed and replacing any earlier `cluster_<N>.fa`. Groups are cut and numbered as for
+    /// `:number-seqs`. Returns the number of groups.
+    pub fn write_fasta_by_cluster(
+        &self,
+        dir: &Path,
+        max_distance: Option<f64>,
+    ) -> Result<usize, String> {
+        let numbers = self.cluster_group_numbers(max_distance)?;
+        let order = self.cluster_order.as_deref().unwrap_or_default();
+        let num_groups = numbers.iter().map(|&(group, _)| group).max().unwrap_or(0);
+
+        let mut groups: Vec<Vec<(String, String)>> = vec![Vec::new(); num_groups];
+        let mut tsv = String::from("sequence_id\tcluster\n");
+        for &row in order {
+            let (Some(seq), Some(&(group, _))) =
+                (self.alignment.sequences.get(row), numbers.get(row))
+            else {
+                continue;
+            };
+            groups[group - 1].push((seq.id.clone(), seq.data()));
+            tsv.push_str(&format!("{}\t{group}\n", seq.id));
+        }
+
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        // Drop group files from an earlier, finer cut so the directory
+        // matches clusters.tsv
+        let entries =
+            std::fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let is_group_file = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("cluster_"))
+                .and_then(|rest| rest.strip_suffix(".fa"))
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+            if is_group_file {
+                std::fs::remove_file(entry.path())
+                    .map_err(|e| format!("Failed to remove {}: {e}", entry.path().display()))?;
+            }
+        }
+        for (idx, records) in groups.iter().enumerate() {
+            let path = dir.join(format!("cluster_{}.fa", idx + 1));
+            writer::write_fasta(records, &path)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        }
+        let path = dir.join("clusters.tsv");
+        std::fs::write(&path, tsv)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(num_groups)
+    }
+
+    /// Rename every sequence to its `group<N>_member<M>` ID (see
+    /// [`App::cluster_member_ids`]), carrying its annotations over.
+    /// Returns the number of groups.
+    pub fn number_sequences_by_cluster(
+        &mut self,
+        max_distance: Option<f64>,
+    ) -> Result<usize, String> {
+        let ids = self.cluster_member_ids(max_distance)?;
+        let groups = ids.iter().filter(|id| id.ends_with("_member1")).count();
+
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        // Pins and the reference follow their sequences to the new IDs
+        let pinned = (self.pinned_header_row(), self.pinned_comparison_row());
+        let reference = self.reference_id.as_ref().map(|_| self.reference_row());
+        self.alignment.rename_sequences(&ids);
+        self.pinned_header_id = pinned.0.and_then(|row| ids.get(row).cloned());
+        self.pinned_comparison_id = pinned.1.and_then(|row| ids.get(row).cloned());
+        self.reference_id = reference.and_then(|row| ids.get(row).cloned());
+        self.mark_modified();
+        Ok(groups)
+    }
+
+    /// Physically reorder sequences to match the current cluster display order,
+    /// then clear clustering state. This "bakes in" the cluster order so that
+    /// subsequent index-based operations (like deletion) work correctly without
+    /// needing to recluster.
+    pub fn materialize_cluster_order(&mut self) {
+        if let Some(order) = self.cluster_order.take() {
+            let old_seqs = self.alignment.sequences.clone();
+            self.alignment.sequences = order
+                .into_iter()
+                .filter(|&i| i < old_seqs.len())
+                .map(|i| old_seqs[i].clone())
+                .collect();
+        }
+        self.cluster_tree = None;
+        self.cluster_dendrogram = None;
+        self.collapsed_tree = None;
+        self.tree_width = 0;
+        self.show_tree = false;
+        self.cluster_group_order = None;
+
+        // Refresh collapse groups since sequence indices changed
+        if self.collapse_identical {
+            self.precompute_collapse_groups();
+        }
+    }
+
+    /// Write the clustering dendrogram to `path` in Newick format.
+    ///
+    /// Exports the tree behind the displayed cluster order; errors when the
+    /// order did not come from `:cluster` (none, or `:import-tree`). Returns
+    /// the number of leaves.
+    pub fn write_tree(&self, path: &Path) -> Result<usize, String> {
+        let Some(tree) = &self.cluster_dendrogram else {
+            return Err("No clustering to export (use :cluster first)".to_string());
+        };
+
+        let labels: Vec<&str> = self
+            .alignment
+            .sequences
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect();
+        let newick = tree.to_newick(&labels);
+
+        std::fs::write(path, newick + "\n")
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        Ok(labels.len())
+    }
+
+    /// Build a profile HMM of the alignment with `hmmbuild` and write it to
+    /// `path`.
+    ///
+    /// The alignment is saved to a temporary Stockholm file first, and the
+    /// model is only copied to `path` once hmmbuild succeeds.
+    pub fn write_hmm(&self, path: &Path) -> Result<HmmSummary, String> {
+        let temp_dir = std::env::temp_dir();
+        let pid = std::process::id();
+        let alignment_path = temp_dir.join(format!("aform_hmmbuild_{pid}.sto"));
+        let hmm_path = temp_dir.join(format!("aform_hmmbuild_{pid}.hmm"));
+
+        let result = writer::write_file(&self.alignment, &alignment_path)
+            .map_err(|e| format!("Failed to write temporary alignment: {e}"))
+            .and_then(|()| build_hmm(&alignment_path, &hmm_path).map_err(|e| e.to_string()))
+            .and_then(|summary| {
+                std::fs::copy(&hmm_path, path)
+                    .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                Ok(summary)
+            });
+        let _ = std::fs::remove_file(&alignment_path);
+        let _ = std::fs::remove_file(&hmm_path);
+        result
+    }
+
+    /// Write a Graphviz graph linking sequences closer than `threshold`.
+    ///
+    /// Distances are the Hamming distances used for clustering (see
+    /// [`crate::clustering::compute_distance_matrix`]); each edge carries its
+    /// distance as a `distance` attribute. Every sequence is a node labelled
+    /// with its ID, so unlinked sequences still appear.
+    pub fn write_distance_graph(&self, threshold: f64, path: &Path) -> Result<(), String> {
+        use std::fmt::Write;
+
+        if self.alignment.sequences.is_empty() {
+            return Err("No sequences".to_string());
+        }
+        let seq_bytes = self.snapshot_seq_bytes();
+        let gap_lut = crate::clustering::build_gap_lut(&self.gap_chars);
+        let distances = crate::clustering::compute_distance_matrix(&seq_bytes, &gap_lut);
+
+        let n = seq_bytes.len();
+        let mut dot = String::from("graph G {\n");
+        for (i, seq) in self.alignment.sequences.iter().enumerate() {
+            let label = seq.id.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(dot, "  n{i} [label=\"{label}\"];");
+        }
+        let mut pairs = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)));
+        for &d in &distances {
+            let Some((i, j)) = pairs.next() else { break };
+            if d < threshold {
+                let _ = writeln!(dot, "  n{i} -- n{j} [distance={d}];");
+            }
+        }
+        dot.push_str("}\n");
+
+        std::fs::write(path, dot).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Write `num_cols` screen columns from `viewport_col` as a fenced
+    /// Markdown code block.
+    ///
+    /// The block starts with a ruler line, then one line per displayed
+    /// sequence (respecting clustering, collapsing and short IDs) and
+    /// `#=GC SS_cons` when present. Columns are hidden and zoomed as on
+    /// screen; no colors are kept.
+    pub fn write_markdown_view(
+        &self,
+        path: &Path,
+        viewport_col: usize,
+        num_cols: usize,
+    ) -> Result<(), String> {
+        use std::fmt::Write;
+
+        let cols = self.screen_columns(viewport_col, num_cols);
+        if cols.is_empty() {
+            return Err("No columns in view".to_string());
+        }
+        let slice = |chars: &[char]| -> String {
+            cols.iter()
+                .map(|&col| chars.get(col).copied().unwrap_or(' '))
+                .collect()
+        };
+
+        let mut rows: Vec<(String, String)> = (0..self.visible_sequence_count())
+            .map(|row| {
+                let seq = &self.alignment.sequences[self.display_to_actual_row(row)];
+                let id = if self.show_short_ids {
+                    short_id(&seq.id)
+                } else {
+                    &seq.id
+                };
+                (id.to_string(), slice(seq.chars()))
+            })
+            .collect();
+        if let Some(ss) = self.alignment.ss_cons() {
+            let ss: Vec<char> = ss.chars().collect();
+            rows.push(("#=GC SS_cons".to_string(), slice(&ss)));
+        }
+        let id_width = rows.iter().map(|(id, _)| id.len()).max().unwrap_or(0) + 2;
+
+        let mut md = String::from("```text\n");
+        let ruler = format!("{:id_width$}{}", "", self.ruler_label_line(&cols));
+        let _ = writeln!(md, "{}", ruler.trim_end());
+        for (id, data) in rows {
+            let _ = writeln!(md, "{id:id_width$}{data}");
+        }
+        md.push_str("```\n");
+
+        std::fs::write(path, md).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Order sequences by the leaf order of a Newick tree file.
+    ///
+    /// Leaves are matched to sequence IDs; sequences missing from the tree keep
+    /// their relative order after the matched ones. Unmatched leaves or sequences
+    /// are reported as a warning in the status bar.
+    pub fn order_by_tree(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let leaves = crate::external::newick::parse_newick(&text)
+            .map_err(|e| format!("Newick parse error: {e}"))?;
+
+        // First occurrence wins for duplicate IDs.
+        let mut index_of: HashMap<&str, usize> = HashMap::new();
+        for (idx, seq) in self.alignment.sequences.iter().enumerate() {
+            index_of.entry(seq.id.as_str()).or_insert(idx);
+        }
+
+        let n = self.alignment.num_sequences();
+        let mut placed = vec![false; n];
+        let mut order = Vec::with_capacity(n);
+        let mut unmatched = 0;
+        for leaf in &leaves {
+            match index_of.get(leaf.as_str()) {
+                Some(&idx) if !placed[idx] => {
+                    placed[idx] = true;
+                    order.push(idx);
+                }
+                _ => unmatched += 1,
+            }
+        }
+        if order.is_empty() {
+            return Err("No tree leaves match sequence IDs".to_string());
+        }
+        let missing = n - order.len();
+        order.extend((0..n).filter(|&idx| !placed[idx]));
+
+        // With collapsed duplicates, groups follow their first member in the tree.
+        self.cluster_group_order = if self.collapse_groups.is_empty() {
+            None
+        } else {
+            let mut group_of = vec![0usize; n];
+            for (group_idx, (_, members)) in self.collapse_groups.iter().enumerate() {
+                for &member in members {
+                    group_of[member] = group_idx;
+                }
+            }
+            let mut seen = vec![false; self.collapse_groups.len()];
+            Some(
+                order
+                    .iter()
+                    .map(|&idx| group_of[idx])
+                    .filter(|&g| !std::mem::replace(&mut seen[g], true))
+                    .collect(),
+            )
+        };
+        self.cluster_order = Some(order);
+        // The imported tree is not drawn.
+        self.cluster_tree = None;
+        self.cluster_dendrogram = None;
+        self.collapsed_tree = None;
+        self.tree_width = 0;
+        self.show_tree = false;
+        self.clamp_cursor();
+
+        let mut warnings = Vec::new();
+        if unmatched > 0 {
+            warnings.push(format!("{unmatched} tree leaves not in alignment"));
+        }
+        if missing > 0 {
+            warnings.push(format!("{missing} sequences not in tree (placed last)"));
+        }
+        if warnings.is_empty() {
+            self.set_status(format!("Ordered sequences by {}", path.display()));
+        } else {
+            self.set_status(format!(
+                "Ordered sequences by {} (warning: {})",
+                path.display(),
+                warnings.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Add a `#=GC <tag>` annotation marking columns covered by BED/GFF3 features.
+    ///
+    /// Feature coordinates are reference positions, numbered by the non-gap
+    /// characters of the RF annotation. Covered columns get `X`, the rest `.`;
+    /// an existing annotation with the same tag is replaced. Returns the number
+    /// of columns marked.
+    pub fn import_features_from_file(&mut self, path: &Path, tag: &str) -> Result<usize, String> {
+        use crate::external::features::{FeatureError, FeatureFormat, parse_features};
+        use crate::stockholm::ColumnAnnotation;
+
+        let format = FeatureFormat::from_path(path)
+            .ok_or_else(|| FeatureError::UnknownFormat.to_string())?;
+        let rf = self
+            .alignment
+            .rf()
+            .ok_or("No RF annotation to map feature coordinates")?;
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let intervals =
+            parse_features(&text, format).map_err(|e| format!("{}: {e}", path.display()))?;
+
+        // Alignment column of each reference position
+        let ref_cols: Vec<usize> = rf
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| !self.gap_chars.contains(c))
+            .map(|(col, _)| col)
+            .collect();
+        let mut marked = vec![false; self.alignment.width()];
+        for (start, end) in intervals {
+            for &col in ref_cols.iter().take(end).skip(start) {
+                marked[col] = true;
+            }
+        }
+        let count = marked.iter().filter(|&&m| m).count();
+        let data: String = marked.iter().map(|&m| if m { 'X' } else { '.' }).collect();
+
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        match self
+            .alignment
+            .column_annotations
+            .iter_mut()
+            .find(|a| a.tag == tag)
+        {
+            Some(existing) => existing.data = data,
+            None => self.alignment.column_annotations.push(ColumnAnnotation {
+                tag: tag.to_string(),
+                data,
+            }),
+        }
+        self.mark_modified();
+        Ok(count)
+    }
+
+    /// Helix number of every SS_cons column, for `#=GC HELIX`.
+    ///
+    /// Paired columns get their 1-based helix number as `1`-`9`, then `A`-`Z`
+    /// and `a`-`z` (`*` beyond helix 61); unpaired columns are `.`. Returns
+    /// `None` without SS_cons.
+    pub fn generate_helix_annotation(&self) -> Option<String> {
+        const LABELS: &[u8] = b"123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+        self.alignment.ss_cons()?;
+        let data = (0..self.alignment.width())
+            .map(|col| match self.structure_cache.get_helix(col) {
+                Some(helix) => LABELS.get(helix).map_or('*', |&b| b as char),
+                None => '.',
+            })
+            .collect();
+        Some(data)
+    }
+
+    /// Store [`App::generate_helix_annotation`] as `#=GC HELIX` (`:annotate-helices`).
+    /// Returns the number of helices.
+    pub fn annotate_helices(&mut self) -> Result<usize, String> {
+        use crate::stockholm::ColumnAnnotation;
+
+        let data = self
+            .generate_helix_annotation()
+            .ok_or("No SS_cons annotation")?;
+        let helices = self
+            .structure_cache
+            .pairs()
+            .iter()
+            .map(|pair| pair.helix_id)
+            .max()
+            .map_or(0, |max| max + 1);
+
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        match self
+            .alignment
+            .column_annotations
+            .iter_mut()
+            .find(|a| a.tag == "HELIX")
+        {
+            Some(existing) => existing.data = data,
+            None => self.alignment.column_annotations.push(ColumnAnnotation {
+                tag: "HELIX".to_string(),
+                data,
+            }),
+        }
+        self.mark_modified();
+        Ok(helices)
+    }
+
+    /// Read coaxial stacks (two 1-based columns per line) into `#=GC COAXIAL`.
+    /// Returns the number of stacks.
+    pub fn load_coaxial_stacking(&mut self, path: &Path) -> Result<usize, String> {
+        use crate::external::coaxial::{COAXIAL_TAG, encode_coaxial, parse_coaxial};
+        use crate::stockholm::ColumnAnnotation;
+
+        let width = self.alignment.width();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let pairs = parse_coaxial(&text, width).map_err(|e| format!("{}: {e}", path.display()))?;
+        let data = encode_coaxial(&pairs, width);
+
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        match self
+            .alignment
+            .column_annotations
+            .iter_mut()
+            .find(|a| a.tag == COAXIAL_TAG)
+        {
+            Some(existing) => existing.data = data,
+            None => self.alignment.column_annotations.push(ColumnAnnotation {
+                tag: COAXIAL_TAG.to_string(),
+                data,
+            }),
+        }
+        self.mark_modified();
+        Ok(pairs.len())
+    }
+
+    /// Read anchors (RF position and label per line) into `#=GC ANCHORS`,
+    /// one label character per anchor column. Positions count RF match
+    /// columns, or alignment columns when there is no RF. Returns the
+    /// number of anchors.
+    pub fn import_anchors(&mut self, path: &Path) -> Result<usize, String> {
+        use crate::external::anchors::{ANCHORS_TAG, encode_anchors, parse_anchor_file};
+        use crate::stockholm::ColumnAnnotation;
+
+        let anchors = parse_anchor_file(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let width = self.alignment.width();
+        let match_cols = self.rf_match_column_indices();
+        let positions = match_cols.as_ref().map_or(width, Vec::len);
+        let columns = anchors
+            .into_iter()
+            .map(|(pos, label)| {
+                let col = match &match_cols {
+                    Some(cols) => cols.get(pos - 1).copied(),
+                    None => (pos <= width).then(|| pos - 1),
+                };
+                col.map(|col| (col, label))
+                    .ok_or_else(|| format!("Anchor position {pos} is outside 1-{positions}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let data = encode_anchors(&columns, width);
+
+        self.history
+            .save(&self.alignment, self.cursor_row, self.cursor_col);
+        match self
+            .alignment
+            .column_annotations
+            .iter_mut()
+            .find(|a| a.tag == ANCHORS_TAG)
+        {
+            Some(existing) => existing.data = data,
+            None => self.alignment.column_annotations.push(ColumnAnnotation {
+                tag: ANCHORS_TAG.to_string(),
+                data,
+            }),
+        }
+        self.mark_modified();
+        Ok(columns.len())
+    }
+
+    /// Alignment columns of the RF match states, in order, or None without
+    /// RF (see [`App::rf_match_columns`]).
+    pub fn rf_match_column_indices(&self) -> Option<Vec<usize>> {
+        let match_cols = self.rf_match_columns()?;
+        Some(
+            match_cols
+                .iter()
+                .enumerate()
+                .filter(|&(_, &is_match)| is_match)
+                .map(|(col, _)| col)
+                .collect(),
+        )
+    }
+
+    /// Column coaxially stacked with `col` according to `#=GC COAXIAL`.
+    pub fn coaxial_partner(&self, col: usize) -> Option<usize> {
+        use crate::external::coaxial::{COAXIAL_TAG, coaxial_partner};
+
+        let ann = self
+            .alignment
+            .column_annotations
+            .iter()
+            .find(|a| a.tag == COAXIAL_TAG)?;
+        coaxial_partner(&ann.data, col)
+    }
+
+    /// Disable clustering and restore original order.
+    pub fn uncluster(&mut self) {
+        self.cluster_order = None;
+        self.cluster_tree = None;
+        self.cluster_dendrogram = None;
+        self.collapsed_tree = None;
+        self.tree_width = 0;
+        self.show_tree = false;
+        self.cluster_group_order = None;
+    }
+
+    /// Toggle dendrogram tree visibility.
+    pub fn toggle_tree(&mut self) {
+        if self.cluster_tree.is_some() {
+            self.show_tree = !self.show_tree;
+        } else {
+            self.status_message = Some("No tree available. Run :cluster first.".to_string());
+        }
+    }
+
+    // === Collapse identical sequences ===
+
+    /// Pre-compute collapse groups by grouping sequences with identical content.
+    /// Called during load since sequences don't change during viewing.
+    pub fn precompute_collapse_groups(&mut self) {
+        self.collapse_groups.clear();
+
+        if self.alignment.sequences.is_empty() {
+            return;
+        }
+
+        // Group by sequence content (chars as String for hashing)
+        let mut content_map: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, seq) in self.alignment.sequences.iter().enumerate() {
+            content_map.entry(seq.data()).or_default().push(idx);
+        }
+
+        // Build groups preserving original order (first occurrence is representative)
+        let mut seen = std::collections::HashSet::new();
+        for (idx, seq) in self.alignment.sequences.iter().enumerate() {
+            let content = seq.data();
+            if seen.insert(content.clone()) {
+                let indices = content_map.remove(&content).unwrap();
+                self.collapse_groups.push((idx, indices));
+            }
+        }
+    }
+
+    /// Get collapse count for a display row (1 if not collapsed or unique).
+    pub fn get_collapse_count(&self, display_row: usize) -> usize {
+        if self.collapse_identical && !self.collapse_groups.is_empty() {
+            // When clustering is also enabled, use group_order to find correct group
+            let group_idx = if let Some(ref group_order) = self.cluster_group_order {
+                group_order.get(display_row).copied().unwrap_or(display_row)
+            } else {
+                display_row
+            };
+
+            if group_idx < self.collapse_groups.len() {
+                self.collapse_groups[group_idx].1.len()
+            } else {
+                1
+            }
+        } else {
+            1
+        }
+    }
+
+    /// Get the maximum collapse count across all groups.
+    pub fn max_collapse_count(&self) -> usize {
+        if self.collapse_identical {
+            self.collapse_groups
+                .iter()
+                .map(|(_, g)| g.len())
+                .max()
+                .unwrap_or(1)
+        } else {
+            1
+        }
+    }
+
+    /// Whether group sizes are shown as an `N×` prefix in the tree column
+    /// (collapsed view with a visible tree) instead of after the IDs.
+    pub fn tree_shows_group_counts(&self) -> bool {
+        self.show_tree && self.cluster_tree.is_some() && self.max_collapse_count() > 1
+    }
+
+    /// Width of the `N×` group-size prefix in the tree column (0 when not shown).
+    pub fn tree_count_width(&self) -> usize {
+        if self.tree_shows_group_counts() {
+            self.max_collapse_count().to_string().len() + 1
+        } else {
+            0
+        }
+    }
+
+    /// Width of the tree column plus its separator (0 when the tree is hidden).
+    pub fn tree_display_width(&self) -> usize {
+        if self.show_tree && self.cluster_tree.is_some() {
+            self.tree_width + self.tree_count_width() + 1
+        } else {
+            0
+        }
+    }
+
+    /// Toggle collapse identical sequences.
+    pub fn toggle_collapse_identical(&mut self) {
+        self.collapse_identical = !self.collapse_identical;
+        // Groups are pre-computed during load, just flip the flag
+
+        // Clamp cursor to visible range
+        if self.cursor_row >= self.visible_sequence_count() {
+            self.cursor_row = self.visible_sequence_count().saturating_sub(1);
+        }
+
+        let msg = if self.collapse_identical {
+            format!(
+                "Collapsed {} sequences into {} groups",
+                self.alignment.num_sequences(),
+                self.collapse_groups.len()
+            )
+        } else {
+            "Collapse disabled".to_string()
+        };
+        self.status_message = Some(msg);
+    }
+
+    // === Sequence type detection ===
+
+    /// Detect sequence type from alignment content.
+    pub fn detect_sequence_type(&mut self) {
+        self.sequence_type = crate::color::detect_sequence_type(&self.alignment, &self.gap_chars);
+    }
+
+    /// Auto-configure display settings based on detected sequence type.
+    /// Picks a color scheme with [`App::auto_color`] unless one was chosen
+    /// explicitly; for protein also enables the consensus and conservation bars.
+    pub fn auto_configure_display(&mut self) {
+        if !self.color_scheme_explicit {
+            self.auto_color();
+        }
+        if self.sequence_type == crate::stockholm::SequenceType::Protein {
+            self.show_consensus = true;
+            self.show_conservation_bar = true;
+        }
+    }
+
+    /// Choose a color scheme for the sequence type (`:auto-color`).
+    ///
+    /// RNA/DNA with SS_cons gets structure coloring, other alignments are
+    /// colored by residue identity (amino acid colors for protein).
+    pub fn auto_color(&mut self) {
+        self.color_scheme = if self.sequence_type != crate::stockholm::SequenceType::Protein
+            && self.alignment.ss_cons().is_some()
+        {
+            ColorScheme::Structure
+        } else {
+            ColorScheme::Base
+        };
+    }
+
+    // === Gap column methods ===
+
+    /// Precompute visible columns (call after loading alignment or toggling hide_gap_columns).
+    ///
+    /// Also applies the `:col-filter` annotation filter and `:slice` range when set.
+    pub fn precompute_visible_columns(&mut self) {
+        if !self.hide_gap_columns && self.column_filter.is_none() && self.column_slice.is_none() {
+            self.visible_columns.clear();
+            return;
+        }
+        let filter_data: Option<Vec<char>> = self.column_filter.as_ref().map(|filter| {
+            self.alignment
+                .column_annotations
+                .iter()
+                .find(|a| a.tag == filter.tag)
+                .map(|a| a.data.chars().collect())
+                .unwrap_or_default()
+        });
+        let (start, end) = self.column_slice.unwrap_or((0, self.alignment.width()));
+        self.visible_columns = (start..end.min(self.alignment.width()))
+            .filter(|&col| {
+                !self.hide_gap_columns || !self.alignment.is_empty_column(col, &self.gap_chars)
+            })
+            .filter(|&col| match (&self.column_filter, &filter_data) {
+                (Some(filter), Some(data)) => data
+                    .get(col)
+                    .is_some_and(|c| filter.regex.is_match(c.encode_utf8(&mut [0; 4]))),
+                _ => true,
+            })
+            .collect();
+    }
+
+    /// Whether some columns are hidden (by `:hidegaps`, `:col-filter` or `:slice`).
+    pub(crate) fn columns_hidden(&self) -> bool {
+        (self.hide_gap_columns || self.column_filter.is_some() || self.column_slice.is_some())
+            && !self.visible_columns.is_empty()
+    }
+
+    /// Show only columns whose `#=GC <tag>` character matches the regex `pattern`.
+    ///
+    /// Returns the number of columns left visible.
+    pub fn set_column_filter(&mut self, tag: &str, pattern: &str) -> Result<usize, String> {
+        if !self
+            .alignment
+            .column_annotations
+            .iter()
+            .any(|a| a.tag == tag)
+        {
+            return Err(format!("No #=GC {tag} annotation"));
+        }
+        let regex = regex::Regex::new(pattern).map_err(|e| format!("Invalid regex: {e}"))?;
+        let previous = self.column_filter.replace(ColumnFilter {
+            tag: tag.to_string(),
+            regex,
+        });
+        self.precompute_visible_columns();
+        if self.visible_columns.is_empty() {
+            self.column_filter = previous;
+            self.precompute_visible_columns();
+            return Err(format!("No columns where {tag} matches '{pattern}'"));
+        }
+        self.clamp_cursor();
+        Ok(self.visible_columns.len())
+    }
+
+    /// Remove the `:col-filter` column filter.
+    pub fn clear_column_filter(&mut self) {
+        self.column_filter = None;
+        self.precompute_visible_columns();
+        self.clamp_cursor();
+    }
+
+    /// Show only columns `[start, end)`, keeping the cursor inside the slice.
+    ///
+    /// Returns the number of columns left visible.
+    pub fn set_column_slice(&mut self, start: usize, end: usize) -> Result<usize, String> {
+        let width = self.alignment.width();
+        if start >= end || end > width {
+            return Err(format!(
+                "Invalid slice {}-{} (alignment has {width} columns)",
+                start + 1,
+                end
+            ));
+        }
+        let previous = self.column_slice.replace((start, end));
+        self.precompute_visible_columns();
+        if self.visible_columns.is_empty() {
+            self.column_slice = previous;
+            self.precompute_visible_columns();
+            return Err(format!("No visible columns in {}-{end}", start + 1));
+        }
+        self.cursor_col = self.cursor_col.clamp(start, end - 1);
+        self.clamp_cursor();
+        // viewport_col is in display column space, which now starts at the slice
+        self.viewport_col = 0;
+        Ok(self.visible_columns.len())
+    }
+
+    /// Remove the `:slice` column range.
+    pub fn clear_column_slice(&mut self) {
+        if let Some((start, _)) = self.column_slice.take() {
+            self.precompute_visible_columns();
+            self.clamp_cursor();
+            self.viewport_col = if self.columns_hidden() {
+                self.actual_to_display_col(start).unwrap_or(0)
+            } else {
+                start
+            };
+        }
+    }
+
+    /// Drop view state tied to column indices (`:slice`, `:set-color-at`,
+    /// `:shade`), for a different alignment or a realignment.
+    pub(crate) fn reset_column_state(&mut self) {
+        self.column_slice = None;
+        self.column_color_overrides.clear();
+        self.shaded_regions.clear();
+        self.precompute_visible_columns();
+    }
+
+    /// Move view state tied to column indices (`:slice`, `:set-color-at`,
+    /// `:shade`) along with an edit that moved columns: `map` gives each old
+    /// column's new index, or `None` for a removed column.
+    pub(crate) fn remap_column_state(&mut self, map: impl Fn(usize) -> Option<usize>) {
+        self.column_color_overrides = std::mem::take(&mut self.column_color_overrides)
+            .into_iter()
+            .filter_map(|(col, color)| Some((map(col)?, color)))
+            .collect();
+        self.shaded_regions = std::mem::take(&mut self.shaded_regions)
+            .into_iter()
+            .filter_map(|(start, end, color)| {
+                let cols: Vec<usize> = (start..=end).filter_map(&map).collect();
+                Some((*cols.iter().min()?, *cols.iter().max()?, color))
+            })
+            .collect();
+        if let Some((start, end)) = self.column_slice {
+            let cols: Vec<usize> = (start..end).filter_map(&map).collect();
+            let first = cols.iter().min().copied();
+            let last = cols.iter().max().copied();
+            self.column_slice = first.zip(last).map(|(first, last)| (first, last + 1));
+            self.precompute_visible_columns();
+        }
+    }
+
+    /// Trim view state tied to column indices to the current width, after
+    /// the alignment was swapped wholesale (undo/redo).
+    pub(crate) fn clamp_column_state(&mut self) {
+        let width = self.alignment.width();
+        self.remap_column_state(|col| (col < width).then_some(col));
+        self.precompute_visible_columns();
+        self.clamp_cursor();
+    }
+
+    /// Column numbered 1 on the ruler: the slice start with `slice_relative_ruler`.
+    pub(crate) fn ruler_origin(&self) -> usize {
+        match self.column_slice {
+            Some((start, _)) if self.slice_relative_ruler => start,
+            _ => 0,
+        }
+    }
+
+    /// Ruler number (1-based) of each column in `cols`.
+    ///
+    /// In [`RulerMode::Reference`] columns are numbered by RF match position
+    /// and insert columns get `None`; match columns are uppercase RF residues,
+    /// or any letter when RF has no uppercase (HMMER's `x` markers).
+    pub(crate) fn ruler_positions(&self, cols: &[usize]) -> Vec<Option<usize>> {
+        let rf = self
+            .alignment
+            .rf()
+            .filter(|_| self.ruler_mode == RulerMode::Reference);
+        let Some(rf) = rf else {
+            let origin = self.ruler_origin();
+            return cols
+                .iter()
+                .map(|&col| Some((col + 1).saturating_sub(origin)))
+                .collect();
+        };
+
+        let has_uppercase = rf.chars().any(|c| c.is_ascii_uppercase());
+        let mut position = 0;
+        let numbering: Vec<Option<usize>> = rf
+            .chars()
+            .map(|c| {
+                let is_match = if has_uppercase {
+                    c.is_ascii_uppercase()
+                } else {
+                    c.is_ascii_alphabetic()
+                };
+                is_match.then(|| {
+                    position += 1;
+                    position
+                })
+            })
+            .collect();
+        cols.iter()
+            .map(|&col| numbering.get(col).copied().flatten())
+            .collect()
+    }
+
+    /// Ruler number line for `cols`: every multiple of 10 ends under its
+    /// column, as on the on-screen ruler.
+    pub(crate) fn ruler_label_line(&self, cols: &[usize]) -> String {
+        let mut line = vec![' '; cols.len()];
+        for (local, pos) in self.ruler_positions(cols).into_iter().enumerate() {
+            if let Some(pos) = pos.filter(|pos| pos.is_multiple_of(10)) {
+                let label = pos.to_string();
+                let start = (local + 1).saturating_sub(label.len());
+                for (slot, ch) in line[start..].iter_mut().zip(label.chars()) {
+                    *slot = ch;
+                }
+            }
+        }
+        line.into_iter().collect()
+    }
+
+    /// Position of the reference sequence at each column in `cols`: the
+    /// number of its residues up to and including the column, offset by the
+    /// `/start-end` suffix of its ID when present (counting down for a
+    /// reverse-strand `end < start`). Columns before its first residue get
+    /// `None`.
+    pub fn reference_coordinates(&self, cols: &[usize]) -> Vec<Option<usize>> {
+        let Some(reference) = self.alignment.sequences.get(self.reference_row()) else {
+            return vec![None; cols.len()];
+        };
+        let mut count = 0;
+        let counts: Vec<usize> = reference
+            .chars()
+            .iter()
+            .map(|c| {
+                if !self.gap_chars.contains(c) {
+                    count += 1;
+                }
+                count
+            })
+            .collect();
+        let range = id_range(&reference.id);
+        cols.iter()
+            .map(|&col| {
+                let n = counts.get(col).copied().filter(|&n| n > 0)?;
+                Some(match range {
+                    Some((_, start, end)) if end < start => start.saturating_sub(n - 1),
+                    Some((_, start, _)) => start + n - 1,
+                    None => n,
+                })
+            })
+            .collect()
+    }
+
+    /// Map display column index to actual column index.
+    pub fn display_to_actual_col(&self, display_col: usize) -> usize {
+        if self.columns_hidden() {
+            self.visible_columns
+                .get(display_col)
+                .copied()
+                .unwrap_or(display_col)
+        } else {
+            display_col
+        }
+    }
+
+    /// Map actual column index to display column index (returns None if hidden).
+    pub fn actual_to_display_col(&self, actual_col: usize) -> Option<usize> {
+        if self.columns_hidden() {
+            self.visible_columns.iter().position(|&c| c == actual_col)
+        } else {
+            Some(actual_col)
+        }
+    }
+
+    /// Get number of visible columns.
+    pub fn visible_column_count(&self) -> usize {
+        if self.columns_hidden() {
+            self.visible_columns.len()
+        } else {
+            self.alignment.width()
+        }
+    }
+
+    /// Actual columns drawn from display column `viewport_col`, at most
+    /// `count` of them. Zoomed out, every `column_stride`-th column is
+    /// sampled in step with the cursor so it stays visible.
+    pub(crate) fn screen_columns(&self, viewport_col: usize, count: usize) -> Vec<usize> {
+        let stride = self.column_stride.max(1);
+        let phase = |first: usize, cursor: usize| {
+            (cursor as isize - first as isize).rem_euclid(stride as isize) as usize
+        };
+        if self.columns_hidden() {
+            let cursor_display = self
+                .actual_to_display_col(self.cursor_col)
+                .unwrap_or(viewport_col);
+            self.visible_columns
+                .iter()
+                .skip(viewport_col + phase(viewport_col, cursor_display))
+                .step_by(stride)
+                .take(count)
+                .copied()
+                .collect()
+        } else {
+            (viewport_col + phase(viewport_col, self.cursor_col)..self.alignment.width())
+                .step_by(stride)
+                .take(count)
+                .collect()
+        }
+    }
+}
+
+/// Parse the optional tree cut distance of `:number-seqs`.
+fn parse_cut_distance(arg: Option<&&str>) -> Result<Option<f64>, String> {
+    arg.map(|arg| {
+        arg.parse::<f64>()
+            .ok()
+            .filter(|d| *d >= 0.0)
+            .ok_or_else(|| format!("Invalid distance: {arg}"))
+    })
+    .transpose()
+}
+
+/// Parse a 1-based `N-M` column range.
+fn parse_column_range(range: &str) -> Option<(usize, usize)> {
+    let (first, last) = range.split_once('-')?;
+    Some((first.parse().ok()?, last.parse().ok()?))
+}
+
+/// Modification time of `path`, if the filesystem reports one.
+fn modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Split a `name/start-end` ID into its name and coordinates.
+fn id_range(id: &str) -> Option<(&str, usize, usize)> {
+    let (name, range) = id.rsplit_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    Some((name, start.parse().ok()?, end.parse().ok()?))
+}
+
+/// Strand of sequence `id` from its `#=GS STRAND` annotation (`+`/`-`).
+pub fn sequence_strand(alignment: &Alignment, id: &str) -> Option<char> {
+    match alignment.get_sequence_annotation(id, STRAND_TAG)? {
+        "+" => Some('+'),
+        "-" => Some('-'),
+        _ => None,
+    }
+}
+
+/// Swap the coordinates of a `name/start-end` ID, for the other strand; IDs
+/// without a coordinate suffix are returned unchanged.
+fn swap_id_range(id: &str) -> String {
+    match id_range(id) {
+        Some((name, start, end)) => format!("{name}/{end}-{start}"),
+        None => id.to_string(),
+    }
+}
+
+/// Reverse complement of nucleotide `residues`, keeping case and complementing
+/// IUPAC ambiguity codes; A pairs with U when `rna`, else T.
+fn reverse_complement(residues: &str, rna: bool) -> String {
+    residues
+        .chars()
+        .rev()
+        .map(|c| {
+            let complement = match c.to_ascii_uppercase() {
+                'A' if rna => 'U',
+                'A' => 'T',
+                'U' | 'T' => 'A',
+                'G' => 'C',
+                'C' => 'G',
+                'R' => 'Y',
+                'Y' => 'R',
+                'K' => 'M',
+                'M' => 'K',
+                'B' => 'V',
+                'V' => 'B',
+                'D' => 'H',
+                'H' => 'D',
+                other => other,
+            };
+            if c.is_ascii_lowercase() {
+                complement.to_ascii_lowercase()
+            } else {
+                complement
+            }
+        })
+        .collect()
+}
+
+/// Rewrite the end of a `name/start-end` ID for a sequence of `len` residues.
+///
+/// Reverse-strand coordinates (`end < start`) count down from `start`; IDs
+/// without a coordinate suffix are returned unchanged.
+fn ungapped_id(id: &str, len: usize) -> String {
+    region_id(id, 0, len)
+}
+
+/// Rewrite a `name/start-end` ID for `len` residues that follow the first
+/// `skipped` residues of the sequence.
+///
+/// Reverse-strand coordinates (`end < start`) count down from `start`; IDs
+/// without a coordinate suffix are returned unchanged.
+fn region_id(id: &str, skipped: usize, len: usize) -> String {
+    match id_range(id) {
+        Some((name, start, end)) if end < start => {
+            let first = start.saturating_sub(skipped);
+            format!("{name}/{first}-{}", first.saturating_sub(len - 1))
+        }
+        Some((name, start, _)) => {
+            let first = start + skipped;
+            format!("{name}/{first}-{}", first + len - 1)
+        }
+        None => id.to_string(),
+    }
+}
+
+/// Whether an SS_cons character satisfies a `:/s` motif character.
+fn structure_chars_match(ss: char, motif: char) -> bool {
+    const OPEN: &[char] = &['<', '(', '[', '{'];
+    const CLOSE: &[char] = &['>', ')', ']', '}'];
+    motif == '.'
+        || ss == motif
+        || (OPEN.contains(&ss) && OPEN.contains(&motif))
+        || (CLOSE.contains(&ss) && CLOSE.contains(&motif))
+}
+
+/// Read all alignments from a Stockholm (optionally gzipped) or Vienna file.
+fn read_alignments(path: &Path) -> Result<Vec<Alignment>, String> {
+    use crate::stockholm::vienna;
+
+    let alignments = if vienna::is_vienna_path(path) {
+        vienna::parse_vienna_file(path)
+            .map(|alignment| vec![alignment])
+            .map_err(|e| format!("Failed to parse file: {e}"))?
+    } else {
+        crate::stockholm::parser::parse_all_file(path)
+            .map_err(|e| format!("Failed to parse file: {e}"))?
+    };
+
+    if alignments.is_empty() {
+        return Err("No alignments found in file".to_string());
+    }
+    Ok(alignments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const MULTI: &str = "# STOCKHOLM 1.0\n#=GF ID first\nseqA ACGU\nseqB ACGU\n//\n\
+                         # STOCKHOLM 1.0\n#=GF ID second\nseqC GGGCCC\nseqD GGG..C\n//\n";
+
+    fn write_temp(name: &str, contents: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("aform_{}_{}.stk", name, std::process::id()));
+        let mut f = std::fs::File::create(&path).unwrap();
+        f.write_all(contents.as_bytes()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_multi_alignment() {
+        let path = write_temp("load", MULTI);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(app.alignments.len(), 2);
+        assert_eq!(app.current_alignment, 0);
+        assert_eq!(app.alignment.get_file_annotation("ID"), Some("first"));
+        assert_eq!(app.alignment.width(), 4);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_select_alignment_switches() {
+        let path = write_temp("select", MULTI);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.select_alignment(1);
+        assert_eq!(app.current_alignment, 1);
+        assert_eq!(app.alignment.get_file_annotation("ID"), Some("second"));
+        assert_eq!(app.alignment.width(), 6);
+
+        // Out-of-range selection is rejected, leaving state unchanged.
+        app.select_alignment(99);
+        assert_eq!(app.current_alignment, 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_picker_navigation_and_confirm() {
+        let path = write_temp("picker", MULTI);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.open_msa_picker();
+        assert!(app.show_msa_picker);
+        assert_eq!(app.msa_picker_selection, 0);
+
+        app.msa_picker_move(1);
+        assert_eq!(app.msa_picker_selection, 1);
+        // Clamped at the end.
+        app.msa_picker_move(5);
+        assert_eq!(app.msa_picker_selection, 1);
+
+        app.msa_picker_confirm();
+        assert!(!app.show_msa_picker);
+        assert_eq!(app.current_alignment, 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_extract_hairpin_loops() {
+        let path = write_temp(
+            "loops",
+            "# STOCKHOLM 1.0\nseqA GGAAACCAGCUUCGGCU\nseqB GGAAACCAGC-UCGGCU\n#=GC SS_cons <<...>>.<<....>>.\n//\n",
+        );
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let loops = app.extract_hairpin_loops();
+        assert_eq!(
+            loops,
+            vec![(1, 5, "AAA".to_string()), (9, 14, "UUCG".to_string())]
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_order_by_tree() {
+        let path = write_temp("tree_aln", MULTI);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let mut tree_path = std::env::temp_dir();
+        tree_path.push(format!("aform_tree_{}.nwk", std::process::id()));
+        std::fs::write(&tree_path, "((seqB:1,other:1):1,seqA:2);\n").unwrap();
+
+        app.order_by_tree(&tree_path).unwrap();
+        assert_eq!(app.cluster_order, Some(vec![1, 0]));
+        assert_eq!(app.display_to_actual_row(0), 1);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("1 tree leaves")
+        );
+
+        // An imported order has no dendrogram to export, and keeps its order
+        let out = tree_path.with_extension("out.nwk").display().to_string();
+        app.execute_file_command(&["write-tree", &out], &format!("write-tree {out}"));
+        assert!(!Path::new(&out).exists());
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("No clustering to export")
+        );
+        assert_eq!(app.cluster_order, Some(vec![1, 0]));
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&tree_path);
+    }
+
+    #[test]
+    fn test_write_tree_matches_clustering() {
+        let contents = "# STOCKHOLM 1.0\ns1 AAAA\ns2 CCCC\ns3 AAAA\n//\n";
+        let path = write_temp("write_tree", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.toggle_collapse_identical();
+
+        let mut tree_path = std::env::temp_dir();
+        tree_path.push(format!("aform_write_tree_{}.nwk", std::process::id()));
+        assert!(app.write_tree(&tree_path).is_err());
+        assert!(!tree_path.exists());
+
+        app.cluster_sequences();
+        assert_eq!(app.write_tree(&tree_path), Ok(3));
+        // The collapsed duplicates share a leaf, as in the displayed tree
+        let newick = std::fs::read_to_string(&tree_path).unwrap();
+        assert!(newick.contains("(s1:0,s3:0):2"), "{newick}");
+        assert!(newick.ends_with(";\n"));
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&tree_path);
+    }
+
+    #[test]
+    fn test_randomize_sequence_order_is_deterministic() {
+        let contents = "# STOCKHOLM 1.0\ns1 A\ns2 C\ns3 G\ns4 U\ns5 A\ns6 C\n//\n";
+        let path = write_temp("randomize", contents);
+        let ids = |app: &App| -> Vec<String> {
+            app.alignment
+                .sequences
+                .iter()
+                .map(|s| s.id.clone())
+                .collect()
+        };
+
+        let mut a = App::new();
+        a.load_file(&path).unwrap();
+        let mut b = App::new();
+        b.load_file(&path).unwrap();
+        let original = ids(&a);
+
+        assert_eq!(a.randomize_sequence_order(Some(42)), 42);
+        b.randomize_sequence_order(Some(42));
+        assert_eq!(ids(&a), ids(&b));
+
+        let mut shuffled = ids(&a);
+        shuffled.sort();
+        assert_eq!(shuffled, original);
+
+        // Undo restores the file order.
+        a.undo();
+        assert_eq!(ids(&a), original);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_pin_header() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\ns3 ACGC\n//\n";
+        let path = write_temp("pin_header", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.cursor_row = 2;
+        app.execute_display_command(&["pin-header"]);
+        assert_eq!(app.pinned_header_row(), Some(2));
+        assert_eq!(app.pinned_header_display_row(), Some(2));
+
+        // The header follows its sequence when the display order changes
+        app.cluster_order = Some(vec![2, 0, 1]);
+        assert_eq!(app.pinned_header_display_row(), Some(0));
+        app.cluster_order = None;
+
+        // ...and when rows above it are deleted
+        app.cursor_row = 1;
+        app.delete_sequence();
+        assert_eq!(app.pinned_header_row(), Some(1));
+        app.undo();
+        assert_eq!(app.pinned_header_row(), Some(2));
+
+        // Loading another alignment drops the pin
+        app.load_file(&path).unwrap();
+        assert_eq!(app.pinned_header_row(), None);
+        app.execute_display_command(&["pin-header"]);
+
+        app.execute_display_command(&["unpin-header"]);
+        assert_eq!(app.pinned_header_display_row(), None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_pinned_comparison_follows_sequence() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\ns3 ACGC\n//\n";
+        let path = write_temp("pin_comparison", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.cursor_row = 2;
+        app.toggle_pinned_comparison();
+        assert_eq!(app.pinned_comparison_row(), Some(2));
+
+        // Deleting a row above keeps the same sequence pinned
+        app.cursor_row = 0;
+        app.delete_sequence();
+        assert_eq!(app.pinned_comparison_row(), Some(1));
+        app.undo();
+        assert_eq!(app.pinned_comparison_row(), Some(2));
+
+        // Loading another alignment drops the pin
+        app.load_file(&path).unwrap();
+        assert_eq!(app.pinned_comparison_row(), None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_color_ref() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\ns3 ACGC\n//\n";
+        let path = write_temp("color_ref", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.cursor_row = 1;
+        app.execute_display_command(&["color-ref"]);
+        assert_eq!(app.reference_row(), 1);
+
+        app.execute_display_command(&["color-ref", "3"]);
+        assert_eq!(app.reference_row(), 2);
+
+        app.execute_display_command(&["color-ref", "s1"]);
+        assert_eq!(app.reference_row(), 0);
+
+        // Unknown IDs and out-of-range rows leave the reference unchanged
+        assert!(app.set_reference_sequence(Some("missing")).is_err());
+        assert!(app.set_reference_sequence(Some("4")).is_err());
+        assert_eq!(app.reference_row(), 0);
+
+        // The reference follows its sequence when rows move or go
+        app.execute_display_command(&["color-ref", "s3"]);
+        app.cursor_row = 0;
+        app.delete_sequence();
+        assert_eq!(app.reference_row(), 1);
+        app.undo();
+        assert_eq!(app.reference_row(), 2);
+        app.cursor_row = 2;
+        app.delete_sequence();
+        assert_eq!(app.reference_row(), 0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_undo_branches() {
+        let path = write_temp("undo_branches", "# STOCKHOLM 1.0\ns1 acgt\n//\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_display_command(&["undo-tree"]);
+        assert!(app.report.is_none());
+
+        // Undo then a different edit keeps both edits
+        app.execute_transform_command(&["upper"]);
+        app.undo();
+        app.execute_transform_command(&["t2u"]);
+        assert_eq!(app.alignment.sequences[0].data(), "acgu");
+
+        app.switch_undo_branch(false);
+        assert_eq!(app.alignment.sequences[0].data(), "ACGT");
+        app.switch_undo_branch(false);
+        assert_eq!(app.status_message.as_deref(), Some("No older undo branch"));
+        app.switch_undo_branch(true);
+        assert_eq!(app.alignment.sequences[0].data(), "acgu");
+        app.undo();
+        assert_eq!(app.alignment.sequences[0].data(), "acgt");
+
+        app.execute_display_command(&["undo-tree"]);
+        assert!(matches!(app.report, Some(Report::UndoTree(ref rows)) if rows.len() == 3));
+
+        // Switching to a narrower branch keeps the slice inside it
+        app.insert_gap_column_range(0, 2);
+        app.undo();
+        app.execute_transform_command(&["upper"]);
+        app.switch_undo_branch(false);
+        assert_eq!(app.alignment.width(), 6);
+        app.execute_display_command(&["slice", "5-6"]);
+        app.switch_undo_branch(true);
+        assert_eq!(app.alignment.width(), 4);
+        assert!(app.column_slice.is_none());
+        app.cursor_line_end();
+        assert_eq!(app.cursor_col, 3);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_macro_recording() {
+        use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+
+        let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        let mut app = App::new();
+        assert!(app.macro_keys('q').is_err());
+        assert!(app.macro_keys('@').is_err());
+
+        // Keys are only kept between start and stop
+        app.record_key(key('x'));
+        app.start_macro_recording('q');
+        app.record_key(key('>'));
+        app.record_key(key('j'));
+        app.stop_macro_recording();
+        app.record_key(key('k'));
+        assert!(app.macro_recording.is_none());
+        assert_eq!(app.macro_keys('q').unwrap(), vec![key('>'), key('j')]);
+        assert_eq!(app.macro_keys('@').unwrap(), vec![key('>'), key('j')]);
+
+        // Played-back keys are not recorded again
+        app.start_macro_recording('a');
+        app.macro_playing = true;
+        app.record_key(key('>'));
+        app.macro_playing = false;
+        app.record_key(key('l'));
+        app.stop_macro_recording();
+        assert_eq!(app.macros[&'a'], vec![key('l')]);
+    }
+
+    #[test]
+    fn test_align_to_reference() {
+        let contents = "# STOCKHOLM 1.0\nref ..ACGU..\nlate ....ACGU\nearly AC......\nfull ACGUACGU\n#=GR late PP ....9999\n//\n";
+        let path = write_temp("align_to_ref", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(app.align_to_reference(), Ok(2));
+        let data: Vec<String> = app.alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["..ACGU..", "..ACGU..", "..AC....", "ACGUACGU"]);
+        assert_eq!(
+            app.alignment.residue_annotations["late"][0].data,
+            "..9999.."
+        );
+
+        // Already aligned: nothing moves and no undo step is recorded
+        let undo_steps = app.history.undo_count();
+        assert_eq!(app.align_to_reference(), Ok(0));
+        assert_eq!(app.history.undo_count(), undo_steps);
+
+        app.undo();
+        assert_eq!(app.alignment.sequences[1].data(), "....ACGU");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_trim_seqs() {
+        let contents = "# STOCKHOLM 1.0\ns1 ..AC-GU.\ns2 ~-ACGU..\ns3 ACGUACGU\n//\n";
+        let path = write_temp("trim_seqs", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        // Without the bang only the count is reported
+        app.execute_transform_command(&["trim-seqs"]);
+        assert_eq!(app.unmasked_terminal_gaps(), 6);
+        assert_eq!(app.alignment.sequences[0].data(), "..AC-GU.");
+
+        app.execute_transform_command(&["trim-seqs!"]);
+        let data: Vec<String> = app.alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["~~AC-GU~", "~~ACGU~~", "ACGUACGU"]);
+        assert_eq!(app.unmasked_terminal_gaps(), 0);
+
+        app.undo();
+        assert_eq!(app.alignment.sequences[1].data(), "~-ACGU..");
+
+        // A custom trim character also recognizes the default '~' as a gap
+        app.execute_set_command("trim_char=#");
+        assert!(app.gap_chars.contains(&'#'));
+        app.execute_transform_command(&["trim-ends"]);
+        assert_eq!(app.alignment.sequences[1].data(), "##ACGU##");
+        assert_eq!(app.compute_ungapped_lengths()[1].1, 4);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_compute_profile() {
+        let contents = "# STOCKHOLM 1.0\n#=GF ID demo\ns1 AC-\ns2 AT-\ns3 aCU\ns4 G.U\n//\n";
+        let path = write_temp("profile", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.sequence_type = SequenceType::RNA;
+
+        let profile = app.compute_profile();
+        assert_eq!(profile.alphabet, ['A', 'C', 'G', 'U']);
+        assert_eq!(profile.frequency(0, 'A'), 0.75);
+        assert_eq!(profile.frequency(0, 'G'), 0.25);
+        // T counts as U in RNA, the gap is ignored
+        assert_eq!(profile.frequency(1, 'C'), 2.0 / 3.0);
+        assert_eq!(profile.frequency(1, 'U'), 1.0 / 3.0);
+
+        app.pwm_pseudocount = 1.0;
+        let profile = app.compute_profile();
+        assert_eq!(profile.frequency(2, 'U'), 0.5);
+        assert_eq!(profile.frequency(2, 'A'), 1.0 / 6.0);
+        assert_eq!(app.profile_name(), "demo");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_search_structure() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGUACGUACGUACGU\n#=GC SS_cons <<..>>::((..))..\n//\n";
+        let path = write_temp("search_structure", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.cursor_row = 0;
+
+        // Bracket types are interchangeable, '.' is a wildcard
+        assert_eq!(app.search_structure("<<..>>"), Ok(2));
+        assert_eq!(app.cursor_col, 0);
+        assert_eq!(app.search.is_structure_match(9), Some(false));
+        assert_eq!(app.is_search_match(0, 0), None);
+
+        app.search_next();
+        assert_eq!(app.cursor_col, 8);
+        app.search_next();
+        assert_eq!(app.cursor_col, 0);
+
+        assert_eq!(app.search_structure("<.>"), Ok(0));
+        assert!(app.search_structure("").is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_auto_cluster_on_load() {
+        let path = write_temp(
+            "auto_cluster.sto",
+            "# STOCKHOLM 1.0\nseq1 AAAA\nseq2 CCCC\nseq3 AAAC\n//\n",
+        );
+        let mut app = App::new();
+        app.execute_set_command("auto_cluster=true");
+        app.execute_set_command("auto_cluster_min=4");
+        app.load_file(&path).unwrap();
+        assert!(app.cluster_order.is_none());
+
+        app.execute_set_command("auto_cluster_min=3");
+        app.load_file(&path).unwrap();
+        assert!(app.cluster_order.is_some());
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .ends_with("auto-clustered 3 sequences")
+        );
+
+        // In the TUI it runs in the background
+        app.background_clustering = true;
+        app.load_file(&path).unwrap();
+        assert!(app.cluster_order.is_none());
+        assert!(app.is_clustering());
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .ends_with("Auto-clustering 3 sequences…")
+        );
+        let started = std::time::Instant::now();
+        while app.is_clustering() && started.elapsed() < std::time::Duration::from_secs(10) {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            app.poll_clustering();
+        }
+        assert!(app.cluster_order.is_some());
+        assert!(!app.show_tree);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Auto-clustered 3 sequences")
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reference_coordinates() {
+        let contents = "# STOCKHOLM 1.0\nplain --AC-G\nfwd/101-103 A-C-G-\nrev/50-47 -ACG-U\n//\n";
+        let path = write_temp("refcoords", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        let cols: Vec<usize> = (0..6).collect();
+
+        assert_eq!(
+            app.reference_coordinates(&cols),
+            vec![None, None, Some(1), Some(2), Some(2), Some(3)]
+        );
+        app.reference_id = Some(app.alignment.sequences[1].id.clone());
+        assert_eq!(
+            app.reference_coordinates(&cols),
+            vec![
+                Some(101),
+                Some(101),
+                Some(102),
+                Some(102),
+                Some(103),
+                Some(103)
+            ]
+        );
+        app.reference_id = Some(app.alignment.sequences[2].id.clone());
+        assert_eq!(
+            app.reference_coordinates(&[1, 3, 5]),
+            vec![Some(50), Some(48), Some(47)]
+        );
+
+        app.execute_display_command(&["ref-ruler"]);
+        assert!(app.ref_ungapped_ruler);
+
+        // The ruler keeps counting the same sequence when rows above it go
+        app.execute_display_command(&["color-ref", "fwd/101-103"]);
+        app.cursor_row = 0;
+        app.delete_sequence();
+        assert_eq!(
+            app.reference_coordinates(&[0, 5]),
+            vec![Some(101), Some(103)]
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_import_anchors() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GUA\n#=GC RF   AC.GUA\n//\n";
+        let path = write_temp("anchors", contents);
+        let anchors = write_temp("anchors_tsv", "# rf\tlabel\n3\tcatalytic\n5\tbulge\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.dispatch_command(
+            &["import-anchors", anchors.to_str().unwrap()],
+            "import-anchors",
+        );
+        assert_eq!(app.alignment.anchors(), Some("...c.b"));
+        assert!(
+            app.visible_annotations(&app.alignment)
+                .contains(&AnnotationKind::Anchors)
+        );
+
+        let bad = write_temp("anchors_bad", "6\toutside\n");
+        assert!(app.import_anchors(&bad).is_err());
+
+        app.undo();
+        assert_eq!(app.alignment.anchors(), None);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&anchors);
+        let _ = std::fs::remove_file(&bad);
+    }
+
+    #[test]
+    fn test_load_coaxial_stacking() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGUACGU\n//\n";
+        let path = write_temp("coaxial", contents);
+        let stacks = write_temp("coaxial_pairs", "2 7\n# comment\n3 6\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(app.load_coaxial_stacking(&stacks), Ok(2));
+        assert_eq!(app.coaxial_partner(1), Some(6));
+        assert_eq!(app.coaxial_partner(5), Some(2));
+        assert_eq!(app.coaxial_partner(0), None);
+
+        let bad = write_temp("coaxial_bad", "2 9\n");
+        assert!(app.load_coaxial_stacking(&bad).is_err());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&stacks);
+        let _ = std::fs::remove_file(&bad);
+    }
+
+    #[test]
+    fn test_helix_covariation() {
+        // Helix 0 is the outer two pairs, helix 1 the inner one
+        let contents = "# STOCKHOLM 1.0\n\
+                        ref GGAAACUCC\n\
+                        dbl CGAAACUCG\n\
+                        bad AGAAACUCC\n\
+                        #=GC SS_cons <<.(.).>>\n//\n";
+        let path = write_temp("helix_covariation", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let helix = app.structure_cache.get_helix(0).unwrap();
+        let cov = app.compute_helix_covariation(helix);
+        assert_eq!(cov.scores, [None, Some(0.5), Some(0.0)]);
+
+        // The overlay only applies with the compensatory scheme
+        app.toggle_helix_covariation();
+        assert!(!app.show_helix_covariation);
+        app.color_scheme = ColorScheme::Compensatory;
+        app.toggle_helix_covariation();
+        assert!(app.cursor_helix_covariation().is_some());
+        assert!(app.helix_covariation_cache.borrow().is_some());
+
+        // A new reference is rescored; an edit drops the cached scores
+        app.reference_id = Some("dbl".to_string());
+        let cov = app.cursor_helix_covariation().unwrap();
+        assert_eq!(cov.scores[1], None);
+        app.cursor_row = 2;
+        app.insert_gap();
+        assert!(app.helix_covariation_cache.borrow().is_none());
+
+        app.cursor_col = 2;
+        assert!(app.cursor_helix_covariation().is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_covariation_matrix() {
+        let contents = "# STOCKHOLM 1.0\ns1 AUG\ns2 AUG\ns3 GCG\ns4 GCG\n//\n";
+        let path = write_temp("covariation", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let matrix = app.compute_covariation_matrix();
+        assert!((matrix[0][1] - 1.0).abs() < 1e-9);
+        // A constant column carries no information
+        assert_eq!(matrix[0][2], 0.0);
+        // Only the upper triangle is filled
+        assert_eq!(matrix[1][0], 0.0);
+
+        app.execute_display_command(&["show-covariation"]);
+        let Some(Report::Covariation(report)) = &app.report else {
+            panic!("expected a covariation report");
+        };
+        assert_eq!(report.top_pairs.len(), 1);
+        assert_eq!((report.top_pairs[0].1, report.top_pairs[0].2), (0, 1));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_infer_ss_from_covariation() {
+        let contents = "# STOCKHOLM 1.0\ns1 GAAAAAAC\ns2 GAAAAAAC\ns3 AAAAAAAU\ns4 AAAAAAAU\n//\n";
+        let path = write_temp("inferss", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.dispatch_command(&["infer-ss", "1.5"], "infer-ss");
+        assert!(app.alignment.ss_cons().is_none());
+
+        app.dispatch_command(&["infer-ss"], "infer-ss");
+        assert_eq!(app.alignment.ss_cons(), Some("<......>"));
+        assert_eq!(app.structure_cache.get_pair(0), Some(7));
+        assert!(app.infer_ss_from_covariation(0.5).is_err());
+
+        // Scoring is limited to pairs within the span
+        let pairs = app.column_pair_mi(3);
+        assert_eq!(pairs.len(), 7 + 6 + 5);
+        assert!(pairs.iter().all(|&(i, j, _)| j - i <= 3));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_find_all_compensatory() {
+        let contents = "# STOCKHOLM 1.0\nref GGAAACC\ns2 AGAAACU\ns3 GAAAACC\ns4 G-AAACC\n\
+                        #=GC SS_cons <<...>>\n//\n";
+        let path = write_temp("showcomp", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let records = app.find_all_compensatory();
+        let summary: Vec<(&str, usize, CompensatoryChange)> = records
+            .iter()
+            .map(|r| (r.seq_id.as_str(), r.left, r.change))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("s2", 0, CompensatoryChange::DoubleCompatible),
+                ("s3", 1, CompensatoryChange::SingleIncompatible),
+                ("s4", 1, CompensatoryChange::InvolvesGap),
+            ]
+        );
+
+        app.execute_display_command(&["show-comp"]);
+        assert!(matches!(app.report, Some(Report::Compensatory(ref r)) if r.len() == 3));
+
+        // The reference stays on s3 after the rows above it are deleted
+        app.execute_display_command(&["color-ref", "s3"]);
+        app.cursor_row = 0;
+        app.delete_sequence();
+        let ids: Vec<String> = app
+            .find_all_compensatory()
+            .into_iter()
+            .map(|r| r.seq_id)
+            .collect();
+        assert!(!ids.contains(&"s3".to_string()));
+        assert!(ids.contains(&"s2".to_string()));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_list_base_pairs() {
+        let contents = "# STOCKHOLM 1.0\ns1 GGAAACC\ns2 GGAAAUU\ns3 G-AAAAC\ns4 AGAAAGC\n\
+                        #=GC SS_cons <<...>>\n//\n";
+        let path = write_temp("showpairs", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let pairs = app.list_base_pairs();
+        assert_eq!(pairs.len(), 2);
+        assert_eq!((pairs[0].left, pairs[0].right), (0, 6));
+        assert_eq!(pairs[0].watson_crick, 0.5);
+        assert_eq!(pairs[0].wobble, 0.25);
+        assert_eq!(pairs[0].mismatch, 0.25);
+        assert_eq!(pairs[0].gap, 0.0);
+        assert_eq!((pairs[1].left, pairs[1].right), (1, 5));
+        assert_eq!(pairs[1].gap, 0.25);
+
+        app.execute_display_command(&["show-pairs"]);
+        assert!(matches!(app.report, Some(Report::BasePairs(_))));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_extract_stem_loop() {
+        let contents = "# STOCKHOLM 1.0\ns1 AGGAAACCUA\n#=GR s1 PP 0123456789\n\
+                        #=GC SS_cons .<<...>>..\n//\n";
+        let path = write_temp("stemloop", contents);
+        let out = write_temp("stemloop_out", "");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert!(app.extract_stem_loop(0, 9, &out).is_err());
+        assert_eq!(app.extract_stem_loop(1, 7, &out), Ok(7));
+
+        let extracted = crate::stockholm::parser::parse_file(&out).unwrap();
+        assert_eq!(extracted.sequences[0].data(), "GGAAACC");
+        assert_eq!(extracted.ss_cons(), Some("<<...>>"));
+        assert_eq!(extracted.residue_annotations["s1"][0].data, "1234567");
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_zoom_navigation() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGUACGUACGU\n//\n";
+        let path = write_temp("zoom", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_display_command(&["zoom", "5"]);
+        assert_eq!(app.column_stride, 5);
+        app.cursor_right();
+        app.cursor_right();
+        assert_eq!(app.cursor_col, 10);
+        // Not enough columns left for another step
+        app.cursor_right();
+        assert_eq!(app.cursor_col, 10);
+        app.cursor_left();
+        assert_eq!(app.cursor_col, 5);
+
+        // Two cells at stride 5 span ten columns
+        app.cursor_col = 11;
+        app.adjust_viewport(10, 2);
+        assert_eq!(app.viewport_col, 2);
+
+        app.execute_display_command(&["zoom", "off"]);
+        assert_eq!(app.column_stride, 1);
+        app.execute_display_command(&["zoom", "0"]);
+        assert_eq!(app.column_stride, 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_annotation_order() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\n#=GC SS_cons <..>\n#=GC RF xxxx\n//\n";
+        let path = write_temp("annotation_order", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.show_rf_bar = true;
+        app.show_consensus = true;
+
+        use AnnotationKind::*;
+        assert_eq!(
+            app.visible_annotations(&app.alignment),
+            [SsCons, Rf, Consensus]
+        );
+
+        app.execute_set_command("annotation_order=cons, SS");
+        assert_eq!(
+            app.annotation_order,
+            [Consensus, SsCons, Rf, PpCons, Conservation, Helix, Anchors]
+        );
+        assert_eq!(
+            app.visible_annotations(&app.alignment),
+            [Consensus, SsCons, Rf]
+        );
+
+        // Invalid lists leave the order unchanged
+        app.execute_set_command("annotation_order=SS,ss_cons");
+        app.execute_set_command("annotation_order=bogus");
+        assert_eq!(app.annotation_order[0], Consensus);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_import_features_from_file() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GUA\n#=GC RF AC.GUA\n//\n";
+        let path = write_temp("features", contents);
+        let bed = std::env::temp_dir().join(format!("aform_features_{}.bed", std::process::id()));
+        std::fs::write(&bed, "ref\t1\t3\n").unwrap();
+
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        // Reference positions 2-3 (C, G) skip the RF gap column
+        assert_eq!(app.import_features_from_file(&bed, "FT"), Ok(2));
+        let ft = app
+            .alignment
+            .column_annotations
+            .iter()
+            .find(|a| a.tag == "FT")
+            .unwrap();
+        assert_eq!(ft.data, ".X.X..");
+
+        assert!(
+            app.import_features_from_file(Path::new("features.txt"), "FT")
+                .is_err()
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&bed);
+    }
+
+    #[test]
+    fn test_inverted_selection() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGU\ns3 ACGU\n//\n";
+        let path = write_temp("invert_selection", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.cursor_row = 1;
+        app.cursor_col = 1;
+        app.enter_visual_mode();
+        app.cursor_col = 2;
+        assert!(app.is_selected(1, 1));
+        assert!(!app.is_selected(0, 0));
+
+        app.invert_selection();
+        assert!(!app.is_selected(1, 1));
+        assert!(app.is_selected(0, 0));
+        assert!(app.is_selected(1, 3));
+        assert!(app.is_row_selected(1));
+
+        app.delete_selection();
+        let data: Vec<String> = app
+            .alignment
+            .sequences
+            .iter()
+            .map(|s| s.chars().iter().collect())
+            .collect();
+        assert_eq!(data, ["....", ".CG.", "...."]);
+
+        // Inverted line selection deletes every other sequence
+        app.undo();
+        app.cursor_row = 1;
+        app.enter_visual_line_mode();
+        app.invert_selection();
+        assert_eq!(app.selected_rows(), [0, 2]);
+        app.delete_selected_sequences();
+        assert_eq!(app.alignment.sequences.len(), 1);
+        assert_eq!(app.alignment.sequences[0].id, "s2");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_column_filter() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GU\ns2 AC-GU\n#=GC RF xx.x.\n//\n";
+        let path = write_temp("col_filter", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert!(app.set_column_filter("SS_cons", "x").is_err());
+        assert!(app.set_column_filter("RF", "z").is_err());
+        assert!(app.column_filter.is_none());
+
+        assert_eq!(app.set_column_filter("RF", "x"), Ok(3));
+        assert_eq!(app.visible_columns, [0, 1, 3]);
+        assert_eq!(app.actual_to_display_col(2), None);
+
+        // Combines with hiding gap columns
+        app.hide_gap_columns = true;
+        app.precompute_visible_columns();
+        assert_eq!(app.visible_columns, [0, 1, 3]);
+        app.set_column_filter("RF", r"\.").unwrap();
+        assert_eq!(app.visible_columns, [4]);
+
+        app.hide_gap_columns = false;
+        app.clear_column_filter();
+        assert!(!app.columns_hidden());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_column_slice() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GUAC-G\ns2 AC-GUAC-G\n//\n";
+        let path = write_temp("column_slice", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.cursor_col = 7;
+
+        assert!(app.set_column_slice(4, 4).is_err());
+        assert!(app.set_column_slice(0, 10).is_err());
+        assert!(app.column_slice.is_none());
+
+        assert!(app.execute_display_command(&["slice", "3-6"]));
+        assert_eq!(app.column_slice, Some((2, 6)));
+        assert_eq!(app.visible_columns, [2, 3, 4, 5]);
+        assert_eq!(app.cursor_col, 5);
+        assert_eq!(app.viewport_col, 0);
+        assert_eq!(app.ruler_origin(), 0);
+        app.execute_set_command("slice_relative_ruler=true");
+        assert_eq!(app.ruler_origin(), 2);
+
+        // Combines with hiding gap columns
+        app.hide_gap_columns = true;
+        app.precompute_visible_columns();
+        assert_eq!(app.visible_columns, [3, 4, 5]);
+        app.hide_gap_columns = false;
+
+        // The slice moves with inserted and removed columns
+        app.insert_gap_column_range(0, 2);
+        assert_eq!(app.column_slice, Some((4, 8)));
+        app.trim_left();
+        assert_eq!(app.column_slice, Some((2, 6)));
+        app.reverse_alignment_columns();
+        assert_eq!(app.column_slice, Some((3, 7)));
+
+        app.execute_display_command(&["slice", "off"]);
+        assert!(app.column_slice.is_none());
+        assert!(!app.columns_hidden());
+        assert_eq!(app.ruler_origin(), 0);
+
+        // Loading a file drops it
+        app.execute_display_command(&["slice", "3-6"]);
+        app.load_file(&path).unwrap();
+        assert!(app.column_slice.is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_column_slice_undo() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GUAC-GU\ns2 AC-GUAC-GU\n//\n";
+        let path = write_temp("column_slice_undo", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_display_command(&["slice", "3-10"]);
+        app.insert_gap_column_range(0, 2);
+        assert_eq!(app.column_slice, Some((4, 12)));
+
+        // Undo shrinks the alignment back; the slice must stay inside it
+        app.undo();
+        assert_eq!(app.alignment.width(), 10);
+        assert_eq!(app.column_slice, Some((4, 10)));
+        assert_eq!(app.visible_columns, [4, 5, 6, 7, 8, 9]);
+        app.cursor_line_end();
+        assert_eq!(app.cursor_col, 9);
+
+        app.redo();
+        app.cursor_line_end();
+        assert_eq!(app.cursor_col, 9);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_alignment_hash() {
+        let first = write_temp(
+            "hash_a",
+            "# STOCKHOLM 1.0\n#=GF ID a\n#=GF DE x\ns1 AC-G\ns2 ACUG\n//\n",
+        );
+        let reordered = write_temp(
+            "hash_b",
+            "# STOCKHOLM 1.0\n#=GF DE y\n#=GF ID b\ns2 ACUG\ns1 AC-G\n#=GC RF xxxx\n//\n",
+        );
+        let changed = write_temp("hash_c", "# STOCKHOLM 1.0\ns1 AC-G\ns2 ACGG\n//\n");
+
+        let hash = |path: &PathBuf| {
+            let mut app = App::new();
+            app.load_file(path).unwrap();
+            app.compute_alignment_hash()
+        };
+        let expected = hash(&first);
+        assert_eq!(expected.len(), 64);
+        assert!(expected.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(hash(&reordered), expected);
+        assert_ne!(hash(&changed), expected);
+
+        // The cached hash follows edits and reloads
+        let mut app = App::new();
+        app.load_file(&first).unwrap();
+        assert_eq!(app.compute_alignment_hash(), expected);
+        app.delete_sequence();
+        assert!(app.alignment_hash_cache.borrow().is_none());
+        assert_ne!(app.compute_alignment_hash(), expected);
+        app.load_file(&first).unwrap();
+        assert_eq!(app.compute_alignment_hash(), expected);
+
+        for path in [first, reordered, changed] {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    #[test]
+    fn test_insert_gap_column_range() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 AC-U\n#=GC SS_cons <..>\n//\n";
+        let path = write_temp("insert_cols", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.cursor_col = 2;
+
+        assert!(app.execute_transform_command(&["insert-cols", "3"]));
+        assert_eq!(app.alignment.width(), 7);
+        assert_eq!(app.alignment.sequences[0].data(), "AC...GU");
+        assert_eq!(app.alignment.ss_cons(), Some("<.....>"));
+        assert_eq!(app.structure_cache.get_pair(0), Some(6));
+
+        // One undo step removes all of them
+        app.undo();
+        assert_eq!(app.alignment.width(), 4);
+
+        app.execute_transform_command(&["insert-cols", "0"]);
+        assert_eq!(app.alignment.width(), 4);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_insert_gap_column_with_hidden_columns() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GU\ns2 AC-GU\n//\n";
+        let path = write_temp("insert_hidden", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.hide_gap_columns = true;
+        app.precompute_visible_columns();
+        app.cursor_col = 3;
+
+        app.insert_gap_column_range(app.cursor_col, 2);
+        assert_eq!(app.alignment.sequences[0].data(), "AC-..GU");
+        assert_eq!(app.visible_columns, [0, 1, 5, 6]);
+        // The cursor stays on the G it was on
+        assert_eq!(app.cursor_col, 5);
+        assert_eq!(app.actual_to_display_col(app.cursor_col), Some(2));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_file_annotation_editing() {
+        let contents = "# STOCKHOLM 1.0\n#=GF ID old\n#=GF CC one\n#=GF CC two\ns1 ACGU\n//\n";
+        let path = write_temp("set_gf", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_display_command(&["set-gf", "DE", "A", "test", "family"]);
+        assert_eq!(
+            app.alignment.get_file_annotation("DE"),
+            Some("A test family")
+        );
+        assert!(app.modified);
+
+        app.execute_display_command(&["set-gf", "CC", "merged"]);
+        assert_eq!(app.alignment.get_file_annotations("CC"), ["merged"]);
+        assert_eq!(app.alignment.file_annotations[1].tag, "CC");
+
+        assert_eq!(app.delete_file_annotation("ID"), Ok(1));
+        assert!(app.delete_file_annotation("ID").is_err());
+        assert_eq!(app.alignment.get_file_annotation("ID"), None);
+
+        app.undo();
+        assert_eq!(app.alignment.get_file_annotation("ID"), Some("old"));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_add_residue_annotation() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 AC-U\n//\n";
+        let path = write_temp("add_gr", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.cursor_row = 1;
+
+        assert!(app.add_residue_annotation("s3", "SS", "<..>").is_err());
+        assert!(app.add_residue_annotation("s1", "SS", "<.>").is_err());
+
+        app.execute_transform_command(&["add-gr", "SS", "<..>"]);
+        app.execute_transform_command(&["add-gr", "s1", "PP", "9999"]);
+        app.execute_transform_command(&["add-gr", "s1", "PP", "8888"]);
+        let s1 = &app.alignment.residue_annotations["s1"];
+        assert_eq!(s1.len(), 1);
+        assert_eq!(s1[0].data, "8888");
+        assert_eq!(app.alignment.residue_annotations["s2"][0].tag, "SS");
+        assert!(app.compare_structure().is_err()); // no SS_cons to compare against
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_compare_structure_cleared_on_edit() {
+        let contents = "# STOCKHOLM 1.0\ns1 GGAACC\n#=GR s1 SS ((..))\n#=GC SS_cons (....)\n//\n";
+        let path = write_temp("compare_ss_edit", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.dispatch_command(&["compare-ss"], "compare-ss");
+        assert!(app.structure_diff.is_some());
+        app.insert_gap();
+        assert!(app.structure_diff.is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_gr_track() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 AC-U\n#=GR s1 PP 9876\n//\n";
+        let path = write_temp("show_gr", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        assert_eq!(app.rows_per_sequence(), 1);
+
+        app.execute_display_command(&["show-gr", "SS"]);
+        assert!(app.show_gr_tag.is_none());
+
+        app.execute_display_command(&["show-gr", "PP"]);
+        assert_eq!(app.show_gr_tag.as_deref(), Some("PP"));
+        assert_eq!(app.rows_per_sequence(), 2);
+        assert_eq!(app.gr_track("s1"), Some("9876"));
+        assert_eq!(app.gr_track("s2"), None);
+
+        app.execute_display_command(&["show-gr", "off"]);
+        assert_eq!(app.rows_per_sequence(), 1);
+        assert_eq!(app.gr_track("s1"), None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_pretty() {
+        let contents = "# STOCKHOLM 1.0\nseq1 ACGU\n#=GR seq1 PP 9999\n#=GC SS_cons <..>\n//\n";
+        let path = write_temp("write_pretty", contents);
+        let out = std::env::temp_dir().join(format!("aform_pretty_out_{}.stk", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_export_command(&["write-pretty", out.to_str().unwrap()]);
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert!(written.contains("seq1               ACGU\n"));
+        assert!(written.contains("#=GC SS_cons       <..>\n"));
+
+        // :w follows the setting
+        app.execute_set_command("write_pretty=true");
+        app.save_file_as(out.clone()).unwrap();
+        let saved = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(saved, written);
+        app.execute_set_command("write_pretty=false");
+        app.save_file().unwrap();
+        assert!(
+            std::fs::read_to_string(&out)
+                .unwrap()
+                .contains("seq1       ACGU\n")
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_save_vienna() {
+        let path = std::env::temp_dir().join(format!("aform_save_{}.db", std::process::id()));
+        std::fs::write(
+            &path,
+            ">s1\nGGGAAACCC\n(((...)))\n>s2\nGGGAAUCCC\n(((...)))\n",
+        )
+        .unwrap();
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        // :w keeps the file's own format
+        app.save_file().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            ">s1\nGGGAAACCC\n(((...)))\n>s2\nGGGAAUCCC\n(((...)))\n"
+        );
+
+        // Several alignments don't fit in one Vienna file
+        let multi = write_temp(
+            "save_vienna_multi",
+            "# STOCKHOLM 1.0\ns1 ACGU\n//\n# STOCKHOLM 1.0\ns2 ACGU\n//\n",
+        );
+        app.load_file(&multi).unwrap();
+        let err = app.save_file_as(path.clone()).unwrap_err();
+        assert!(err.contains(".sto"), "{err}");
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&multi);
+    }
+
+    #[test]
+    fn test_write_interleaved_command() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGUACGU\ns2 ACGU-CGU\n//\n";
+        let path = write_temp("interleaved", contents);
+        let out =
+            std::env::temp_dir().join(format!("aform_interleaved_out_{}.stk", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_set_command("block_width=3");
+        assert_eq!(app.block_width, 3);
+        app.execute_export_command(&["w-interleaved", out.to_str().unwrap()]);
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(written.matches("s1 ").count(), 3);
+
+        app.execute_export_command(&["w-interleaved!", out.to_str().unwrap(), "5"]);
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(written.matches("s1 ").count(), 2);
+        assert!(!app.modified);
+
+        for p in [path, out] {
+            let _ = std::fs::remove_file(&p);
+        }
+    }
+
+    #[test]
+    fn test_ruler_reference_positions() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GUA\n#=GC RF Ac.GUA\n//\n";
+        let path = write_temp("ruler_mode", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        let cols = [0, 1, 2, 3, 5];
+
+        assert_eq!(
+            app.ruler_positions(&cols),
+            [Some(1), Some(2), Some(3), Some(4), Some(6)]
+        );
+
+        app.execute_display_command(&["ruler-mode", "ref"]);
+        assert_eq!(app.ruler_mode, RulerMode::Reference);
+        assert_eq!(
+            app.ruler_positions(&cols),
+            [Some(1), None, None, Some(2), Some(4)]
+        );
+
+        // Lowercase-only RF (HMMER match markers) counts every letter
+        app.alignment.column_annotations[0].data = "xx.xxx".to_string();
+        assert_eq!(
+            app.ruler_positions(&cols),
+            [Some(1), Some(2), None, Some(3), Some(5)]
+        );
+
+        app.execute_display_command(&["ruler-mode", "aln"]);
+        assert_eq!(app.ruler_positions(&[2]), [Some(3)]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_ungapped_fasta() {
+        assert_eq!(ungapped_id("seq/10-30", 5), "seq/10-14");
+        assert_eq!(ungapped_id("seq/30-10", 5), "seq/30-26");
+        assert_eq!(ungapped_id("seq", 5), "seq");
+        assert_eq!(ungapped_id("seq/a-b", 5), "seq/a-b");
+        assert_eq!(region_id("seq/10-30", 3, 5), "seq/13-17");
+        assert_eq!(region_id("seq/30-10", 3, 5), "seq/27-23");
+
+        let contents = "# STOCKHOLM 1.0\nhit/101-110 AC-G.U\nbare ------\n//\n";
+        let path = write_temp("write_ungapped", contents);
+        let out =
+            std::env::temp_dir().join(format!("aform_ungapped_out_{}.fa", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_export_command(&["wu", out.to_str().unwrap()]);
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(written, ">hit/101-104\nACGU\n");
+
+        for p in [path, out] {
+            let _ = std::fs::remove_file(&p);
+        }
+    }
+
+    #[test]
+    fn test_set_strand() {
+        assert_eq!(reverse_complement("ACgUn", true), "nAcGU");
+        assert_eq!(reverse_complement("AACRT", false), "AYGTT");
+        assert_eq!(swap_id_range("seq/10-13"), "seq/13-10");
+
+        let contents = "# STOCKHOLM 1.0\nfwd/1-4 AC-GU\nrev/10-13 AAC-G\n//\n";
+        let path = write_temp("set_strand", contents);
+        let out = std::env::temp_dir().join(format!("aform_strand_out_{}.fa", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.cursor_row = 1;
+        app.execute_transform_command(&["set-strand", "-"]);
+        assert_eq!(sequence_strand(&app.alignment, "rev/10-13"), Some('-'));
+        assert_eq!(sequence_strand(&app.alignment, "fwd/1-4"), None);
+        assert_eq!(
+            app.alignment.get_sequence_annotation("rev/10-13", "STRAND"),
+            Some("-")
+        );
+
+        // Minus-strand sequences are reverse-complemented on export
+        app.execute_export_command(&["wu", out.to_str().unwrap()]);
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(written, ">fwd/1-4\nACGU\n>rev/13-10\nCGUU\n");
+
+        app.execute_transform_command(&["set-strand", "x"]);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Usage: :set-strand +|-")
+        );
+        app.undo();
+        assert_eq!(sequence_strand(&app.alignment, "rev/10-13"), None);
+
+        for p in [path, out] {
+            let _ = std::fs::remove_file(&p);
+        }
+    }
+
+    #[test]
+    fn test_sequence_annotation_table() {
+        let contents = "# STOCKHOLM 1.0\n#=GS s2 OS Homo sapiens\n#=GS s1 DR PDB; 1ABC\n\
+                        #=GS s1 DR PDB; 2XYZ\n#=GS s2 AC P12345\ns1 ACGU\ns2 ACGU\ns3 ACGU\n//\n";
+        let path = write_temp("gsview", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let table = app.sequence_annotation_table();
+        assert_eq!(table.tags, ["DR", "OS", "AC"]);
+        assert_eq!(
+            table.rows,
+            [
+                (
+                    "s1".to_string(),
+                    vec![
+                        "PDB; 1ABC; PDB; 2XYZ".to_string(),
+                        String::new(),
+                        String::new()
+                    ]
+                ),
+                (
+                    "s2".to_string(),
+                    vec![
+                        String::new(),
+                        "Homo sapiens".to_string(),
+                        "P12345".to_string()
+                    ]
+                ),
+            ]
+        );
+
+        app.execute_display_command(&["gsview"]);
+        assert!(matches!(app.report, Some(Report::SequenceAnnotations(_))));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_complete_sequence_id() {
+        let contents = "# STOCKHOLM 1.0\nHuman_1 ACGU\nhuman_2 ACGU\nMouse ACGU\n//\n";
+        let path = write_temp("complete_id", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(app.complete_sequence_id("HUM"), ["Human_1", "human_2"]);
+        assert_eq!(app.complete_sequence_id("m"), ["Mouse"]);
+        assert_eq!(
+            app.complete_sequence_id(""),
+            ["Human_1", "human_2", "Mouse"]
+        );
+        assert!(app.complete_sequence_id("rat").is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_consensus_cache() {
+        let contents = "# STOCKHOLM 1.0\ns1 AAGU\ns2 AAGU\ns3 ACGU\n//\n";
+        let path = write_temp("consensus_cache", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert!(app.consensus_cache.borrow().is_none());
+        assert_eq!(*app.consensus_chars(), ['A', 'a', 'G', 'U']);
+        assert!(app.consensus_cache.borrow().is_some());
+
+        // Editing invalidates the cache
+        app.cursor_row = 2;
+        app.delete_sequence();
+        assert!(app.consensus_cache.borrow().is_none());
+        assert_eq!(app.consensus_chars()[1], 'A');
+
+        // So does undo
+        app.undo();
+        assert_eq!(app.consensus_chars()[1], 'a');
+
+        app.execute_display_command(&["consensus-threshold", "0.5"]);
+        assert!(app.consensus_cache.borrow().is_none());
+        app.execute_display_command(&["recompute-consensus"]);
+        assert!(app.consensus_cache.borrow().is_some());
+        assert_eq!(app.consensus_chars()[1], 'A');
+
+        // And changing what counts as a gap
+        app.execute_set_command("gap=~");
+        assert!(app.consensus_cache.borrow().is_none());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_mouse_cell() {
+        let mut app = App::new();
+        assert_eq!(app.mouse_cell(), None);
+
+        *app.sequence_grid.borrow_mut() = Some(SequenceGrid {
+            id_x: 1,
+            x: 20,
+            y: 5,
+            first_row: 10,
+            rows: 3,
+            rows_per_seq: 1,
+            columns: vec![100, 101, 105],
+            header_row: None,
+        });
+        app.mouse_pos = Some((22, 6));
+        assert_eq!(app.mouse_cell(), Some((11, 105)));
+        // Left of the sequences, past the last column, below the last row
+        for pos in [(19, 6), (23, 6), (20, 8)] {
+            app.mouse_pos = Some(pos);
+            assert_eq!(app.mouse_cell(), None);
+        }
+
+        // A pinned header sits two rows above the scrolling rows, and
+        // `:show-gr` tracks map to the sequence above them
+        if let Some(grid) = app.sequence_grid.borrow_mut().as_mut() {
+            grid.header_row = Some(0);
+            grid.rows_per_seq = 2;
+        }
+        app.mouse_pos = Some((20, 3));
+        assert_eq!(app.mouse_cell(), Some((0, 100)));
+        app.mouse_pos = Some((20, 4));
+        assert_eq!(app.mouse_cell(), None);
+        app.mouse_pos = Some((21, 8));
+        assert_eq!(app.mouse_cell(), Some((11, 101)));
+
+        // IDs sit left of the separator in front of the sequences
+        app.mouse_pos = Some((5, 8));
+        assert_eq!(app.mouse_cell(), None);
+        assert_eq!(app.mouse_id_row(), Some(11));
+        for pos in [(0, 8), (19, 8), (5, 4)] {
+            app.mouse_pos = Some(pos);
+            assert_eq!(app.mouse_id_row(), None);
+        }
+    }
+
+    #[test]
+    fn test_compact_ids() {
+        let mut app = App::new();
+        assert_eq!(app.id_width_cap(), None);
+
+        app.execute_display_command(&["compact"]);
+        assert_eq!(app.id_width_cap(), Some(8));
+        app.execute_set_command("compact_id_width=12");
+        assert_eq!(app.id_width_cap(), Some(12));
+        app.execute_set_command("compact_id_width=1");
+        assert_eq!(app.compact_id_width, 12);
+
+        app.execute_display_command(&["compact"]);
+        assert_eq!(app.id_width_cap(), None);
+    }
+
+    #[test]
+    fn test_wrap_ids() {
+        let mut app = App::new();
+        assert_eq!(app.rows_per_sequence(), 1);
+
+        app.execute_display_command(&["wrapids"]);
+        assert!(app.wrap_ids);
+        assert_eq!(app.rows_per_sequence(), 2);
+        assert_eq!(app.id_width_cap(), Some(30));
+        app.execute_set_command("id_wrap_width=12");
+        assert_eq!(app.id_width_cap(), Some(12));
+        app.execute_set_command("id_wrap_width=x");
+        assert_eq!(app.id_wrap_width, 12);
+
+        // Compact mode's narrower cap wins
+        app.execute_display_command(&["compact"]);
+        assert_eq!(app.id_width_cap(), Some(8));
+
+        app.execute_display_command(&["wrapids"]);
+        assert_eq!(app.rows_per_sequence(), 1);
+    }
+
+    #[test]
+    fn test_deduplicate_sequences() {
+        let contents = "# STOCKHOLM 1.0\n#=GS s3 DE copy\ns1 ACGU\ns2 AC-U\ns3 ACGU\n\
+                        s4 AC-U\ns5 GGGG\n#=GR s3 SS ....\n//\n";
+        let path = write_temp("dedup", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_transform_command(&["dedup"]);
+        let ids: Vec<&str> = app
+            .alignment
+            .sequences
+            .iter()
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(ids, ["s1", "s2", "s5"]);
+        assert!(!app.alignment.sequence_annotations.contains_key("s3"));
+        assert!(!app.alignment.residue_annotations.contains_key("s3"));
+        assert_eq!(app.collapse_groups.len(), 3);
+        assert!(app.modified);
+
+        assert_eq!(app.deduplicate_sequences(), 0);
+        app.undo();
+        assert_eq!(app.alignment.num_sequences(), 5);
+
+        // An imported tree order is kept, not replaced by a clustering, and
+        // a removed reference moves to the copy that stays
+        let tree_path = path.with_extension("nwk");
+        std::fs::write(&tree_path, "(s5:1,(s4:1,(s3:1,(s2:1,s1:1):1):1):1);\n").unwrap();
+        app.order_by_tree(&tree_path).unwrap();
+        app.execute_display_command(&["color-ref", "s2"]);
+        app.execute_transform_command(&["dedup"]);
+        let ids: Vec<&str> = (0..app.visible_sequence_count())
+            .map(|row| {
+                app.alignment.sequences[app.display_to_actual_row(row)]
+                    .id
+                    .as_str()
+            })
+            .collect();
+        assert_eq!(ids, ["s5", "s4", "s3"]);
+        assert_eq!(app.alignment.sequences[app.reference_row()].id, "s4");
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&tree_path);
+    }
+
+    #[test]
+    fn test_merge_alignment() {
+        let left = "# STOCKHOLM 1.0\n#=GF ID left\ns1 ACG\ns2 AC-\n#=GR s1 SS <.>\n\
+                    #=GC SS_cons <.>\n//\n";
+        let right = "# STOCKHOLM 1.0\n#=GF ID right\n#=GS s3 DE new\ns3 GGUU\ns1 UUCC\n\
+                     #=GC RF xxxx\n//\n";
+        let left_path = write_temp("merge_left", left);
+        let right_path = write_temp("merge_right", right);
+        let mut app = App::new();
+        app.load_file(&left_path).unwrap();
+
+        let command = format!("merge {}", right_path.display());
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        app.dispatch_command(&parts, &command);
+        let rows: Vec<(String, String)> = app
+            .alignment
+            .sequences
+            .iter()
+            .map(|s| (s.id.clone(), s.data()))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("s1".to_string(), "ACGUUCC".to_string()),
+                ("s2".to_string(), "AC-----".to_string()),
+                ("s3".to_string(), "---GGUU".to_string()),
+            ]
+        );
+        assert_eq!(app.alignment.ss_cons(), Some("<.>...."));
+        assert_eq!(app.alignment.rf(), Some("...xxxx"));
+        assert_eq!(app.alignment.residue_annotations["s1"][0].data, "<.>....");
+        assert_eq!(app.alignment.sequence_annotations["s3"][0].value, "new");
+        assert_eq!(
+            app.alignment.get_file_annotation("MERGED:ID"),
+            Some("right")
+        );
+        assert_eq!(app.alignment.get_file_annotation("ID"), Some("left"));
+
+        app.undo();
+        assert_eq!(app.alignment.width(), 3);
+
+        let _ = std::fs::remove_file(&left_path);
+        let _ = std::fs::remove_file(&right_path);
+    }
+
+    #[test]
+    fn test_split_at_column() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGUAC\n#=GR s1 PP 123456\ns2 AC-UAC\n\
+                        #=GC SS_cons <>..<>\n//\n";
+        let path = write_temp("split_at", contents);
+        let left = write_temp("split_at_left", "");
+        let right = write_temp("split_at_right", "");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert!(app.split_at_column(0, &left, &right).is_err());
+        assert!(app.split_at_column(6, &left, &right).is_err());
+
+        let command = format!("split-at 3 {} {}", left.display(), right.display());
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        app.dispatch_command(&parts, &command);
+        assert_eq!(app.alignment.width(), 6);
+        assert!(!app.modified);
+
+        let mut half = App::new();
+        half.load_file(&left).unwrap();
+        assert_eq!(half.alignment.sequences[1].data(), "AC");
+        assert_eq!(half.alignment.ss_cons(), Some("<>"));
+        assert_eq!(half.alignment.residue_annotations["s1"][0].data, "12");
+        half.load_file(&right).unwrap();
+        assert_eq!(half.alignment.sequences[0].data(), "GUAC");
+        assert_eq!(half.alignment.ss_cons(), Some("..<>"));
+        assert_eq!(half.alignment.residue_annotations["s1"][0].data, "3456");
+
+        for p in [path, left, right] {
+            let _ = std::fs::remove_file(&p);
+        }
+    }
+
+    #[test]
+    fn test_insert_aligned_columns() {
+        let contents = "# STOCKHOLM 1.0\ns1 AAUU\ns2 AA-U\n#=GC SS_cons <..>\n//\n";
+        let inserted = "# STOCKHOLM 1.0\ns2 CG\ns1 C-\n#=GC SS_cons <>\n//\n";
+        let path = write_temp("insert_aligned", contents);
+        let other = write_temp("insert_aligned_other", inserted);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let command = format!("insert-aligned {} at 3", other.display());
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        app.dispatch_command(&parts, &command);
+        let data: Vec<String> = app.alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["AAC-UU", "AACG-U"]);
+        assert_eq!(app.alignment.ss_cons(), Some("<.<>.>"));
+
+        // Past the end is an error
+        let mut other_alignment = app.alignment.clone();
+        other_alignment.sequences.truncate(1);
+        assert!(app.insert_aligned_columns(other_alignment, 7).is_err());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&other);
+    }
+
+    #[test]
+    fn test_fetch_command_validates_accession() {
+        let mut app = App::new();
+        app.execute_file_command(&["fetch", "PF00001"], "fetch PF00001");
+        assert!(app.pending_fetch.is_none());
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("Invalid Rfam accession")
+        );
+
+        app.execute_file_command(&["fetch", "rf00005"], "fetch rf00005");
+        if cfg!(feature = "network") {
+            assert_eq!(app.pending_fetch.as_deref(), Some("RF00005"));
+
+            // Unsaved edits are only discarded with :fetch!
+            app.pending_fetch = None;
+            app.modified = true;
+            app.execute_file_command(&["fetch", "RF00005"], "fetch RF00005");
+            assert!(app.pending_fetch.is_none());
+            app.execute_file_command(&["fetch!", "RF00005"], "fetch! RF00005");
+            assert_eq!(app.pending_fetch.as_deref(), Some("RF00005"));
+        } else {
+            assert!(app.pending_fetch.is_none());
+            assert_eq!(
+                app.status_message.as_deref(),
+                Some("Network support not compiled in")
+            );
+        }
+    }
+
+    #[test]
+    fn test_column_profile() {
+        let contents = "# STOCKHOLM 1.0\ns1 AG\ns2 a.\ns3 C-\ns4 AG\n//\n";
+        let path = write_temp("column_profile", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(app.column_profile(0), [('A', 3), ('C', 1)]);
+        assert_eq!(app.column_profile(1), [('G', 2), ('.', 2)]);
+        assert!(app.column_profile(5).is_empty());
+
+        app.execute_display_command(&["column-profile"]);
+        assert!(app.show_column_profile);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_distance_graph() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\ns\"3 UUUU\n//\n";
+        let path = write_temp("writedot", contents);
+        let out = write_temp("writedot_out", "");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.write_distance_graph(2.0, &out).unwrap();
+        let dot = std::fs::read_to_string(&out).unwrap();
+        assert!(dot.starts_with("graph G {\n"));
+        assert!(dot.contains("  n2 [label=\"s\\\"3\"];"));
+        assert!(dot.contains("  n0 -- n1 [distance=1];"));
+        assert!(!dot.contains("n2 --"));
+        assert!(dot.ends_with("}\n"));
+
+        app.dispatch_command(&["write-dot", "abc", "x.dot"], "write-dot abc x.dot");
+        assert!(app.status_message.as_deref().unwrap().contains("Invalid"));
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_pp_filter() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU-\n#=GR s1 PP 9*24.\ns2 ACGUA\n//\n";
+        let path = write_temp("ppfilter", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_transform_command(&["pp-filter", "0.5"]);
+        assert_eq!(app.alignment.sequences[0].data(), "AC..-");
+        assert_eq!(app.alignment.sequences[1].data(), "ACGUA");
+        assert!(app.status_message.as_deref().unwrap().contains("Masked 2"));
+        assert_eq!(app.filter_by_pp(0.5), 0);
+
+        app.undo();
+        assert_eq!(app.alignment.sequences[0].data(), "ACGU-");
+        app.execute_transform_command(&["pp-filter", "1.5"]);
+        assert!(app.status_message.as_deref().unwrap().contains("Invalid"));
+        assert_eq!(app.alignment.sequences[0].data(), "ACGU-");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_pp_cons_threshold() {
+        let mut app = App::new();
+        assert_eq!(app.pp_cons_threshold, 0.0);
+
+        app.execute_set_command("pp_threshold=0.9");
+        assert_eq!(app.pp_cons_threshold, 0.9);
+        assert!(app.execute_display_command(&["ppbar-threshold", "0.5"]));
+        assert_eq!(app.pp_cons_threshold, 0.5);
+
+        app.execute_display_command(&["ppbar-threshold", "2"]);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("between 0.0 and 1.0")
+        );
+        assert_eq!(app.pp_cons_threshold, 0.5);
+    }
+
+    #[test]
+    fn test_interpolate_gaps() {
+        let contents = "# STOCKHOLM 1.0\ns1 --AC..-GU-\ns2 ACGUACGUAC\n//\n";
+        let path = write_temp("interpgaps", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_transform_command(&["interp-gaps"]);
+        assert_eq!(app.alignment.sequences[0].data(), "--ACNNNGU-");
+        assert!(app.status_message.as_deref().unwrap().contains("Filled 3"));
+        assert_eq!(app.interpolate_gaps(0, 'N'), Ok(0));
+
+        app.undo();
+        assert_eq!(app.interpolate_gaps(0, '?'), Ok(3));
+        assert_eq!(app.alignment.sequences[0].data(), "--AC???GU-");
+        assert!(app.interpolate_gaps(0, '-').is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_convert_all_gaps() {
+        let contents = "# STOCKHOLM 1.0\ns1 A-C.G\ns2 A~C-_\n#=GC SS_cons <.-.>\n//\n";
+        let path = write_temp("convertgaps", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_transform_command(&["convert-gaps", "-"]);
+        assert_eq!(app.alignment.sequences[0].data(), "A-C-G");
+        assert_eq!(app.alignment.sequences[1].data(), "A-C--");
+        assert_eq!(app.alignment.ss_cons(), Some("<.-.>"));
+        assert_eq!(app.gap_char, '-');
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("Converted 3")
+        );
+        assert_eq!(app.convert_all_gaps('-'), 0);
+
+        app.undo();
+        assert_eq!(app.alignment.sequences[0].data(), "A-C.G");
+        app.execute_transform_command(&["convert-gaps", "X"]);
+        assert!(app.status_message.as_deref().unwrap().contains("not a gap"));
+        assert_eq!(app.alignment.sequences[0].data(), "A-C.G");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_gap_mask() {
+        let contents = "# STOCKHOLM 1.0\ns1 A-C.G\ns2 A.C-U\n//\n";
+        let path = write_temp("gapmask", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_transform_command(&["gap-mask"]);
+        assert_eq!(app.alignment.sequences[0].data(), "A|C|G");
+        assert_eq!(app.alignment.sequences[1].data(), "A|C|U");
+        assert_eq!(app.mask_gap_columns('|'), 0);
+
+        app.undo();
+        assert_eq!(app.alignment.sequences[0].data(), "A-C.G");
+        app.execute_transform_command(&["gap-mask", "-"]);
+        assert_eq!(app.alignment.sequences[0].data(), "A-C.G");
+        app.execute_transform_command(&["gap-mask", "#"]);
+        assert_eq!(app.alignment.sequences[1].data(), "A#C#U");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_apply_transform_and_export() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGT-\ns2 ACGTT\n//\n";
+        let path = write_temp("batch", contents);
+        let fasta = std::env::temp_dir().join(format!("aform_batch_{}.fa", std::process::id()));
+        let phylip = std::env::temp_dir().join(format!("aform_batch_{}.phy", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.apply_transform(":t2u").unwrap();
+        assert!(app.apply_transform("ruler").is_err());
+        assert_eq!(
+            app.apply_transform("convert-gaps x"),
+            Err("'x' is not a gap character".to_string())
+        );
+        app.apply_transform("convert-gaps -").unwrap();
+
+        app.export_alignment(&fasta).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&fasta).unwrap(),
+            ">s1\nACGU-\n>s2\nACGUU\n"
+        );
+        app.export_alignment(&phylip).unwrap();
+        assert!(
+            std::fs::read_to_string(&phylip)
+                .unwrap()
+                .starts_with("2 5\n")
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&fasta);
+        let _ = std::fs::remove_file(&phylip);
+    }
+
+    #[test]
+    fn test_insertion_stats() {
+        let contents = "# STOCKHOLM 1.0\ns1 ..AC.GU.\ns2 agACaGU.\ns3 g.AC.GUa\n\
+                        #=GC RF ..AC.GU.\n//\n";
+        let path = write_temp("insertions", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let stats = app.compute_insertion_stats();
+        assert!(!stats.contains_key("s1"));
+        assert_eq!(stats["s2"], vec![(0, 2), (2, 1)]);
+        assert_eq!(stats["s3"], vec![(0, 1), (4, 1)]);
+
+        app.execute_display_command(&["show-insertions"]);
+        match &app.report {
+            Some(Report::Insertions(rows)) => assert_eq!(rows[0].0, "s2"),
+            other => panic!("unexpected report {other:?}"),
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_sequences_individually() {
+        let contents = "# STOCKHOLM 1.0\nchr1/1-4 AC-GU\nchr1:2 A-C..\nchr1/2 ACGUU\n//\n";
+        let path = write_temp("perseq", contents);
+        let dir = std::env::temp_dir().join(format!("aform_perseq_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(
+            app.write_sequences_individually(&dir, "ungapped", false),
+            Ok(3)
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("chr1_1-4.fa")).unwrap(),
+            ">chr1/1-4\nACGU\n"
+        );
+        assert!(dir.join("chr1_2.fa").exists());
+        assert!(dir.join("chr1_2_2.fa").exists());
+
+        // Existing files are kept unless overwriting
+        assert!(
+            app.write_sequences_individually(&dir, "aligned", false)
+                .is_err()
+        );
+        assert_eq!(
+            app.write_sequences_individually(&dir, "aligned", true),
+            Ok(3)
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("chr1_2.fa")).unwrap(),
+            ">chr1:2\nA-C..\n"
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_write_fasta_with_coords() {
+        let contents =
+            "# STOCKHOLM 1.0\nfwd/11-16 AC-GU-AC\nrev/40-35 -ACG-UAC\nnone ------GG\n//\n";
+        let path = write_temp("fastacoords", contents);
+        let out = std::env::temp_dir().join(format!("aform_fastacoords_{}.fa", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_display_command(&["slice", "3-6"]);
+        app.dispatch_command(
+            &["write-fasta-coords", out.to_str().unwrap()],
+            "write-fasta-coords",
+        );
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            ">fwd/13-14\nGU\n>rev/39-37\nCGU\n"
+        );
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_write_conservation_bed() {
+        let contents = "# STOCKHOLM 1.0\n#=GF ID demo\n\
+            s1 AAGAC\ns2 AAUCC\ns3 AACGC\n#=GC RF XX.XX\n//\n";
+        let path = write_temp("consbed", contents);
+        let out = std::env::temp_dir().join(format!("aform_consbed_{}.bed", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.dispatch_command(
+            &["write-cons-bed", "0.9", out.to_str().unwrap()],
+            "write-cons-bed",
+        );
+        let bed = std::fs::read_to_string(&out).unwrap();
+        let fields: Vec<Vec<&str>> = bed.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0], ["demo", "0", "2", "conserved", "1000"]);
+        assert_eq!(fields[1][..4], ["demo", "2", "3", "variable"]);
+        assert_eq!(fields[2], ["demo", "3", "4", "conserved", "1000"]);
+
+        // HMMER marks match columns with lowercase `x`
+        let contents = "# STOCKHOLM 1.0\n#=GF ID demo\n\
+            s1 AAGAC\ns2 AAUCC\ns3 AACGC\n#=GC RF xx.xx\n//\n";
+        std::fs::write(&path, contents).unwrap();
+        app.load_file(&path).unwrap();
+        app.dispatch_command(
+            &["write-cons-bed", "0.9", out.to_str().unwrap()],
+            "write-cons-bed",
+        );
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), bed);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_write_columns_individually() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GUACGUA\ns2 A-CGUACGUA\n//\n";
+        let path = write_temp("percol", contents);
+        let dir = std::env::temp_dir().join(format!("aform_percol_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.dispatch_command(&["write-per-col", dir.to_str().unwrap()], "write-per-col");
+        assert!(app.status_message.as_deref().unwrap().contains("10 column"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("col_01.fa")).unwrap(),
+            ">s1\nA\n>s2\nA\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("col_02.fa")).unwrap(),
+            ">s1\nC\n"
+        );
+        assert!(dir.join("col_10.fa").exists());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_remove_insertion_columns() {
+        // Lowercase, '.' and '-' RF columns are insertions
+        let contents = "# STOCKHOLM 1.0\ns1 ACGUACG\n#=GR s1 PP 9876543\ns2 AacguGU\n\
+                        #=GC SS_cons <.....>\n#=GC RF Ac.g-GU\n//\n";
+        let path = write_temp("ungap_rf", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(app.remove_insertion_columns(), Ok(4));
+        let data: Vec<String> = app.alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["ACG", "AGU"]);
+        assert_eq!(app.alignment.residue_annotations["s1"][0].data, "943");
+        assert_eq!(app.alignment.ss_cons(), Some("<.>"));
+        assert_eq!(app.alignment.rf(), Some("AGU"));
+        assert_eq!(app.remove_insertion_columns(), Ok(0));
+
+        app.undo();
+        assert_eq!(app.alignment.sequences[0].data(), "ACGUACG");
+
+        // HMMER-style 'x' markers are all match columns
+        let xs = write_temp("ungap_rf_x", "# STOCKHOLM 1.0\ns1 ACGU\n#=GC RF x..x\n//\n");
+        app.load_file(&xs).unwrap();
+        app.dispatch_command(&["ungap-rf"], "ungap-rf");
+        assert_eq!(app.alignment.sequences[0].data(), "AU");
+
+        let none = write_temp("ungap_rf_none", "# STOCKHOLM 1.0\ns1 ACGU\n//\n");
+        app.load_file(&none).unwrap();
+        assert!(app.remove_insertion_columns().is_err());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&xs);
+        let _ = std::fs::remove_file(&none);
+    }
+
+    #[test]
+    fn test_rf_conservation() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GU\ns2 AG-GA\ns3 AGUGA\n#=GC RF   Ax.GU\n//\n";
+        let path = write_temp("rfcons", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let cons = app.compute_rf_conservation();
+        let cols: Vec<usize> = cons.iter().map(|&(col, _)| col).collect();
+        assert_eq!(cols, vec![0, 3, 4]);
+        assert!((cons[0].1 - 1.0).abs() < 1e-9);
+        assert!((cons[2].1 - 2.0 / 3.0).abs() < 1e-9);
+        // Columns 1 (2/3) and 2 (1 residue, fully conserved)
+        let insert = app.compute_insert_conservation().unwrap();
+        assert!((insert - (2.0 / 3.0 + 1.0) / 2.0).abs() < 1e-9);
+
+        app.dispatch_command(&["show-rf-cons"], "show-rf-cons");
+        assert!(matches!(
+            app.report,
+            Some(Report::RfConservation(_, Some(_)))
+        ));
+
+        // HMMER marks match columns with lowercase `x`
+        let contents = "# STOCKHOLM 1.0\ns1 AC-GU\ns2 AG-GA\n#=GC RF   xx.xx\n//\n";
+        std::fs::write(&path, contents).unwrap();
+        app.load_file(&path).unwrap();
+        let cols: Vec<usize> = app
+            .compute_rf_conservation()
+            .iter()
+            .map(|&(col, _)| col)
+            .collect();
+        assert_eq!(cols, vec![0, 1, 3, 4]);
+        assert_eq!(app.compute_insert_conservation(), Some(0.0));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_write_structure_svg() {
+        let contents = "# STOCKHOLM 1.0\ns1 GGGAAAUCC\ns2 GGGAAAUCC\n#=GC SS_cons (((...)))\n//\n";
+        let path = write_temp("arcsvg", contents);
+        let out = std::env::temp_dir().join(format!("aform_arcs_{}.svg", std::process::id()));
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.dispatch_command(&["write-svg", out.to_str().unwrap()], "write-svg");
+        assert!(app.status_message.as_deref().unwrap().contains("3 pairs"));
+        let svg = std::fs::read_to_string(&out).unwrap();
+        assert!(svg.starts_with("<?xml"));
+        // Two G-C pairs arc above the axis, the G-U wobble below
+        assert_eq!(svg.matches(" 0 0 1 ").count(), 2);
+        assert_eq!(svg.matches(" 0 0 0 ").count(), 1);
+        assert!(svg.contains(">U</text>"));
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_sort_sequences_by_gs() {
+        let contents = "# STOCKHOLM 1.0\n#=GS a LEN 10\n#=GS b LEN 9\n#=GS d LEN 100\n#=GS a OS Mus\n#=GS c OS Homo\na A\nb C\nc G\nd U\n//\n";
+        let path = write_temp("sortgs", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        let ids = |app: &App| -> Vec<String> {
+            app.alignment
+                .sequences
+                .iter()
+                .map(|seq| seq.id.clone())
+                .collect()
+        };
+
+        // Numeric, with the sequence lacking LEN last
+        app.execute_transform_command(&["sort-gs", "LEN"]);
+        assert_eq!(ids(&app), ["b", "a", "d", "c"]);
+        app.execute_transform_command(&["sort-gs-r", "LEN"]);
+        assert_eq!(ids(&app), ["d", "a", "b", "c"]);
+
+        // Lexicographic
+        app.execute_transform_command(&["sort-gs", "OS"]);
+        assert_eq!(ids(&app), ["c", "a", "d", "b"]);
+
+        app.execute_transform_command(&["sort-gs", "XX"]);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("No sequence")
+        );
+        app.undo();
+        assert_eq!(ids(&app), ["d", "a", "b", "c"]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_set_poll_interval() {
+        let mut app = App::new();
+        assert_eq!(app.poll_interval, std::time::Duration::from_millis(100));
+        app.execute_set_command("poll_interval=50");
+        assert_eq!(app.poll_interval, std::time::Duration::from_millis(50));
+        app.execute_set_command("poll_interval=0");
+        assert_eq!(app.poll_interval, std::time::Duration::from_millis(50));
+        assert!(app.status_message.as_deref().unwrap().contains("Invalid"));
+    }
+
+    #[test]
+    fn test_command_history_popup() {
+        let mut app = App::new();
+        for command in ["ruler", "rownum", "set block_width=30"] {
+            app.line_input = tui_input::Input::new(command.to_string());
+            app.execute_command();
+        }
+        assert!(!app.show_ruler);
+
+        app.open_command_history();
+        assert!(app.show_history_popup);
+        assert_eq!(
+            app.history_matches(),
+            vec![(3, "set block_width=30"), (2, "rownum"), (1, "ruler")]
+        );
+
+        app.history_filter = "ru".to_string();
+        assert_eq!(app.history_matches(), vec![(1, "ruler")]);
+        app.history_move(5);
+        assert_eq!(app.history_selection, 0);
+
+        // Running an entry executes it again and records it as the newest
+        app.history_confirm();
+        assert!(!app.show_history_popup);
+        assert!(app.show_ruler);
+        assert_eq!(app.command_history.entries().last().unwrap(), "ruler");
+    }
+
+    #[test]
+    fn test_shade_regions() {
+        let path = write_temp("shade", "# STOCKHOLM 1.0\ns1 ACGUACGU\n//\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_display_command(&["shade", "2-4"]);
+        app.execute_display_command(&["shade", "4-6", "#102030"]);
+        let rgb = |c: Option<Rgb>| c.map(|c| (c.r, c.g, c.b));
+        assert_eq!(rgb(app.shade_color(0)), None);
+        assert_eq!(
+            rgb(app.shade_color(1)),
+            Some((
+                DEFAULT_SHADE_COLOR.r,
+                DEFAULT_SHADE_COLOR.g,
+                DEFAULT_SHADE_COLOR.b
+            ))
+        );
+        // The later region wins where they overlap
+        assert_eq!(rgb(app.shade_color(3)), Some((0x10, 0x20, 0x30)));
+
+        app.execute_display_command(&["shade", "5-9"]);
+        assert!(app.status_message.as_deref().unwrap().contains("1-8"));
+        app.execute_display_command(&["shade", "x"]);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("Invalid range")
+        );
+
+        app.execute_display_command(&["unshade", "2-3"]);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("not shaded")
+        );
+        app.execute_display_command(&["unshade", "2-4"]);
+        assert_eq!(rgb(app.shade_color(1)), None);
+
+        // Regions move with inserted columns and go with a new file
+        app.insert_gap_column_range(0, 1);
+        assert_eq!(app.shaded_regions[0].0..=app.shaded_regions[0].1, 4..=6);
+        app.execute_display_command(&["shade", "8-9"]);
+        // Undo shrinks the alignment; regions are cut back to fit it
+        app.undo();
+        assert_eq!(app.shaded_regions[1].0..=app.shaded_regions[1].1, 7..=7);
+        app.load_file(&path).unwrap();
+        assert!(app.shaded_regions.is_empty());
+
+        app.execute_display_command(&["shade", "2-4"]);
+        app.execute_display_command(&["unshade-all"]);
+        assert!(app.shaded_regions.is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_column_color_overrides() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\n//\n";
+        let path = write_temp("colorat", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.execute_display_command(&["set-color-at", "2", "#ff0000"]);
+        app.execute_display_command(&["set-color-at", "4", "#00ff00"]);
+        let color = app.column_color_overrides[&1];
+        assert_eq!((color.r, color.g, color.b), (255, 0, 0));
+        assert!(app.set_column_color(5, "#ff0000").is_err());
+        assert!(app.set_column_color(1, "red").is_err());
+
+        // Overrides move with inserted columns and go with a new file
+        app.insert_gap_column_range(0, 1);
+        assert!(app.column_color_overrides.contains_key(&2));
+        assert!(!app.column_color_overrides.contains_key(&1));
+        // Undo shrinks the alignment; the override pushed past its end goes
+        app.undo();
+        assert!(app.column_color_overrides.contains_key(&2));
+        assert!(!app.column_color_overrides.contains_key(&4));
+        app.load_file(&path).unwrap();
+        assert!(app.column_color_overrides.is_empty());
+
+        app.execute_display_command(&["set-color-at", "2", "#ff0000"]);
+        app.execute_display_command(&["set-color-at", "4", "#00ff00"]);
+        app.execute_display_command(&["clear-color-at", "2"]);
+        assert!(!app.column_color_overrides.contains_key(&1));
+        app.execute_display_command(&["clear-color-all"]);
+        assert!(app.column_color_overrides.is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_remove_duplicate_ids() {
+        let contents = "# STOCKHOLM 1.0\n#=GS s1 DE first\ns1 ACGU\ns2 AC-U\ns1 GGGG\n\
+                        s3 CCCC\ns2 UUUU\n//\n";
+        let path = write_temp("dedup_ids", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        assert_eq!(app.alignment.sequences.len(), 5);
+        assert!(app.status_message.as_ref().unwrap().contains("s1, s2"));
+
+        app.execute_transform_command(&["dedup-ids"]);
+        let data: Vec<String> = app.alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["ACGU", "AC-U", "CCCC"]);
+        assert!(app.alignment.sequence_annotations.contains_key("s1"));
+        assert!(app.status_message.as_ref().unwrap().contains("Removed 2"));
+        assert!(app.modified);
+        assert_eq!(app.remove_duplicate_ids(), 0);
+
+        app.undo();
+        assert_eq!(app.alignment.sequences.len(), 5);
+    }
+
+    #[test]
+    fn test_write_markdown_view() {
+        let contents = "# STOCKHOLM 1.0\nseq1 ACGUACGUACGU\nlong_id/3-12 AC-UACGUAC-U\n\
+                        #=GC SS_cons <<<....>>>..\n//\n";
+        let path = write_temp("markdown", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let out = std::env::temp_dir().join("aform_test_view.md");
+        app.write_markdown_view(&out, 2, 9).unwrap();
+        let md = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(lines.first(), Some(&"```text"));
+        assert_eq!(lines[1], "                    10");
+        assert_eq!(lines[2], "seq1          GUACGUACG");
+        assert_eq!(lines[3], "long_id/3-12  -UACGUAC-");
+        assert_eq!(lines[4], "#=GC SS_cons  <....>>>.");
+        assert_eq!(lines.last(), Some(&"```"));
+
+        assert!(app.write_markdown_view(&out, 12, 5).is_err());
+
+        // Zoomed out with short IDs, the export matches the screen
+        app.show_short_ids = true;
+        app.column_stride = 2;
+        app.write_markdown_view(&out, 0, 6).unwrap();
+        let md = std::fs::read_to_string(&out).unwrap();
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(lines[2], "seq1          AGAGAG");
+        assert_eq!(lines[3], "long_id       A-AGA-");
+        assert_eq!(lines[4], "#=GC SS_cons  <<..>.");
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_write_html_view() {
+        let contents = "# STOCKHOLM 1.0\ns<1> ACGU\ns2   AC-U\n#=GC SS_cons <..>\n//\n";
+        let path = write_temp("html_view", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.color_scheme = ColorScheme::Base;
+
+        let out = std::env::temp_dir().join("aform_test_view.html");
+        app.execute_export_command(&["write-html", out.to_str().unwrap()]);
+        let html = std::fs::read_to_string(&out).unwrap();
+        assert!(html.contains("<pre>"));
+        assert!(html.contains("s&lt;1&gt;"));
+        assert_eq!(html.matches("<span style=\"background-color: #").count(), 9);
+        assert!(html.contains("&lt;..&gt;</span>"));
+        let _ = std::fs::remove_file(&out);
+    }
+
+    #[test]
+    fn test_auto_color() {
+        let path = write_temp("auto_color_rna", "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\n//\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.execute_display_command(&["auto-color"]);
+        assert_eq!(app.color_scheme, ColorScheme::Base);
+
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\n#=GC SS_cons <..>\n//\n";
+        let path = write_temp("auto_color_ss", contents);
+        app.load_file(&path).unwrap();
+        app.auto_color();
+        assert_eq!(app.color_scheme, ColorScheme::Structure);
+
+        let contents = "# STOCKHOLM 1.0\np1 MKVLWE\np2 MKILWD\n#=GC SS_cons HHHH..\n//\n";
+        let path = write_temp("auto_color_protein", contents);
+        app.load_file(&path).unwrap();
+        app.auto_configure_display();
+        assert_eq!(app.color_scheme, ColorScheme::Base);
+        assert!(app.show_conservation_bar);
+
+        // A scheme picked with :color survives :e
+        app.execute_display_command(&["color", "cons"]);
+        app.execute_file_command(&["e", path.to_str().unwrap()], "");
+        assert_eq!(app.color_scheme, ColorScheme::Conservation);
+        app.execute_display_command(&["auto-color"]);
+        app.execute_file_command(&["e", path.to_str().unwrap()], "");
+        assert_eq!(app.color_scheme, ColorScheme::Base);
+    }
+
+    #[test]
+    fn test_compute_sequence_gc() {
+        let contents = "# STOCKHOLM 1.0\nat AUAU--\ngc GGCC--\nmid ACGU--\nempty ------\n//\n";
+        let path = write_temp("gc_content", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let gc = app.compute_sequence_gc();
+        let ids: Vec<&str> = gc.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["gc", "mid", "at", "empty"]);
+        assert!((gc[1].1 - 0.5).abs() < 1e-9);
+
+        app.execute_display_command(&["show-gc"]);
+        assert!(matches!(app.report, Some(Report::GcContent(_))));
+        assert!(app.jump_to_sequence_id("mid"));
+        assert_eq!(app.cursor_row, 2);
+        assert!(!app.jump_to_sequence_id("missing"));
+    }
+
+    #[test]
+    fn test_compute_gap_fractions() {
+        let contents = "# STOCKHOLM 1.0\nfull ACGU\nhalf A--U\nmost -.-U\n//\n";
+        let path = write_temp("gapfractions", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let gaps = app.compute_gap_fractions();
+        assert_eq!(
+            gaps,
+            vec![
+                ("most".to_string(), 0.75),
+                ("half".to_string(), 0.5),
+                ("full".to_string(), 0.0)
+            ]
+        );
+
+        app.execute_display_command(&["show-gap-seqs"]);
+        assert!(matches!(app.report, Some(Report::GapFractions(_))));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_show_column_annotation() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\n#=GC R2R_LABEL .pq.\n#=GC SS_cons <..>\n//\n";
+        let path = write_temp("showgc", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        app.cursor_col = 2;
+        app.execute_display_command(&["show-gc", "R2R_LABEL"]);
+        match &app.report {
+            Some(Report::ColumnAnnotation {
+                tag,
+                data,
+                selected,
+            }) => {
+                assert_eq!(tag, "R2R_LABEL");
+                assert_eq!(data, ".pq.");
+                assert_eq!(*selected, 2);
+            }
+            _ => panic!("expected a #=GC report"),
+        }
+
+        app.report = None;
+        app.execute_display_command(&["show-gc", "MISSING"]);
+        assert!(app.report.is_none());
+        let status = app.status_message.as_deref().unwrap();
+        assert!(status.contains("R2R_LABEL, SS_cons"));
+
+        // Without a tag it is still the per-sequence GC content report
+        app.execute_display_command(&["show-gc"]);
+        assert!(matches!(app.report, Some(Report::GcContent(_))));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_check_alphabet() {
+        let contents = "# STOCKHOLM 1.0\nzeta ACGUN\nalpha acg-X\nmid ACGU.\n//\n";
+        let path = write_temp("alphabet", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        assert_eq!(
+            app.check_alphabet(),
+            vec![("alpha".to_string(), 4, 'X'), ("zeta".to_string(), 4, 'N')]
+        );
+        app.execute_display_command(&["check-alphabet"]);
+        assert!(matches!(app.report, Some(Report::AlphabetIssues(_))));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_run_diagnostics() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU.A\ns2 AC-U.G\nempty ---...\n#=GR s1 PP 999999\n#=GC SS_cons <<..>>\n//\n";
+        let path = write_temp("diagnostics", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let issues = app.run_diagnostics();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].seq_id.as_deref(), Some("empty"));
+        assert_eq!(issues[1].col, Some(4));
+
+        // Edits that bypass the editor's length bookkeeping are reported
+        app.alignment.sequences[1] =
+            std::rc::Rc::new(crate::stockholm::Sequence::new("s2", "AC-U.GG"));
+        app.alignment.residue_annotations.get_mut("s1").unwrap()[0].data = "99".to_string();
+        app.alignment.column_annotations[0].data = "<<..>.".to_string();
+        let messages: Vec<String> = app
+            .run_diagnostics()
+            .into_iter()
+            .map(|issue| issue.message)
+            .collect();
+        assert!(messages.contains(&"Sequence has 7 columns, expected 6".to_string()));
+        assert!(messages.contains(&"#=GR PP has 2 columns, expected 6".to_string()));
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.starts_with("SS_cons: Unmatched opening"))
+        );
+
+        app.execute_display_command(&["diagnostic"]);
+        assert!(matches!(app.report, Some(Report::Diagnostics(_))));
+
+        // A ragged file loads with a warning instead of failing, and a column
+        // with the same residue everywhere cannot be paired
+        let contents = "# STOCKHOLM 1.0\ns1 GACGUC\ns2 GAUAUCC\n#=GC SS_cons <....>\n//\n";
+        std::fs::write(&path, contents).unwrap();
+        app.load_file(&path).unwrap();
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("lengths differ")
+        );
+        let issues = app.run_diagnostics();
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].seq_id.as_deref(), Some("s2"));
+        assert_eq!(
+            issues[1].message,
+            "Paired with column 6 in SS_cons but 'G' in every sequence"
+        );
+        assert_eq!(issues[2].col, Some(5));
+
+        let _ = std::fs::remove_file(&path);
     }
 
-    /// Toggle dendrogram tree visibility.
-    pub fn toggle_tree(&mut self) {
-        if self.cluster_tree.is_some() {
-            self.show_tree = !self.show_tree;
-        } else {
-            self.status_message = Some("No tree available. Run :cluster first.".to_string());
-        }
-    }
+    #[test]
+    fn test_estimate_memory_usage() {
+        let contents = "# STOCKHOLM 1.0\n#=GS s1 DE x\ns1 ACGU\ns2 AC-U\n#=GR s1 PP 9999\n#=GC SS_cons <..>\n//\n";
+        let path = write_temp("memory", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
 
-    // === Collapse identical sequences ===
+        let stats = app.estimate_memory_usage();
+        let seq_bytes = std::mem::size_of::<crate::stockholm::Sequence>() + 4 * 4 + 2;
+        assert_eq!(stats.sequences, 2 * seq_bytes);
+        assert_eq!(stats.column_annotations, "SS_cons".len() + 4);
+        assert_eq!(stats.residue_annotations, "s1PP9999".len());
+        assert_eq!(stats.sequence_annotations, "s1DEx".len());
+        assert_eq!(stats.undo_states, 0);
+
+        // Editing one sequence leaves the other shared with the snapshot
+        app.cursor_row = 1;
+        app.cursor_col = 2;
+        app.delete_gap();
+        let stats = app.estimate_memory_usage();
+        assert_eq!(stats.undo_states, 2);
+        let annotation_bytes =
+            stats.column_annotations + stats.residue_annotations + stats.sequence_annotations;
+        assert_eq!(stats.undo_history, seq_bytes + 2 * annotation_bytes);
+
+        app.execute_display_command(&["show-memory"]);
+        assert!(matches!(app.report, Some(Report::Memory(_))));
 
-    /// Pre-compute collapse groups by grouping sequences with identical content.
-    /// Called during load since sequences don't change during viewing.
-    pub fn precompute_collapse_groups(&mut self) {
-        use std::collections::HashMap;
-        self.collapse_groups.clear();
+        let _ = std::fs::remove_file(&path);
+    }
 
-        if self.alignment.sequences.is_empty() {
-            return;
-        }
+    #[test]
+    fn test_compute_ungapped_lengths() {
+        let contents = "# STOCKHOLM 1.0\nshort A--U\nfull ACGU\nnone -.-~\n//\n";
+        let path = write_temp("ungappedlen", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
 
-        // Group by sequence content (chars as String for hashing)
-        let mut content_map: HashMap<String, Vec<usize>> = HashMap::new();
-        for (idx, seq) in self.alignment.sequences.iter().enumerate() {
-            content_map.entry(seq.data()).or_default().push(idx);
-        }
+        assert_eq!(
+            app.compute_ungapped_lengths(),
+            vec![
+                ("short".to_string(), 2),
+                ("full".to_string(), 4),
+                ("none".to_string(), 0)
+            ]
+        );
 
-        // Build groups preserving original order (first occurrence is representative)
-        let mut seen = std::collections::HashSet::new();
-        for (idx, seq) in self.alignment.sequences.iter().enumerate() {
-            let content = seq.data();
-            if seen.insert(content.clone()) {
-                let indices = content_map.remove(&content).unwrap();
-                self.collapse_groups.push((idx, indices));
+        app.execute_display_command(&["show-lengths"]);
+        match &app.report {
+            Some(Report::SequenceLengths(rows)) => {
+                assert_eq!(rows[0], ("full".to_string(), 4));
+                assert_eq!(rows[2], ("none".to_string(), 0));
             }
+            _ => panic!("expected a length report"),
         }
-    }
 
-    /// Get collapse count for a display row (1 if not collapsed or unique).
-    pub fn get_collapse_count(&self, display_row: usize) -> usize {
-        if self.collapse_identical && !self.collapse_groups.is_empty() {
-            // When clustering is also enabled, use group_order to find correct group
-            let group_idx = if let Some(ref group_order) = self.cluster_group_order {
-                group_order.get(display_row).copied().unwrap_or(display_row)
-            } else {
-                display_row
-            };
+        let _ = std::fs::remove_file(&path);
+    }
 
-            if group_idx < self.collapse_groups.len() {
-                self.collapse_groups[group_idx].1.len()
-            } else {
-                1
-            }
-        } else {
-            1
-        }
+    #[test]
+    fn test_link_panes() {
+        let mut app = App::new();
+        app.vertical_split();
+        app.execute_display_command(&["link-panes"]);
+        assert!(app.linked_panes);
+
+        app.viewport_col = 40;
+        app.viewport_row = 3;
+        app.sync_linked_viewport();
+        assert_eq!(app.secondary_viewport_col, 40);
+        assert_eq!(app.secondary_viewport_row, 0);
+
+        // The link holds after switching panes
+        app.switch_pane();
+        app.viewport_col = 12;
+        app.sync_linked_viewport();
+        app.switch_pane();
+        assert_eq!(app.viewport_col, 12);
+
+        app.horizontal_split();
+        app.viewport_row = 5;
+        app.sync_linked_viewport();
+        assert_eq!(app.secondary_viewport_row, 5);
+
+        app.execute_display_command(&["unlink-panes"]);
+        app.viewport_row = 7;
+        app.sync_linked_viewport();
+        assert_eq!(app.secondary_viewport_row, 5);
     }
 
-    /// Get the maximum collapse count across all groups.
-    pub fn max_collapse_count(&self) -> usize {
-        if self.collapse_identical {
-            self.collapse_groups
-                .iter()
-                .map(|(_, g)| g.len())
-                .max()
-                .unwrap_or(1)
-        } else {
-            1
-        }
+    #[test]
+    fn test_watch_reload() {
+        let path = write_temp("watch", "# STOCKHOLM 1.0\ns1 ACGUACGU\ns2 ACGAACGA\n//\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.execute_display_command(&["watch"]);
+        assert!(app.watch_mode);
+        app.cursor_row = 1;
+        app.cursor_col = 5;
+
+        std::fs::write(&path, "# STOCKHOLM 1.0\ns1 GGGUACGU\ns2 ACGAACGA\n//\n").unwrap();
+        // Force a different mtime and skip the one-second rate limit
+        app.file_mtime = Some(std::time::SystemTime::UNIX_EPOCH);
+        app.watch_checked_at -= WATCH_INTERVAL;
+        app.check_watched_file();
+        assert_eq!(app.alignment.sequences[0].data(), "GGGUACGU");
+        assert_eq!((app.cursor_row, app.cursor_col), (1, 5));
+
+        // Unsaved edits are kept
+        app.modified = true;
+        std::fs::write(&path, "# STOCKHOLM 1.0\ns1 CCCUACGU\ns2 ACGAACGA\n//\n").unwrap();
+        app.file_mtime = Some(std::time::SystemTime::UNIX_EPOCH);
+        app.watch_checked_at -= WATCH_INTERVAL;
+        app.check_watched_file();
+        assert_eq!(app.alignment.sequences[0].data(), "GGGUACGU");
+        assert!(app.status_message.as_ref().unwrap().contains("unsaved"));
+
+        let _ = std::fs::remove_file(&path);
     }
 
-    /// Toggle collapse identical sequences.
-    pub fn toggle_collapse_identical(&mut self) {
-        self.collapse_identical = !self.collapse_identical;
-        // Groups are pre-computed during load, just flip the flag
+    #[test]
+    fn test_annotate_helices() {
+        let contents = "# STOCKHOLM 1.0\ns1 GGAAACCUUGGGAAACCCA\n\
+                        #=GC SS_cons <<...>>..<<<...>>>.\n//\n";
+        let path = write_temp("annotate_helices", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        assert_eq!(
+            app.generate_helix_annotation().as_deref(),
+            Some("11...11..222...222.")
+        );
 
-        // Clamp cursor to visible range
-        if self.cursor_row >= self.visible_sequence_count() {
-            self.cursor_row = self.visible_sequence_count().saturating_sub(1);
-        }
+        app.execute_display_command(&["annotate-helices"]);
+        assert_eq!(app.alignment.helix(), Some("11...11..222...222."));
+        assert!(app.modified);
+        assert!(
+            !app.visible_annotations(&app.alignment)
+                .contains(&AnnotationKind::Helix)
+        );
+        app.execute_display_command(&["helix-bar"]);
+        assert!(
+            app.visible_annotations(&app.alignment)
+                .contains(&AnnotationKind::Helix)
+        );
 
-        let msg = if self.collapse_identical {
-            format!(
-                "Collapsed {} sequences into {} groups",
-                self.alignment.num_sequences(),
-                self.collapse_groups.len()
-            )
-        } else {
-            "Collapse disabled".to_string()
-        };
-        self.status_message = Some(msg);
+        app.undo();
+        assert_eq!(app.alignment.helix(), None);
+
+        let _ = std::fs::remove_file(&path);
     }
 
-    // === Sequence type detection ===
+    #[test]
+    fn test_add_consensus_sequence() {
+        let contents = "# STOCKHOLM 1.0\ns1 AC-G.\ns2 AC-U.\ns3 AG-U.\n//\n";
+        let path = write_temp("consensusseq", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.reference_id = Some("s2".to_string());
 
-    /// Detect sequence type from alignment content.
-    pub fn detect_sequence_type(&mut self) {
-        self.sequence_type = crate::color::detect_sequence_type(&self.alignment, &self.gap_chars);
+        app.execute_transform_command(&["consensus-seq"]);
+        assert_eq!(app.alignment.sequences.len(), 4);
+        assert_eq!(app.alignment.sequences[0].id, "CONSENSUS");
+        assert_eq!(app.alignment.sequences[0].data(), "AC.U.");
+        assert_eq!(app.reference_row(), 2);
+
+        // A second run refreshes the row instead of adding another
+        std::rc::Rc::make_mut(&mut app.alignment.sequences[3]).set(3, 'G');
+        app.execute_transform_command(&["consensus-seq"]);
+        assert_eq!(app.alignment.sequences.len(), 4);
+        assert_eq!(app.alignment.sequences[0].data(), "AC.G.");
+        assert!(app.status_message.as_deref().unwrap().contains("Updated"));
+
+        let _ = std::fs::remove_file(&path);
     }
 
-    /// Auto-configure display settings based on detected sequence type.
-    /// For protein: enable base coloring, consensus, and conservation bar.
-    /// For RNA/DNA with SS_cons: enable structure coloring.
-    pub fn auto_configure_display(&mut self) {
-        if self.sequence_type == crate::stockholm::SequenceType::Protein {
-            self.color_scheme = ColorScheme::Base;
-            self.show_consensus = true;
-            self.show_conservation_bar = true;
-        } else if self.alignment.ss_cons().is_some() {
-            self.color_scheme = ColorScheme::Structure;
-        }
+    #[test]
+    fn test_add_rf_as_sequence() {
+        let path = write_temp("rftoseq", "# STOCKHOLM 1.0\ns1 ACGU\n//\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.execute_transform_command(&["rf-to-seq"]);
+        assert_eq!(app.status_message.as_deref(), Some("No RF annotation"));
+
+        let path2 = write_temp("rftoseq_rf", "# STOCKHOLM 1.0\ns1 ACGU\n#=GC RF AC.U\n//\n");
+        app.load_file(&path2).unwrap();
+        app.execute_transform_command(&["rf-to-seq"]);
+        assert_eq!(app.alignment.sequences[0].id, "RF_REFERENCE");
+        assert_eq!(app.alignment.sequences[0].data(), "AC.U");
+        assert_eq!(app.reference_row(), 1);
+        app.execute_transform_command(&["rf-to-seq"]);
+        assert_eq!(app.alignment.sequences.len(), 2);
+
+        app.undo();
+        app.undo();
+        assert_eq!(app.alignment.sequences.len(), 1);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&path2);
     }
 
-    // === Gap column methods ===
+    #[test]
+    fn test_import_hmm_consensus() {
+        let hmm = "\
+HMMER3/f [3.4 | Aug 2023]
+NAME  hairpin
+LENG  4
+ALPH  RNA
+CS    yes
+HMM          A        C        G        U
+  COMPO   1.38629  1.38629  1.38629  1.38629
+      1   0.1 3.0 3.0 3.0      1 G x - <
+      2   0.1 3.0 3.0 3.0      2 a x - .
+      3   0.1 3.0 3.0 3.0      3 a x - .
+      4   0.1 3.0 3.0 3.0      4 C x - >
+//
+";
+        let hmm_path = write_temp("hmmcons_model", hmm);
+        let path = write_temp("hmmcons", "# STOCKHOLM 1.0\ns1 GAAC\n//\n");
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
 
-    /// Precompute visible columns (call after loading alignment or toggling hide_gap_columns).
-    pub fn precompute_visible_columns(&mut self) {
-        if self.hide_gap_columns {
-            self.visible_columns = (0..self.alignment.width())
-                .filter(|&col| !self.alignment.is_empty_column(col, &self.gap_chars))
-                .collect();
-        } else {
-            self.visible_columns.clear();
-        }
+        app.execute_transform_command(&["import-hmm-cons", hmm_path.to_str().unwrap()]);
+        assert_eq!(app.alignment.sequences.len(), 2);
+        assert_eq!(app.alignment.sequences[1].id, "HMM_CONSENSUS");
+        assert_eq!(app.alignment.sequences[1].data(), "GaaC");
+        assert_eq!(app.alignment.ss_cons(), Some("<..>"));
+
+        // Importing twice would duplicate the ID
+        app.execute_transform_command(&["import-hmm-cons", hmm_path.to_str().unwrap()]);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("already exists")
+        );
+
+        app.undo();
+        assert_eq!(app.alignment.sequences.len(), 1);
+        assert_eq!(app.alignment.ss_cons(), None);
+
+        let wide = write_temp("hmmcons_wide", "# STOCKHOLM 1.0\ns1 GAACU\n//\n");
+        app.load_file(&wide).unwrap();
+        assert!(app.import_hmm_consensus(&hmm_path).is_err());
+
+        let _ = std::fs::remove_file(&hmm_path);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&wide);
     }
 
-    /// Map display column index to actual column index.
-    pub fn display_to_actual_col(&self, display_col: usize) -> usize {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
-            self.visible_columns
-                .get(display_col)
-                .copied()
-                .unwrap_or(display_col)
-        } else {
-            display_col
-        }
+    #[test]
+    fn test_motif_highlight_priority() {
+        let mut app = App::new();
+        assert!(app.set_motif_highlight(0, "GA", "#FF8000").is_err());
+        assert!(app.set_motif_highlight(1, "GA", "orange").is_err());
+        app.set_motif_highlight(2, "aga", "#00FFFF").unwrap();
+        app.set_motif_highlight(1, "GA", "#FF8000").unwrap();
+
+        let seq: Vec<char> = "AG-AC".chars().collect();
+        let colors = app.motif_highlight_colors(&seq);
+        let rgb = |c: Option<Rgb>| c.map(|c| (c.r, c.g, c.b));
+        let colors: Vec<_> = colors.into_iter().map(rgb).collect();
+        // Slot 1 (GA, across the gap) overrides slot 2 (AGA) where they overlap
+        assert_eq!(
+            colors,
+            [
+                Some((0, 255, 255)),
+                Some((255, 128, 0)),
+                Some((255, 128, 0)),
+                Some((255, 128, 0)),
+                None,
+            ]
+        );
+
+        app.clear_motif_highlight(1).unwrap();
+        assert!(app.clear_motif_highlight(1).is_err());
+        let colors = app.motif_highlight_colors(&seq);
+        assert!(colors[..4].iter().all(|c| c.is_some()));
     }
 
-    /// Map actual column index to display column index (returns None if hidden).
-    pub fn actual_to_display_col(&self, actual_col: usize) -> Option<usize> {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
-            self.visible_columns.iter().position(|&c| c == actual_col)
-        } else {
-            Some(actual_col)
-        }
+    #[test]
+    fn test_delete_sequences_by_pattern() {
+        let contents = "# STOCKHOLM 1.0\nhuman_1 A\nmouse_1 C\nhuman_2 G\nrat_12 U\n//\n";
+        let path = write_temp("delete_seqs", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        let ids = |app: &App| -> Vec<String> {
+            app.alignment
+                .sequences
+                .iter()
+                .map(|s| s.id.clone())
+                .collect()
+        };
+
+        assert_eq!(app.delete_sequences_by_pattern("human", false), Ok(2));
+        assert_eq!(ids(&app), ["mouse_1", "rat_12"]);
+        assert!(app.delete_sequences_by_pattern("human", false).is_err());
+
+        // One undo restores everything that was removed.
+        app.undo();
+        assert_eq!(app.alignment.sequences.len(), 4);
+
+        assert_eq!(app.delete_sequences_by_pattern(r"_\d$", true), Ok(3));
+        assert_eq!(ids(&app), ["rat_12"]);
+        assert!(app.delete_sequences_by_pattern("(", true).is_err());
+
+        let _ = std::fs::remove_file(&path);
     }
 
-    /// Get number of visible columns.
-    #[allow(dead_code)] // Part of public API for gap column hiding
-    pub fn visible_column_count(&self) -> usize {
-        if self.hide_gap_columns && !self.visible_columns.is_empty() {
-            self.visible_columns.len()
-        } else {
-            self.alignment.width()
-        }
+    #[test]
+    fn test_compute_alignment_stats() {
+        let contents = "# STOCKHOLM 1.0\ns1 GC-A\ns2 GC-U\ns3 GC-A\n#=GC SS_cons <>..\n//\n";
+        let path = write_temp("stats", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let stats = app.compute_alignment_stats();
+        assert_eq!(stats.num_sequences, 3);
+        assert_eq!(stats.width, 4);
+        assert_eq!(stats.gap_only_columns, 1);
+        assert_eq!(stats.conserved_columns, 2);
+        assert_eq!(stats.base_pairs, 1);
+        assert_eq!(stats.unique_sequences, 2);
+        assert!((stats.gap_percent - 25.0).abs() < 1e-9);
+        // 6 G/C out of 9 residues.
+        assert!((stats.gc_percent.unwrap() - 200.0 / 3.0).abs() < 1e-9);
+        // Columns: 1.0, 1.0, 2/3 -> mean 8/9.
+        assert!((stats.mean_conservation - 8.0 / 9.0).abs() < 1e-9);
+
+        // Edits are counted without reloading
+        app.cursor_row = 2;
+        app.cursor_col = 3;
+        app.insert_gap();
+        assert_eq!(app.compute_alignment_stats().unique_sequences, 3);
+
+        let _ = std::fs::remove_file(&path);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
+    #[test]
+    fn test_write_fasta_by_cluster() {
+        let contents = "# STOCKHOLM 1.0\na1 AAAAAAAA\nc1 CCCCCCCC\na2 AAAAAAAC\nc2 CCCCCCCA\n//\n";
+        let path = write_temp("bycluster", contents);
+        let dir = std::env::temp_dir().join(format!("aform_bycluster_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
 
-    const MULTI: &str = "# STOCKHOLM 1.0\n#=GF ID first\nseqA ACGU\nseqB ACGU\n//\n\
-                         # STOCKHOLM 1.0\n#=GF ID second\nseqC GGGCCC\nseqD GGG..C\n//\n";
+        assert!(app.write_fasta_by_cluster(&dir, None).is_err());
+        app.cluster_sequences();
 
-    fn write_temp(name: &str, contents: &str) -> PathBuf {
-        let mut path = std::env::temp_dir();
-        path.push(format!("aform_{}_{}.stk", name, std::process::id()));
-        let mut f = std::fs::File::create(&path).unwrap();
-        f.write_all(contents.as_bytes()).unwrap();
-        path
+        app.execute_clustering_command(&["write-by-cluster", dir.to_str().unwrap()]);
+        assert!(app.status_message.as_deref().unwrap().contains("2 cluster"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("cluster_1.fa")).unwrap(),
+            ">a1\nAAAAAAAA\n>a2\nAAAAAAAC\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("clusters.tsv")).unwrap(),
+            "sequence_id\tcluster\na1\t1\na2\t1\nc1\t2\nc2\t2\n"
+        );
+
+        // A coarser cut leaves no stale group files behind
+        app.execute_clustering_command(&["write-by-cluster", dir.to_str().unwrap(), "100"]);
+        assert!(app.status_message.as_deref().unwrap().contains("1 cluster"));
+        assert!(dir.join("cluster_1.fa").exists());
+        assert!(!dir.join("cluster_2.fa").exists());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn test_load_multi_alignment() {
-        let path = write_temp("load", MULTI);
+    fn test_number_sequences_by_cluster() {
+        let contents = "# STOCKHOLM 1.0\n#=GS c2 DE second\na1 AAAAAAAA\nc1 CCCCCCCC\na2 AAAAAAAC\nc2 CCCCCCCA\n//\n";
+        let path = write_temp("numberseqs", contents);
         let mut app = App::new();
         app.load_file(&path).unwrap();
 
-        assert_eq!(app.alignments.len(), 2);
-        assert_eq!(app.current_alignment, 0);
-        assert_eq!(app.alignment.get_file_annotation("ID"), Some("first"));
-        assert_eq!(app.alignment.width(), 4);
+        assert!(app.number_sequences_by_cluster(None).is_err());
+        app.cluster_sequences();
+
+        // Without ! only the count is reported
+        app.execute_clustering_command(&["number-seqs"]);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains(":number-seqs!")
+        );
+        assert_eq!(app.alignment.sequences[0].id, "a1");
+
+        app.execute_clustering_command(&["number-seqs!"]);
+        let ids: Vec<&str> = app
+            .alignment
+            .sequences
+            .iter()
+            .map(|seq| seq.id.as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                "group1_member1",
+                "group2_member1",
+                "group1_member2",
+                "group2_member2"
+            ]
+        );
+        assert!(
+            app.alignment
+                .sequence_annotations
+                .contains_key("group2_member2")
+        );
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("2 cluster group")
+        );
+
+        app.undo();
+        assert_eq!(app.alignment.sequences[0].id, "a1");
+        app.execute_clustering_command(&["number-seqs!", "x"]);
+        assert!(
+            app.status_message
+                .as_deref()
+                .unwrap()
+                .contains("Invalid distance")
+        );
 
         let _ = std::fs::remove_file(&path);
     }
 
     #[test]
-    fn test_select_alignment_switches() {
-        let path = write_temp("select", MULTI);
+    fn test_compute_identity_matrix() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\ns3 ACGU\n//\n";
+        let path = write_temp("pidmatrix", contents);
         let mut app = App::new();
         app.load_file(&path).unwrap();
 
-        app.select_alignment(1);
-        assert_eq!(app.current_alignment, 1);
-        assert_eq!(app.alignment.get_file_annotation("ID"), Some("second"));
-        assert_eq!(app.alignment.width(), 6);
+        assert!(app.compute_identity_matrix().is_none());
+        app.execute_clustering_command(&["show-pid"]);
+        assert!(app.report.is_none());
 
-        // Out-of-range selection is rejected, leaving state unchanged.
-        app.select_alignment(99);
-        assert_eq!(app.current_alignment, 1);
+        app.cluster_order = Some(vec![2, 0, 1]);
+        app.execute_clustering_command(&["show-pid"]);
+        let Some(Report::IdentityMatrix(matrix)) = &app.report else {
+            panic!("expected identity matrix report");
+        };
+        assert_eq!(matrix.ids, ["s3", "s1", "s2"]);
+        assert_eq!(matrix.total, 3);
+        assert_eq!(matrix.pids[0][1], Some(100.0));
+        assert_eq!(matrix.pids[1][2], Some(75.0));
+        assert_eq!(matrix.pids[2][2], Some(100.0));
 
         let _ = std::fs::remove_file(&path);
     }
 
     #[test]
-    fn test_picker_navigation_and_confirm() {
-        let path = write_temp("picker", MULTI);
+    fn test_compute_pid_histogram() {
+        // Pairs: s1/s2 75%, s1/s3 100%, s2/s3 75%.
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\ns3 ACGU\n//\n";
+        let path = write_temp("pidhist", contents);
         let mut app = App::new();
         app.load_file(&path).unwrap();
 
-        app.open_msa_picker();
-        assert!(app.show_msa_picker);
-        assert_eq!(app.msa_picker_selection, 0);
+        let hist = app.compute_pid_histogram().unwrap();
+        assert_eq!(hist.pairs, 3);
+        assert!(!hist.sampled);
+        assert_eq!(hist.bins[7], 2);
+        assert_eq!(hist.bins[9], 1);
+        assert_eq!(hist.median, 75.0);
+        assert_eq!(hist.min, 75.0);
+        assert_eq!(hist.max, 100.0);
+        assert!((hist.mean - 250.0 / 3.0).abs() < 1e-9);
 
-        app.msa_picker_move(1);
-        assert_eq!(app.msa_picker_selection, 1);
-        // Clamped at the end.
-        app.msa_picker_move(5);
-        assert_eq!(app.msa_picker_selection, 1);
+        let _ = std::fs::remove_file(&path);
+    }
 
-        app.msa_picker_confirm();
-        assert!(!app.show_msa_picker);
-        assert_eq!(app.current_alignment, 1);
+    #[test]
+    fn test_compute_gap_stats() {
+        let contents = "# STOCKHOLM 1.0\ns1 ACGU\ns2 --GU\ns3 A--U\ns4 AC.-\n//\n";
+        let path = write_temp("gapstats", contents);
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let stats = app.compute_gap_stats();
+        assert_eq!(stats.ungapped_seqs, 1);
+        assert_eq!(stats.terminal_only_seqs, 2);
+        assert_eq!(stats.internal_gap_seqs, 1);
+        assert_eq!(stats.max_run_length, 2);
+        assert_eq!(stats.mean_run_length, 2.0);
+        assert_eq!(stats.seq_gap_fraction, (0.0, 0.5, 0.375, 0.5));
+        assert_eq!(stats.column_gap_fraction, vec![0.25, 0.5, 0.5, 0.25]);
 
         let _ = std::fs::remove_file(&path);
     }
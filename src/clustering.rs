@@ -6,6 +6,9 @@
 use kodama::{Method, linkage};
 use rayon::prelude::*;
 
+/// Default cap on the dendrogram width (in characters).
+pub const DEFAULT_TREE_MAX_WIDTH: usize = 32;
+
 /// Result of clustering: leaf order and optional tree visualization.
 #[derive(Debug, Clone)]
 pub struct ClusterResult {
@@ -21,6 +24,76 @@ pub struct ClusterResult {
     /// Tree lines for collapsed view (one per group, not per sequence).
     /// Only populated when clustering with collapse groups.
     pub collapsed_tree_lines: Option<Vec<String>>,
+    /// The dendrogram itself, for Newick export.
+    pub dendrogram: ClusterTree,
+}
+
+/// A clustering dendrogram, kept so it can be exported without reclustering.
+#[derive(Debug, Clone, Default)]
+pub struct ClusterTree {
+    /// Merge steps over the leaves; step `i` creates cluster `leaves.len() + i`.
+    pub steps: Vec<kodama::Step<f64>>,
+    /// Sequence indices behind each leaf: one sequence, or every member of a
+    /// collapse group when clustering with collapse.
+    pub leaves: Vec<Vec<usize>>,
+}
+
+impl ClusterTree {
+    /// Format the tree in Newick format, e.g. `((a:0.5,b:0.5):1,c:1.5);`.
+    ///
+    /// Sequences are labelled from `labels` (indexed by sequence index); the
+    /// members of a collapse group hang off their leaf at distance 0.
+    pub fn to_newick(&self, labels: &[&str]) -> String {
+        let label = |i: usize| newick_label(labels.get(i).copied().unwrap_or(""));
+        let leaf_text: Vec<String> = self
+            .leaves
+            .iter()
+            .map(|members| match members.as_slice() {
+                [member] => label(*member),
+                members => {
+                    let members: Vec<String> =
+                        members.iter().map(|&m| format!("{}:0", label(m))).collect();
+                    format!("({})", members.join(","))
+                }
+            })
+            .collect();
+        steps_to_newick(&self.steps, &leaf_text)
+    }
+
+    /// Cut the tree into groups whose members were joined at a Hamming
+    /// distance of at most `max_distance`; `None` cuts at half the root
+    /// height. Returns a group label for each of `num_sequences` sequences,
+    /// equal within a group.
+    pub fn cut(&self, num_sequences: usize, max_distance: Option<f64>) -> Vec<usize> {
+        let n = self.leaves.len();
+        let cut = max_distance
+            .unwrap_or_else(|| self.steps.last().map_or(0.0, |step| step.dissimilarity) / 2.0);
+
+        // Union-find over leaves; each merge is represented by one of its leaves
+        let mut parent: Vec<usize> = (0..n).collect();
+        let mut step_leaf = Vec::with_capacity(self.steps.len());
+        for step in &self.steps {
+            let leaf = |c: usize| if c < n { c } else { step_leaf[c - n] };
+            let (a, b) = (leaf(step.cluster1), leaf(step.cluster2));
+            if step.dissimilarity <= cut {
+                let (ra, rb) = (find_root(&mut parent, a), find_root(&mut parent, b));
+                parent[rb] = ra;
+            }
+            step_leaf.push(a);
+        }
+
+        // Members of a collapse group share their leaf's group
+        let mut groups = vec![0; num_sequences];
+        for (leaf, members) in self.leaves.iter().enumerate() {
+            let root = find_root(&mut parent, leaf);
+            for &member in members {
+                if let Some(group) = groups.get_mut(member) {
+                    *group = root;
+                }
+            }
+        }
+        groups
+    }
 }
 
 /// Build a 256-entry lookup table marking which ASCII bytes are gap characters.
@@ -56,6 +129,24 @@ pub fn hamming_distance(seq1: &[u8], seq2: &[u8], gap_lut: &[bool; 256]) -> usiz
         .count()
 }
 
+/// Percent identity between two aligned byte sequences.
+///
+/// Identity is `1 - mismatches / columns`, counting only columns where at least
+/// one sequence has a residue (see [`hamming_distance`]). Returns `None` when
+/// both sequences are entirely gaps.
+pub fn percent_identity(seq1: &[u8], seq2: &[u8], gap_lut: &[bool; 256]) -> Option<f64> {
+    let columns = seq1
+        .iter()
+        .zip(seq2.iter())
+        .filter(|&(&a, &b)| !(gap_lut[a as usize] && gap_lut[b as usize]))
+        .count();
+    if columns == 0 {
+        return None;
+    }
+    let mismatches = hamming_distance(seq1, seq2, gap_lut);
+    Some(100.0 * (1.0 - mismatches as f64 / columns as f64))
+}
+
 /// Compute condensed distance matrix for all sequence pairs.
 /// Returns distances in row-major condensed form for kodama:
 /// for i in 0..n, for j in i+1..n. Parallelized across rows with rayon while
@@ -85,11 +176,16 @@ pub fn compute_distance_matrix(sequences: &[Vec<u8>], gap_lut: &[bool; 256]) ->
 /// Uses UPGMA (average linkage) for balanced trees.
 #[allow(dead_code)]
 pub fn cluster_sequences(sequences: &[Vec<u8>], gap_lut: &[bool; 256]) -> Vec<usize> {
-    cluster_sequences_with_tree(sequences, gap_lut).order
+    cluster_sequences_with_tree(sequences, gap_lut, DEFAULT_TREE_MAX_WIDTH).order
 }
 
 /// Perform hierarchical clustering and return both order and tree visualization.
-pub fn cluster_sequences_with_tree(sequences: &[Vec<u8>], gap_lut: &[bool; 256]) -> ClusterResult {
+/// The tree is at most `max_tree_width` characters wide.
+pub fn cluster_sequences_with_tree(
+    sequences: &[Vec<u8>],
+    gap_lut: &[bool; 256],
+    max_tree_width: usize,
+) -> ClusterResult {
     let n = sequences.len();
     if n <= 1 {
         return ClusterResult {
@@ -102,6 +198,10 @@ pub fn cluster_sequences_with_tree(sequences: &[Vec<u8>], gap_lut: &[bool; 256])
             tree_width: if n == 1 { 1 } else { 0 },
             group_order: None,
             collapsed_tree_lines: None,
+            dendrogram: ClusterTree {
+                steps: Vec::new(),
+                leaves: (0..n).map(|i| vec![i]).collect(),
+            },
         };
     }
 
@@ -112,7 +212,7 @@ pub fn cluster_sequences_with_tree(sequences: &[Vec<u8>], gap_lut: &[bool; 256])
     let order = dendrogram_order(&dendrogram, n);
 
     // Build tree visualization
-    let (tree_lines, tree_width) = build_tree_chars(&dendrogram, n, &order);
+    let (tree_lines, tree_width) = build_tree_chars(&dendrogram, n, &order, max_tree_width);
 
     ClusterResult {
         order,
@@ -120,6 +220,10 @@ pub fn cluster_sequences_with_tree(sequences: &[Vec<u8>], gap_lut: &[bool; 256])
         tree_width,
         group_order: None,
         collapsed_tree_lines: None,
+        dendrogram: ClusterTree {
+            steps: dendrogram.steps().to_vec(),
+            leaves: (0..n).map(|i| vec![i]).collect(),
+        },
     }
 }
 
@@ -130,6 +234,7 @@ pub fn cluster_sequences_with_collapse(
     sequences: &[Vec<u8>],
     gap_lut: &[bool; 256],
     collapse_groups: &[(usize, Vec<usize>)],
+    max_tree_width: usize,
 ) -> ClusterResult {
     let n = sequences.len();
     let num_unique = collapse_groups.len();
@@ -137,7 +242,7 @@ pub fn cluster_sequences_with_collapse(
     // If no duplicates or trivial case, use standard clustering
     // but still produce group_order so collapse+cluster works correctly
     if num_unique == n || n <= 1 {
-        let mut result = cluster_sequences_with_tree(sequences, gap_lut);
+        let mut result = cluster_sequences_with_tree(sequences, gap_lut, max_tree_width);
         // Map each sequence index back to its group index
         // When all sequences are unique, group i contains sequence collapse_groups[i].0
         // So we need: for each position in order, find which group that sequence belongs to
@@ -165,6 +270,10 @@ pub fn cluster_sequences_with_collapse(
             tree_width: 1,
             group_order: Some(vec![0]), // Only one group at position 0
             collapsed_tree_lines: Some(vec!["─".to_string()]), // One group = one line
+            dendrogram: ClusterTree {
+                steps: Vec::new(),
+                leaves: vec![collapse_groups[0].1.clone()],
+            },
         };
     }
 
@@ -183,7 +292,8 @@ pub fn cluster_sequences_with_collapse(
     let rep_order = dendrogram_order(&dendrogram, num_unique);
 
     // Build tree for representatives (one line per group / representative).
-    let (rep_tree_lines, tree_width) = build_tree_chars(&dendrogram, num_unique, &rep_order);
+    let (rep_tree_lines, tree_width) =
+        build_tree_chars(&dendrogram, num_unique, &rep_order, max_tree_width);
 
     // Expand order: for each representative in order, include all its members.
     let mut order = Vec::with_capacity(n);
@@ -214,6 +324,13 @@ pub fn cluster_sequences_with_collapse(
         tree_width,
         group_order: Some(rep_order),
         collapsed_tree_lines: Some(collapsed_tree_lines),
+        dendrogram: ClusterTree {
+            steps: dendrogram.steps().to_vec(),
+            leaves: collapse_groups
+                .iter()
+                .map(|(_, members)| members.clone())
+                .collect(),
+        },
     }
 }
 
@@ -252,6 +369,86 @@ fn traverse_cluster(cluster: usize, n: usize, steps: &[kodama::Step<f64>], order
     }
 }
 
+/// Format merge `steps` over leaves written as `leaf_text` in Newick notation.
+///
+/// Node heights are half the merge dissimilarity, so the tree is ultrametric
+/// and the path length between two leaves equals their (average-linkage)
+/// distance.
+fn steps_to_newick(steps: &[kodama::Step<f64>], leaf_text: &[String]) -> String {
+    let mut out = String::new();
+
+    if steps.is_empty() {
+        if let Some(leaf) = leaf_text.first() {
+            out.push_str(leaf);
+        }
+    } else {
+        let root = leaf_text.len() + steps.len() - 1;
+        write_newick_node(root, steps, leaf_text, &mut out);
+    }
+
+    out.push(';');
+    out
+}
+
+/// Union-find root of leaf `i`, halving the path on the way.
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Recursively write a cluster (and its subtree) in Newick notation.
+fn write_newick_node(
+    cluster: usize,
+    steps: &[kodama::Step<f64>],
+    leaf_text: &[String],
+    out: &mut String,
+) {
+    let n = leaf_text.len();
+    if cluster < n {
+        out.push_str(&leaf_text[cluster]);
+        return;
+    }
+
+    let step = &steps[cluster - n];
+    let height = step.dissimilarity / 2.0;
+    let child_height = |c: usize| {
+        if c < n {
+            0.0
+        } else {
+            steps[c - n].dissimilarity / 2.0
+        }
+    };
+
+    out.push('(');
+    write_newick_node(step.cluster1, steps, leaf_text, out);
+    out.push_str(&format!(
+        ":{}",
+        (height - child_height(step.cluster1)).max(0.0)
+    ));
+    out.push(',');
+    write_newick_node(step.cluster2, steps, leaf_text, out);
+    out.push_str(&format!(
+        ":{}",
+        (height - child_height(step.cluster2)).max(0.0)
+    ));
+    out.push(')');
+}
+
+/// Quote a Newick leaf label if it contains characters with special meaning.
+fn newick_label(label: &str) -> String {
+    if label
+        .chars()
+        .any(|c| c.is_whitespace() || "()[]':;,".contains(c))
+    {
+        format!("'{}'", label.replace('\'', "''"))
+    } else {
+        label.to_string()
+    }
+}
+
 /// Merge a newly-drawn box-drawing direction into an existing grid cell so that
 /// segments meeting at a cell pick a visually-correct junction character.
 ///
@@ -327,11 +524,12 @@ fn draw(
 ///
 /// Columns encode merge height (dissimilarity): leaves sit at column 0 on the left,
 /// the root merge sits at the far right. Rows are the display order. Returns
-/// (tree_lines, tree_width).
+/// (tree_lines, tree_width); the width never exceeds `max_width`.
 fn build_tree_chars(
     dend: &kodama::Dendrogram<f64>,
     n: usize,
     order: &[usize],
+    max_width: usize,
 ) -> (Vec<String>, usize) {
     let steps = dend.steps();
 
@@ -345,8 +543,14 @@ fn build_tree_chars(
         orig_to_row[orig] = row;
     }
 
-    // Choose an adaptive tree width, capped so wide dendrograms stay readable.
-    let tree_width = 32.min((n - 1).max(1));
+    // One column per merge level at most, capped so wide dendrograms stay
+    // readable.
+    let mut depth = vec![0usize; n + steps.len()];
+    for (i, step) in steps.iter().enumerate() {
+        depth[n + i] = 1 + depth[step.cluster1].max(depth[step.cluster2]);
+    }
+    let max_depth = depth.last().copied().unwrap_or(1);
+    let tree_width = max_width.min(max_depth).max(1);
 
     let num_nodes = 2 * n - 1;
     let mut conn_row = vec![0usize; num_nodes];
@@ -540,7 +744,7 @@ mod tests {
             .iter()
             .map(|s| s.data().into_bytes())
             .collect();
-        let r = cluster_sequences_with_tree(&seqs, &gaps());
+        let r = cluster_sequences_with_tree(&seqs, &gaps(), DEFAULT_TREE_MAX_WIDTH);
         assert_eq!(
             count_dead_ends(&r.tree_lines, r.tree_width),
             0,
@@ -610,11 +814,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cut_tree() {
+        let seqs = vec![
+            seq("AAAAAAAA"),
+            seq("AAAAAAAC"),
+            seq("CCCCCCCC"),
+            seq("CCCCCCCA"),
+        ];
+        let tree = cluster_sequences_with_tree(&seqs, &gaps(), DEFAULT_TREE_MAX_WIDTH).dendrogram;
+
+        let groups = tree.cut(seqs.len(), None);
+        assert_eq!(groups[0], groups[1]);
+        assert_eq!(groups[2], groups[3]);
+        assert_ne!(groups[0], groups[2]);
+
+        let singletons = tree.cut(seqs.len(), Some(0.0));
+        assert_ne!(singletons[0], singletons[1]);
+        let single = tree.cut(seqs.len(), Some(100.0));
+        assert!(single.iter().all(|&g| g == single[0]));
+    }
+
     #[test]
     fn test_tree_rendering() {
         // Test with 4 sequences: 0,1 similar, 2,3 similar
         let sequences = vec![seq("AAAA"), seq("AAAG"), seq("UUUU"), seq("UUUG")];
-        let result = cluster_sequences_with_tree(&sequences, &gaps());
+        let result = cluster_sequences_with_tree(&sequences, &gaps(), DEFAULT_TREE_MAX_WIDTH);
 
         // Check we got 4 tree lines
         assert_eq!(result.tree_lines.len(), 4);
@@ -651,7 +876,7 @@ mod tests {
         // Three steps: two pair merges (low diss) then the root merge (max diss).
         let max_diss = steps.last().unwrap().dissimilarity;
         assert!(max_diss > 0.0);
-        let tree_width = 32.min((n - 1).max(1));
+        let tree_width = DEFAULT_TREE_MAX_WIDTH.min((n - 1).max(1));
 
         let col_of = |diss: f64| ((diss / max_diss) * (tree_width - 1) as f64).round() as usize;
 
@@ -670,7 +895,7 @@ mod tests {
         );
 
         // And the rendered tree uses only box-drawing characters.
-        let result = cluster_sequences_with_tree(&sequences, &gap_lut);
+        let result = cluster_sequences_with_tree(&sequences, &gap_lut, DEFAULT_TREE_MAX_WIDTH);
         for line in &result.tree_lines {
             assert!(
                 line.chars().all(|c| "─│┌┐└┘├┤┬┴┼╭╮╯╰╷╵╴╶ ".contains(c)),
@@ -680,10 +905,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_percent_identity() {
+        // Both-gap columns are ignored: 3 columns compared, 1 mismatch.
+        let pid = percent_identity(&seq("AC-G."), &seq("AU-G-"), &gaps()).unwrap();
+        assert!((pid - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            percent_identity(&seq("ACGU"), &seq("acgu"), &gaps()),
+            Some(100.0)
+        );
+        assert_eq!(percent_identity(&seq("--"), &seq(".."), &gaps()), None);
+    }
+
+    #[test]
+    fn test_dendrogram_to_newick() {
+        // d(0,1) = 1, d(0,2) = 4, d(1,2) = 3: 0 and 1 merge first at height 0.5,
+        // then join 2 at height (4 + 3) / 2 / 2 = 1.75.
+        let sequences = vec![seq("AAAA"), seq("AAAC"), seq("CCCC")];
+        let tree = cluster_sequences_with_tree(&sequences, &gaps(), 32).dendrogram;
+
+        let newick = tree.to_newick(&["s0", "s1", "s2"]);
+        assert!(newick.ends_with(';'));
+        assert!(newick.contains("(s0:0.5,s1:0.5):1.25"), "{newick}");
+        assert!(newick.contains("s2:1.75"), "{newick}");
+    }
+
+    #[test]
+    fn test_newick_tree_labels() {
+        let tree = cluster_sequences_with_tree(&[seq("ACGU")], &gaps(), 32).dendrogram;
+        assert_eq!(tree.to_newick(&["seq/1-4"]), "seq/1-4;");
+        assert_eq!(tree.to_newick(&["it's here"]), "'it''s here';");
+    }
+
+    #[test]
+    fn test_newick_tree_collapsed() {
+        // s0 and s2 are identical and share a leaf
+        let sequences = vec![seq("AAAA"), seq("CCCC"), seq("AAAA")];
+        let groups = vec![(0, vec![0, 2]), (1, vec![1])];
+        let tree = cluster_sequences_with_collapse(&sequences, &gaps(), &groups, 32).dendrogram;
+        let newick = tree.to_newick(&["s0", "s1", "s2"]);
+        assert!(newick.contains("(s0:0,s2:0):2"), "{newick}");
+        assert!(newick.contains("s1:2"), "{newick}");
+    }
+
+    #[test]
+    fn test_tree_max_width() {
+        // 50 sequences, sequence i with its own run of i mismatches: each joins
+        // the cluster of all before it, so the tree's depth is n - 1 = 49.
+        let sequences: Vec<Vec<u8>> = (0..50usize)
+            .map(|i| {
+                let run = i * i.saturating_sub(1) / 2..i * (i + 1) / 2;
+                (0..1225)
+                    .map(|pos| if run.contains(&pos) { b'A' } else { b'C' })
+                    .collect()
+            })
+            .collect();
+
+        for (max_width, expected) in [(8, 8), (40, 40), (usize::MAX, 49)] {
+            let result = cluster_sequences_with_tree(&sequences, &gaps(), max_width);
+            assert_eq!(result.tree_width, expected);
+            assert!(
+                result
+                    .tree_lines
+                    .iter()
+                    .all(|line| line.chars().count() == expected)
+            );
+        }
+
+        // A balanced tree is only as wide as its depth
+        let balanced = vec![seq("AAAA"), seq("AAAC"), seq("CCCC"), seq("CCCA")];
+        let result = cluster_sequences_with_tree(&balanced, &gaps(), usize::MAX);
+        assert_eq!(result.tree_width, 2);
+    }
+
     #[test]
     fn test_tree_rendering_single() {
         let sequences = vec![seq("ACGU")];
-        let result = cluster_sequences_with_tree(&sequences, &gaps());
+        let result = cluster_sequences_with_tree(&sequences, &gaps(), DEFAULT_TREE_MAX_WIDTH);
 
         assert_eq!(result.tree_lines.len(), 1);
         assert_eq!(result.tree_width, 1);
@@ -709,7 +1007,12 @@ mod tests {
             (4, vec![4]),       // C appears once
         ];
 
-        let result = cluster_sequences_with_collapse(&sequences, &gaps(), &collapse_groups);
+        let result = cluster_sequences_with_collapse(
+            &sequences,
+            &gaps(),
+            &collapse_groups,
+            DEFAULT_TREE_MAX_WIDTH,
+        );
 
         // Should have all 5 sequences in order
         assert_eq!(result.order.len(), 5);
@@ -737,7 +1040,12 @@ mod tests {
         let sequences: Vec<Vec<u8>> = vec![seq("AAAA"), seq("AAAA"), seq("AAAA")];
         let collapse_groups = vec![(0, vec![0, 1, 2])];
 
-        let result = cluster_sequences_with_collapse(&sequences, &gaps(), &collapse_groups);
+        let result = cluster_sequences_with_collapse(
+            &sequences,
+            &gaps(),
+            &collapse_groups,
+            DEFAULT_TREE_MAX_WIDTH,
+        );
 
         assert_eq!(result.order.len(), 3);
         assert_eq!(result.tree_lines.len(), 3);
@@ -821,7 +1129,12 @@ mod tests {
         // Create collapse groups where each sequence is its own group
         let collapse_groups = vec![(0, vec![0]), (1, vec![1]), (2, vec![2]), (3, vec![3])];
 
-        let result = cluster_sequences_with_collapse(&sequences, &gaps(), &collapse_groups);
+        let result = cluster_sequences_with_collapse(
+            &sequences,
+            &gaps(),
+            &collapse_groups,
+            DEFAULT_TREE_MAX_WIDTH,
+        );
 
         // Should still produce a valid ordering with all 4 sequences
         assert_eq!(result.order.len(), 4);
@@ -841,7 +1154,12 @@ mod tests {
             .collect();
         let collapse_groups: Vec<(usize, Vec<usize>)> = (0..n).map(|i| (i, vec![i])).collect();
 
-        let result = cluster_sequences_with_collapse(&sequences, &gaps(), &collapse_groups);
+        let result = cluster_sequences_with_collapse(
+            &sequences,
+            &gaps(),
+            &collapse_groups,
+            DEFAULT_TREE_MAX_WIDTH,
+        );
 
         assert_eq!(result.order.len(), n);
         assert_eq!(
@@ -4,7 +4,7 @@ use ratatui::style::Color;
 
 use crate::app::{ColorScheme, TerminalTheme};
 use crate::stockholm::{Alignment, SequenceType};
-use crate::structure::{CompensatoryChange, StructureCache, analyze_compensatory};
+use crate::structure::{CompensatoryChange, StructDiffClass, StructureCache, analyze_compensatory};
 
 /// Colors for helix highlighting (cycling through these).
 pub const HELIX_COLORS: &[Color] = &[
@@ -111,6 +111,31 @@ pub const COMP_DOUBLE_INCOMPATIBLE: Color = Color::Red;
 pub const COMP_SINGLE_INCOMPATIBLE: Color = Color::Rgb(255, 165, 0); // orange
 pub const COMP_GAP: Color = Color::Magenta;
 
+/// Base pair type colors (residue and its SS_cons partner in the same row).
+pub const PAIR_TYPE_AU: Color = Color::Rgb(0, 200, 0); // green
+pub const PAIR_TYPE_GC: Color = Color::Rgb(65, 105, 225); // royalblue
+pub const PAIR_TYPE_GU: Color = Color::Rgb(255, 165, 0); // orange
+pub const PAIR_TYPE_MISMATCH: Color = Color::Red;
+pub const PAIR_TYPE_GAP: Color = Color::Rgb(128, 128, 128); // gray
+
+/// Structure comparison colors (per-sequence SS vs SS_cons).
+pub const STRUCT_DIFF_REF_PAIRED: Color = Color::Rgb(135, 206, 235); // skyblue
+pub const STRUCT_DIFF_SEQ_PAIRED: Color = Color::Rgb(255, 165, 0); // orange
+pub const STRUCT_DIFF_CONFLICT: Color = Color::Red;
+
+/// Pairwise comparison colors (residues compared with the pinned sequence).
+pub const PIN_IDENTICAL: Color = Color::Rgb(144, 238, 144); // lightgreen
+pub const PIN_CONSERVATIVE: Color = Color::Rgb(255, 255, 0); // yellow
+pub const PIN_RADICAL: Color = Color::Rgb(255, 99, 71); // tomato
+
+/// Numeric value of a posterior probability character (`0`-`9`, `*`).
+pub fn pp_value(ch: char) -> Option<f64> {
+    match ch {
+        '*' => Some(1.0),
+        _ => ch.to_digit(10).map(|d| f64::from(d) / 10.0),
+    }
+}
+
 /// Convert a PP (posterior probability) character to a color.
 /// PP values: 0-9 (probability * 10), * = highest (>0.95).
 /// Uses a red-yellow-green gradient.
@@ -155,6 +180,10 @@ pub fn get_color(
             get_compensatory_color(col, row, alignment, cache, gap_chars, reference_seq)
         }
         ColorScheme::PP => get_pp_color(ch, col, row, alignment, gap_chars),
+        ColorScheme::Accessibility => get_accessibility_color(col, row, alignment, gap_chars),
+        ColorScheme::PairType => get_pair_type_color(ch, col, row, alignment, cache, gap_chars),
+        // Clades color the ID column only (see `get_clade_color`)
+        ColorScheme::Clade => None,
     }
 }
 
@@ -170,7 +199,7 @@ const BASE_GAP_COLOR: Color = Color::Rgb(40, 40, 40); // dark gray
 const BASE_GAP_COLOR_LIGHT: Color = Color::Rgb(220, 220, 220); // light gray
 
 /// Get color based on base/amino acid identity.
-fn get_base_color(
+pub fn get_base_color(
     ch: char,
     gap_chars: &[char],
     sequence_type: SequenceType,
@@ -298,6 +327,102 @@ fn get_compensatory_color(
     }
 }
 
+/// Get color by the base pair a residue forms with its SS_cons partner in the
+/// same sequence: AU green, GC blue, GU orange, any other pair red, and gray
+/// when either side is a gap. Unpaired columns are not colored.
+pub fn get_pair_type_color(
+    ch: char,
+    col: usize,
+    row: usize,
+    alignment: &Alignment,
+    cache: &StructureCache,
+    gap_chars: &[char],
+) -> Option<Color> {
+    let partner = cache.get_pair(col)?;
+    let partner_ch = alignment.sequences.get(row)?.get(partner)?;
+    if gap_chars.contains(&ch) || gap_chars.contains(&partner_ch) {
+        return Some(PAIR_TYPE_GAP);
+    }
+
+    let normalize = |c: char| match c.to_ascii_uppercase() {
+        'T' => 'U',
+        other => other,
+    };
+    let (a, b) = (normalize(ch), normalize(partner_ch));
+    Some(match (a.min(b), a.max(b)) {
+        ('A', 'U') => PAIR_TYPE_AU,
+        ('C', 'G') => PAIR_TYPE_GC,
+        ('G', 'U') => PAIR_TYPE_GU,
+        _ => PAIR_TYPE_MISMATCH,
+    })
+}
+
+/// Get the color for a structure comparison class (`None` when the structures agree).
+pub fn struct_diff_color(class: StructDiffClass) -> Option<Color> {
+    match class {
+        StructDiffClass::Agree => None,
+        StructDiffClass::RefPaired => Some(STRUCT_DIFF_REF_PAIRED),
+        StructDiffClass::SeqPaired => Some(STRUCT_DIFF_SEQ_PAIRED),
+        StructDiffClass::Conflict => Some(STRUCT_DIFF_CONFLICT),
+    }
+}
+
+/// Get the color for a residue compared with the pinned sequence's residue.
+///
+/// Identical residues (ignoring case and T/U) are green, substitutions within
+/// a chemical class (purine/pyrimidine, or the amino acid groups used for base
+/// coloring) yellow, and other substitutions or indels red. Columns where
+/// both are gaps are not colored.
+pub fn pinned_comparison_color(
+    ch: char,
+    pinned: char,
+    gap_chars: &[char],
+    sequence_type: SequenceType,
+) -> Option<Color> {
+    let is_gap = gap_chars.contains(&ch);
+    let pinned_is_gap = gap_chars.contains(&pinned);
+    if is_gap && pinned_is_gap {
+        return None;
+    }
+    if is_gap || pinned_is_gap {
+        return Some(PIN_RADICAL);
+    }
+
+    let normalize = |c: char| match c.to_ascii_uppercase() {
+        'T' if sequence_type != SequenceType::Protein => 'U',
+        other => other,
+    };
+    let (a, b) = (normalize(ch), normalize(pinned));
+    if a == b {
+        return Some(PIN_IDENTICAL);
+    }
+    match (
+        residue_class(a, sequence_type),
+        residue_class(b, sequence_type),
+    ) {
+        (Some(x), Some(y)) if x == y => Some(PIN_CONSERVATIVE),
+        _ => Some(PIN_RADICAL),
+    }
+}
+
+/// Chemical class of an uppercase residue for conservative substitutions.
+fn residue_class(c: char, sequence_type: SequenceType) -> Option<u8> {
+    match sequence_type {
+        SequenceType::Protein => match c {
+            'A' | 'I' | 'L' | 'M' | 'F' | 'W' | 'V' => Some(0),
+            'S' | 'T' | 'N' | 'Q' => Some(1),
+            'K' | 'R' | 'H' => Some(2),
+            'D' | 'E' => Some(3),
+            _ => None,
+        },
+        SequenceType::RNA | SequenceType::DNA => match c {
+            'A' | 'G' | 'R' => Some(0),
+            'C' | 'U' | 'Y' => Some(1),
+            _ => None,
+        },
+    }
+}
+
 /// Get color based on per-residue PP (posterior probability) annotation.
 fn get_pp_color(
     ch: char,
@@ -326,6 +451,80 @@ fn get_pp_color(
     None // No PP annotation for this residue
 }
 
+/// No covariation (gray) and full covariation (green) ends of the helix overlay.
+const HELIX_COV_NONE: (u8, u8, u8) = (110, 110, 110);
+const HELIX_COV_FULL: (u8, u8, u8) = (0, 200, 0);
+
+/// Blend a helix covariation score (0.0-1.0) from gray to green.
+pub fn helix_covariation_color(score: f64) -> Color {
+    let score = score.clamp(0.0, 1.0);
+    let mix = |none: u8, full: u8| {
+        (f64::from(none) + (f64::from(full) - f64::from(none)) * score).round() as u8
+    };
+    Color::Rgb(
+        mix(HELIX_COV_NONE.0, HELIX_COV_FULL.0),
+        mix(HELIX_COV_NONE.1, HELIX_COV_FULL.1),
+        mix(HELIX_COV_NONE.2, HELIX_COV_FULL.2),
+    )
+}
+
+/// Buried (blue) and exposed (yellow) ends of the accessibility gradient.
+const ACC_BURIED: (u8, u8, u8) = (40, 80, 200);
+const ACC_EXPOSED: (u8, u8, u8) = (240, 220, 40);
+
+/// Get color from the per-residue solvent accessibility annotation (#=GR ACC).
+///
+/// Levels are DSSP-style digits, 0 (buried) to 9 (exposed), mapped onto a
+/// blue-to-yellow gradient. Gaps and other characters are not colored.
+pub fn get_accessibility_color(
+    col: usize,
+    row: usize,
+    alignment: &Alignment,
+    gap_chars: &[char],
+) -> Option<Color> {
+    let seq = alignment.sequences.get(row)?;
+    if gap_chars.contains(&seq.get(col)?) {
+        return None;
+    }
+
+    let level = alignment
+        .residue_annotations
+        .get(&seq.id)?
+        .iter()
+        .find(|ann| ann.tag == "ACC")?
+        .data
+        .chars()
+        .nth(col)?
+        .to_digit(10)?;
+    let mix = |buried: u8, exposed: u8| {
+        let (buried, exposed) = (u32::from(buried), u32::from(exposed));
+        ((buried * (9 - level) + exposed * level) / 9) as u8
+    };
+    Some(Color::Rgb(
+        mix(ACC_BURIED.0, ACC_EXPOSED.0),
+        mix(ACC_BURIED.1, ACC_EXPOSED.1),
+        mix(ACC_BURIED.2, ACC_EXPOSED.2),
+    ))
+}
+
+/// ID column background for a sequence's `#=GS CLADE` annotation.
+///
+/// Each clade name hashes (FNV-1a) to a fixed entry of [`HELIX_COLORS`], so a
+/// clade keeps its color across sessions and files.
+pub fn get_clade_color(seq_id: &str, alignment: &Alignment) -> Option<Color> {
+    let clade = alignment
+        .sequence_annotations
+        .get(seq_id)?
+        .iter()
+        .find(|ann| ann.tag == "CLADE")?
+        .value
+        .trim();
+    let hash = clade.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    Some(HELIX_COLORS[(hash % HELIX_COLORS.len() as u64) as usize])
+}
+
 /// Get consensus character for a column.
 pub fn get_consensus_char(col: usize, alignment: &Alignment, gap_chars: &[char]) -> char {
     if alignment.sequences.is_empty() {
@@ -353,6 +552,19 @@ pub fn get_consensus_char(col: usize, alignment: &Alignment, gap_chars: &[char])
         .unwrap_or('.')
 }
 
+/// Whether a residue differs from its column consensus, ignoring case and T/U.
+///
+/// Gaps, and columns without a consensus residue, never count as differing.
+pub fn differs_from_consensus(ch: char, consensus: char, gap_chars: &[char]) -> bool {
+    let normalize = |c: char| match c.to_ascii_uppercase() {
+        'T' => 'U',
+        other => other,
+    };
+    !gap_chars.contains(&ch)
+        && !gap_chars.contains(&consensus)
+        && normalize(ch) != normalize(consensus)
+}
+
 /// Get consensus character with case indicating conservation level.
 /// Uppercase if conservation >= threshold, lowercase otherwise.
 pub fn get_consensus_char_with_case(
@@ -504,6 +716,129 @@ mod tests {
         assert!((cons - 0.666).abs() < 0.01);
     }
 
+    #[test]
+    fn test_helix_covariation_color() {
+        assert_eq!(helix_covariation_color(0.0), Color::Rgb(110, 110, 110));
+        assert_eq!(helix_covariation_color(1.0), Color::Rgb(0, 200, 0));
+        assert_eq!(helix_covariation_color(0.5), Color::Rgb(55, 155, 55));
+    }
+
+    #[test]
+    fn test_accessibility_colors() {
+        use crate::stockholm::ResidueAnnotation;
+
+        let mut alignment = Alignment::new();
+        alignment
+            .sequences
+            .push(Rc::new(Sequence::new("s1", "AC-GU")));
+        alignment.residue_annotations.insert(
+            "s1".to_string(),
+            vec![ResidueAnnotation {
+                tag: "ACC".to_string(),
+                data: "09.5x".to_string(),
+            }],
+        );
+        let gap_chars = ['.', '-'];
+
+        assert_eq!(
+            get_accessibility_color(0, 0, &alignment, &gap_chars),
+            Some(Color::Rgb(40, 80, 200))
+        );
+        assert_eq!(
+            get_accessibility_color(1, 0, &alignment, &gap_chars),
+            Some(Color::Rgb(240, 220, 40))
+        );
+        // Gap residue, then a non-digit level
+        assert_eq!(get_accessibility_color(2, 0, &alignment, &gap_chars), None);
+        assert_eq!(get_accessibility_color(4, 0, &alignment, &gap_chars), None);
+        assert!(get_accessibility_color(3, 0, &alignment, &gap_chars).is_some());
+    }
+
+    #[test]
+    fn test_clade_colors() {
+        use crate::stockholm::SequenceAnnotation;
+
+        let mut alignment = Alignment::new();
+        for (id, clade) in [("s1", "Bacteria"), ("s2", "Bacteria"), ("s3", "Archaea")] {
+            alignment.sequence_annotations.insert(
+                id.to_string(),
+                vec![SequenceAnnotation {
+                    tag: "CLADE".to_string(),
+                    value: clade.to_string(),
+                }],
+            );
+        }
+
+        let bacteria = get_clade_color("s1", &alignment);
+        assert!(bacteria.is_some());
+        assert_eq!(get_clade_color("s2", &alignment), bacteria);
+        assert_ne!(get_clade_color("s3", &alignment), bacteria);
+        assert_eq!(get_clade_color("s4", &alignment), None);
+    }
+
+    #[test]
+    fn test_pinned_comparison_colors() {
+        let gaps = ['.', '-'];
+        let rna = SequenceType::RNA;
+        assert_eq!(
+            pinned_comparison_color('a', 'A', &gaps, rna),
+            Some(PIN_IDENTICAL)
+        );
+        assert_eq!(
+            pinned_comparison_color('T', 'U', &gaps, SequenceType::DNA),
+            Some(PIN_IDENTICAL)
+        );
+        assert_eq!(
+            pinned_comparison_color('G', 'A', &gaps, rna),
+            Some(PIN_CONSERVATIVE)
+        );
+        assert_eq!(
+            pinned_comparison_color('C', 'A', &gaps, rna),
+            Some(PIN_RADICAL)
+        );
+        assert_eq!(
+            pinned_comparison_color('-', 'A', &gaps, rna),
+            Some(PIN_RADICAL)
+        );
+        assert_eq!(pinned_comparison_color('.', '-', &gaps, rna), None);
+        assert_eq!(
+            pinned_comparison_color('I', 'L', &gaps, SequenceType::Protein),
+            Some(PIN_CONSERVATIVE)
+        );
+    }
+
+    #[test]
+    fn test_differs_from_consensus() {
+        let gaps = ['.', '-'];
+        assert!(!differs_from_consensus('a', 'A', &gaps));
+        assert!(!differs_from_consensus('T', 'U', &gaps));
+        assert!(differs_from_consensus('G', 'A', &gaps));
+        assert!(!differs_from_consensus('-', 'A', &gaps));
+        assert!(!differs_from_consensus('G', '.', &gaps));
+    }
+
+    #[test]
+    fn test_pair_type_colors() {
+        let mut alignment = Alignment::new();
+        alignment
+            .sequences
+            .push(Rc::new(Sequence::new("s1", "AGGAGAA-CUCU")));
+        let mut cache = StructureCache::new();
+        cache.update("<<<<<..>>>>>").unwrap();
+        let gaps = ['.', '-'];
+        let color = |col: usize| {
+            let ch = alignment.sequences[0].get(col).unwrap();
+            get_pair_type_color(ch, col, 0, &alignment, &cache, &gaps)
+        };
+
+        assert_eq!(color(0), Some(PAIR_TYPE_AU));
+        assert_eq!(color(10), Some(PAIR_TYPE_GC));
+        assert_eq!(color(2), Some(PAIR_TYPE_GU));
+        assert_eq!(color(3), Some(PAIR_TYPE_MISMATCH));
+        assert_eq!(color(4), Some(PAIR_TYPE_GAP));
+        assert_eq!(color(5), None);
+    }
+
     #[test]
     fn test_structure_colors() {
         let mut cache = StructureCache::new();
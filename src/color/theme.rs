@@ -30,10 +30,20 @@ impl Rgb {
         Color::Rgb(self.r, self.g, self.b)
     }
 
+    /// Halfway mix of two colors.
+    pub fn blend(self, other: Rgb) -> Self {
+        let mid = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+        Self::new(
+            mid(self.r, other.r),
+            mid(self.g, other.g),
+            mid(self.b, other.b),
+        )
+    }
+
     /// Parse from hex string like "#FF8000" or "FF8000"
-    fn from_hex(s: &str) -> Option<Self> {
+    pub fn from_hex(s: &str) -> Option<Self> {
         let s = s.strip_prefix('#').unwrap_or(s);
-        if s.len() != 6 {
+        if s.len() != 6 || !s.is_ascii() {
             return None;
         }
         let r = u8::from_str_radix(&s[0..2], 16).ok()?;
@@ -178,6 +188,7 @@ pub struct RulerColors {
     pub numbers: Rgb,
     pub ticks: Rgb,
     pub pair_line: Rgb,
+    pub anchor: Rgb,
 }
 
 impl Default for RulerColors {
@@ -186,6 +197,7 @@ impl Default for RulerColors {
             numbers: Rgb::new(128, 128, 128), // DarkGray
             ticks: Rgb::new(128, 128, 128),   // DarkGray
             pair_line: Rgb::new(255, 0, 255), // Magenta
+            anchor: Rgb::new(255, 165, 0),    // Orange
         }
     }
 }
@@ -197,6 +209,7 @@ impl RulerColors {
             numbers: Rgb::new(80, 80, 80),    // Gray
             ticks: Rgb::new(80, 80, 80),      // Gray
             pair_line: Rgb::new(180, 0, 180), // Darker magenta
+            anchor: Rgb::new(200, 100, 0),    // Dark orange
         }
     }
 }
@@ -402,6 +415,9 @@ pub struct SelectionColors {
     pub pair_highlight_bg: Rgb,
     pub pair_highlight_fg: Rgb,
     pub gap_column_bg: Rgb,
+    pub variable_col_bg: Rgb,
+    pub rf_insertion_bg: Rgb,
+    pub cursor_column_bg: Rgb,
 }
 
 impl Default for SelectionColors {
@@ -415,7 +431,10 @@ impl Default for SelectionColors {
             search_other_fg: Rgb::new(255, 255, 255),
             pair_highlight_bg: Rgb::new(255, 0, 255), // Magenta
             pair_highlight_fg: Rgb::new(255, 255, 255),
-            gap_column_bg: Rgb::new(80, 50, 50), // Dim red
+            gap_column_bg: Rgb::new(80, 50, 50),    // Dim red
+            variable_col_bg: Rgb::new(45, 45, 60),  // Dim slate
+            rf_insertion_bg: Rgb::new(70, 50, 90),  // Soft purple
+            cursor_column_bg: Rgb::new(38, 38, 44), // Barely lifted from black
         }
     }
 }
@@ -433,6 +452,9 @@ impl SelectionColors {
             pair_highlight_bg: Rgb::new(255, 180, 255), // Light magenta
             pair_highlight_fg: Rgb::new(0, 0, 0),       // Black
             gap_column_bg: Rgb::new(250, 220, 220),     // Light red
+            variable_col_bg: Rgb::new(230, 230, 240),   // Pale slate
+            rf_insertion_bg: Rgb::new(225, 210, 240),   // Soft lavender
+            cursor_column_bg: Rgb::new(238, 238, 242),  // Barely off white
         }
     }
 }
@@ -472,6 +494,8 @@ impl CommandLineColors {
 #[serde(default)]
 pub struct MiscColors {
     pub separator: Rgb,
+    pub separator_active: Rgb,
+    pub separator_section: Rgb,
     pub tree_dark_theme: Rgb,
     pub tree_light_theme: Rgb,
     pub popup_bg: Rgb,
@@ -481,11 +505,13 @@ pub struct MiscColors {
 impl Default for MiscColors {
     fn default() -> Self {
         Self {
-            separator: Rgb::new(128, 128, 128),       // DarkGray
-            tree_dark_theme: Rgb::new(255, 255, 255), // White
-            tree_light_theme: Rgb::new(0, 0, 0),      // Black
-            popup_bg: Rgb::new(0, 0, 0),              // Black
-            popup_border: Rgb::new(0, 255, 255),      // Cyan
+            separator: Rgb::new(128, 128, 128),         // DarkGray
+            separator_active: Rgb::new(0, 255, 255),    // Cyan
+            separator_section: Rgb::new(160, 160, 160), // Gray
+            tree_dark_theme: Rgb::new(255, 255, 255),   // White
+            tree_light_theme: Rgb::new(0, 0, 0),        // Black
+            popup_bg: Rgb::new(0, 0, 0),                // Black
+            popup_border: Rgb::new(0, 255, 255),        // Cyan
         }
     }
 }
@@ -495,6 +521,8 @@ impl MiscColors {
     pub fn default_for_light() -> Self {
         Self {
             separator: Rgb::new(100, 100, 100),       // Gray
+            separator_active: Rgb::new(0, 100, 150),  // Dark cyan
+            separator_section: Rgb::new(80, 80, 80),  // Dark gray
             tree_dark_theme: Rgb::new(255, 255, 255), // White (unchanged)
             tree_light_theme: Rgb::new(0, 0, 0),      // Black (unchanged)
             popup_bg: Rgb::new(255, 255, 255),        // White
@@ -1,9 +1,21 @@
 //! Editor commands for alignment manipulation.
 
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::rc::Rc;
 
+use rand_chacha::ChaCha8Rng;
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+
 use crate::app::App;
-use crate::stockholm::ShiftDirection;
+use crate::color::{get_consensus_char, pp_value};
+use crate::external::hmmer::{align_to_hmm, parse_hmm_consensus};
+use crate::external::rnafold::{expand_structure_to_alignment, fold_sequence};
+use crate::stockholm::{
+    Alignment, ColumnAnnotation, FileAnnotation, ResidueAnnotation, Sequence, SequenceType,
+    ShiftDirection, writer,
+};
+use crate::structure::{StructureCache, reverse_structure};
 
 impl App {
     /// Insert a gap at the cursor position in the current sequence.
@@ -70,23 +82,42 @@ impl App {
         false
     }
 
-    /// Insert a gap column at the cursor position.
-    pub fn insert_gap_column(&mut self) {
+    /// Insert `count` gap columns before `col` as a single undo step.
+    ///
+    /// `col` is an alignment column, not a display column. When columns are
+    /// hidden and the new gap columns are among them, the cursor moves along
+    /// with the column it was on.
+    pub fn insert_gap_column_range(&mut self, col: usize, count: usize) {
+        if count == 0 {
+            return;
+        }
         self.save_undo_state();
-        self.alignment
-            .insert_gap_column(self.cursor_col, self.gap_char);
+        // Each insertion at `col` pushes the previous ones right
+        for _ in 0..count {
+            self.alignment.insert_gap_column(col, self.gap_char);
+        }
+        self.remap_column_state(|c| Some(if c >= col { c + count } else { c }));
         self.mark_modified();
         self.update_structure_cache();
+        self.precompute_visible_columns();
+        if self.columns_hidden() && self.actual_to_display_col(self.cursor_col).is_none() {
+            self.cursor_col += count;
+        }
+        self.clamp_cursor();
     }
 
     /// Delete a gap column at the cursor position.
     pub fn delete_gap_column(&mut self) -> bool {
-        if self
-            .alignment
-            .delete_gap_column(self.cursor_col, &self.gap_chars)
-        {
+        let col = self.cursor_col;
+        if self.alignment.delete_gap_column(col, &self.gap_chars) {
             self.save_undo_state();
+            self.remap_column_state(|c| match c.cmp(&col) {
+                std::cmp::Ordering::Less => Some(c),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some(c - 1),
+            });
             self.mark_modified();
+            self.precompute_visible_columns();
             self.clamp_cursor();
             self.update_structure_cache();
             true
@@ -189,8 +220,9 @@ impl App {
             self.alignment = snapshot.alignment;
             self.cursor_row = snapshot.cursor_row;
             self.cursor_col = snapshot.cursor_col;
-            self.modified = true; // Still modified from original save
+            self.mark_modified(); // Still modified from original save
             self.update_structure_cache();
+            self.clamp_column_state();
             self.set_status("Undo");
         } else {
             self.set_status("Nothing to undo");
@@ -206,14 +238,42 @@ impl App {
             self.alignment = snapshot.alignment;
             self.cursor_row = snapshot.cursor_row;
             self.cursor_col = snapshot.cursor_col;
-            self.modified = true;
+            self.mark_modified();
             self.update_structure_cache();
+            self.clamp_column_state();
             self.set_status("Redo");
         } else {
             self.set_status("Nothing to redo");
         }
     }
 
+    /// Switch to the previous or next undo branch at the current state's
+    /// parent (Alt-Left/Alt-Right).
+    pub fn switch_undo_branch(&mut self, forward: bool) {
+        if let Some(snapshot) =
+            self.history
+                .switch_branch(forward, &self.alignment, self.cursor_row, self.cursor_col)
+        {
+            self.alignment = snapshot.alignment;
+            self.cursor_row = snapshot.cursor_row;
+            self.cursor_col = snapshot.cursor_col;
+            self.mark_modified();
+            self.update_structure_cache();
+            self.clamp_column_state();
+            let (pos, count) = self
+                .history
+                .tree_rows()
+                .into_iter()
+                .find(|row| row.current)
+                .map_or((0, 1), |row| row.branch);
+            self.set_status(format!("Undo branch {}/{count}", pos + 1));
+        } else if forward {
+            self.set_status("No newer undo branch");
+        } else {
+            self.set_status("No older undo branch");
+        }
+    }
+
     /// Save current state for undo.
     fn save_undo_state(&mut self) {
         self.history
@@ -248,11 +308,11 @@ impl App {
 
     /// Delete all sequences in the current visual selection.
     pub fn delete_selected_sequences(&mut self) {
-        let Some((min_row, _, max_row, _)) = self.get_selection_bounds() else {
+        if self.selection_anchor.is_none() || self.alignment.sequences.is_empty() {
             return;
-        };
-
-        if self.alignment.sequences.is_empty() {
+        }
+        if self.selected_rows().is_empty() {
+            self.set_status("No sequences selected");
             return;
         }
 
@@ -264,7 +324,9 @@ impl App {
         }
 
         // Map display rows to actual sequence indices
-        let actual_indices: Vec<usize> = (min_row..=max_row)
+        let actual_indices: Vec<usize> = self
+            .selected_rows()
+            .into_iter()
             .map(|r| self.display_to_actual_row(r))
             .collect();
         let count = actual_indices.len();
@@ -289,6 +351,467 @@ impl App {
         self.set_status(format!("Deleted {count} sequence(s)"));
     }
 
+    /// Indices of sequences whose ID matches `pattern`.
+    ///
+    /// Matches by substring, or as a regular expression when `regex` is set.
+    pub(crate) fn sequences_matching(
+        &self,
+        pattern: &str,
+        regex: bool,
+    ) -> Result<Vec<usize>, String> {
+        if pattern.is_empty() {
+            return Err("Empty pattern".to_string());
+        }
+        let re = if regex {
+            Some(regex::Regex::new(pattern).map_err(|e| format!("Invalid regex: {e}"))?)
+        } else {
+            None
+        };
+        Ok(self
+            .alignment
+            .sequences
+            .iter()
+            .enumerate()
+            .filter(|(_, seq)| match &re {
+                Some(re) => re.is_match(&seq.id),
+                None => seq.id.contains(pattern),
+            })
+            .map(|(i, _)| i)
+            .collect())
+    }
+
+    /// Delete all sequences whose ID matches `pattern` as a single undo step.
+    ///
+    /// Returns the number of sequences removed.
+    pub fn delete_sequences_by_pattern(
+        &mut self,
+        pattern: &str,
+        regex: bool,
+    ) -> Result<usize, String> {
+        let matches = self.sequences_matching(pattern, regex)?;
+        if matches.is_empty() {
+            return Err(format!("No sequences match '{pattern}'"));
+        }
+
+        self.save_undo_state();
+        let matches = if self.cluster_order.is_some() {
+            // Materializing reorders the sequences, so match again afterwards
+            self.materialize_cluster_order();
+            self.sequences_matching(pattern, regex)?
+        } else {
+            matches
+        };
+
+        for &row in matches.iter().rev() {
+            let seq_id = self.alignment.sequences[row].id.clone();
+            self.alignment.sequences.remove(row);
+            self.alignment.sequence_annotations.remove(&seq_id);
+            self.alignment.residue_annotations.remove(&seq_id);
+        }
+
+        self.precompute_collapse_groups();
+        self.mark_modified();
+        self.clamp_cursor();
+        Ok(matches.len())
+    }
+
+    /// Remove sequences identical to an earlier one as a single undo step.
+    ///
+    /// The first occurrence of each sequence in display order is kept and a
+    /// cluster order is kept as shown; the duplicates lose their `#=GS` and
+    /// `#=GR` annotations. Returns the number of sequences removed.
+    pub fn deduplicate_sequences(&mut self) -> usize {
+        self.precompute_collapse_groups();
+        if self
+            .collapse_groups
+            .iter()
+            .all(|(_, members)| members.len() < 2)
+        {
+            return 0;
+        }
+
+        self.save_undo_state();
+        // Materialize cluster order so the displayed order survives the removal
+        if self.cluster_order.is_some() {
+            self.materialize_cluster_order();
+            self.precompute_collapse_groups();
+        }
+        let mut duplicates: Vec<usize> = self
+            .collapse_groups
+            .iter()
+            .flat_map(|(rep, members)| members.iter().copied().filter(move |i| i != rep))
+            .collect();
+        // A removed reference hands over to the copy that is kept
+        let reference_row = self.reference_row();
+        if self.reference_id.is_some()
+            && let Some((rep, _)) = self
+                .collapse_groups
+                .iter()
+                .find(|(_, members)| members.contains(&reference_row))
+        {
+            self.reference_id = Some(self.alignment.sequences[*rep].id.clone());
+        }
+        duplicates.sort_unstable();
+        for &row in duplicates.iter().rev() {
+            let seq_id = self.alignment.sequences[row].id.clone();
+            self.alignment.sequences.remove(row);
+            self.alignment.sequence_annotations.remove(&seq_id);
+            self.alignment.residue_annotations.remove(&seq_id);
+        }
+
+        self.precompute_collapse_groups();
+        self.mark_modified();
+        self.clamp_cursor();
+        duplicates.len()
+    }
+
+    /// Remove sequences whose ID repeats an earlier one as a single undo step.
+    ///
+    /// The first sequence with each ID is kept along with its `#=GS` and
+    /// `#=GR` annotations. Returns the number of sequences removed.
+    pub fn remove_duplicate_ids(&mut self) -> usize {
+        if self.alignment.duplicate_ids().is_empty() {
+            return 0;
+        }
+
+        self.save_undo_state();
+        if self.cluster_order.is_some() {
+            self.materialize_cluster_order();
+        }
+        let before = self.alignment.sequences.len();
+        let mut seen = HashSet::new();
+        self.alignment
+            .sequences
+            .retain(|seq| seen.insert(seq.id.clone()));
+        let removed = before - self.alignment.sequences.len();
+
+        self.precompute_collapse_groups();
+        self.mark_modified();
+        self.clamp_cursor();
+        removed
+    }
+
+    /// Reverse the column order of the alignment (`:reverse-cols`).
+    ///
+    /// The cursor stays on the same alignment column.
+    pub fn reverse_alignment_columns(&mut self) {
+        let width = self.alignment.width();
+        if width == 0 {
+            return;
+        }
+        self.save_undo_state();
+        self.alignment.reverse_columns();
+        self.remap_column_state(|c| Some(width - 1 - c));
+        self.cursor_col = width - 1 - self.cursor_col.min(width - 1);
+        self.mark_modified();
+        self.update_structure_cache();
+    }
+
+    /// Append the columns of `other` to the right of the alignment (`:merge`).
+    ///
+    /// Works like [`App::insert_aligned_columns`] at the last column; `#=GF`
+    /// lines from `other` are kept with a `MERGED:` tag prefix.
+    pub fn merge_alignment(&mut self, mut other: Alignment) -> Result<(), String> {
+        let file_annotations = std::mem::take(&mut other.file_annotations);
+        self.insert_aligned_columns(other, self.alignment.width())?;
+        self.alignment
+            .file_annotations
+            .extend(file_annotations.into_iter().map(|ann| FileAnnotation {
+                tag: format!("MERGED:{}", ann.tag),
+                value: ann.value,
+            }));
+        Ok(())
+    }
+
+    /// The gap character the alignment already uses most, or `gap_char` when
+    /// it has no gaps.
+    pub(crate) fn alignment_gap_char(&self) -> char {
+        let mut counts = vec![0usize; self.gap_chars.len()];
+        for seq in &self.alignment.sequences {
+            for c in seq.chars() {
+                if let Some(idx) = self.gap_chars.iter().position(|g| g == c) {
+                    counts[idx] += 1;
+                }
+            }
+        }
+        self.gap_chars
+            .iter()
+            .zip(counts)
+            .filter(|&(_, count)| count > 0)
+            .max_by_key(|&(&c, count)| (count, c == self.gap_char))
+            .map_or(self.gap_char, |(&c, _)| c)
+    }
+
+    /// Insert the columns of `other` before column `col` (`:insert-aligned`).
+    ///
+    /// Sequences are matched by ID; a sequence missing from either side is
+    /// padded with the alignment's gap character (see
+    /// [`App::alignment_gap_char`]), and sequences only in `other` are added
+    /// at the end.
+    /// `#=GC` and `#=GR` lines are joined the same way (padded with `.`).
+    pub fn insert_aligned_columns(&mut self, other: Alignment, col: usize) -> Result<(), String> {
+        if other.sequences.is_empty() {
+            return Err("Nothing to insert: alignment has no sequences".to_string());
+        }
+        let width = self.alignment.width();
+        if col > width {
+            return Err(format!("Column {} is past the end ({width})", col + 1));
+        }
+
+        self.save_undo_state();
+        if self.cluster_order.is_some() {
+            self.materialize_cluster_order();
+        }
+
+        let inserted_width = other.width();
+        // Each side is cut or padded to its own width so the columns line up
+        let join = |current: Option<&str>, inserted: Option<&str>, pad: char| -> String {
+            let fit = |data: Option<&str>, width: usize| -> Vec<char> {
+                data.unwrap_or_default()
+                    .chars()
+                    .chain(std::iter::repeat(pad))
+                    .take(width)
+                    .collect()
+            };
+            let current = fit(current, width);
+            let inserted = fit(inserted, inserted_width);
+            current[..col]
+                .iter()
+                .chain(&inserted)
+                .chain(&current[col..])
+                .collect()
+        };
+
+        // Sequences: matched by ID, new ones appended after the existing rows
+        let mut ids: Vec<String> = self
+            .alignment
+            .sequences
+            .iter()
+            .map(|seq| seq.id.clone())
+            .collect();
+        let mut seen: HashSet<String> = ids.iter().cloned().collect();
+        for seq in &other.sequences {
+            if seen.insert(seq.id.clone()) {
+                ids.push(seq.id.clone());
+            }
+        }
+        let by_id = |alignment: &Alignment| -> HashMap<String, String> {
+            // First occurrence wins for duplicate IDs
+            let mut rows = HashMap::new();
+            for seq in alignment.sequences.iter().rev() {
+                rows.insert(seq.id.clone(), seq.data());
+            }
+            rows
+        };
+        let (current_rows, inserted_rows) = (by_id(&self.alignment), by_id(&other));
+        let pad = self.alignment_gap_char();
+        let sequences: Vec<Rc<Sequence>> = ids
+            .iter()
+            .map(|id| {
+                let data = join(
+                    current_rows.get(id).map(String::as_str),
+                    inserted_rows.get(id).map(String::as_str),
+                    pad,
+                );
+                Rc::new(Sequence::new(id.clone(), data))
+            })
+            .collect();
+
+        // #=GR: the union of tags per sequence
+        let mut residue_annotations = std::collections::HashMap::new();
+        for id in &ids {
+            let current = self.alignment.residue_annotations.get(id);
+            let inserted = other.residue_annotations.get(id);
+            let mut tags: Vec<&str> = Vec::new();
+            for ann in current.into_iter().chain(inserted).flatten() {
+                if !tags.contains(&ann.tag.as_str()) {
+                    tags.push(&ann.tag);
+                }
+            }
+            let data_for = |anns: Option<&Vec<ResidueAnnotation>>, tag: &str| {
+                anns.and_then(|anns| anns.iter().find(|a| a.tag == tag))
+                    .map(|a| a.data.clone())
+            };
+            let merged: Vec<ResidueAnnotation> = tags
+                .iter()
+                .map(|&tag| ResidueAnnotation {
+                    tag: tag.to_string(),
+                    data: join(
+                        data_for(current, tag).as_deref(),
+                        data_for(inserted, tag).as_deref(),
+                        '.',
+                    ),
+                })
+                .collect();
+            if !merged.is_empty() {
+                residue_annotations.insert(id.clone(), merged);
+            }
+        }
+
+        // #=GC: the union of tags
+        let mut tags: Vec<String> = Vec::new();
+        for ann in self
+            .alignment
+            .column_annotations
+            .iter()
+            .chain(&other.column_annotations)
+        {
+            if !tags.contains(&ann.tag) {
+                tags.push(ann.tag.clone());
+            }
+        }
+        let data_for = |alignment: &Alignment, tag: &str| {
+            alignment
+                .column_annotations
+                .iter()
+                .find(|a| a.tag == tag)
+                .map(|a| a.data.clone())
+        };
+        let column_annotations: Vec<ColumnAnnotation> = tags
+            .iter()
+            .map(|tag| ColumnAnnotation {
+                tag: tag.clone(),
+                data: join(
+                    data_for(&self.alignment, tag).as_deref(),
+                    data_for(&other, tag).as_deref(),
+                    '.',
+                ),
+            })
+            .collect();
+
+        // #=GS for sequences new to this alignment
+        for (id, anns) in other.sequence_annotations {
+            self.alignment
+                .sequence_annotations
+                .entry(id)
+                .or_insert(anns);
+        }
+        self.alignment.sequences = sequences;
+        self.alignment.residue_annotations = residue_annotations;
+        self.remap_column_state(|c| Some(if c >= col { c + inserted_width } else { c }));
+        self.alignment.column_annotations = column_annotations;
+
+        self.precompute_collapse_groups();
+        self.mark_modified();
+        self.update_structure_cache();
+        self.clamp_cursor();
+        Ok(())
+    }
+
+    /// Write columns `0..col` to `path_left` and `col..` to `path_right` (`:split-at`).
+    ///
+    /// `#=GC` and `#=GR` lines are cut at the same column; the current
+    /// alignment is left unchanged.
+    pub fn split_at_column(
+        &self,
+        col: usize,
+        path_left: &Path,
+        path_right: &Path,
+    ) -> Result<(), String> {
+        let width = self.alignment.width();
+        if col == 0 || col >= width {
+            return Err(format!(
+                "Split column must be between 2 and {width} so both halves have columns"
+            ));
+        }
+
+        for (path, left) in [(path_left, true), (path_right, false)] {
+            let keep: Vec<bool> = (0..width).map(|c| (c < col) == left).collect();
+            let mut half = self.alignment.clone();
+            half.retain_columns(&keep);
+            writer::write_file(&half, path)
+                .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Shuffle the sequence order with a Fisher–Yates shuffle driven by a
+    /// seeded ChaCha8 generator.
+    ///
+    /// The same seed always gives the same order (starting from the current
+    /// file order), which makes it useful for testing clustering stability.
+    /// Without a seed one is derived from the clock. Returns the seed used.
+    pub fn randomize_sequence_order(&mut self, seed: Option<u64>) -> u64 {
+        let seed = seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default()
+        });
+
+        self.save_undo_state();
+        self.uncluster();
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let sequences = &mut self.alignment.sequences;
+        for i in (1..sequences.len()).rev() {
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            sequences.swap(i, j);
+        }
+
+        self.precompute_collapse_groups();
+        self.mark_modified();
+        self.clamp_cursor();
+        self.set_status(format!("Randomized sequence order (seed {seed})"));
+        seed
+    }
+
+    /// Sort sequences by their `#=GS <tag>` value, numerically if every value
+    /// parses as a number and lexicographically otherwise. Sequences without
+    /// the tag go last in either direction; ties keep their order.
+    pub fn sort_sequences_by_gs(&mut self, tag: &str, reverse: bool) -> Result<(), String> {
+        let values: Vec<Option<String>> = self
+            .alignment
+            .sequences
+            .iter()
+            .map(|seq| {
+                self.alignment
+                    .sequence_annotations
+                    .get(&seq.id)?
+                    .iter()
+                    .find(|ann| ann.tag == tag)
+                    .map(|ann| ann.value.trim().to_string())
+            })
+            .collect();
+        if values.iter().all(Option::is_none) {
+            return Err(format!("No sequence has a #=GS {tag} annotation"));
+        }
+        let numbers: Option<Vec<Option<f64>>> = values
+            .iter()
+            .map(|value| match value {
+                Some(value) => value.parse::<f64>().ok().map(Some),
+                None => Some(None),
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        let compare = |a: usize, b: usize| {
+            let ordering = match &numbers {
+                Some(numbers) => numbers[a].zip(numbers[b]).map(|(x, y)| x.total_cmp(&y)),
+                None => values[a]
+                    .as_ref()
+                    .zip(values[b].as_ref())
+                    .map(|(x, y)| x.cmp(y)),
+            };
+            match ordering {
+                Some(ordering) if reverse => ordering.reverse(),
+                Some(ordering) => ordering,
+                // Missing values sort last
+                None => values[a].is_none().cmp(&values[b].is_none()),
+            }
+        };
+        order.sort_by(|&a, &b| compare(a, b));
+
+        self.save_undo_state();
+        self.uncluster();
+        let sequences = std::mem::take(&mut self.alignment.sequences);
+        self.alignment.sequences = order.iter().map(|&i| sequences[i].clone()).collect();
+        self.precompute_collapse_groups();
+        self.mark_modified();
+        self.clamp_cursor();
+        Ok(())
+    }
+
     /// Convert alignment to uppercase.
     pub fn uppercase_alignment(&mut self) {
         self.save_undo_state();
@@ -333,6 +856,7 @@ impl App {
     pub fn trim_left(&mut self) {
         self.save_undo_state();
         let removed = self.alignment.trim_left(&self.gap_chars);
+        self.remap_column_state(|c| c.checked_sub(removed));
         if removed > 0 {
             self.mark_modified();
             self.clamp_cursor();
@@ -347,6 +871,8 @@ impl App {
     pub fn trim_right(&mut self) {
         self.save_undo_state();
         let removed = self.alignment.trim_right(&self.gap_chars);
+        let width = self.alignment.width();
+        self.remap_column_state(|c| (c < width).then_some(c));
         if removed > 0 {
             self.mark_modified();
             self.clamp_cursor();
@@ -362,6 +888,8 @@ impl App {
         self.save_undo_state();
         let left = self.alignment.trim_left(&self.gap_chars);
         let right = self.alignment.trim_right(&self.gap_chars);
+        let width = self.alignment.width();
+        self.remap_column_state(|c| c.checked_sub(left).filter(|&c| c < width));
         let total = left + right;
         if total > 0 {
             self.mark_modified();
@@ -374,4 +902,679 @@ impl App {
             self.set_status("No gap-only columns to trim");
         }
     }
+
+    /// Count the leading and trailing gaps that `:trim-seqs` would mask.
+    pub(crate) fn unmasked_terminal_gaps(&self) -> usize {
+        self.alignment
+            .sequences
+            .iter()
+            .map(|seq| {
+                seq.untrimmed_terminal_gaps(&self.gap_chars, self.trim_char)
+                    .len()
+            })
+            .sum()
+    }
+
+    /// Mask each sequence's leading and trailing gaps with `trim_char`,
+    /// leaving internal gaps untouched so the two are easy to tell apart.
+    /// Returns the number of sequences changed.
+    pub fn mask_terminal_gaps(&mut self) -> usize {
+        if self.unmasked_terminal_gaps() == 0 {
+            return 0;
+        }
+
+        self.save_undo_state();
+        let mut masked = 0;
+        for seq in &mut self.alignment.sequences {
+            if seq
+                .untrimmed_terminal_gaps(&self.gap_chars, self.trim_char)
+                .is_empty()
+            {
+                continue;
+            }
+            Rc::make_mut(seq).trim_terminal_gaps(&self.gap_chars, self.trim_char);
+            masked += 1;
+        }
+        self.mark_modified();
+        masked
+    }
+
+    /// Fill every gap-only column with `marker` so the column stays visible
+    /// in exported text. Returns the number of columns masked.
+    pub fn mask_gap_columns(&mut self, marker: char) -> usize {
+        let columns: Vec<usize> = (0..self.alignment.width())
+            .filter(|&col| self.alignment.is_empty_column(col, &self.gap_chars))
+            .collect();
+        if columns.is_empty() {
+            return 0;
+        }
+
+        self.save_undo_state();
+        for seq in &mut self.alignment.sequences {
+            let seq = Rc::make_mut(seq);
+            for &col in &columns {
+                seq.set(col, marker);
+            }
+        }
+        self.mark_modified();
+        self.precompute_visible_columns();
+        self.clamp_cursor();
+        columns.len()
+    }
+
+    /// Rewrite every gap character in the sequences as `to` and make `to` the
+    /// gap character for new edits. Column annotations are left alone since
+    /// their `.`, `-`, `_` and `~` carry structure and RF meaning. Returns
+    /// the number of characters replaced.
+    pub fn convert_all_gaps(&mut self, to: char) -> usize {
+        let gap_chars = self.gap_chars.clone();
+        let is_other_gap = |c: &char| *c != to && gap_chars.contains(c);
+        let count: usize = self
+            .alignment
+            .sequences
+            .iter()
+            .map(|seq| seq.chars().iter().filter(|c| is_other_gap(c)).count())
+            .sum();
+        self.gap_char = to;
+        if count == 0 {
+            return 0;
+        }
+
+        self.save_undo_state();
+        for seq in &mut self.alignment.sequences {
+            if seq.chars().iter().any(is_other_gap) {
+                for c in Rc::make_mut(seq).chars_mut() {
+                    if is_other_gap(c) {
+                        *c = to;
+                    }
+                }
+            }
+        }
+        self.mark_modified();
+        count
+    }
+
+    /// Default `:interp-gaps` fill: `X` for protein, `N` for nucleotides.
+    pub fn ambiguous_residue(&self) -> char {
+        match self.sequence_type {
+            SequenceType::Protein => 'X',
+            SequenceType::RNA | SequenceType::DNA => 'N',
+        }
+    }
+
+    /// Replace internal gaps in `row` (those with a residue somewhere on
+    /// both sides) with `fill_char`, marking them as unknown rather than
+    /// absent. Terminal gaps are kept. Returns the number of gaps filled.
+    pub fn interpolate_gaps(&mut self, row: usize, fill_char: char) -> Result<usize, String> {
+        if self.gap_chars.contains(&fill_char) {
+            return Err(format!("'{fill_char}' is a gap character"));
+        }
+        let seq = self
+            .alignment
+            .sequences
+            .get(row)
+            .ok_or_else(|| format!("No sequence at row {}", row + 1))?;
+        let is_residue = |c: &char| !self.gap_chars.contains(c);
+        let chars = seq.chars();
+        let (Some(first), Some(last)) = (
+            chars.iter().position(is_residue),
+            chars.iter().rposition(is_residue),
+        ) else {
+            return Ok(0);
+        };
+        let cols: Vec<usize> = (first..=last)
+            .filter(|&col| !is_residue(&chars[col]))
+            .collect();
+        if cols.is_empty() {
+            return Ok(0);
+        }
+
+        self.save_undo_state();
+        let seq = Rc::make_mut(&mut self.alignment.sequences[row]);
+        for &col in &cols {
+            seq.set(col, fill_char);
+        }
+        self.mark_modified();
+        self.precompute_visible_columns();
+        Ok(cols.len())
+    }
+
+    /// Replace residues whose `#=GR PP` posterior probability is below
+    /// `threshold` with the gap character, as done for `cmalign --sub`
+    /// output. PP digit `d` reads as `d/10` and `*` as 1.0; sequences
+    /// without PP are left alone. Returns the number of residues masked.
+    pub fn filter_by_pp(&mut self, threshold: f64) -> usize {
+        let mut targets: Vec<(usize, Vec<usize>)> = Vec::new();
+        for (row, seq) in self.alignment.sequences.iter().enumerate() {
+            let Some(pp) = self
+                .alignment
+                .residue_annotations
+                .get(&seq.id)
+                .and_then(|anns| anns.iter().find(|a| a.tag == "PP"))
+            else {
+                continue;
+            };
+            let cols: Vec<usize> = pp
+                .data
+                .chars()
+                .enumerate()
+                .filter(|&(col, ch)| {
+                    pp_value(ch).is_some_and(|p| p < threshold)
+                        && seq.get(col).is_some_and(|c| !self.gap_chars.contains(&c))
+                })
+                .map(|(col, _)| col)
+                .collect();
+            if !cols.is_empty() {
+                targets.push((row, cols));
+            }
+        }
+        if targets.is_empty() {
+            return 0;
+        }
+
+        self.save_undo_state();
+        let gap = self.gap_char;
+        let mut masked = 0;
+        for (row, cols) in targets {
+            let seq = Rc::make_mut(&mut self.alignment.sequences[row]);
+            for &col in &cols {
+                seq.set(col, gap);
+            }
+            masked += cols.len();
+            let id = seq.id.clone();
+            if let Some(pp) = self
+                .alignment
+                .residue_annotations
+                .get_mut(&id)
+                .and_then(|anns| anns.iter_mut().find(|a| a.tag == "PP"))
+            {
+                let mut data: Vec<char> = pp.data.chars().collect();
+                for &col in &cols {
+                    data[col] = '.';
+                }
+                pp.data = data.into_iter().collect();
+            }
+        }
+        self.mark_modified();
+        self.precompute_collapse_groups();
+        self.precompute_visible_columns();
+        masked
+    }
+
+    /// Set SS_cons from a bracket-notation string, replacing any existing one.
+    ///
+    /// The string must be exactly as wide as the alignment and well formed.
+    /// Returns the number of base pairs.
+    pub fn set_ss_cons(&mut self, structure: &str) -> Result<usize, String> {
+        let width = self.alignment.width();
+        let len = structure.chars().count();
+        if len != width {
+            return Err(format!(
+                "Structure length {len} does not match alignment width {width}"
+            ));
+        }
+        let mut cache = StructureCache::new();
+        cache
+            .update(structure)
+            .map_err(|e| format!("Invalid structure: {e}"))?;
+
+        self.save_undo_state();
+        match self.alignment.ss_cons_mut() {
+            Some(existing) => *existing = structure.to_string(),
+            None => self.alignment.column_annotations.push(ColumnAnnotation {
+                tag: "SS_cons".to_string(),
+                data: structure.to_string(),
+            }),
+        }
+        let num_pairs = cache.pairs().len();
+        self.structure_cache = cache;
+        self.mark_modified();
+        Ok(num_pairs)
+    }
+
+    /// Remove the SS_cons annotation and clear the structure cache.
+    pub fn clear_ss_cons(&mut self) -> Result<(), String> {
+        if self.alignment.ss_cons().is_none() {
+            return Err("No SS_cons annotation".to_string());
+        }
+        self.save_undo_state();
+        self.alignment
+            .column_annotations
+            .retain(|a| a.tag != "SS_cons");
+        self.structure_cache.clear();
+        self.structure_diff = None;
+        self.mark_modified();
+        Ok(())
+    }
+
+    /// Replace SS_cons with the structure of the reverse strand (`:complement-ss`).
+    pub fn complement_ss(&mut self) -> Result<(), String> {
+        let ss = self
+            .alignment
+            .ss_cons()
+            .ok_or_else(|| "No SS_cons annotation".to_string())?;
+        let complemented = reverse_structure(ss);
+        self.set_ss_cons(&complemented).map(|_| ())
+    }
+
+    /// Add a `#=GR <seq_id> <tag>` annotation, replacing one with the same tag.
+    ///
+    /// The data must be exactly as wide as the alignment.
+    pub fn add_residue_annotation(
+        &mut self,
+        seq_id: &str,
+        tag: &str,
+        data: &str,
+    ) -> Result<(), String> {
+        if !self.alignment.sequences.iter().any(|s| s.id == seq_id) {
+            return Err(format!("No sequence named {seq_id}"));
+        }
+        let width = self.alignment.width();
+        let len = data.chars().count();
+        if len != width {
+            return Err(format!(
+                "Annotation length {len} does not match alignment width {width}"
+            ));
+        }
+
+        self.save_undo_state();
+        let annotations = self
+            .alignment
+            .residue_annotations
+            .entry(seq_id.to_string())
+            .or_default();
+        match annotations.iter_mut().find(|a| a.tag == tag) {
+            Some(existing) => existing.data = data.to_string(),
+            None => annotations.push(ResidueAnnotation {
+                tag: tag.to_string(),
+                data: data.to_string(),
+            }),
+        }
+        self.mark_modified();
+        Ok(())
+    }
+
+    /// Which columns are match (consensus) columns according to RF, or `None`
+    /// without an RF annotation.
+    ///
+    /// A column is an insertion when its RF character is a gap (`.`, `-`) or
+    /// lowercase. RF lines with no uppercase residues (such as HMMER's `x`
+    /// match markers) treat every letter as a match column.
+    pub(crate) fn rf_match_columns(&self) -> Option<Vec<bool>> {
+        let rf = self.alignment.rf()?;
+        let has_uppercase = rf.chars().any(|c| c.is_ascii_uppercase());
+        Some(
+            rf.chars()
+                .map(|c| match c {
+                    '.' | '-' => false,
+                    c if c.is_ascii_lowercase() => !has_uppercase,
+                    _ => true,
+                })
+                .collect(),
+        )
+    }
+
+    /// Remove insertion columns relative to the RF annotation (see
+    /// [`App::rf_match_columns`]). Returns the number of columns removed.
+    pub fn remove_insertion_columns(&mut self) -> Result<usize, String> {
+        let keep = self.rf_match_columns().ok_or("No RF annotation")?;
+
+        if keep.iter().all(|&k| k) {
+            return Ok(0);
+        }
+
+        self.save_undo_state();
+        let removed = self.alignment.retain_columns(&keep);
+        let new_index: Vec<Option<usize>> = keep
+            .iter()
+            .scan(0, |next, &kept| {
+                let index = kept.then_some(*next);
+                *next += usize::from(kept);
+                Some(index)
+            })
+            .collect();
+        self.remap_column_state(|c| new_index.get(c).copied().flatten());
+        self.mark_modified();
+        self.precompute_visible_columns();
+        self.clamp_cursor();
+        self.update_structure_cache();
+        Ok(removed)
+    }
+
+    /// Realign every sequence to the profile HMM at `hmm_path` with `hmmalign`,
+    /// replacing the alignment with its output.
+    ///
+    /// `#=GF` lines missing from the result and `#=GS` annotations are carried
+    /// over; columns, `#=GC` and `#=GR` lines come from hmmalign. Returns the
+    /// number of sequences aligned.
+    pub fn align_to_hmm_profile(&mut self, hmm_path: &Path) -> Result<usize, String> {
+        let records: Vec<(String, String)> = self
+            .alignment
+            .sequences
+            .iter()
+            .map(|seq| {
+                let residues: String = seq
+                    .chars()
+                    .iter()
+                    .filter(|&&c| !self.gap_chars.contains(&c))
+                    .collect();
+                (seq.id.clone(), residues)
+            })
+            .filter(|(_, residues)| !residues.is_empty())
+            .collect();
+        if records.is_empty() {
+            return Err("No residues to align".to_string());
+        }
+
+        let mut aligned = align_to_hmm(&records, hmm_path).map_err(|e| e.to_string())?;
+        for ann in &self.alignment.file_annotations {
+            if !aligned.file_annotations.iter().any(|a| a.tag == ann.tag) {
+                aligned.file_annotations.push(ann.clone());
+            }
+        }
+        for (id, anns) in &self.alignment.sequence_annotations {
+            aligned
+                .sequence_annotations
+                .entry(id.clone())
+                .or_insert_with(|| anns.clone());
+        }
+
+        self.save_undo_state();
+        self.alignment = aligned;
+        self.reset_column_state();
+        self.uncluster();
+        self.mark_modified();
+        self.precompute_collapse_groups();
+        self.precompute_visible_columns();
+        self.clamp_cursor();
+        self.update_structure_cache();
+        Ok(records.len())
+    }
+
+    /// Insert the column consensus as a `CONSENSUS` sequence at row 0, or
+    /// refresh an existing `CONSENSUS` row in place. The consensus is computed
+    /// from the other sequences; all-gap columns get the gap character.
+    /// Returns true if a new row was added.
+    pub fn add_consensus_sequence(&mut self) -> Result<bool, String> {
+        const CONSENSUS_ID: &str = "CONSENSUS";
+
+        let others = Alignment {
+            sequences: self
+                .alignment
+                .sequences
+                .iter()
+                .filter(|seq| seq.id != CONSENSUS_ID)
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        if others.sequences.is_empty() {
+            return Err("No sequences".to_string());
+        }
+        let consensus: String = (0..self.alignment.width())
+            .map(
+                |col| match get_consensus_char(col, &others, &self.gap_chars) {
+                    c if self.gap_chars.contains(&c) => self.gap_char,
+                    c => c,
+                },
+            )
+            .collect();
+
+        Ok(self.put_sequence_first(CONSENSUS_ID, consensus))
+    }
+
+    /// Insert the RF annotation as an `RF_REFERENCE` sequence at row 0, or
+    /// refresh an existing `RF_REFERENCE` row, so it can be shown and picked
+    /// as the reference sequence. Returns true if a new row was added.
+    pub fn add_rf_as_sequence(&mut self) -> Result<bool, String> {
+        let rf = self.alignment.rf().ok_or("No RF annotation")?.to_string();
+        Ok(self.put_sequence_first("RF_REFERENCE", rf))
+    }
+
+    /// Replace the sequence named `id` with `data`, or insert it at row 0
+    /// keeping the reference on the same sequence. Saves undo state and
+    /// returns true if a new row was added.
+    fn put_sequence_first(&mut self, id: &str, data: String) -> bool {
+        self.save_undo_state();
+        let existing = self.alignment.sequences.iter().position(|seq| seq.id == id);
+        let added = match existing {
+            Some(row) => {
+                self.alignment.sequences[row] = Rc::new(Sequence::new(id, data));
+                false
+            }
+            None => {
+                if self.reference_id.is_none() {
+                    self.reference_id = self.alignment.sequences.first().map(|seq| seq.id.clone());
+                }
+                self.alignment
+                    .sequences
+                    .insert(0, Rc::new(Sequence::new(id, data)));
+                true
+            }
+        };
+        self.mark_modified();
+        self.precompute_collapse_groups();
+        self.precompute_visible_columns();
+        if self.cluster_order.is_some() {
+            self.cluster_sequences();
+        }
+        added
+    }
+
+    /// Add the consensus of the profile HMM at `path` as a sequence named
+    /// `HMM_CONSENSUS`, and set SS_cons from the model's consensus structure
+    /// when it has one.
+    ///
+    /// The model must have one match state per alignment column, unless the
+    /// alignment is empty. Returns the model length and whether SS_cons was set.
+    pub fn import_hmm_consensus(&mut self, path: &Path) -> Result<(usize, bool), String> {
+        const CONSENSUS_ID: &str = "HMM_CONSENSUS";
+
+        let (consensus, structure) = parse_hmm_consensus(path).map_err(|e| e.to_string())?;
+        let length = consensus.chars().count();
+        let width = self.alignment.width();
+        if !self.alignment.sequences.is_empty() && length != width {
+            return Err(format!(
+                "HMM has {length} match states but the alignment is {width} columns wide"
+            ));
+        }
+        if self
+            .alignment
+            .sequences
+            .iter()
+            .any(|s| s.id == CONSENSUS_ID)
+        {
+            return Err(format!("Sequence {CONSENSUS_ID} already exists"));
+        }
+        if let Some(structure) = &structure {
+            StructureCache::new()
+                .update(structure)
+                .map_err(|e| format!("Invalid HMM structure: {e}"))?;
+        }
+
+        self.save_undo_state();
+        if self.cluster_order.is_some() {
+            self.materialize_cluster_order();
+        }
+        self.alignment
+            .sequences
+            .push(Rc::new(Sequence::new(CONSENSUS_ID, consensus)));
+        if let Some(structure) = &structure {
+            match self.alignment.ss_cons_mut() {
+                Some(existing) => *existing = structure.clone(),
+                None => self.alignment.column_annotations.push(ColumnAnnotation {
+                    tag: "SS_cons".to_string(),
+                    data: structure.clone(),
+                }),
+            }
+        }
+        self.mark_modified();
+        self.precompute_collapse_groups();
+        self.precompute_visible_columns();
+        self.update_structure_cache();
+        Ok((length, structure.is_some()))
+    }
+
+    /// Slide every sequence so its first residue lines up with the reference's.
+    ///
+    /// Each sequence moves as a whole through its terminal gaps, carrying its
+    /// `#=GR` annotations along; one without enough trailing gaps to reach the
+    /// reference column moves as far as it can. Returns the number of
+    /// sequences moved.
+    pub fn align_to_reference(&mut self) -> Result<usize, String> {
+        let gap_chars = self.gap_chars.clone();
+        let is_residue = |c: &char| !gap_chars.contains(c);
+        let reference_row = self.reference_row();
+        let reference = self
+            .alignment
+            .sequences
+            .get(reference_row)
+            .ok_or("No reference sequence")?;
+        let anchor = reference
+            .chars()
+            .iter()
+            .position(is_residue)
+            .ok_or_else(|| format!("Reference sequence {} is all gaps", reference.id))?;
+
+        let mut moved = 0;
+        for idx in 0..self.alignment.sequences.len() {
+            if idx == reference_row {
+                continue;
+            }
+            let seq = &self.alignment.sequences[idx];
+            let Some(first) = seq.chars().iter().position(is_residue) else {
+                continue;
+            };
+            let direction = if first > anchor {
+                ShiftDirection::Left
+            } else {
+                ShiftDirection::Right
+            };
+            let steps = match direction {
+                ShiftDirection::Left => first - anchor,
+                ShiftDirection::Right => {
+                    let trailing = seq
+                        .chars()
+                        .iter()
+                        .rev()
+                        .take_while(|c| gap_chars.contains(c))
+                        .count();
+                    (anchor - first).min(trailing)
+                }
+            };
+            if steps == 0 {
+                continue;
+            }
+
+            let id = seq.id.clone();
+            // Only a change is worth an undo step
+            if moved == 0 {
+                self.save_undo_state();
+            }
+            let chars = Rc::make_mut(&mut self.alignment.sequences[idx]).chars_mut();
+            rotate_columns(chars, direction, steps);
+            if let Some(annotations) = self.alignment.residue_annotations.get_mut(&id) {
+                for ann in annotations {
+                    let mut data: Vec<char> = ann.data.chars().collect();
+                    rotate_columns(&mut data, direction, steps);
+                    ann.data = data.into_iter().collect();
+                }
+            }
+            moved += 1;
+        }
+
+        if moved > 0 {
+            self.mark_modified();
+            self.update_structure_cache();
+        }
+        Ok(moved)
+    }
+
+    /// Fold the cursor sequence within `col_start..=col_end` with RNAfold and
+    /// merge the predicted structure into SS_cons at those columns only.
+    /// Returns the number of predicted base pairs.
+    pub fn fold_sequence_region(
+        &mut self,
+        col_start: usize,
+        col_end: usize,
+    ) -> Result<usize, String> {
+        let width = self.alignment.width();
+        let col_end = col_end.min(width.saturating_sub(1));
+        if width == 0 || col_start > col_end {
+            return Err("Empty column range".to_string());
+        }
+
+        let actual_row = self.display_to_actual_row(self.cursor_row);
+        let region: Vec<char> = self
+            .alignment
+            .sequences
+            .get(actual_row)
+            .and_then(|seq| seq.chars().get(col_start..=col_end))
+            .ok_or("No sequence at cursor")?
+            .to_vec();
+        let ungapped: String = region
+            .iter()
+            .filter(|c| !self.gap_chars.contains(c))
+            .collect();
+        if ungapped.is_empty() {
+            return Err("No residues in region".to_string());
+        }
+
+        let folded = fold_sequence(&ungapped).map_err(|e| e.to_string())?;
+        let expanded = expand_structure_to_alignment(&region, &folded, &self.gap_chars);
+
+        // Start from the existing SS_cons and drop any pair that crosses the
+        // region boundary, so the merged structure stays balanced.
+        let mut ss: Vec<char> = self
+            .alignment
+            .ss_cons()
+            .map(|s| s.chars().collect())
+            .unwrap_or_default();
+        ss.resize(width, '.');
+        let mut cache = StructureCache::new();
+        if cache.update(&ss.iter().collect::<String>()).is_ok() {
+            for col in col_start..=col_end {
+                if let Some(partner) = cache.get_pair(col)
+                    && !(col_start..=col_end).contains(&partner)
+                {
+                    ss[partner] = '.';
+                }
+            }
+        }
+
+        let mut num_pairs = 0;
+        for (offset, ch) in expanded.chars().enumerate() {
+            ss[col_start + offset] = match ch {
+                '(' => {
+                    num_pairs += 1;
+                    '<'
+                }
+                ')' => '>',
+                _ => '.',
+            };
+        }
+
+        self.save_undo_state();
+        let new_ss: String = ss.into_iter().collect();
+        match self.alignment.ss_cons_mut() {
+            Some(existing) => *existing = new_ss,
+            None => self.alignment.column_annotations.push(ColumnAnnotation {
+                tag: "SS_cons".to_string(),
+                data: new_ss,
+            }),
+        }
+        self.mark_modified();
+        self.update_structure_cache();
+        Ok(num_pairs)
+    }
+}
+
+/// Move every column `steps` places in `direction`, wrapping the columns that
+/// fall off one end (terminal gaps) around to the other.
+fn rotate_columns(chars: &mut [char], direction: ShiftDirection, steps: usize) {
+    let steps = steps.min(chars.len());
+    match direction {
+        ShiftDirection::Left => chars.rotate_left(steps),
+        ShiftDirection::Right => chars.rotate_right(steps),
+    }
 }
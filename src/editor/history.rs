@@ -1,4 +1,8 @@
 //! Undo/redo history.
+//!
+//! History is a tree of states rather than a stack: undoing and then making
+//! a new change starts a new branch instead of discarding the undone states,
+//! which stay reachable through sibling navigation.
 
 use crate::stockholm::Alignment;
 
@@ -10,52 +14,92 @@ pub struct Snapshot {
     pub cursor_col: usize,
 }
 
+/// One state in the undo tree.
+#[derive(Debug, Clone)]
+pub struct UndoNode {
+    /// The state as it was when this node was last left.
+    pub state: Snapshot,
+    /// Indices of the states reached by editing this one, oldest first.
+    pub children: Vec<usize>,
+    /// Index of the state this one was edited from.
+    pub parent: Option<usize>,
+    /// Child redo follows: the most recently created or visited one.
+    active_child: usize,
+}
+
+/// One row of the undo tree listing (`:undo-tree`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoTreeRow {
+    /// Distance from the oldest state.
+    pub depth: usize,
+    /// Number of later branches taken on the way to this state, for indenting.
+    pub level: usize,
+    /// Position among its siblings and the number of siblings.
+    pub branch: (usize, usize),
+    /// Whether this is the current state.
+    pub current: bool,
+    /// Cursor position saved with the state.
+    pub cursor: (usize, usize),
+}
+
 /// Undo/redo history manager.
 #[derive(Debug, Default)]
 pub struct History {
-    /// Undo stack.
-    undo_stack: Vec<Snapshot>,
-    /// Redo stack.
-    redo_stack: Vec<Snapshot>,
-    /// Maximum history size.
+    /// All states, parents before children.
+    nodes: Vec<UndoNode>,
+    /// Index of the node for the live alignment, if anything was saved.
+    current: Option<usize>,
+    /// Maximum number of undo steps kept.
     max_size: usize,
 }
 
 impl History {
     /// Create a new history with default max size.
     pub fn new() -> Self {
-        Self {
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-            max_size: 100,
-        }
+        Self::with_max_size(100)
     }
 
     /// Create a new history with a specific max size.
-    #[allow(dead_code)] // API for configurable history size
     pub fn with_max_size(max_size: usize) -> Self {
         Self {
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            nodes: Vec::new(),
+            current: None,
             max_size,
         }
     }
 
     /// Save a snapshot before making changes.
+    ///
+    /// The snapshot becomes the current node's state and a new child is
+    /// started for the changed alignment; the child's state is filled in
+    /// when it is left by undo or sibling navigation.
     pub fn save(&mut self, alignment: &Alignment, cursor_row: usize, cursor_col: usize) {
-        // Clear redo stack when making new changes
-        self.redo_stack.clear();
-
-        // Add snapshot to undo stack
-        self.undo_stack.push(Snapshot {
+        let state = Snapshot {
             alignment: alignment.clone(),
             cursor_row,
             cursor_col,
-        });
+        };
+        let parent = match self.current {
+            Some(current) => {
+                self.nodes[current].state = state.clone();
+                current
+            }
+            None => self.push_node(state.clone(), None),
+        };
+        let child = self.push_node(state, Some(parent));
+        self.current = Some(child);
 
-        // Trim if exceeds max size
-        while self.undo_stack.len() > self.max_size {
-            self.undo_stack.remove(0);
+        // Trim the oldest states if the tree grew too deep
+        while self.undo_count() > self.max_size {
+            self.drop_root();
+        }
+        // Abandoned branches get as much room as the undo steps; past that
+        // their oldest states go first
+        while self.nodes.len() > 2 * self.max_size + 1 {
+            let Some(leaf) = self.oldest_abandoned_leaf() else {
+                break;
+            };
+            self.remove_leaf(leaf);
         }
     }
 
@@ -66,67 +110,236 @@ impl History {
         cursor_row: usize,
         cursor_col: usize,
     ) -> Option<Snapshot> {
-        if let Some(snapshot) = self.undo_stack.pop() {
-            // Save current state to redo stack
-            self.redo_stack.push(Snapshot {
-                alignment: current.clone(),
-                cursor_row,
-                cursor_col,
-            });
-            Some(snapshot)
-        } else {
-            None
-        }
+        let node = self.current?;
+        let parent = self.nodes[node].parent?;
+        self.move_to(parent, current, cursor_row, cursor_col);
+        self.nodes[parent].active_child = self.child_position(node);
+        Some(self.nodes[parent].state.clone())
     }
 
-    /// Redo the last undone change.
+    /// Redo the last undone change on the active branch.
     pub fn redo(
         &mut self,
         current: &Alignment,
         cursor_row: usize,
         cursor_col: usize,
     ) -> Option<Snapshot> {
-        if let Some(snapshot) = self.redo_stack.pop() {
-            // Save current state to undo stack
-            self.undo_stack.push(Snapshot {
-                alignment: current.clone(),
-                cursor_row,
-                cursor_col,
-            });
-            Some(snapshot)
+        let node = self.current?;
+        let child = *self.nodes[node]
+            .children
+            .get(self.nodes[node].active_child)?;
+        self.move_to(child, current, cursor_row, cursor_col);
+        Some(self.nodes[child].state.clone())
+    }
+
+    /// Switch to the previous (`forward == false`) or next sibling branch,
+    /// returning its state.
+    pub fn switch_branch(
+        &mut self,
+        forward: bool,
+        current: &Alignment,
+        cursor_row: usize,
+        cursor_col: usize,
+    ) -> Option<Snapshot> {
+        let node = self.current?;
+        let parent = self.nodes[node].parent?;
+        let pos = self.child_position(node);
+        let target = if forward {
+            pos + 1
         } else {
-            None
-        }
+            pos.checked_sub(1)?
+        };
+        let sibling = *self.nodes[parent].children.get(target)?;
+        self.move_to(sibling, current, cursor_row, cursor_col);
+        self.nodes[parent].active_child = target;
+        Some(self.nodes[sibling].state.clone())
     }
 
     /// Check if undo is available.
-    #[allow(dead_code)] // API for status bar display
     pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
+        self.undo_count() > 0
     }
 
     /// Check if redo is available.
-    #[allow(dead_code)] // API for status bar display
     pub fn can_redo(&self) -> bool {
-        !self.redo_stack.is_empty()
+        self.redo_count() > 0
     }
 
     /// Clear all history.
     pub fn clear(&mut self) {
-        self.undo_stack.clear();
-        self.redo_stack.clear();
+        self.nodes.clear();
+        self.current = None;
     }
 
     /// Get the number of undo steps available.
-    #[allow(dead_code)] // API for status display
     pub fn undo_count(&self) -> usize {
-        self.undo_stack.len()
+        let mut count = 0;
+        let mut node = self.current;
+        while let Some(parent) = node.and_then(|n| self.nodes[n].parent) {
+            count += 1;
+            node = Some(parent);
+        }
+        count
     }
 
-    /// Get the number of redo steps available.
+    /// Get the number of redo steps available on the active branch.
     #[allow(dead_code)] // API for status display
     pub fn redo_count(&self) -> usize {
-        self.redo_stack.len()
+        let mut count = 0;
+        let mut node = self.current;
+        while let Some(child) =
+            node.and_then(|n| self.nodes[n].children.get(self.nodes[n].active_child))
+        {
+            count += 1;
+            node = Some(*child);
+        }
+        count
+    }
+
+    /// Every saved state, in creation order.
+    pub fn snapshots(&self) -> impl Iterator<Item = &Snapshot> {
+        self.nodes.iter().map(|node| &node.state)
+    }
+
+    /// The tree in depth-first order, oldest branch first.
+    pub fn tree_rows(&self) -> Vec<UndoTreeRow> {
+        let mut rows = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<(usize, usize, usize)> = self
+            .nodes
+            .iter()
+            .position(|n| n.parent.is_none())
+            .map(|root| (root, 0, 0))
+            .into_iter()
+            .collect();
+        while let Some((idx, depth, level)) = stack.pop() {
+            let node = &self.nodes[idx];
+            let branch = match node.parent {
+                Some(parent) => (self.child_position(idx), self.nodes[parent].children.len()),
+                None => (0, 1),
+            };
+            let level = level + usize::from(branch.0 > 0);
+            rows.push(UndoTreeRow {
+                depth,
+                level,
+                branch,
+                current: self.current == Some(idx),
+                cursor: (node.state.cursor_row, node.state.cursor_col),
+            });
+            stack.extend(node.children.iter().rev().map(|&c| (c, depth + 1, level)));
+        }
+        rows
+    }
+
+    fn push_node(&mut self, state: Snapshot, parent: Option<usize>) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(UndoNode {
+            state,
+            children: Vec::new(),
+            parent,
+            active_child: 0,
+        });
+        if let Some(parent) = parent {
+            let parent = &mut self.nodes[parent];
+            parent.children.push(idx);
+            parent.active_child = parent.children.len() - 1;
+        }
+        idx
+    }
+
+    /// Record the live state in the current node and make `target` current.
+    fn move_to(&mut self, target: usize, live: &Alignment, cursor_row: usize, cursor_col: usize) {
+        if let Some(node) = self.current {
+            self.nodes[node].state = Snapshot {
+                alignment: live.clone(),
+                cursor_row,
+                cursor_col,
+            };
+        }
+        self.current = Some(target);
+    }
+
+    fn child_position(&self, node: usize) -> usize {
+        self.nodes[node]
+            .parent
+            .and_then(|p| self.nodes[p].children.iter().position(|&c| c == node))
+            .unwrap_or(0)
+    }
+
+    /// The oldest state with no children that is not on the way to the
+    /// current state.
+    fn oldest_abandoned_leaf(&self) -> Option<usize> {
+        let mut on_path = vec![false; self.nodes.len()];
+        let mut node = self.current;
+        while let Some(idx) = node {
+            on_path[idx] = true;
+            node = self.nodes[idx].parent;
+        }
+        (0..self.nodes.len()).find(|&idx| !on_path[idx] && self.nodes[idx].children.is_empty())
+    }
+
+    /// Remove a state with no children, renumbering the later ones.
+    fn remove_leaf(&mut self, leaf: usize) {
+        if let Some(parent) = self.nodes[leaf].parent {
+            let pos = self.child_position(leaf);
+            let parent = &mut self.nodes[parent];
+            parent.children.remove(pos);
+            if parent.active_child > pos || parent.active_child == parent.children.len() {
+                parent.active_child = parent.active_child.saturating_sub(1);
+            }
+        }
+        self.nodes.remove(leaf);
+        let shift = |idx: usize| if idx > leaf { idx - 1 } else { idx };
+        for node in &mut self.nodes {
+            node.parent = node.parent.map(shift);
+            for child in &mut node.children {
+                *child = shift(*child);
+            }
+        }
+        self.current = self.current.map(shift);
+    }
+
+    /// Drop the oldest state, keeping only the branch that leads to the
+    /// current state.
+    fn drop_root(&mut self) {
+        let Some(mut keep) = self.current else {
+            return;
+        };
+        while let Some(parent) = self.nodes[keep].parent {
+            if self.nodes[parent].parent.is_none() {
+                break;
+            }
+            keep = parent;
+        }
+
+        // Nodes in the kept subtree, renumbered in their original order
+        let mut kept = vec![false; self.nodes.len()];
+        kept[keep] = true;
+        for idx in keep + 1..self.nodes.len() {
+            if let Some(parent) = self.nodes[idx].parent {
+                kept[idx] = kept[parent];
+            }
+        }
+        let mut new_index = vec![None; self.nodes.len()];
+        let mut next = 0;
+        for (idx, &k) in kept.iter().enumerate() {
+            if k {
+                new_index[idx] = Some(next);
+                next += 1;
+            }
+        }
+
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes
+            .into_iter()
+            .zip(kept)
+            .filter(|(_, k)| *k)
+            .map(|(mut node, _)| {
+                node.parent = node.parent.and_then(|p| new_index[p]);
+                node.children = node.children.iter().filter_map(|&c| new_index[c]).collect();
+                node
+            })
+            .collect();
+        self.current = self.current.and_then(|c| new_index[c]);
     }
 }
 
@@ -174,6 +387,80 @@ mod tests {
         assert_eq!(snapshot.unwrap().alignment.sequences[0].data(), "ACGU.");
     }
 
+    #[test]
+    fn test_undo_tree_branches() {
+        let mut history = History::new();
+
+        let base = make_alignment("ACGU");
+        let first = make_alignment("ACGU.");
+        let second = make_alignment("ACGU..");
+
+        // Edit, undo, then edit differently: both edits stay reachable
+        history.save(&base, 0, 0);
+        let snapshot = history.undo(&first, 0, 1).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "ACGU");
+        history.save(&base, 0, 0);
+        assert_eq!(history.undo_count(), 1);
+
+        let snapshot = history.switch_branch(false, &second, 0, 2).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "ACGU.");
+        assert!(history.switch_branch(false, &first, 0, 1).is_none());
+        let snapshot = history.switch_branch(true, &first, 0, 1).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "ACGU..");
+
+        // Redo follows the branch visited last
+        history.switch_branch(false, &second, 0, 2);
+        history.undo(&first, 0, 1);
+        let snapshot = history.redo(&base, 0, 0).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "ACGU.");
+
+        let rows = history.tree_rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows.iter()
+                .map(|r| (r.depth, r.level, r.branch))
+                .collect::<Vec<_>>(),
+            vec![(0, 0, (0, 1)), (1, 0, (0, 2)), (1, 1, (1, 2))]
+        );
+        assert!(rows[1].current);
+    }
+
+    #[test]
+    fn test_undo_tree_max_size() {
+        let mut history = History::with_max_size(2);
+        for len in 0..4 {
+            history.save(&make_alignment(&"A".repeat(len + 1)), 0, len);
+        }
+        assert_eq!(history.undo_count(), 2);
+        assert_eq!(history.tree_rows().len(), 3);
+
+        let snapshot = history.undo(&make_alignment("AAAAA"), 0, 4).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "AAAA");
+        let snapshot = history.undo(&make_alignment("AAAA"), 0, 3).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "AAA");
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_undo_tree_abandoned_branches_capped() {
+        let mut history = History::with_max_size(2);
+        let base = make_alignment("ACGU");
+        history.save(&base, 0, 0);
+        // Each edit made after undoing leaves the previous one as a branch
+        for len in 1..8 {
+            history.undo(&make_alignment(&"U".repeat(len)), 0, len);
+            history.save(&base, 0, 0);
+        }
+        assert_eq!(history.tree_rows().len(), 5);
+        assert_eq!(history.undo_count(), 1);
+
+        // The newest branches are the ones kept
+        let snapshot = history.switch_branch(false, &base, 0, 0).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "UUUUUUU");
+        let snapshot = history.switch_branch(false, &base, 0, 0).unwrap();
+        assert_eq!(snapshot.alignment.sequences[0].data(), "UUUUUU");
+    }
+
     #[test]
     fn test_redo_cleared_on_new_change() {
         let mut history = History::new();
@@ -0,0 +1,107 @@
+//! Anchor (landmark) positions.
+//!
+//! Anchors are read from a two-column TSV of 1-based RF position and label
+//! (`#` starts a comment) and stored as `#=GC ANCHORS`, where each anchor
+//! column holds the first character of its label and every other column is
+//! `.`.
+
+use std::path::Path;
+
+use thiserror::Error;
+
+/// `#=GC` tag holding anchor labels.
+pub const ANCHORS_TAG: &str = "ANCHORS";
+
+#[derive(Error, Debug)]
+pub enum AnchorError {
+    #[error("Failed to read anchor file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Line {line}: expected an RF position and a label")]
+    MissingFields { line: usize },
+    #[error("Line {line}: invalid RF position '{value}'")]
+    InvalidPosition { line: usize, value: String },
+}
+
+/// Read anchors as `(RF position, label)`, positions 1-based as written.
+pub fn parse_anchor_file(path: &Path) -> Result<Vec<(usize, String)>, AnchorError> {
+    parse_anchors(&std::fs::read_to_string(path)?)
+}
+
+fn parse_anchors(text: &str) -> Result<Vec<(usize, String)>, AnchorError> {
+    let mut anchors = Vec::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut fields = line.splitn(2, '\t');
+        let (Some(position), Some(label)) = (fields.next(), fields.next()) else {
+            return Err(AnchorError::MissingFields { line: line_no });
+        };
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(AnchorError::MissingFields { line: line_no });
+        }
+        let position = position
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&p| p > 0)
+            .ok_or_else(|| AnchorError::InvalidPosition {
+                line: line_no,
+                value: position.trim().to_string(),
+            })?;
+        anchors.push((position, label.to_string()));
+    }
+
+    Ok(anchors)
+}
+
+/// Encode anchors at 0-based alignment columns as `#=GC ANCHORS` data, one
+/// label character per anchor column.
+pub fn encode_anchors(anchors: &[(usize, String)], width: usize) -> String {
+    let mut data = vec!['.'; width];
+    for (col, label) in anchors {
+        if let (Some(slot), Some(ch)) = (data.get_mut(*col), label.chars().next()) {
+            *slot = ch;
+        }
+    }
+    data.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_encode() {
+        let text = "# position\tlabel\n3\tcatalytic G\n\n10\tA-minor  # tail\n";
+        let anchors = parse_anchors(text).unwrap();
+        assert_eq!(
+            anchors,
+            vec![(3, "catalytic G".to_string()), (10, "A-minor".to_string())]
+        );
+
+        let cols: Vec<(usize, String)> = vec![(2, "catalytic".into()), (5, "A-minor".into())];
+        assert_eq!(encode_anchors(&cols, 7), "..c..A.");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse_anchors("3\n"),
+            Err(AnchorError::MissingFields { line: 1 })
+        ));
+        assert!(matches!(
+            parse_anchors("x\tlabel\n"),
+            Err(AnchorError::InvalidPosition { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse_anchors("# header\n0\tlabel\n"),
+            Err(AnchorError::InvalidPosition { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,72 @@
+//! BPSEQ pairing table export.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::structure::StructureCache;
+
+/// Write a single sequence and its structure in BPSEQ format.
+///
+/// `sequence` and `structure` must be ungapped and of equal length. Each line
+/// holds `index base paired_to`, with 0 for unpaired bases.
+pub fn write_bpseq(sequence: &str, structure: &str, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_bpseq_to(sequence, structure, &mut writer)?;
+    writer.flush()
+}
+
+/// Write a BPSEQ table to any writer.
+fn write_bpseq_to<W: Write>(sequence: &str, structure: &str, writer: &mut W) -> io::Result<()> {
+    let mut cache = StructureCache::new();
+    cache
+        .update(structure)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    for (i, base) in sequence.chars().enumerate() {
+        let paired = cache.get_pair(i).map_or(0, |p| p + 1);
+        writeln!(writer, "{} {} {paired}", i + 1, standard_base(base))?;
+    }
+    Ok(())
+}
+
+/// Map a residue to an upper-case nucleotide letter most tools accept.
+///
+/// IUPAC codes are kept; the MODOMICS one-letter codes for pseudouridine (`P`)
+/// and inosine (`I`) become `U` and `G`, and anything else becomes `N`.
+fn standard_base(base: char) -> char {
+    match base.to_ascii_uppercase() {
+        b @ ('A' | 'C' | 'G' | 'U' | 'T' | 'R' | 'Y' | 'S' | 'W' | 'K' | 'M' | 'B' | 'D' | 'H'
+        | 'V' | 'N') => b,
+        'P' => 'U',
+        'I' => 'G',
+        _ => 'N',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_bpseq() {
+        let mut out = Vec::new();
+        write_bpseq_to("GgAPAIC", "((...))", &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "1 G 7");
+        assert_eq!(lines[1], "2 G 6");
+        assert_eq!(lines[3], "4 U 0");
+        assert_eq!(lines[5], "6 G 2");
+        assert_eq!(lines[6], "7 C 1");
+    }
+
+    #[test]
+    fn test_standard_base() {
+        assert_eq!(standard_base('u'), 'U');
+        assert_eq!(standard_base('R'), 'R');
+        assert_eq!(standard_base('7'), 'N');
+    }
+}
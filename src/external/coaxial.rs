@@ -0,0 +1,147 @@
+//! Coaxial stacking annotations.
+//!
+//! Stacks are read from a plain text file with two 1-based alignment columns
+//! per line (`#` starts a comment) and stored as `#=GC COAXIAL`, where both
+//! columns of a stack carry the same letter and every other column is `.`.
+
+use thiserror::Error;
+
+/// `#=GC` tag holding coaxial stacks.
+pub const COAXIAL_TAG: &str = "COAXIAL";
+
+/// Labels for stacks, in order; one letter per stack.
+const LABELS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CoaxialError {
+    #[error("Line {line}: expected two column numbers")]
+    MissingFields { line: usize },
+    #[error("Line {line}: invalid column '{value}'")]
+    InvalidColumn { line: usize, value: String },
+    #[error("Line {line}: column {column} is outside the alignment (1-{width})")]
+    OutOfRange {
+        line: usize,
+        column: usize,
+        width: usize,
+    },
+    #[error("Line {line}: column {column} is already in a stack")]
+    Overlap { line: usize, column: usize },
+    #[error("At most {max} coaxial stacks are supported")]
+    TooMany { max: usize },
+}
+
+/// Parse column pairs as 0-based `(left, right)` with `left < right`.
+pub fn parse_coaxial(text: &str, width: usize) -> Result<Vec<(usize, usize)>, CoaxialError> {
+    let mut pairs = Vec::new();
+    let mut used = vec![false; width];
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|f| !f.is_empty())
+            .collect();
+        if fields.len() != 2 {
+            return Err(CoaxialError::MissingFields { line: line_no });
+        }
+
+        let mut cols = [0; 2];
+        for (col, field) in cols.iter_mut().zip(&fields) {
+            let column: usize = field.parse().map_err(|_| CoaxialError::InvalidColumn {
+                line: line_no,
+                value: field.to_string(),
+            })?;
+            if column == 0 || column > width {
+                return Err(CoaxialError::OutOfRange {
+                    line: line_no,
+                    column,
+                    width,
+                });
+            }
+            if used[column - 1] {
+                return Err(CoaxialError::Overlap {
+                    line: line_no,
+                    column,
+                });
+            }
+            used[column - 1] = true;
+            *col = column - 1;
+        }
+        pairs.push((cols[0].min(cols[1]), cols[0].max(cols[1])));
+    }
+
+    if pairs.len() > LABELS.len() {
+        return Err(CoaxialError::TooMany { max: LABELS.len() });
+    }
+    Ok(pairs)
+}
+
+/// Encode stacks as `#=GC COAXIAL` data, labelling each stack with a letter.
+pub fn encode_coaxial(pairs: &[(usize, usize)], width: usize) -> String {
+    let mut data = vec!['.'; width];
+    for (&(left, right), &label) in pairs.iter().zip(LABELS) {
+        data[left] = label as char;
+        data[right] = label as char;
+    }
+    data.into_iter().collect()
+}
+
+/// Column stacked with `col` in `#=GC COAXIAL` data, if any.
+pub fn coaxial_partner(data: &str, col: usize) -> Option<usize> {
+    let chars: Vec<char> = data.chars().collect();
+    let label = *chars.get(col)?;
+    if !label.is_ascii_alphabetic() {
+        return None;
+    }
+    chars
+        .iter()
+        .enumerate()
+        .position(|(i, &c)| i != col && c == label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_encode() {
+        let text = "# stacks\n2 9\n12,5  # second\n\n";
+        let pairs = parse_coaxial(text, 12).unwrap();
+        assert_eq!(pairs, vec![(1, 8), (4, 11)]);
+
+        let data = encode_coaxial(&pairs, 12);
+        assert_eq!(data, ".A..B...A..B");
+        assert_eq!(coaxial_partner(&data, 1), Some(8));
+        assert_eq!(coaxial_partner(&data, 11), Some(4));
+        assert_eq!(coaxial_partner(&data, 0), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_coaxial("3\n", 10),
+            Err(CoaxialError::MissingFields { line: 1 })
+        );
+        assert_eq!(
+            parse_coaxial("1 11\n", 10),
+            Err(CoaxialError::OutOfRange {
+                line: 1,
+                column: 11,
+                width: 10
+            })
+        );
+        assert_eq!(
+            parse_coaxial("1 4\n4 6\n", 10),
+            Err(CoaxialError::Overlap { line: 2, column: 4 })
+        );
+        assert!(matches!(
+            parse_coaxial("1 x\n", 10),
+            Err(CoaxialError::InvalidColumn { line: 1, .. })
+        ));
+    }
+}
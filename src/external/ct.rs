@@ -0,0 +1,65 @@
+//! Connectivity table (`.ct`) export.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::structure::StructureCache;
+
+/// Write a single sequence and its structure as a connectivity table.
+///
+/// `sequence` and `structure` must be ungapped and of equal length. Each line
+/// holds the standard six columns: index, base, previous, next, paired_to
+/// (0 if unpaired), and natural numbering.
+pub fn write_ct(sequence: &str, structure: &str, name: &str, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_ct_to(sequence, structure, name, &mut writer)?;
+    writer.flush()
+}
+
+/// Write a connectivity table to any writer.
+fn write_ct_to<W: Write>(
+    sequence: &str,
+    structure: &str,
+    name: &str,
+    writer: &mut W,
+) -> io::Result<()> {
+    let mut cache = StructureCache::new();
+    cache
+        .update(structure)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    let bases: Vec<char> = sequence.chars().collect();
+    let n = bases.len();
+    writeln!(writer, "{n:5} {name}")?;
+    for (i, base) in bases.iter().enumerate() {
+        let index = i + 1;
+        let next = if index == n { 0 } else { index + 1 };
+        let paired = cache.get_pair(i).map_or(0, |p| p + 1);
+        writeln!(
+            writer,
+            "{index:5} {base} {:5} {next:5} {paired:5} {index:5}",
+            index - 1
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_ct() {
+        let mut out = Vec::new();
+        write_ct_to("GGAAACC", "((...))", "hairpin", &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "    7 hairpin");
+        assert_eq!(lines[1], "    1 G     0     2     7     1");
+        assert_eq!(lines[4], "    4 A     3     5     0     4");
+        assert_eq!(lines[7], "    7 C     6     0     1     7");
+    }
+}
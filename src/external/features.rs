@@ -0,0 +1,161 @@
+//! BED and GFF3 feature interval readers.
+//!
+//! Only the coordinates are read; sequence names, strands and attributes are
+//! ignored, so every feature is assumed to lie on the alignment's reference.
+
+use std::path::Path;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FeatureError {
+    #[error("Unknown feature format (expected .bed, .gff or .gff3)")]
+    UnknownFormat,
+    #[error("Line {line}: expected at least {expected} fields")]
+    MissingFields { line: usize, expected: usize },
+    #[error("Line {line}: invalid coordinate '{value}'")]
+    InvalidCoordinate { line: usize, value: String },
+    #[error("Line {line}: feature ends before it starts")]
+    InvertedInterval { line: usize },
+}
+
+/// Feature file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeatureFormat {
+    /// BED: 0-based, half-open `chrom start end`.
+    Bed,
+    /// GFF3: 1-based, inclusive start and end in columns 4 and 5.
+    Gff,
+}
+
+impl FeatureFormat {
+    /// Detect the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "bed" => Some(Self::Bed),
+            "gff" | "gff3" => Some(Self::Gff),
+            _ => None,
+        }
+    }
+}
+
+/// Parse feature intervals as 0-based, half-open `(start, end)` pairs.
+pub fn parse_features(
+    text: &str,
+    format: FeatureFormat,
+) -> Result<Vec<(usize, usize)>, FeatureError> {
+    let mut intervals = Vec::new();
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            // GFF3 may append sequences after a ##FASTA directive
+            if format == FeatureFormat::Gff && line.starts_with("##FASTA") {
+                break;
+            }
+            continue;
+        }
+
+        let (start, end) = match format {
+            FeatureFormat::Bed => {
+                if line.starts_with("track") || line.starts_with("browser") {
+                    continue;
+                }
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                if fields.len() < 3 {
+                    return Err(FeatureError::MissingFields {
+                        line: line_no,
+                        expected: 3,
+                    });
+                }
+                (
+                    parse_coordinate(fields[1], line_no)?,
+                    parse_coordinate(fields[2], line_no)?,
+                )
+            }
+            FeatureFormat::Gff => {
+                let fields: Vec<&str> = line.split('\t').collect();
+                if fields.len() < 5 {
+                    return Err(FeatureError::MissingFields {
+                        line: line_no,
+                        expected: 5,
+                    });
+                }
+                let start = parse_coordinate(fields[3], line_no)?;
+                if start == 0 {
+                    return Err(FeatureError::InvalidCoordinate {
+                        line: line_no,
+                        value: fields[3].to_string(),
+                    });
+                }
+                (start - 1, parse_coordinate(fields[4], line_no)?)
+            }
+        };
+
+        if end < start {
+            return Err(FeatureError::InvertedInterval { line: line_no });
+        }
+        intervals.push((start, end));
+    }
+
+    Ok(intervals)
+}
+
+fn parse_coordinate(value: &str, line: usize) -> Result<usize, FeatureError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| FeatureError::InvalidCoordinate {
+            line,
+            value: value.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bed() {
+        let bed = "track name=test\n# comment\nchr1\t0\t3\tfeat\nchr1 5 6\n";
+        assert_eq!(
+            parse_features(bed, FeatureFormat::Bed).unwrap(),
+            vec![(0, 3), (5, 6)]
+        );
+        assert_eq!(
+            parse_features("chr1\t4\n", FeatureFormat::Bed),
+            Err(FeatureError::MissingFields {
+                line: 1,
+                expected: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_gff() {
+        let gff = "##gff-version 3\nref\tsrc\thairpin\t2\t4\t.\t+\t.\tID=h1\n##FASTA\n>ref\n";
+        assert_eq!(
+            parse_features(gff, FeatureFormat::Gff).unwrap(),
+            vec![(1, 4)]
+        );
+        assert!(matches!(
+            parse_features("ref\tsrc\tx\t0\t4\n", FeatureFormat::Gff),
+            Err(FeatureError::InvalidCoordinate { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            FeatureFormat::from_path(Path::new("a.BED")),
+            Some(FeatureFormat::Bed)
+        );
+        assert_eq!(
+            FeatureFormat::from_path(Path::new("a.gff3")),
+            Some(FeatureFormat::Gff)
+        );
+        assert_eq!(FeatureFormat::from_path(Path::new("a.txt")), None);
+    }
+}
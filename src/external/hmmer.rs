@@ -0,0 +1,256 @@
+//! Profile HMMs with HMMER: `hmmbuild` to build one, `hmmalign` to align to one,
+//! and a reader for the consensus line of a HMMER3 `.hmm` file.
+
+use std::path::Path;
+use std::process::Command;
+
+use thiserror::Error;
+
+use crate::stockholm::{Alignment, parser, writer};
+
+#[derive(Error, Debug)]
+pub enum HmmerError {
+    #[error("{0} not found in PATH (install HMMER 3)")]
+    NotFound(&'static str),
+    #[error("Failed to run HMMER: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0} failed: {1}")]
+    Failed(&'static str, String),
+    #[error("Unexpected hmmbuild output")]
+    InvalidOutput,
+    #[error("hmmalign returned an invalid alignment: {0}")]
+    Parse(#[from] parser::ParseError),
+    #[error("Invalid HMM file: {0}")]
+    InvalidHmm(&'static str),
+}
+
+/// Model summary printed by `hmmbuild`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HmmSummary {
+    /// Number of sequences in the alignment.
+    pub nseq: usize,
+    /// Alignment length (columns).
+    pub alen: usize,
+    /// Model length (match columns, from RF when present).
+    pub mlen: usize,
+    /// Effective number of sequences after weighting.
+    pub eff_nseq: f64,
+}
+
+/// Build an RNA profile HMM from a Stockholm file by running
+/// `hmmbuild --rna <output_path> <alignment_path>`.
+pub fn build_hmm(alignment_path: &Path, output_path: &Path) -> Result<HmmSummary, HmmerError> {
+    let stdout = run(
+        "hmmbuild",
+        Command::new("hmmbuild")
+            .arg("--rna")
+            .arg(output_path)
+            .arg(alignment_path),
+    )?;
+    parse_hmmbuild_output(&stdout)
+}
+
+/// Align unaligned `(id, sequence)` records to a profile HMM with
+/// `hmmalign --trim --rna --outformat stockholm`.
+pub fn align_to_hmm(
+    sequences: &[(String, String)],
+    hmm_path: &Path,
+) -> Result<Alignment, HmmerError> {
+    let fasta_path = std::env::temp_dir().join(format!("aform_hmmalign_{}.fa", std::process::id()));
+    writer::write_fasta(sequences, &fasta_path)?;
+    let result = run(
+        "hmmalign",
+        Command::new("hmmalign")
+            .args(["--trim", "--rna", "--outformat", "stockholm"])
+            .arg(hmm_path)
+            .arg(&fasta_path),
+    );
+    let _ = std::fs::remove_file(&fasta_path);
+    Ok(parser::parse_str(&result?)?)
+}
+
+/// Read the consensus sequence and, when the model has one (`CS yes`), the
+/// consensus structure of the first model in a HMMER3 `.hmm` file.
+///
+/// Both come from the annotation columns at the end of each match state line.
+/// Files without a CONS column (HMMER 3.0) get the most probable residue.
+pub fn parse_hmm_consensus(path: &Path) -> Result<(String, Option<String>), HmmerError> {
+    parse_consensus(&std::fs::read_to_string(path)?)
+}
+
+fn parse_consensus(text: &str) -> Result<(String, Option<String>), HmmerError> {
+    let mut lines = text.lines();
+    let mut has_cs = false;
+    let alphabet: Vec<char> = loop {
+        let line = lines
+            .next()
+            .ok_or(HmmerError::InvalidHmm("no HMM section"))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["CS", flag] => has_cs = flag.eq_ignore_ascii_case("yes"),
+            ["HMM", symbols @ ..] => {
+                break symbols.iter().filter_map(|s| s.chars().next()).collect();
+            }
+            _ => {}
+        }
+    };
+    if alphabet.is_empty() {
+        return Err(HmmerError::InvalidHmm("no alphabet on the HMM line"));
+    }
+
+    let mut consensus = String::new();
+    let mut structure = String::new();
+    for line in lines.take_while(|line| !line.starts_with("//")) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < alphabet.len() + 4 || fields[0].parse::<usize>().is_err() {
+            continue;
+        }
+        let annotations = &fields[1 + alphabet.len()..];
+        // HMMER 3.1+: MAP CONS RF MM CS; HMMER 3.0: MAP RF CS
+        let cons = match annotations {
+            [_, cons, _, _, _, ..] => cons.chars().next().filter(|&c| c != '-'),
+            _ => None,
+        };
+        let cons = match cons {
+            Some(c) => c,
+            None => most_probable(&fields[1..=alphabet.len()], &alphabet)?,
+        };
+        consensus.push(cons);
+        structure.push(
+            annotations
+                .last()
+                .and_then(|cs| cs.chars().next())
+                .unwrap_or('.'),
+        );
+    }
+
+    if consensus.is_empty() {
+        return Err(HmmerError::InvalidHmm("no match states"));
+    }
+    Ok((consensus, has_cs.then_some(structure)))
+}
+
+/// Residue with the lowest emission score (scores are negative natural logs).
+fn most_probable(scores: &[&str], alphabet: &[char]) -> Result<char, HmmerError> {
+    let mut best = None;
+    for (score, &residue) in scores.iter().zip(alphabet) {
+        let score: f64 = score
+            .parse()
+            .map_err(|_| HmmerError::InvalidHmm("invalid emission score"))?;
+        if best.is_none_or(|(lowest, _)| score < lowest) {
+            best = Some((score, residue));
+        }
+    }
+    best.map(|(_, residue)| residue.to_ascii_lowercase())
+        .ok_or(HmmerError::InvalidHmm("no emission scores"))
+}
+
+/// Run a HMMER program and return its standard output.
+fn run(program: &'static str, command: &mut Command) -> Result<String, HmmerError> {
+    let output = command.output().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => HmmerError::NotFound(program),
+        _ => HmmerError::Io(e),
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // HMMER reports input problems on stdout
+        let message = if stderr.trim().is_empty() {
+            stdout
+        } else {
+            stderr
+        };
+        return Err(HmmerError::Failed(program, message.trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Read the first model row of hmmbuild's summary table:
+/// `idx name nseq alen mlen eff_nseq re/pos description`.
+fn parse_hmmbuild_output(output: &str) -> Result<HmmSummary, HmmerError> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|fields| fields.len() >= 6 && fields[0].parse::<usize>().is_ok())
+        .find_map(|fields| {
+            Some(HmmSummary {
+                nseq: fields[2].parse().ok()?,
+                alen: fields[3].parse().ok()?,
+                mlen: fields[4].parse().ok()?,
+                eff_nseq: fields[5].parse().ok()?,
+            })
+        })
+        .ok_or(HmmerError::InvalidOutput)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hmmbuild_output() {
+        let output = "\
+# hmmbuild :: profile HMM construction from multiple sequence alignments
+# HMMER 3.4 (Aug 2023); http://hmmer.org/
+# input alignment file:             in.sto
+# ------------------------------------------------------------
+
+# idx name                  nseq  alen  mlen eff_nseq re/pos description
+#---- -------------------- ----- ----- ----- -------- ------ -----------
+1     tRNA                   954    72    71     6.33  0.590 tRNA
+
+# CPU time: 0.21u 0.00s 00:00:00.21 Elapsed: 00:00:00.21
+";
+        let summary = parse_hmmbuild_output(output).unwrap();
+        assert_eq!(summary.nseq, 954);
+        assert_eq!(summary.alen, 72);
+        assert_eq!(summary.mlen, 71);
+        assert!((summary.eff_nseq - 6.33).abs() < 1e-9);
+
+        assert!(parse_hmmbuild_output("# nothing\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_consensus() {
+        let hmm = "\
+HMMER3/f [3.4 | Aug 2023]
+NAME  hairpin
+LENG  3
+ALPH  RNA
+CONS  yes
+CS    yes
+HMM          A        C        G        U
+            m->m     m->i     m->d     i->m     i->i     d->m     d->d
+  COMPO   1.38629  1.38629  1.38629  1.38629
+          1.38629  1.38629  1.38629  1.38629
+          0.00000        *        *  1.00000  0.00000        *        *
+      1   0.10000  3.00000  3.00000  3.00000      1 A x - <
+          1.38629  1.38629  1.38629  1.38629
+          0.10000  3.00000  3.00000  1.00000  0.00000  0.00000        *
+      2   2.00000  1.50000  2.00000  1.00000      2 u x - .
+          1.38629  1.38629  1.38629  1.38629
+          0.10000  3.00000  3.00000  1.00000  0.00000  0.00000        *
+      3   3.00000  3.00000  3.00000  0.10000      3 U x - >
+          1.38629  1.38629  1.38629  1.38629
+          0.10000        *  0.00000  1.00000  0.00000  0.00000        *
+//
+";
+        let (consensus, structure) = parse_consensus(hmm).unwrap();
+        assert_eq!(consensus, "AuU");
+        assert_eq!(structure.as_deref(), Some("<.>"));
+
+        // HMMER 3.0 has no CONS column; the best-scoring residue is used
+        let old = hmm
+            .replace("HMMER3/f", "HMMER3/b")
+            .replace("CS    yes", "CS    no")
+            .replace("      1 A x - <", "      1 x -")
+            .replace("      2 u x - .", "      2 x -")
+            .replace("      3 U x - >", "      3 x -");
+        let (consensus, structure) = parse_consensus(&old).unwrap();
+        assert_eq!(consensus, "auu");
+        assert_eq!(structure, None);
+
+        assert!(parse_consensus("HMMER3/f\nNAME x\n").is_err());
+    }
+}
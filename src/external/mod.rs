@@ -0,0 +1,12 @@
+//! Interfaces to external RNA tools and file formats.
+
+pub mod anchors;
+pub mod bpseq;
+pub mod coaxial;
+pub mod ct;
+pub mod features;
+pub mod hmmer;
+pub mod newick;
+pub mod r2r;
+pub mod rfam;
+pub mod rnafold;
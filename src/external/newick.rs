@@ -0,0 +1,139 @@
+//! Minimal Newick tree reader.
+//!
+//! Only the leaf order is extracted; branch lengths, internal node labels and
+//! `[...]` comments are skipped.
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum NewickError {
+    #[error("Unbalanced parentheses")]
+    UnbalancedParens,
+    #[error("Unterminated quoted label")]
+    UnterminatedQuote,
+    #[error("Unterminated comment")]
+    UnterminatedComment,
+    #[error("Tree has no leaf labels")]
+    NoLeaves,
+}
+
+/// Parse a Newick tree and return its leaf labels in depth-first (tree) order.
+///
+/// Quoted labels (`'...'`, with `''` as an escaped quote) are unquoted;
+/// unquoted labels are returned verbatim (underscores are not converted).
+pub fn parse_newick(s: &str) -> Result<Vec<String>, NewickError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut labels = Vec::new();
+    let mut depth = 0usize;
+    // A label directly after ')' names an internal node, not a leaf.
+    let mut after_close = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '(' => {
+                depth += 1;
+                after_close = false;
+                i += 1;
+            }
+            ')' => {
+                depth = depth.checked_sub(1).ok_or(NewickError::UnbalancedParens)?;
+                after_close = true;
+                i += 1;
+            }
+            ',' => {
+                after_close = false;
+                i += 1;
+            }
+            ';' => break,
+            ':' => {
+                // Branch length: skip to the next delimiter.
+                i += 1;
+                while i < chars.len() && !"(),;[".contains(chars[i]) {
+                    i += 1;
+                }
+            }
+            '[' => {
+                let close = chars[i..]
+                    .iter()
+                    .position(|&c| c == ']')
+                    .ok_or(NewickError::UnterminatedComment)?;
+                i += close + 1;
+            }
+            '\'' => {
+                let mut label = String::new();
+                i += 1;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some('\''), Some('\'')) => {
+                            label.push('\'');
+                            i += 2;
+                        }
+                        (Some('\''), _) => {
+                            i += 1;
+                            break;
+                        }
+                        (Some(&c), _) => {
+                            label.push(c);
+                            i += 1;
+                        }
+                        (None, _) => return Err(NewickError::UnterminatedQuote),
+                    }
+                }
+                if !after_close {
+                    labels.push(label);
+                }
+            }
+            c if c.is_whitespace() => i += 1,
+            _ => {
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"(),:;[".contains(chars[i]) {
+                    i += 1;
+                }
+                if !after_close {
+                    labels.push(chars[start..i].iter().collect());
+                }
+            }
+        }
+    }
+
+    if depth != 0 {
+        return Err(NewickError::UnbalancedParens);
+    }
+    if labels.is_empty() {
+        return Err(NewickError::NoLeaves);
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_newick_leaf_order() {
+        let tree = "((a:0.5,b:0.5)ab:1.25,(c/1-10,'d e''s')[comment]:0.1)root;";
+        assert_eq!(
+            parse_newick(tree).unwrap(),
+            vec!["a", "b", "c/1-10", "d e's"]
+        );
+    }
+
+    #[test]
+    fn test_parse_newick_multiline() {
+        let tree = "(\n  seq1:1,\n  (seq2:1, seq3:1):2\n);\n";
+        assert_eq!(parse_newick(tree).unwrap(), vec!["seq1", "seq2", "seq3"]);
+    }
+
+    #[test]
+    fn test_parse_newick_errors() {
+        assert_eq!(parse_newick("((a,b);"), Err(NewickError::UnbalancedParens));
+        assert_eq!(parse_newick("(a,b));"), Err(NewickError::UnbalancedParens));
+        assert_eq!(parse_newick("('a,b);"), Err(NewickError::UnterminatedQuote));
+        assert_eq!(
+            parse_newick("(a[x,b);"),
+            Err(NewickError::UnterminatedComment)
+        );
+        assert_eq!(parse_newick("(,);"), Err(NewickError::NoLeaves));
+    }
+}
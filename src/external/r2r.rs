@@ -0,0 +1,184 @@
+//! R2R figure input export.
+//!
+//! R2R draws consensus secondary structure figures from a Stockholm file with
+//! `SS_cons`. Drawing instructions are `#=GF R2R` lines whose arguments refer
+//! to columns through the labels in `#=GC R2R_LABEL`. Nucleotide frequencies
+//! are added by R2R itself (`r2r --GSC-weighted-consensus`), so the file
+//! written here is the input to that step.
+
+use std::io;
+use std::path::Path;
+
+use ratatui::style::Color;
+
+use crate::color::{HELIX_COLORS, Rgb};
+use crate::stockholm::{Alignment, ColumnAnnotation, FileAnnotation, writer};
+use crate::structure::StructureCache;
+
+/// `#=GC` tag R2R reads column labels from.
+pub const R2R_LABEL_TAG: &str = "R2R_LABEL";
+
+/// Labels for helix colors, in `HELIX_COLORS` order.
+const HELIX_LABELS: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+/// Labels for shaded column ranges, in order.
+const SHADE_LABELS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// What to add to the alignment for R2R.
+///
+/// There is no choice of highlighted sequences: the figure drawn from this
+/// file is the consensus alone, and R2R sets up single-sequence drawings in
+/// runs of its own.
+#[derive(Debug, Clone, Default)]
+pub struct R2rOptions {
+    /// Column ranges (0-based, inclusive) to shade along the backbone, each in
+    /// its own color; drawn over helix colors.
+    pub shaded: Vec<(usize, usize, Rgb)>,
+    /// Shade each helix in its structure color-scheme color.
+    pub helix_colors: bool,
+    /// Draw every SS_cons pair, even ones R2R would drop for lack of support.
+    pub keep_all_pairs: bool,
+}
+
+/// Write `alignment` with R2R labels and drawing commands to `path`.
+///
+/// Existing `#=GC R2R_LABEL` and `#=GF R2R` lines are replaced. Fails if the
+/// alignment has no valid SS_cons or there are more shaded ranges than labels.
+pub fn write_r2r(alignment: &Alignment, path: &Path, options: &R2rOptions) -> io::Result<()> {
+    writer::write_file(&r2r_alignment(alignment, options)?, path)
+}
+
+fn r2r_alignment(alignment: &Alignment, options: &R2rOptions) -> io::Result<Alignment> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let ss_cons = alignment
+        .ss_cons()
+        .ok_or_else(|| invalid("R2R needs an SS_cons annotation".to_string()))?;
+    let mut cache = StructureCache::new();
+    cache
+        .update(ss_cons)
+        .map_err(|e| invalid(format!("Invalid SS_cons: {e}")))?;
+    if options.shaded.len() > SHADE_LABELS.len() {
+        return Err(invalid(format!(
+            "At most {} shaded ranges can be exported",
+            SHADE_LABELS.len()
+        )));
+    }
+
+    let width = alignment.width();
+    let mut labels = vec!['.'; width];
+    let mut commands = Vec::new();
+    if options.keep_all_pairs {
+        commands.push("keep allpairs".to_string());
+    }
+
+    if options.helix_colors {
+        // One label per color, so helices sharing a color share a command
+        let mut used = vec![false; HELIX_COLORS.len()];
+        for (col, label) in labels.iter_mut().enumerate() {
+            if let Some(helix) = cache.get_helix(col) {
+                let idx = helix % HELIX_COLORS.len();
+                *label = HELIX_LABELS[idx] as char;
+                used[idx] = true;
+            }
+        }
+        for (idx, color) in HELIX_COLORS.iter().enumerate() {
+            if let (true, Color::Rgb(r, g, b)) = (used[idx], color) {
+                commands.push(shade_command(
+                    HELIX_LABELS[idx] as char,
+                    Rgb::new(*r, *g, *b),
+                ));
+            }
+        }
+    }
+
+    for (&(first, last, color), &label) in options.shaded.iter().zip(SHADE_LABELS) {
+        if first >= width {
+            continue;
+        }
+        labels[first..=last.min(width - 1)].fill(label as char);
+        commands.push(shade_command(label as char, color));
+    }
+
+    let mut out = alignment.clone();
+    out.column_annotations.retain(|a| a.tag != R2R_LABEL_TAG);
+    out.file_annotations.retain(|a| a.tag != "R2R");
+    if labels.iter().any(|&c| c != '.') {
+        out.column_annotations.push(ColumnAnnotation {
+            tag: R2R_LABEL_TAG.to_string(),
+            data: labels.into_iter().collect(),
+        });
+    }
+    out.file_annotations
+        .extend(commands.into_iter().map(|value| FileAnnotation {
+            tag: "R2R".to_string(),
+            value,
+        }));
+    Ok(out)
+}
+
+fn shade_command(label: char, color: Rgb) -> String {
+    format!(
+        "shade_along_backbone {label} rgb:{},{},{}",
+        color.r, color.g, color.b
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stockholm::Sequence;
+    use std::rc::Rc;
+
+    fn hairpins() -> Alignment {
+        let mut alignment = Alignment::new();
+        alignment
+            .sequences
+            .push(Rc::new(Sequence::new("s1", "GGAAACCGAAAC")));
+        alignment.column_annotations.push(ColumnAnnotation {
+            tag: "SS_cons".to_string(),
+            data: "<<...>><...>".to_string(),
+        });
+        alignment
+    }
+
+    #[test]
+    fn test_r2r_annotations() {
+        let options = R2rOptions {
+            shaded: vec![(2, 4, Rgb::new(255, 0, 0))],
+            helix_colors: true,
+            keep_all_pairs: true,
+        };
+        let out = r2r_alignment(&hairpins(), &options).unwrap();
+
+        let labels = out
+            .column_annotations
+            .iter()
+            .find(|a| a.tag == R2R_LABEL_TAG)
+            .unwrap();
+        assert_eq!(labels.data, "aaAAAaab...b");
+        assert_eq!(
+            out.get_file_annotations("R2R"),
+            [
+                "keep allpairs",
+                "shade_along_backbone a rgb:135,206,235",
+                "shade_along_backbone b rgb:144,238,144",
+                "shade_along_backbone A rgb:255,0,0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_r2r_requires_ss_cons() {
+        let mut alignment = hairpins();
+        alignment.column_annotations.clear();
+        assert!(r2r_alignment(&alignment, &R2rOptions::default()).is_err());
+
+        // Without options nothing is labelled
+        let out = r2r_alignment(&hairpins(), &R2rOptions::default()).unwrap();
+        assert!(
+            out.column_annotations
+                .iter()
+                .all(|a| a.tag != R2R_LABEL_TAG)
+        );
+        assert!(out.get_file_annotations("R2R").is_empty());
+    }
+}
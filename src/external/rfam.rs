@@ -0,0 +1,81 @@
+//! Rfam seed alignment download (`:fetch`).
+//!
+//! Requires the `network` feature; without it [`fetch_rfam_seed`] always
+//! fails with [`RfamError::NetworkUnsupported`].
+
+use thiserror::Error;
+
+use crate::stockholm::Alignment;
+
+#[derive(Error, Debug)]
+pub enum RfamError {
+    #[error("Invalid Rfam accession '{0}' (expected RF followed by five digits)")]
+    InvalidAccession(String),
+    #[error("Network support not compiled in")]
+    NetworkUnsupported,
+    #[cfg(feature = "network")]
+    #[error("Rfam request failed: {0}")]
+    Http(#[from] ureq::Error),
+    #[cfg(feature = "network")]
+    #[error("Rfam returned an invalid alignment: {0}")]
+    Parse(#[from] crate::stockholm::parser::ParseError),
+}
+
+/// Normalize an accession such as `rf00005` to `RF00005`.
+pub fn normalize_accession(accession: &str) -> Result<String, RfamError> {
+    let upper = accession.trim().to_ascii_uppercase();
+    match upper.strip_prefix("RF") {
+        Some(digits) if digits.len() == 5 && digits.bytes().all(|b| b.is_ascii_digit()) => {
+            Ok(upper)
+        }
+        _ => Err(RfamError::InvalidAccession(accession.to_string())),
+    }
+}
+
+/// URL of the Stockholm seed alignment for a family.
+#[cfg(any(feature = "network", test))]
+pub fn seed_url(accession: &str) -> String {
+    format!(
+        "https://rfam.org/family/{accession}/alignment?acc={accession}&format=stockholm&download=0"
+    )
+}
+
+/// Download and parse the seed alignment of an Rfam family (blocking).
+#[cfg(feature = "network")]
+pub fn fetch_rfam_seed(accession: &str) -> Result<Alignment, RfamError> {
+    let accession = normalize_accession(accession)?;
+    let mut response = ureq::get(&seed_url(&accession)).call()?;
+    let body = response.body_mut().read_to_string()?;
+    Ok(crate::stockholm::parser::parse_str(&body)?)
+}
+
+/// Download and parse the seed alignment of an Rfam family (blocking).
+#[cfg(not(feature = "network"))]
+pub fn fetch_rfam_seed(accession: &str) -> Result<Alignment, RfamError> {
+    normalize_accession(accession)?;
+    Err(RfamError::NetworkUnsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_accession() {
+        assert_eq!(normalize_accession(" rf00005 ").unwrap(), "RF00005");
+        for bad in ["RF5", "RF0000A", "PF00001", "RF000001"] {
+            assert!(matches!(
+                normalize_accession(bad),
+                Err(RfamError::InvalidAccession(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_seed_url() {
+        assert_eq!(
+            seed_url("RF00001"),
+            "https://rfam.org/family/RF00001/alignment?acc=RF00001&format=stockholm&download=0"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Secondary structure prediction with ViennaRNA `RNAfold`.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FoldError {
+    #[error("Failed to run RNAfold: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("RNAfold failed: {0}")]
+    Failed(String),
+    #[error("Unexpected RNAfold output")]
+    InvalidOutput,
+}
+
+/// Predict the MFE structure of an ungapped sequence by running `RNAfold`.
+///
+/// Returns a dot-bracket string with one character per residue.
+pub fn fold_sequence(sequence: &str) -> Result<String, FoldError> {
+    let mut child = Command::new("RNAfold")
+        .arg("--noPS")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{sequence}")?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(FoldError::Failed(stderr.trim().to_string()));
+    }
+
+    parse_rnafold_output(
+        &String::from_utf8_lossy(&output.stdout),
+        sequence.chars().count(),
+    )
+}
+
+/// Extract the dot-bracket structure from RNAfold output.
+///
+/// RNAfold echoes the sequence, then prints `structure ( energy)`.
+fn parse_rnafold_output(output: &str, len: usize) -> Result<String, FoldError> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .find(|token| token.len() == len && token.chars().all(|c| matches!(c, '(' | ')' | '.')))
+        .map(str::to_string)
+        .ok_or(FoldError::InvalidOutput)
+}
+
+/// Expand an ungapped structure back onto aligned columns.
+///
+/// Gap columns in `aligned` become `.`; each residue takes the next structure
+/// character in order.
+pub fn expand_structure_to_alignment(
+    aligned: &[char],
+    structure: &str,
+    gap_chars: &[char],
+) -> String {
+    let mut structure_chars = structure.chars();
+    aligned
+        .iter()
+        .map(|c| {
+            if gap_chars.contains(c) {
+                '.'
+            } else {
+                structure_chars.next().unwrap_or('.')
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rnafold_output() {
+        let output = "GGGAAACCC\n(((...))) ( -1.20)\n";
+        assert_eq!(parse_rnafold_output(output, 9).unwrap(), "(((...)))");
+        assert!(parse_rnafold_output("GGGAAACCC\n", 9).is_err());
+    }
+
+    #[test]
+    fn test_expand_structure_to_alignment() {
+        let aligned: Vec<char> = "GG-GA..AACCC".chars().collect();
+        let expanded = expand_structure_to_alignment(&aligned, "(((...)))", &['-', '.']);
+        assert_eq!(expanded, "((.(.....)))");
+    }
+}
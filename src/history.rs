@@ -77,6 +77,11 @@ impl InputHistory {
         self.saved.clear();
     }
 
+    /// All entries, oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
     /// Check if currently browsing history.
     #[allow(dead_code)]
     pub fn is_browsing(&self) -> bool {
@@ -0,0 +1,120 @@
+//! HTML export for alignment visualization.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::app::{App, TerminalTheme};
+use crate::color::get_color;
+use crate::stockholm::short_id;
+use crate::svg::{color_to_hex, xml_escape};
+
+impl App {
+    /// Write the alignment as an HTML page, one `<span>` per colored residue.
+    ///
+    /// Residues are colored with the active color scheme as on screen; hidden
+    /// columns and collapsed rows are left out, and `#=GC SS_cons` follows the
+    /// sequences. The page background follows the terminal theme.
+    pub fn write_html_view(&self, path: &Path) -> Result<(), String> {
+        let alignment = &self.alignment;
+        if alignment.num_sequences() == 0 {
+            return Err("Empty alignment".to_string());
+        }
+
+        let cols: Vec<usize> = if self.columns_hidden() {
+            self.visible_columns.clone()
+        } else {
+            (0..alignment.width()).collect()
+        };
+        let num_rows = self.visible_sequence_count();
+        let display_id = |id: &str| -> String {
+            if self.show_short_ids {
+                short_id(id).to_string()
+            } else {
+                id.to_string()
+            }
+        };
+        let id_width = (0..num_rows)
+            .map(|row| display_id(&alignment.sequences[self.display_to_actual_row(row)].id).len())
+            .chain(alignment.ss_cons().map(|_| "#=GC SS_cons".len()))
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let (background, foreground) = match self.terminal_theme {
+            TerminalTheme::Light => ("#FFFFFF", "#000000"),
+            TerminalTheme::Dark => ("#1E1E1E", "#D4D4D4"),
+        };
+        let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        let _ = writeln!(
+            html,
+            "<style>\nbody {{ background-color: {background}; color: {foreground}; }}\n\
+             pre {{ font-family: monospace; font-size: 14px; line-height: 1.2; }}\n</style>"
+        );
+        html.push_str("</head>\n<body>\n<pre>\n");
+
+        if self.show_ruler {
+            let ruler_color = color_to_hex(self.theme.ruler.numbers.to_color());
+            let numbers = self.ruler_label_line(&cols);
+            let _ = writeln!(
+                html,
+                "{:id_width$}<span style=\"color: {ruler_color}\">{}</span>",
+                "",
+                numbers.trim_end()
+            );
+        }
+
+        let reference_row = self.reference_row();
+        for display_row in 0..num_rows {
+            let actual_row = self.display_to_actual_row(display_row);
+            let seq = &alignment.sequences[actual_row];
+            let id = format!("{:id_width$}", display_id(&seq.id));
+            html.push_str(&xml_escape(&id));
+            for &col in &cols {
+                let ch = seq.get(col).unwrap_or(' ');
+                let bg = get_color(
+                    self.color_scheme,
+                    ch,
+                    col,
+                    actual_row,
+                    alignment,
+                    &self.structure_cache,
+                    &self.gap_chars,
+                    reference_row,
+                    self.sequence_type,
+                    self.terminal_theme,
+                );
+                let text = xml_escape(&ch.to_string());
+                match bg {
+                    Some(color) => {
+                        let _ = write!(
+                            html,
+                            "<span style=\"background-color: {}; color: #000000\">{text}</span>",
+                            color_to_hex(color)
+                        );
+                    }
+                    None => html.push_str(&text),
+                }
+            }
+            html.push('\n');
+        }
+
+        if let Some(ss) = alignment.ss_cons() {
+            let ss: Vec<char> = ss.chars().collect();
+            let fg = color_to_hex(self.theme.annotations.ss_cons_fg.to_color());
+            let bg = color_to_hex(self.theme.annotations.ss_cons_bg.to_color());
+            let bar: String = cols
+                .iter()
+                .map(|&col| ss.get(col).copied().unwrap_or(' '))
+                .collect();
+            let _ = writeln!(
+                html,
+                "{:id_width$}<span style=\"background-color: {bg}; color: {fg}\">{}</span>",
+                "#=GC SS_cons",
+                xml_escape(&bar)
+            );
+        }
+
+        html.push_str("</pre>\n</body>\n</html>\n");
+        std::fs::write(path, html).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+}
@@ -3,7 +3,7 @@
 use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use tui_input::backend::crossterm::EventHandler;
 
-use crate::app::{App, Mode};
+use crate::app::{App, Mode, Report};
 
 /// Handle keys while the MSA selection overlay is open.
 fn handle_msa_picker(app: &mut App, key: KeyEvent) {
@@ -26,6 +26,39 @@ fn handle_msa_picker(app: &mut App, key: KeyEvent) {
     }
 }
 
+fn handle_history_popup(app: &mut App, key: KeyEvent) {
+    if app.history_filter_editing {
+        match key.code {
+            KeyCode::Char(c) => {
+                app.history_filter.push(c);
+                app.history_selection = 0;
+            }
+            KeyCode::Backspace => {
+                app.history_filter.pop();
+                app.history_selection = 0;
+            }
+            KeyCode::Enter => app.history_filter_editing = false,
+            KeyCode::Esc => {
+                app.history_filter.clear();
+                app.history_filter_editing = false;
+                app.history_selection = 0;
+            }
+            _ => {}
+        }
+        return;
+    }
+    match key.code {
+        KeyCode::Char('j') | KeyCode::Down => app.history_move(1),
+        KeyCode::Char('k') | KeyCode::Up => app.history_move(-1),
+        KeyCode::Char('g') | KeyCode::Home => app.history_move(isize::MIN),
+        KeyCode::Char('G') | KeyCode::End => app.history_move(isize::MAX),
+        KeyCode::Char('/') => app.history_filter_editing = true,
+        KeyCode::Enter => app.history_confirm(),
+        KeyCode::Esc | KeyCode::Char('q') => app.show_history_popup = false,
+        _ => {}
+    }
+}
+
 /// Handle keys while a scrollable overlay (help/info) is open.
 /// Scrolling keys adjust `scroll`; any other key closes the overlay.
 /// The scroll offset is clamped against content by the renderer, so here we
@@ -130,14 +163,56 @@ fn handle_movement_keys(app: &mut App, key: KeyEvent, page_size: usize) -> bool
     }
 }
 
-/// Handle a key event.
+/// Handle a key event, adding it to the macro being recorded.
 pub fn handle_key(app: &mut App, key: KeyEvent, page_size: usize) {
+    // Keys that start or stop the recording are not part of it
+    let recording = app.macro_recording.is_some();
+    dispatch_key(app, key, page_size);
+    if recording {
+        app.record_key(key);
+    }
+}
+
+/// Replay macro `register` (`@` for the last one played) through
+/// `handle_key`, repeated by the count prefix.
+fn play_macro(app: &mut App, register: char, page_size: usize) {
+    let count = app.take_count();
+    if app.macro_playing {
+        app.set_status("A macro cannot play another macro");
+        return;
+    }
+    let keys = match app.macro_keys(register) {
+        Ok(keys) => keys,
+        Err(e) => {
+            app.set_status(e);
+            return;
+        }
+    };
+    app.macro_playing = true;
+    'replay: for _ in 0..count {
+        for &key in &keys {
+            handle_key(app, key, page_size);
+            if app.should_quit {
+                break 'replay;
+            }
+        }
+    }
+    app.macro_playing = false;
+}
+
+fn dispatch_key(app: &mut App, key: KeyEvent, page_size: usize) {
     // MSA selection overlay captures keys while open.
     if app.show_msa_picker {
         handle_msa_picker(app, key);
         return;
     }
 
+    // Command history popup captures keys while open.
+    if app.show_history_popup {
+        handle_history_popup(app, key);
+        return;
+    }
+
     // Help overlay: scroll with j/k/arrows/Ctrl-d/u, close on Esc/q/Enter/other.
     if app.show_help {
         handle_overlay_keys(key, &mut app.show_help, &mut app.help_scroll);
@@ -150,6 +225,144 @@ pub fn handle_key(app: &mut App, key: KeyEvent, page_size: usize) {
         return;
     }
 
+    // Column profile popup: any key closes it.
+    if app.show_column_profile {
+        app.show_column_profile = false;
+        return;
+    }
+
+    // Report popup: same keys as the info overlay.
+    if app.report.is_some() {
+        // Wide tables also scroll sideways.
+        if matches!(app.report, Some(Report::SequenceAnnotations(_))) {
+            match (key.modifiers, key.code) {
+                (KeyModifiers::NONE, KeyCode::Char('h') | KeyCode::Left) => {
+                    app.report_hscroll = app.report_hscroll.saturating_sub(4);
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Char('l') | KeyCode::Right) => {
+                    app.report_hscroll = app.report_hscroll.saturating_add(4);
+                    return;
+                }
+                _ => {}
+            }
+        }
+        // A #=GC annotation keeps a selected column; Enter moves the cursor there.
+        if let Some(Report::ColumnAnnotation { data, selected, .. }) = &mut app.report {
+            let last = data.chars().count().saturating_sub(1);
+            match (key.modifiers, key.code) {
+                (KeyModifiers::NONE, KeyCode::Char('h') | KeyCode::Left) => {
+                    *selected = selected.saturating_sub(1);
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Char('l') | KeyCode::Right) => {
+                    *selected = (*selected + 1).min(last);
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Char('b')) => {
+                    *selected = selected.saturating_sub(10);
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Char('w')) => {
+                    *selected = (*selected + 10).min(last);
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Char('0') | KeyCode::Home) => {
+                    *selected = 0;
+                    return;
+                }
+                (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char('$'))
+                | (KeyModifiers::NONE, KeyCode::End) => {
+                    *selected = last;
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Enter) => {
+                    app.cursor_col = *selected;
+                    app.report = None;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        // Base pair, GC, gap and length tables keep a selected row instead of
+        // scrolling.
+        let selectable_rows = match &app.report {
+            Some(Report::BasePairs(pairs)) => Some(pairs.len()),
+            Some(Report::GcContent(rows) | Report::GapFractions(rows)) => Some(rows.len()),
+            Some(Report::SequenceLengths(rows)) => Some(rows.len()),
+            Some(Report::Compensatory(records)) => Some(records.len()),
+            Some(Report::Diagnostics(issues)) => Some(issues.len()),
+            _ => None,
+        };
+        if let Some(rows) = selectable_rows {
+            let last = rows.saturating_sub(1) as u16;
+            match (key.modifiers, key.code) {
+                (KeyModifiers::NONE, KeyCode::Char('j') | KeyCode::Down) => {
+                    app.report_scroll = app.report_scroll.saturating_add(1).min(last);
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Char('k') | KeyCode::Up) => {
+                    app.report_scroll = app.report_scroll.saturating_sub(1);
+                    return;
+                }
+                (KeyModifiers::NONE, KeyCode::Enter) => {
+                    let selected = app.report_scroll as usize;
+                    match app.report.take() {
+                        Some(Report::BasePairs(pairs)) => {
+                            if let Some(pair) = pairs.get(selected) {
+                                app.cursor_col = pair.left;
+                            }
+                        }
+                        Some(Report::GcContent(rows) | Report::GapFractions(rows)) => {
+                            if let Some((id, _)) = rows.get(selected)
+                                && !app.jump_to_sequence_id(id)
+                            {
+                                app.set_status(format!("{id} is not displayed"));
+                            }
+                        }
+                        Some(Report::SequenceLengths(rows)) => {
+                            if let Some((id, _)) = rows.get(selected)
+                                && !app.jump_to_sequence_id(id)
+                            {
+                                app.set_status(format!("{id} is not displayed"));
+                            }
+                        }
+                        Some(Report::Diagnostics(issues)) => {
+                            if let Some(issue) = issues.get(selected) {
+                                if let Some(col) = issue.col {
+                                    app.cursor_col = col;
+                                }
+                                if let Some(id) = &issue.seq_id
+                                    && !app.jump_to_sequence_id(id)
+                                {
+                                    app.set_status(format!("{id} is not displayed"));
+                                }
+                            }
+                        }
+                        Some(Report::Compensatory(records)) => {
+                            if let Some(record) = records.get(selected) {
+                                app.cursor_col = record.left;
+                                if !app.jump_to_sequence_id(&record.seq_id) {
+                                    app.set_status(format!("{} is not displayed", record.seq_id));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                    app.report_scroll = 0;
+                    return;
+                }
+                _ => {}
+            }
+        }
+        let mut show = true;
+        handle_overlay_keys(key, &mut show, &mut app.report_scroll);
+        if !show {
+            app.report = None;
+        }
+        return;
+    }
+
     match app.mode {
         Mode::Normal => handle_normal_mode(app, key, page_size),
         Mode::Insert => handle_insert_mode(app, key),
@@ -165,6 +378,23 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent, page_size: usize) {
     let pending_status = app.status_message.clone();
     app.clear_status();
 
+    // Macro register after q or @ (keeps the count for @)
+    match (pending_status.as_deref(), key.code) {
+        (Some("q..."), KeyCode::Char(register @ 'a'..='z')) => {
+            app.start_macro_recording(register);
+            return;
+        }
+        (Some("@..."), KeyCode::Char(register @ ('a'..='z' | '@'))) => {
+            play_macro(app, register, page_size);
+            return;
+        }
+        (Some("q..." | "@..."), _) => {
+            app.clear_count();
+            return;
+        }
+        _ => {}
+    }
+
     // Check if this is a digit key for count prefix
     let is_count_digit = matches!(
         (key.modifiers, key.code),
@@ -172,12 +402,15 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent, page_size: usize) {
     ) || (matches!(key.code, KeyCode::Char('0'))
         && !app.count_buffer.is_empty());
 
-    // Clear count for non-digit keys (except | which consumes it)
-    let is_pipe = matches!(
+    // Clear count for non-digit keys (except |, I and @ which consume it)
+    let takes_count = matches!(
         (key.modifiers, key.code),
-        (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char('|'))
+        (
+            KeyModifiers::NONE | KeyModifiers::SHIFT,
+            KeyCode::Char('|' | '@')
+        ) | (KeyModifiers::SHIFT, KeyCode::Char('I'))
     );
-    if !is_count_digit && !is_pipe {
+    if !is_count_digit && !takes_count {
         app.clear_count();
     }
 
@@ -199,14 +432,17 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent, page_size: usize) {
     }
 
     match (key.modifiers, key.code) {
-        // Quit
-        (KeyModifiers::NONE, KeyCode::Char('q')) => {
-            if app.modified {
-                app.set_status("No write since last change (use :q! to force)");
+        // Macros: q<reg> records until the next q, @<reg> plays back
+        (KeyModifiers::NONE, KeyCode::Char('q')) if !in_two_key_sequence => {
+            if app.macro_recording.is_some() {
+                app.stop_macro_recording();
             } else {
-                app.should_quit = true;
+                app.set_status("q...");
             }
         }
+        (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char('@')) if !in_two_key_sequence => {
+            app.set_status("@...");
+        }
 
         // Count prefix digits (1-9 start a count, 0 continues a count)
         (KeyModifiers::NONE, KeyCode::Char(c @ '1'..='9')) => {
@@ -264,9 +500,10 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent, page_size: usize) {
             app.delete_gap();
         }
 
-        // Insert gap column
+        // Insert gap column(s), N with a count prefix
         (KeyModifiers::SHIFT, KeyCode::Char('I')) => {
-            app.insert_gap_column();
+            let count = app.take_count();
+            app.insert_gap_column_range(app.cursor_col, count);
         }
 
         // Delete gap column
@@ -290,6 +527,19 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent, page_size: usize) {
             app.throw_sequence_right();
         }
 
+        // Pairwise comparison against the cursor sequence
+        (KeyModifiers::SHIFT, KeyCode::Char('C')) => {
+            app.toggle_pinned_comparison();
+        }
+        (KeyModifiers::NONE, KeyCode::Esc) if app.pinned_comparison_id.is_some() => {
+            app.toggle_pinned_comparison();
+        }
+
+        // Helix covariation overlay (compensatory scheme)
+        (KeyModifiers::SHIFT, KeyCode::Char('H')) => {
+            app.toggle_helix_covariation();
+        }
+
         // Undo/Redo
         (KeyModifiers::NONE, KeyCode::Char('u')) => {
             app.undo();
@@ -297,6 +547,12 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent, page_size: usize) {
         (KeyModifiers::CONTROL, KeyCode::Char('r')) => {
             app.redo();
         }
+        (KeyModifiers::ALT, KeyCode::Left) => {
+            app.switch_undo_branch(false);
+        }
+        (KeyModifiers::ALT, KeyCode::Right) => {
+            app.switch_undo_branch(true);
+        }
 
         // Command mode
         (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(':')) => {
@@ -343,6 +599,16 @@ fn handle_normal_mode(app: &mut App, key: KeyEvent, page_size: usize) {
             ("g...", KeyCode::Char('g')) => {
                 app.cursor_first_sequence();
             }
+            ("g...", KeyCode::Char('P')) => {
+                app.show_column_profile = true;
+            }
+            ("g...", KeyCode::Char('r')) => match app.set_reference_sequence(None) {
+                Ok(()) => {
+                    let id = app.alignment.sequences[app.reference_row()].id.clone();
+                    app.set_status(format!("Reference: {id}"));
+                }
+                Err(e) => app.set_status(e),
+            },
             ("d...", KeyCode::Char('d')) => {
                 app.delete_sequence();
             }
@@ -432,22 +698,30 @@ fn handle_command_mode(app: &mut App, key: KeyEvent) {
     }
 }
 
-/// Handle tab completion for file paths in command mode.
+/// Commands whose argument is completed as a file path.
+const PATH_COMMANDS: &[&str] = &["e ", "edit ", "w ", "write "];
+
+/// Commands whose argument is completed as a sequence ID.
+const SEQUENCE_ID_COMMANDS: &[&str] = &["color-ref ", "delete-seqs "];
+
+/// Handle tab completion for file paths and sequence IDs in command mode.
 fn handle_tab_completion(app: &mut App) {
     use crate::app::CompletionState;
 
-    // Check if we're completing a file path command
+    // Check if we're completing a file path or sequence ID command
     let buffer = app.line_input.value().to_string();
-    let (cmd, partial_path) = if let Some(rest) = buffer.strip_prefix("e ") {
-        ("e ", rest)
-    } else if let Some(rest) = buffer.strip_prefix("edit ") {
-        ("edit ", rest)
-    } else if let Some(rest) = buffer.strip_prefix("w ") {
-        ("w ", rest)
-    } else if let Some(rest) = buffer.strip_prefix("write ") {
-        ("write ", rest)
+    let (cmd, partial, complete_ids) = if let Some((cmd, rest)) = PATH_COMMANDS
+        .iter()
+        .find_map(|cmd| Some((*cmd, buffer.strip_prefix(cmd)?)))
+    {
+        (cmd, rest, false)
+    } else if let Some((cmd, rest)) = SEQUENCE_ID_COMMANDS
+        .iter()
+        .find_map(|cmd| Some((*cmd, buffer.strip_prefix(cmd)?)))
+    {
+        (cmd, rest, true)
     } else {
-        return; // Not a file command
+        return; // Not a completable command
     };
 
     // If we have existing completion state, cycle through candidates
@@ -460,7 +734,11 @@ fn handle_tab_completion(app: &mut App) {
     }
 
     // Get completions
-    let candidates = complete_path(partial_path);
+    let candidates = if complete_ids {
+        app.complete_sequence_id(partial)
+    } else {
+        complete_path(partial)
+    };
 
     if candidates.is_empty() {
         app.set_status("No matches");
@@ -477,7 +755,7 @@ fn handle_tab_completion(app: &mut App) {
         app.completion = Some(CompletionState {
             candidates,
             index: 0,
-            prefix: partial_path.to_string(),
+            prefix: partial.to_string(),
         });
         // Show available options in status
         if let Some(ref state) = app.completion {
@@ -637,6 +915,11 @@ fn handle_visual_mode(app: &mut App, key: KeyEvent, page_size: usize) {
             app.set_status("g...");
         }
 
+        // Command mode (keeps the selection for range commands like :fold-region)
+        (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(':')) => {
+            app.enter_command_mode();
+        }
+
         // Yank (copy) selection
         (KeyModifiers::NONE, KeyCode::Char('y')) => {
             app.yank_selection();
@@ -650,6 +933,11 @@ fn handle_visual_mode(app: &mut App, key: KeyEvent, page_size: usize) {
             app.delete_selection();
         }
 
+        // Invert selection (terminals usually report Ctrl+I as Tab)
+        (KeyModifiers::CONTROL, KeyCode::Char('i')) | (KeyModifiers::NONE, KeyCode::Tab) => {
+            app.invert_selection();
+        }
+
         _ => {}
     }
 }
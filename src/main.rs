@@ -8,7 +8,9 @@ mod clustering;
 mod color;
 mod config;
 mod editor;
+mod external;
 mod history;
+mod html;
 mod input;
 mod stockholm;
 mod structure;
@@ -16,7 +18,7 @@ mod svg;
 mod ui;
 
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use clap::Parser;
@@ -64,6 +66,10 @@ struct Args {
     #[arg(long)]
     cluster: bool,
 
+    /// Cluster sequences whenever a file is loaded (see `:set auto_cluster_min`).
+    #[arg(long)]
+    auto_cluster: bool,
+
     /// Collapse identical sequences.
     #[arg(long)]
     collapse: bool,
@@ -83,6 +89,25 @@ struct Args {
     /// Show short IDs (strip coordinate suffix like /10000-20000).
     #[arg(long)]
     shortid: bool,
+
+    /// How long to wait for input between redraws, in milliseconds (1-1000).
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = app::DEFAULT_POLL_INTERVAL_MS,
+        value_parser = clap::value_parser!(u64).range(1..=app::MAX_POLL_INTERVAL_MS)
+    )]
+    poll_interval: u64,
+
+    /// Write the alignment to PATH and exit without starting the editor.
+    /// The format follows the extension: .fa/.fasta, .phy, .db or Stockholm.
+    #[arg(short, long, value_name = "PATH", requires = "file")]
+    output: Option<PathBuf>,
+
+    /// Transform command to apply before writing --output (e.g. "t2u");
+    /// may be repeated.
+    #[arg(short, long, value_name = "COMMAND", requires = "output")]
+    transform: Vec<String>,
 }
 
 const AFTER_HELP: &str = "\
@@ -123,16 +148,31 @@ COLOR SCHEMES:
   :color base         Color by nucleotide/amino acid identity
   :color conservation Color by column conservation
   :color compensatory Color by compensatory mutations (requires SS_cons)
+  :color acc          Color by solvent accessibility (#=GR ACC)
+  :color clade        Color sequence IDs by #=GS CLADE
+  :color pairtype     Color paired residues by pair type (AU/GC/GU/mismatch)
 
   Aliases: ss=structure, nt/residue/aa/protein=base, cons=conservation, comp=compensatory
 
 EXPORT:
   :svg <path>         Export alignment as SVG image
+  :write-html <path>  Export alignment as colored HTML
+  :write-r2r <path>   Export R2R input for structure figures
+
+RESPONSIVENESS:
+  --poll-interval MS  Wait up to MS milliseconds for input between redraws
+                      (default 100). 16-250 works well: lower values reduce
+                      input lag, higher values save CPU on low-power systems.
+  :set poll_interval=MS  Change it while running
 ";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
+    if let (Some(input), Some(output)) = (&args.file, &args.output) {
+        return convert(input, output, args.msa, &args.transform);
+    }
+
     // Detect terminal theme before entering raw mode (must happen before raw mode)
     let terminal_theme = detect_terminal_theme();
 
@@ -160,10 +200,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut app = App::new();
     app.terminal_theme = terminal_theme;
     app.theme = theme;
+    app.auto_cluster = args.auto_cluster;
+    app.background_clustering = true;
 
     // Set color scheme
     if let Some(scheme) = app::ColorScheme::from_str(&args.color) {
         app.color_scheme = scheme;
+        app.color_scheme_explicit = args.color != "none";
     }
 
     // Load file if provided
@@ -202,13 +245,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     if args.shortid {
         app.show_short_ids = true;
     }
+    app.poll_interval = Duration::from_millis(args.poll_interval);
 
     // Apply clustering options (only if file loaded)
     if app.alignment.num_sequences() > 0 {
         if args.collapse {
             app.toggle_collapse_identical();
         }
-        if args.cluster || args.tree {
+        if (args.cluster || args.tree) && app.cluster_order.is_none() && !app.is_clustering() {
             app.cluster_sequences();
         }
         if args.tree {
@@ -240,11 +284,7 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
         // Calculate visible dimensions for viewport adjustment
         let size = terminal.size()?;
         let area = ratatui::layout::Rect::new(0, 0, size.width, size.height);
-        let tree_display_width = if app.show_tree && app.cluster_tree.is_some() {
-            app.tree_width + 1
-        } else {
-            0
-        };
+        let tree_display_width = app.tree_display_width();
         let max_id_len = if app.show_short_ids {
             app.alignment.max_short_id_len()
         } else {
@@ -254,30 +294,48 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
             area,
             app.visible_sequence_count(),
             max_id_len,
-            app.show_ruler,
+            ui::ruler_height(app),
             app.show_row_numbers,
             app.show_short_ids,
+            app.show_strand,
             app.split_mode,
-            app.alignment.ss_cons().is_some(),
-            app.alignment.rf().is_some(),
-            app.show_rf_bar,
-            app.alignment.pp_cons().is_some(),
-            app.show_pp_cons,
-            app.show_consensus,
-            app.show_conservation_bar,
-            app.max_collapse_count(),
+            // One row per annotation bar
+            app.visible_annotations(&app.alignment).len() as u16,
+            // Pinned header row plus its separator
+            if app.pinned_header_display_row().is_some() {
+                2
+            } else {
+                0
+            },
+            // Group sizes move to the tree column when it is shown.
+            if app.tree_shows_group_counts() {
+                1
+            } else {
+                app.max_collapse_count()
+            },
             tree_display_width,
-            app.alignment.width(),
+            // Zoomed out, each cell shows one of every column_stride columns
+            app.alignment.width().div_ceil(app.column_stride),
+            app.rows_per_sequence(),
+            app.id_width_cap(),
         );
 
         // Adjust viewport to keep cursor visible
         app.adjust_viewport(visible_rows, visible_cols);
+        app.sync_compare_viewport();
+        app.sync_linked_viewport();
 
         // Draw UI
         terminal.draw(|f| ui::render(f, app))?;
 
+        // `:watch` reloads the file when it changes on disk
+        app.check_watched_file();
+
+        // A queued `:fetch` blocks, so run it only once its status is on screen
+        app.run_pending_fetch();
+
         // Handle events
-        if event::poll(Duration::from_millis(100))? {
+        if event::poll(app.poll_interval)? {
             match event::read()? {
                 Event::Key(key) => input::handle_key(app, key, visible_rows),
                 Event::Mouse(mouse) => match mouse.kind {
@@ -285,6 +343,7 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
                     MouseEventKind::ScrollDown => app.cursor_down(),
                     MouseEventKind::ScrollLeft => app.cursor_left(),
                     MouseEventKind::ScrollRight => app.cursor_right(),
+                    MouseEventKind::Moved => app.mouse_pos = Some((mouse.column, mouse.row)),
                     _ => {}
                 },
                 _ => {}
@@ -292,8 +351,9 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
         }
 
         // Advance any background clustering job (animate spinner / apply result).
-        // The 100ms poll above returns immediately on input, so this still runs
-        // frequently enough to animate the spinner even when the user is idle.
+        // The poll above returns immediately on input and otherwise times out
+        // after `poll_interval`, so this still runs often enough to animate the
+        // spinner even when the user is idle.
         app.poll_clustering();
 
         if app.should_quit {
@@ -302,6 +362,35 @@ fn run_app(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, app: &mut App)
     }
 }
 
+/// Load `input`, apply each transform in order and write the result to
+/// `output` without starting the TUI.
+fn convert(
+    input: &Path,
+    output: &Path,
+    msa: Option<usize>,
+    transforms: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App::new();
+    app.load_file(input)?;
+    match msa {
+        Some(n) if (1..=app.alignments.len()).contains(&n) => app.select_alignment(n - 1),
+        Some(n) => {
+            return Err(format!("No alignment {n} (file has {})", app.alignments.len()).into());
+        }
+        None => {}
+    }
+
+    for command in transforms {
+        app.apply_transform(command)
+            .map_err(|e| format!("{command}: {e}"))?;
+        if let Some(message) = app.status_message.take() {
+            eprintln!("{command}: {message}");
+        }
+    }
+    app.export_alignment(output)?;
+    Ok(())
+}
+
 /// Detect terminal background theme using termbg.
 fn detect_terminal_theme() -> TerminalTheme {
     // termbg needs a timeout for terminals that don't respond
@@ -18,10 +18,14 @@ pub enum ParseError {
     #[allow(dead_code)] // Error variant for detailed error messages
     #[error("Invalid line format: {0}")]
     InvalidLine(String),
-    #[error("Inconsistent sequence lengths")]
-    InconsistentLengths,
+    #[cfg(not(feature = "gzip"))]
+    #[error("File is gzip-compressed, but aform was built without the gzip feature")]
+    GzipUnsupported,
 }
 
+/// Leading bytes of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 /// Accumulates the lines of a single alignment record (between `# STOCKHOLM`
 /// headers / `//` terminators) and builds an [`Alignment`] from them.
 #[derive(Default)]
@@ -30,8 +34,15 @@ struct RecordBuilder {
     /// Blocked format: accumulated sequence data across blocks.
     seq_data: HashMap<String, String>,
     seq_order: Vec<String>,
+    /// Every sequence line as (block, ID, residue count), to split duplicate
+    /// IDs back into separate sequences (see `split_duplicate`).
+    seq_lines: Vec<(usize, String, usize)>,
     /// Blocked residue annotations.
     gr_data: HashMap<(String, String), String>,
+    /// Every `#=GR` line as (block, (ID, tag), length).
+    gr_lines: Vec<(usize, (String, String), usize)>,
+    /// Index of the current blank-line separated block.
+    block: usize,
     gc_data: HashMap<String, String>,
     /// Whether any content line has been seen for this record.
     has_content: bool,
@@ -47,6 +58,11 @@ impl RecordBuilder {
         self.has_content
     }
 
+    /// A blank line ends the current block of sequence lines.
+    fn end_block(&mut self) {
+        self.block += 1;
+    }
+
     /// Process a single (non-empty, non-terminator) line of a record.
     fn process_line(&mut self, line: &str) {
         // Comment line (not annotation) - includes the `# STOCKHOLM` header.
@@ -75,11 +91,10 @@ impl RecordBuilder {
 
         // Sequence annotation: #=GS seqid tag value
         if let Some(rest) = line.strip_prefix("#=GS") {
-            let parts: Vec<&str> = rest.trim().splitn(3, char::is_whitespace).collect();
-            if parts.len() >= 3 {
-                let seqid = parts[0].to_string();
-                let tag = parts[1].to_string();
-                let value = parts[2].trim().to_string();
+            if let Some(([seqid, tag], value)) = split_fields(rest) {
+                let seqid = seqid.to_string();
+                let tag = tag.to_string();
+                let value = value.to_string();
                 self.alignment
                     .sequence_annotations
                     .entry(seqid)
@@ -105,13 +120,13 @@ impl RecordBuilder {
 
         // Residue annotation: #=GR seqid tag data
         if let Some(rest) = line.strip_prefix("#=GR") {
-            let parts: Vec<&str> = rest.trim().splitn(3, char::is_whitespace).collect();
-            if parts.len() >= 3 {
-                let seqid = parts[0].to_string();
-                let tag = parts[1].to_string();
-                let data = parts[2].trim().to_string();
+            if let Some(([seqid, tag], data)) = split_fields(rest) {
+                let key = (seqid.to_string(), tag.to_string());
+                let data = data.to_string();
+                self.gr_lines
+                    .push((self.block, key.clone(), data.chars().count()));
                 self.gr_data
-                    .entry((seqid, tag))
+                    .entry(key)
                     .and_modify(|s| s.push_str(&data))
                     .or_insert(data);
             }
@@ -124,6 +139,9 @@ impl RecordBuilder {
             let seqid = parts[0].to_string();
             let data = parts[1].trim().replace(' ', ""); // Remove any internal spaces
 
+            self.seq_lines
+                .push((self.block, seqid.clone(), data.chars().count()));
+
             if !self.seq_data.contains_key(&seqid) {
                 self.seq_order.push(seqid.clone());
             }
@@ -134,15 +152,130 @@ impl RecordBuilder {
         }
     }
 
+    /// One row per sequence: the data of each ID joined across its lines,
+    /// except that an ID whose joined data is a whole multiple of the
+    /// alignment width (that of the `#=GC` lines, or else the shortest
+    /// sequence) is split into that many duplicate sequences (see
+    /// `split_duplicate`). Rows are in the order of their first line.
+    fn build_rows(&mut self) -> Vec<(String, String)> {
+        let merged: Vec<(String, String)> = std::mem::take(&mut self.seq_order)
+            .into_iter()
+            .filter_map(|id| self.seq_data.remove(&id).map(|data| (id, data)))
+            .collect();
+        let width = match self.gc_data.values().next() {
+            Some(data) => data.chars().count(),
+            None => merged
+                .iter()
+                .map(|(_, data)| data.chars().count())
+                .min()
+                .unwrap_or(0),
+        };
+
+        let mut rows: Vec<(usize, String, String)> = Vec::with_capacity(merged.len());
+        for (id, data) in merged {
+            let len = data.chars().count();
+            if width > 0
+                && len > width
+                && len.is_multiple_of(width)
+                && let Some(copies) = self.split_duplicate(&id, &data, len / width)
+            {
+                rows.extend(
+                    copies
+                        .into_iter()
+                        .map(|(first_line, copy)| (first_line, id.clone(), copy)),
+                );
+            } else {
+                let first_line = self
+                    .seq_lines
+                    .iter()
+                    .position(|(_, line_id, _)| *line_id == id);
+                rows.push((first_line.unwrap_or(usize::MAX), id, data));
+            }
+        }
+        rows.sort_by_key(|(first_line, _, _)| *first_line);
+        rows.into_iter().map(|(_, id, data)| (id, data)).collect()
+    }
+
+    /// Split the joined `data` of `id` into `copies` sequences, dealing its
+    /// lines out in turn within each block, or `None` if the copies would
+    /// differ in length. Each copy comes with the index of its first line in
+    /// `seq_lines`. `#=GR` lines can only belong to one sequence per ID, so
+    /// the first copy keeps them.
+    fn split_duplicate(
+        &mut self,
+        id: &str,
+        data: &str,
+        copies: usize,
+    ) -> Option<Vec<(usize, String)>> {
+        /// Copy each line belongs to: its occurrence in the block, modulo `copies`.
+        fn copy_of<'a, K: 'a>(
+            lines: impl Iterator<Item = &'a (usize, K, usize)>,
+            copies: usize,
+        ) -> Vec<(usize, usize)> {
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            lines
+                .map(|(block, _, len)| {
+                    let count = counts.entry(*block).or_default();
+                    *count += 1;
+                    ((*count - 1) % copies, *len)
+                })
+                .collect()
+        }
+
+        let indices: Vec<usize> = (0..self.seq_lines.len())
+            .filter(|&index| self.seq_lines[index].1 == id)
+            .collect();
+        let lines = indices.iter().map(|&index| &self.seq_lines[index]);
+        let mut chars = data.chars();
+        let mut split = vec![(usize::MAX, String::new()); copies];
+        for ((copy, len), index) in copy_of(lines, copies).into_iter().zip(&indices) {
+            split[copy].0 = split[copy].0.min(*index);
+            split[copy].1.extend(chars.by_ref().take(len));
+        }
+        let len = split[0].1.chars().count();
+        if split.iter().any(|(_, copy)| copy.chars().count() != len) {
+            return None;
+        }
+
+        let gr_lines: Vec<&(usize, (String, String), usize)> = self
+            .gr_lines
+            .iter()
+            .filter(|(_, (gr_id, _), _)| gr_id == id)
+            .collect();
+        let mut tags: Vec<&String> = gr_lines.iter().map(|(_, (_, tag), _)| tag).collect();
+        tags.sort();
+        tags.dedup();
+        for tag in tags {
+            let key = (id.to_string(), tag.clone());
+            let Some(joined) = self.gr_data.remove(&key) else {
+                continue;
+            };
+            let lines = gr_lines.iter().copied().filter(|(_, (_, t), _)| t == tag);
+            let mut chars = joined.chars();
+            let mut first = String::new();
+            for (copy, len) in copy_of(lines, copies) {
+                let piece: String = chars.by_ref().take(len).collect();
+                if copy == 0 {
+                    first.push_str(&piece);
+                }
+            }
+            self.gr_data.insert(key, first);
+        }
+        Some(split)
+    }
+
     /// Finalize the accumulated lines into an [`Alignment`].
     fn finish(mut self) -> Result<Alignment, ParseError> {
         // Build sequences in order
-        for seqid in std::mem::take(&mut self.seq_order) {
-            if let Some(data) = self.seq_data.remove(&seqid) {
-                self.alignment
-                    .sequences
-                    .push(Rc::new(Sequence::new(seqid, data)));
-            }
+        for (id, data) in self.build_rows() {
+            self.alignment
+                .sequences
+                .push(Rc::new(Sequence::new(id, data)));
+        }
+        let duplicates = self.alignment.duplicate_ids();
+        if !duplicates.is_empty() {
+            let warning = format!("duplicate ID(s) {}", duplicates.join(", "));
+            self.alignment.parse_warnings.push(warning);
         }
 
         // Build column annotations
@@ -161,15 +294,30 @@ impl RecordBuilder {
                 .push(ResidueAnnotation { tag, data });
         }
 
-        // Validate lengths
-        if !self.alignment.is_valid() {
-            return Err(ParseError::InconsistentLengths);
+        // Ragged files still load; `:diagnostic` lists the offending lines
+        if !self.alignment.is_rectangular() {
+            let warning = "sequence or #=GC lengths differ".to_string();
+            self.alignment.parse_warnings.push(warning);
         }
 
         Ok(self.alignment)
     }
 }
 
+/// Split the first `N` whitespace-separated fields (e.g. sequence ID and tag)
+/// off `text` and return them with the trimmed remainder. IDs may be padded
+/// with any number of spaces, as the writer does.
+fn split_fields<const N: usize>(text: &str) -> Option<([&str; N], &str)> {
+    let mut rest = text.trim_start();
+    let mut fields = [""; N];
+    for field in &mut fields {
+        let end = rest.find(char::is_whitespace)?;
+        *field = &rest[..end];
+        rest = rest[end..].trim_start();
+    }
+    Some((fields, rest.trim_end()))
+}
+
 /// Parse the first alignment from a Stockholm format reader.
 ///
 /// A Stockholm file may contain several alignments; this returns only the
@@ -203,8 +351,9 @@ pub fn parse_all<R: Read>(reader: R) -> Result<Vec<Alignment>, ParseError> {
     for line_result in lines {
         let line = line_result?;
 
-        // Skip empty lines
-        if line.is_empty() {
+        // Empty lines separate blocks
+        if line.trim().is_empty() {
+            builder.end_block();
             continue;
         }
 
@@ -234,37 +383,38 @@ pub fn parse_str(s: &str) -> Result<Alignment, ParseError> {
     parse(s.as_bytes())
 }
 
-/// Open a Stockholm file, transparently decompressing `.gz` files, and pass the
+/// Open a Stockholm file, transparently decompressing gzip files, and pass the
 /// reader to `f`.
 fn with_reader<T>(
     path: &std::path::Path,
     f: impl FnOnce(Box<dyn Read>) -> Result<T, ParseError>,
 ) -> Result<T, ParseError> {
-    use flate2::read::GzDecoder;
+    let mut reader = BufReader::new(std::fs::File::open(path)?);
 
-    let file = std::fs::File::open(path)?;
-
-    // Check if file is gzip-compressed by extension
-    let is_gzip = path
-        .extension()
-        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"));
+    // Detect gzip by its magic number, so the extension doesn't matter
+    if !reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        return f(Box::new(reader));
+    }
 
-    if is_gzip {
-        f(Box::new(GzDecoder::new(file)))
-    } else {
-        f(Box::new(file))
+    #[cfg(feature = "gzip")]
+    {
+        f(Box::new(flate2::read::GzDecoder::new(reader)))
+    }
+    #[cfg(not(feature = "gzip"))]
+    {
+        Err(ParseError::GzipUnsupported)
     }
 }
 
 /// Parse the first alignment from a Stockholm file path.
-/// Automatically handles gzip-compressed files (.gz extension).
+/// Automatically handles gzip-compressed files.
 #[allow(dead_code)] // retained for single-alignment callers
 pub fn parse_file(path: &std::path::Path) -> Result<Alignment, ParseError> {
     with_reader(path, parse)
 }
 
 /// Parse every alignment from a Stockholm file path.
-/// Automatically handles gzip-compressed files (.gz extension).
+/// Automatically handles gzip-compressed files.
 pub fn parse_all_file(path: &std::path::Path) -> Result<Vec<Alignment>, ParseError> {
     with_reader(path, parse_all)
 }
@@ -319,6 +469,54 @@ seq2    WXYZ
         assert_eq!(alignment.width(), 8);
     }
 
+    #[test]
+    fn test_parse_padded_annotation_ids() {
+        // IDs padded to a common width, as written for longer IDs elsewhere
+        let input = "# STOCKHOLM 1.0\n#=GS s1    STRAND -\ns1    ACGU\n#=GR s1    PP   9876\n//\n";
+        let alignment = parse_str(input).unwrap();
+
+        let gs = &alignment.sequence_annotations["s1"][0];
+        assert_eq!((gs.tag.as_str(), gs.value.as_str()), ("STRAND", "-"));
+        let gr = &alignment.residue_annotations["s1"][0];
+        assert_eq!((gr.tag.as_str(), gr.data.as_str()), ("PP", "9876"));
+    }
+
+    #[test]
+    fn test_parse_duplicate_ids() {
+        // A repeat within a block is a separate sequence; blocks still join
+        let input = "# STOCKHOLM 1.0\ns1 ACGU\n#=GR s1 PP 1234\ns2 GGGG\ns1 UUUU\n#=GR s1 PP 5678\n\n\
+                     s1 AA\n#=GR s1 PP 99\ns2 CC\ns1 GG\n#=GR s1 PP 00\n//\n";
+        let alignment = parse_str(input).unwrap();
+        let data: Vec<String> = alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["ACGUAA", "GGGGCC", "UUUUGG"]);
+        assert_eq!(alignment.duplicate_ids(), ["s1"]);
+        assert_eq!(alignment.parse_warnings, ["duplicate ID(s) s1"]);
+        // #=GR lines go with the first s1
+        assert_eq!(alignment.residue_annotations["s1"][0].data, "123499");
+    }
+
+    #[test]
+    fn test_parse_interleaved_without_blank_lines() {
+        // Repeated IDs continue their sequence when lengths only add up that way
+        let input =
+            "# STOCKHOLM 1.0\ns1 AC\ns2 GG\n#=GR s1 PP 12\ns1 GU\ns2 CC\n#=GR s1 PP 34\n//\n";
+        let alignment = parse_str(input).unwrap();
+        let data: Vec<String> = alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["ACGU", "GGCC"]);
+        assert_eq!(alignment.residue_annotations["s1"][0].data, "1234");
+        assert!(alignment.duplicate_ids().is_empty());
+        assert!(alignment.parse_warnings.is_empty());
+
+        // A duplicate repeated in each stretch is split off after joining
+        let input = "# STOCKHOLM 1.0\ns1 AC\ns2 GG\ns1 UU\n#=GR s1 PP 12\n#=GR s1 PP 00\n\
+                     s1 GU\ns2 CC\ns1 AA\n#=GR s1 PP 34\n#=GR s1 PP 00\n//\n";
+        let alignment = parse_str(input).unwrap();
+        let data: Vec<String> = alignment.sequences.iter().map(|s| s.data()).collect();
+        assert_eq!(data, ["ACGU", "GGCC", "UUAA"]);
+        assert_eq!(alignment.residue_annotations["s1"][0].data, "1234");
+        assert_eq!(alignment.parse_warnings, ["duplicate ID(s) s1"]);
+    }
+
     #[test]
     fn test_invalid_header() {
         let result = parse_str("not a stockholm file\n//\n");
@@ -409,6 +607,29 @@ seqE UU
         assert_eq!(alignments[0].sequences.len(), 2);
     }
 
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_parse_gzip_by_magic_number() {
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+
+        let input = "# STOCKHOLM 1.0\nseq1 ACGU\n//\n";
+        // No .gz extension: detection relies on the content alone
+        let mut path = std::env::temp_dir();
+        path.push(format!("aform_gzip_magic_{}.sto", std::process::id()));
+        let mut encoder = GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            Compression::default(),
+        );
+        encoder.write_all(input.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let alignment = parse_file(&path).unwrap();
+        assert_eq!(alignment.sequences[0].data(), "ACGU");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[test]
     fn test_parse_all_skips_empty_records() {
         // A stray `//` or empty header block should not produce empty alignments.
@@ -1,7 +1,7 @@
 //! Core types for Stockholm format alignments.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 /// Extract short ID from a Stockholm ID (strips coordinate suffix like /10000-20000).
@@ -35,6 +35,38 @@ impl SequenceType {
             SequenceType::Protein => "Protein",
         }
     }
+
+    /// Residue alphabet used for position weight matrices.
+    pub fn profile_alphabet(&self) -> &'static [char] {
+        match self {
+            SequenceType::RNA => &['A', 'C', 'G', 'U'],
+            SequenceType::DNA => &['A', 'C', 'G', 'T'],
+            SequenceType::Protein => &[
+                'A', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'K', 'L', 'M', 'N', 'P', 'Q', 'R', 'S',
+                'T', 'V', 'W', 'Y',
+            ],
+        }
+    }
+}
+
+/// Per-column residue frequencies (a position frequency matrix).
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// Residues in output order.
+    pub alphabet: Vec<char>,
+    /// Normalized frequency of each residue, one map per alignment column.
+    pub columns: Vec<HashMap<char, f64>>,
+}
+
+impl Profile {
+    /// Frequency of `residue` in column `col` (0.0 if absent).
+    pub fn frequency(&self, col: usize, residue: char) -> f64 {
+        self.columns
+            .get(col)
+            .and_then(|column| column.get(&residue))
+            .copied()
+            .unwrap_or(0.0)
+    }
 }
 
 /// A Stockholm format alignment.
@@ -53,6 +85,8 @@ pub struct Alignment {
     pub column_annotations: Vec<ColumnAnnotation>,
     /// Per-residue annotations (#=GR)
     pub residue_annotations: HashMap<String, Vec<ResidueAnnotation>>,
+    /// Problems the parser noticed but read past (e.g. duplicate IDs).
+    pub parse_warnings: Vec<String>,
 }
 
 // Custom Serde for Alignment - unwrap Rc for serialization
@@ -94,6 +128,7 @@ impl<'de> serde::Deserialize<'de> for Alignment {
             sequence_annotations: helper.sequence_annotations,
             column_annotations: helper.column_annotations,
             residue_annotations: helper.residue_annotations,
+            parse_warnings: Vec::new(),
         })
     }
 }
@@ -199,7 +234,6 @@ impl Alignment {
     }
 
     /// Get a mutable reference to the consensus secondary structure.
-    #[allow(dead_code)] // API for structure editing
     pub fn ss_cons_mut(&mut self) -> Option<&mut String> {
         self.column_annotations
             .iter_mut()
@@ -223,6 +257,22 @@ impl Alignment {
             .map(|a| a.data.as_str())
     }
 
+    /// Get the helix number annotation (`#=GC HELIX`) if present.
+    pub fn helix(&self) -> Option<&str> {
+        self.column_annotations
+            .iter()
+            .find(|a| a.tag == "HELIX")
+            .map(|a| a.data.as_str())
+    }
+
+    /// Get the anchor label annotation (`#=GC ANCHORS`) if present.
+    pub fn anchors(&self) -> Option<&str> {
+        self.column_annotations
+            .iter()
+            .find(|a| a.tag == "ANCHORS")
+            .map(|a| a.data.as_str())
+    }
+
     /// Get a file-level annotation value by tag.
     pub fn get_file_annotation(&self, tag: &str) -> Option<&str> {
         self.file_annotations
@@ -240,17 +290,69 @@ impl Alignment {
             .collect()
     }
 
-    /// Check if all sequences have the same length.
-    pub fn is_valid(&self) -> bool {
-        if self.sequences.is_empty() {
-            return true;
+    /// Get a `#=GS` annotation value of sequence `id` by tag.
+    pub fn get_sequence_annotation(&self, id: &str, tag: &str) -> Option<&str> {
+        self.sequence_annotations
+            .get(id)?
+            .iter()
+            .find(|a| a.tag == tag)
+            .map(|a| a.value.as_str())
+    }
+
+    /// Set the `#=GS` annotation `tag` of sequence `id`, replacing an existing
+    /// value or adding it after the sequence's other tags.
+    pub fn set_sequence_annotation(&mut self, id: &str, tag: &str, value: impl Into<String>) {
+        let annotations = self.sequence_annotations.entry(id.to_string()).or_default();
+        let value = value.into();
+        match annotations.iter_mut().find(|a| a.tag == tag) {
+            Some(ann) => ann.value = value,
+            None => annotations.push(SequenceAnnotation {
+                tag: tag.to_string(),
+                value,
+            }),
         }
-        let width = self.sequences[0].len();
+    }
+
+    /// Whether every sequence and `#=GC` line is as long as the first sequence.
+    pub fn is_rectangular(&self) -> bool {
+        let width = self.width();
         self.sequences.iter().all(|s| s.len() == width)
             && self
                 .column_annotations
                 .iter()
-                .all(|a| a.data.len() == width)
+                .all(|a| a.data.chars().count() == width)
+    }
+
+    /// IDs used by more than one sequence, in order of first repeat.
+    pub fn duplicate_ids(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        for seq in &self.sequences {
+            if !seen.insert(seq.id.as_str()) && !duplicates.contains(&seq.id.as_str()) {
+                duplicates.push(seq.id.as_str());
+            }
+        }
+        duplicates
+    }
+
+    /// Rename every sequence to `new_ids` (in sequence order), moving its
+    /// `#=GS` and `#=GR` annotations to the new ID.
+    pub fn rename_sequences(&mut self, new_ids: &[String]) {
+        let mut gs = HashMap::new();
+        let mut gr = HashMap::new();
+        for (seq, new_id) in self.sequences.iter_mut().zip(new_ids) {
+            let seq = Rc::make_mut(seq);
+            if let Some(anns) = self.sequence_annotations.remove(&seq.id) {
+                gs.insert(new_id.clone(), anns);
+            }
+            if let Some(anns) = self.residue_annotations.remove(&seq.id) {
+                gr.insert(new_id.clone(), anns);
+            }
+            seq.id = new_id.clone();
+        }
+        // Annotations of IDs without a sequence stay where they were
+        self.sequence_annotations.extend(gs);
+        self.residue_annotations.extend(gr);
     }
 
     /// Get the maximum sequence ID length (for formatting).
@@ -286,6 +388,32 @@ impl Alignment {
         }
     }
 
+    /// Reverse the column order of all sequences and per-column annotations.
+    ///
+    /// Brackets in structure lines (`#=GC SS_cons`, `#=GR SS`) are mirrored so
+    /// base pairs stay balanced.
+    pub fn reverse_columns(&mut self) {
+        let reverse = |tag: &str, data: &str| -> String {
+            if tag.starts_with("SS") {
+                crate::structure::reverse_structure(data)
+            } else {
+                data.chars().rev().collect()
+            }
+        };
+
+        for seq in &mut self.sequences {
+            Rc::make_mut(seq).chars_mut().reverse();
+        }
+        for ann in &mut self.column_annotations {
+            ann.data = reverse(&ann.tag, &ann.data);
+        }
+        for annotations in self.residue_annotations.values_mut() {
+            for ann in annotations {
+                ann.data = reverse(&ann.tag, &ann.data);
+            }
+        }
+    }
+
     /// Delete a column if it contains only gaps in all sequences.
     pub fn delete_gap_column(&mut self, col: usize, gap_chars: &[char]) -> bool {
         // Check if column is all gaps (O(1) per sequence now)
@@ -320,6 +448,43 @@ impl Alignment {
         true
     }
 
+    /// Keep only the columns whose entry in `keep` is true, in all sequences and
+    /// per-column annotations. Columns beyond the end of `keep` are kept.
+    /// Returns the number of columns removed.
+    pub fn retain_columns(&mut self, keep: &[bool]) -> usize {
+        let retain = |col: usize| keep.get(col).copied().unwrap_or(true);
+        let removed = (0..self.width()).filter(|&col| !retain(col)).count();
+        if removed == 0 {
+            return 0;
+        }
+
+        let filter = |data: &str| -> String {
+            data.chars()
+                .enumerate()
+                .filter(|&(col, _)| retain(col))
+                .map(|(_, c)| c)
+                .collect()
+        };
+
+        for seq in &mut self.sequences {
+            let mut col = 0;
+            Rc::make_mut(seq).chars_mut().retain(|_| {
+                col += 1;
+                retain(col - 1)
+            });
+        }
+        for ann in &mut self.column_annotations {
+            ann.data = filter(&ann.data);
+        }
+        for annotations in self.residue_annotations.values_mut() {
+            for ann in annotations {
+                ann.data = filter(&ann.data);
+            }
+        }
+
+        removed
+    }
+
     /// Get character at a specific position (O(1)).
     pub fn get_char(&self, row: usize, col: usize) -> Option<char> {
         self.sequences.get(row)?.get(col)
@@ -547,12 +712,103 @@ impl Sequence {
             }
         }
     }
+
+    /// Positions of the gaps before the first residue and after the last one
+    /// that are not yet `trim_char`. An all-gap sequence is terminal gaps
+    /// throughout.
+    pub fn untrimmed_terminal_gaps(&self, gap_chars: &[char], trim_char: char) -> Vec<usize> {
+        let is_gap = |c: &&char| gap_chars.contains(c) || **c == trim_char;
+        let len = self.chars.len();
+        let leading = self.chars.iter().take_while(is_gap).count();
+        let trailing_start = if leading == len {
+            len
+        } else {
+            len - self.chars.iter().rev().take_while(is_gap).count()
+        };
+        (0..leading)
+            .chain(trailing_start..len)
+            .filter(|&i| self.chars[i] != trim_char)
+            .collect()
+    }
+
+    /// Replace leading and trailing gaps with `trim_char`, leaving internal
+    /// gaps untouched. Returns the number of characters replaced.
+    pub fn trim_terminal_gaps(&mut self, gap_chars: &[char], trim_char: char) -> usize {
+        let positions = self.untrimmed_terminal_gaps(gap_chars, trim_char);
+        for &i in &positions {
+            self.chars[i] = trim_char;
+        }
+        positions.len()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_trim_terminal_gaps() {
+        let gaps = ['.', '-'];
+        let mut seq = Sequence::new("s1", "..AC-GU.");
+        assert_eq!(seq.trim_terminal_gaps(&gaps, '~'), 3);
+        assert_eq!(seq.data(), "~~AC-GU~");
+        assert_eq!(seq.trim_terminal_gaps(&gaps, '~'), 0);
+
+        // Already-trimmed characters still count as terminal gaps
+        let mut seq = Sequence::new("s2", "#-ACGU-#");
+        assert_eq!(seq.trim_terminal_gaps(&gaps, '#'), 2);
+        assert_eq!(seq.data(), "##ACGU##");
+
+        let mut seq = Sequence::new("s3", "-.-");
+        assert_eq!(seq.trim_terminal_gaps(&gaps, '~'), 3);
+        assert_eq!(seq.data(), "~~~");
+    }
+
+    #[test]
+    fn test_reverse_columns() {
+        let mut alignment = Alignment::new();
+        alignment
+            .sequences
+            .push(Rc::new(Sequence::new("seq1", "GGAC.C")));
+        alignment.column_annotations.push(ColumnAnnotation {
+            tag: "SS_cons".to_string(),
+            data: "<(..)>".to_string(),
+        });
+        alignment.column_annotations.push(ColumnAnnotation {
+            tag: "RF".to_string(),
+            data: "xx..xx".to_string(),
+        });
+        alignment.residue_annotations.insert(
+            "seq1".to_string(),
+            vec![ResidueAnnotation {
+                tag: "SS".to_string(),
+                data: "[[..].".to_string(),
+            }],
+        );
+
+        alignment.reverse_columns();
+        assert_eq!(alignment.sequences[0].data(), "C.CAGG");
+        assert_eq!(alignment.ss_cons(), Some("<(..)>"));
+        assert_eq!(alignment.rf(), Some("xx..xx"));
+        assert_eq!(alignment.residue_annotations["seq1"][0].data, ".[..]]");
+    }
+
+    #[test]
+    fn test_sequence_annotation_accessors() {
+        let mut alignment = Alignment::new();
+        assert_eq!(alignment.get_sequence_annotation("seq1", "STRAND"), None);
+
+        alignment.set_sequence_annotation("seq1", "DE", "first");
+        alignment.set_sequence_annotation("seq1", "STRAND", "+");
+        alignment.set_sequence_annotation("seq1", "STRAND", "-");
+        assert_eq!(
+            alignment.get_sequence_annotation("seq1", "STRAND"),
+            Some("-")
+        );
+        assert_eq!(alignment.sequence_annotations["seq1"].len(), 2);
+        assert_eq!(alignment.sequence_annotations["seq1"][0].tag, "DE");
+    }
+
     #[test]
     fn test_alignment_width() {
         let mut alignment = Alignment::new();
@@ -588,4 +844,42 @@ mod tests {
         assert!(seq.shift_right(2, &['.']));
         assert_eq!(seq.data(), "AC.GU");
     }
+
+    #[test]
+    fn test_rename_sequences() {
+        let mut alignment = Alignment::new();
+        for id in ["a", "b"] {
+            alignment.sequences.push(Rc::new(Sequence::new(id, "AC")));
+        }
+        alignment.residue_annotations.insert(
+            "a".to_string(),
+            vec![ResidueAnnotation {
+                tag: "PP".to_string(),
+                data: "99".to_string(),
+            }],
+        );
+        // Swapping names must not mix up the annotations
+        alignment.rename_sequences(&["b".to_string(), "c".to_string()]);
+        assert_eq!(alignment.sequences[0].id, "b");
+        assert_eq!(alignment.sequences[1].id, "c");
+        assert_eq!(alignment.residue_annotations["b"][0].data, "99");
+        assert!(!alignment.residue_annotations.contains_key("a"));
+        assert!(!alignment.residue_annotations.contains_key("c"));
+    }
+
+    #[test]
+    fn test_retain_columns() {
+        let mut alignment = Alignment::new();
+        alignment
+            .sequences
+            .push(Rc::new(Sequence::new("seq1", "AC.GU")));
+        alignment.column_annotations.push(ColumnAnnotation {
+            tag: "RF".to_string(),
+            data: "xx.xx".to_string(),
+        });
+        let removed = alignment.retain_columns(&[true, true, false, true]);
+        assert_eq!(removed, 1);
+        assert_eq!(alignment.sequences[0].data(), "ACGU");
+        assert_eq!(alignment.rf(), Some("xxxx"));
+    }
 }
@@ -0,0 +1,172 @@
+//! Vienna (FASTA + dot-bracket) format parser.
+//!
+//! Each record is a `>id` header, a sequence line, and a structure line that
+//! may be followed by its free energy:
+//!
+//! ```text
+//! >seq1
+//! GGGAAACCC
+//! (((...))) (-1.20)
+//! ```
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+use thiserror::Error;
+
+use super::types::*;
+
+#[derive(Error, Debug)]
+pub enum ViennaError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Expected '>' header, found: {0}")]
+    MissingHeader(String),
+    #[error("Missing sequence for {0}")]
+    MissingSequence(String),
+    #[error("Missing structure for {0}")]
+    MissingStructure(String),
+    #[error("Structure length does not match sequence length for {0}")]
+    LengthMismatch(String),
+    #[error("No records found")]
+    Empty,
+}
+
+/// Check whether a path looks like a Vienna file (`.vienna` or `.db`).
+pub fn is_vienna_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("vienna") || ext.eq_ignore_ascii_case("db"))
+}
+
+/// Parse a Vienna format reader into an alignment.
+///
+/// Each record becomes a row with its structure stored as `#=GR SS`. Shorter
+/// records are padded with gaps to the longest length, and the first record's
+/// structure is used as SS_cons.
+pub fn parse_vienna<R: Read>(reader: R) -> Result<Alignment, ViennaError> {
+    let mut lines = BufReader::new(reader)
+        .lines()
+        .filter(|l| !matches!(l, Ok(l) if l.trim().is_empty()));
+
+    let mut records: Vec<(String, String, String)> = Vec::new();
+    while let Some(header) = lines.next() {
+        let header = header?;
+        let id = header
+            .trim()
+            .strip_prefix('>')
+            .ok_or_else(|| ViennaError::MissingHeader(header.clone()))?
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        let sequence = lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| ViennaError::MissingSequence(id.clone()))?
+            .trim()
+            .to_string();
+        let structure = lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| ViennaError::MissingStructure(id.clone()))?
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        if structure.chars().count() != sequence.chars().count() {
+            return Err(ViennaError::LengthMismatch(id));
+        }
+        records.push((id, sequence, structure));
+    }
+
+    if records.is_empty() {
+        return Err(ViennaError::Empty);
+    }
+
+    let width = records
+        .iter()
+        .map(|(_, seq, _)| seq.chars().count())
+        .max()
+        .unwrap_or(0);
+    let pad = |s: &str| -> String {
+        let len = s.chars().count();
+        format!("{s}{}", ".".repeat(width - len))
+    };
+
+    let mut alignment = Alignment::new();
+    for (id, sequence, structure) in &records {
+        alignment
+            .sequences
+            .push(Rc::new(Sequence::new(id.clone(), pad(sequence))));
+        alignment
+            .residue_annotations
+            .entry(id.clone())
+            .or_default()
+            .push(ResidueAnnotation {
+                tag: "SS".to_string(),
+                data: pad(structure),
+            });
+    }
+    alignment.column_annotations.push(ColumnAnnotation {
+        tag: "SS_cons".to_string(),
+        data: pad(&records[0].2),
+    });
+
+    Ok(alignment)
+}
+
+/// Parse a Vienna format file.
+pub fn parse_vienna_file(path: &Path) -> Result<Alignment, ViennaError> {
+    parse_vienna(std::fs::File::open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIENNA: &str = ">seq1 hairpin
+GGGAAACCC
+(((...))) (-1.20)
+
+>seq2
+GGAAACC
+((...))
+";
+
+    #[test]
+    fn test_parse_vienna() {
+        let alignment = parse_vienna(VIENNA.as_bytes()).unwrap();
+        assert_eq!(alignment.num_sequences(), 2);
+        assert_eq!(alignment.sequences[0].id, "seq1");
+        assert_eq!(alignment.width(), 9);
+        assert_eq!(alignment.sequences[1].data(), "GGAAACC..");
+        assert_eq!(alignment.ss_cons(), Some("(((...)))"));
+        assert_eq!(alignment.residue_annotations["seq2"][0].data, "((...))..");
+    }
+
+    #[test]
+    fn test_parse_vienna_errors() {
+        assert!(matches!(
+            parse_vienna("GGG\n".as_bytes()),
+            Err(ViennaError::MissingHeader(_))
+        ));
+        assert!(matches!(
+            parse_vienna(">a\nGGG\n".as_bytes()),
+            Err(ViennaError::MissingStructure(_))
+        ));
+        assert!(matches!(
+            parse_vienna(">a\nGGG\n((.))\n".as_bytes()),
+            Err(ViennaError::LengthMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_is_vienna_path() {
+        assert!(is_vienna_path(Path::new("x.vienna")));
+        assert!(is_vienna_path(Path::new("x.DB")));
+        assert!(!is_vienna_path(Path::new("x.sto")));
+    }
+}
@@ -5,7 +5,60 @@ use std::io::{Result, Write};
 use super::types::*;
 
 /// Write a Stockholm format alignment to a writer.
-pub fn write<W: Write>(alignment: &Alignment, mut writer: W) -> Result<()> {
+pub fn write<W: Write>(alignment: &Alignment, writer: W) -> Result<()> {
+    write_with(alignment, writer, false)
+}
+
+/// Write a Stockholm alignment, with `pretty_print` padding every sequence
+/// ID and `#=GR`/`#=GC` label to one width so the data columns line up, as
+/// HMMER and Infernal do.
+pub fn write_with<W: Write>(
+    alignment: &Alignment,
+    mut writer: W,
+    pretty_print: bool,
+) -> Result<()> {
+    let padding = write_header(alignment, &mut writer)?;
+    let data_column = if pretty_print {
+        data_column(alignment, padding)
+    } else {
+        0
+    };
+    write_block(alignment, &mut writer, padding, data_column, None)?;
+
+    // Terminator
+    writeln!(writer, "//")?;
+
+    Ok(())
+}
+
+/// Write an alignment in interleaved blocks of `block_width` columns.
+///
+/// Each block repeats the sequences, their `#=GR` lines and the `#=GC` lines
+/// for the same columns, separated by blank lines.
+pub fn write_interleaved<W: Write>(
+    alignment: &Alignment,
+    mut writer: W,
+    block_width: usize,
+) -> Result<()> {
+    let padding = write_header(alignment, &mut writer)?;
+    let block_width = block_width.max(1);
+    let width = alignment.width();
+    for start in (0..width.max(1)).step_by(block_width) {
+        if start > 0 {
+            writeln!(writer)?;
+        }
+        let end = (start + block_width).min(width);
+        write_block(alignment, &mut writer, padding, 0, Some(start..end))?;
+    }
+
+    // Terminator
+    writeln!(writer, "//")?;
+
+    Ok(())
+}
+
+/// Write the header, `#=GF` and `#=GS` lines, returning the ID column width.
+fn write_header<W: Write>(alignment: &Alignment, writer: &mut W) -> Result<usize> {
     // Header
     writeln!(writer, "# STOCKHOLM 1.0")?;
 
@@ -35,26 +88,60 @@ pub fn write<W: Write>(alignment: &Alignment, mut writer: W) -> Result<()> {
         writeln!(writer)?;
     }
 
+    Ok(padding)
+}
+
+/// Width of the longest sequence, `#=GR` or `#=GC` label with IDs padded to
+/// `padding`, i.e. where the data starts when every label is padded to it.
+fn data_column(alignment: &Alignment, padding: usize) -> usize {
+    let gr_tags = alignment
+        .residue_annotations
+        .values()
+        .flatten()
+        .map(|ann| "#=GR ".len() + padding + 1 + ann.tag.chars().count());
+    let gc_tags = alignment
+        .column_annotations
+        .iter()
+        .map(|ann| "#=GC ".len() + padding.max(ann.tag.chars().count()));
+    gr_tags.chain(gc_tags).fold(padding, usize::max)
+}
+
+/// Write sequences, `#=GR` and `#=GC` lines for `columns` (all when `None`),
+/// padding each label to at least `data_column` characters.
+fn write_block<W: Write>(
+    alignment: &Alignment,
+    writer: &mut W,
+    padding: usize,
+    data_column: usize,
+    columns: Option<std::ops::Range<usize>>,
+) -> Result<()> {
+    let slice = |data: &str| -> String {
+        match &columns {
+            Some(range) => data.chars().skip(range.start).take(range.len()).collect(),
+            None => data.to_string(),
+        }
+    };
+
     // Sequences and their residue annotations (#=GR)
     for seq in &alignment.sequences {
-        writeln!(writer, "{:padding$} {}", seq.id, seq.data())?;
+        let label = format!("{:padding$}", seq.id);
+        writeln!(writer, "{label:data_column$} {}", slice(&seq.data()))?;
 
         // Per-residue annotations for this sequence
         if let Some(annotations) = alignment.residue_annotations.get(&seq.id) {
             for ann in annotations {
-                writeln!(writer, "#=GR {:padding$} {} {}", seq.id, ann.tag, ann.data)?;
+                let label = format!("#=GR {:padding$} {}", seq.id, ann.tag);
+                writeln!(writer, "{label:data_column$} {}", slice(&ann.data))?;
             }
         }
     }
 
     // Column annotations (#=GC)
     for ann in &alignment.column_annotations {
-        writeln!(writer, "#=GC {:padding$} {}", ann.tag, ann.data)?;
+        let label = format!("#=GC {:padding$}", ann.tag);
+        writeln!(writer, "{label:data_column$} {}", slice(&ann.data))?;
     }
 
-    // Terminator
-    writeln!(writer, "//")?;
-
     Ok(())
 }
 
@@ -62,9 +149,13 @@ pub fn write<W: Write>(alignment: &Alignment, mut writer: W) -> Result<()> {
 ///
 /// Each alignment is emitted as its own `# STOCKHOLM`/`//` record, producing a
 /// valid multi-alignment Stockholm file.
-pub fn write_all<W: Write>(alignments: &[Alignment], mut writer: W) -> Result<()> {
+pub fn write_all<W: Write>(
+    alignments: &[Alignment],
+    mut writer: W,
+    pretty_print: bool,
+) -> Result<()> {
     for alignment in alignments {
-        write(alignment, &mut writer)?;
+        write_with(alignment, &mut writer, pretty_print)?;
     }
     Ok(())
 }
@@ -77,22 +168,249 @@ pub fn write_string(alignment: &Alignment) -> Result<String> {
     Ok(String::from_utf8_lossy(&buffer).to_string())
 }
 
-/// Write a Stockholm alignment to a file.
+/// Create `path` and pass a writer for it to `f`, gzip-compressing the output
+/// when the path ends in `.gz`.
+fn with_writer(path: &std::path::Path, f: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<()> {
+    let is_gzip = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"));
+    // Refuse before creating the file, so an existing one is left intact
+    #[cfg(not(feature = "gzip"))]
+    if is_gzip {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "writing .gz files requires the gzip feature",
+        ));
+    }
+
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    #[cfg(feature = "gzip")]
+    if is_gzip {
+        use flate2::{Compression, write::GzEncoder};
+
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        f(&mut encoder)?;
+        return encoder.finish()?.flush();
+    }
+    f(&mut file)?;
+    file.flush()
+}
+
+/// Write a Stockholm alignment to a file (gzip-compressed for `.gz` paths).
 pub fn write_file(alignment: &Alignment, path: &std::path::Path) -> Result<()> {
+    write_file_with(alignment, path, false)
+}
+
+/// Write a Stockholm alignment to a file, aligning the data columns when
+/// `pretty_print` is set (see [`write_with`]).
+pub fn write_file_with(
+    alignment: &Alignment,
+    path: &std::path::Path,
+    pretty_print: bool,
+) -> Result<()> {
+    with_writer(path, |writer| write_with(alignment, writer, pretty_print))
+}
+
+/// Write an interleaved Stockholm alignment to a file (gzip-compressed for
+/// `.gz` paths).
+pub fn write_interleaved_file(
+    alignment: &Alignment,
+    path: &std::path::Path,
+    block_width: usize,
+) -> Result<()> {
+    with_writer(path, |writer| {
+        write_interleaved(alignment, writer, block_width)
+    })
+}
+
+/// Write several Stockholm alignments to a file, in order (gzip-compressed
+/// for `.gz` paths), aligning the data columns when `pretty_print` is set.
+pub fn write_all_file(
+    alignments: &[Alignment],
+    path: &std::path::Path,
+    pretty_print: bool,
+) -> Result<()> {
+    with_writer(path, |writer| write_all(alignments, writer, pretty_print))
+}
+
+/// Residues per line in FASTA output.
+const FASTA_LINE_WIDTH: usize = 60;
+
+/// Write `(header, sequence)` records as FASTA, wrapping sequences at 60 residues.
+pub fn write_fasta_to<W: Write>(records: &[(String, String)], mut writer: W) -> Result<()> {
+    for (header, sequence) in records {
+        writeln!(writer, ">{header}")?;
+        let chars: Vec<char> = sequence.chars().collect();
+        for line in chars.chunks(FASTA_LINE_WIDTH) {
+            writeln!(writer, "{}", line.iter().collect::<String>())?;
+        }
+    }
+    Ok(())
+}
+
+/// Write FASTA records to a file (gzip-compressed for `.gz` paths).
+pub fn write_fasta(records: &[(String, String)], path: &std::path::Path) -> Result<()> {
+    with_writer(path, |writer| write_fasta_to(records, writer))
+}
+
+/// Write an alignment in Vienna format: per sequence a `>id` header, the
+/// aligned sequence, and its `#=GR SS` structure (falling back to SS_cons,
+/// then to all unpaired), as read by [`super::vienna::parse_vienna`].
+pub fn write_vienna_to<W: Write>(alignment: &Alignment, mut writer: W) -> Result<()> {
+    let unpaired = ".".repeat(alignment.width());
+    for seq in &alignment.sequences {
+        let structure = alignment
+            .residue_annotations
+            .get(&seq.id)
+            .and_then(|annotations| annotations.iter().find(|a| a.tag == "SS"))
+            .map(|a| a.data.as_str())
+            .or(alignment.ss_cons())
+            .unwrap_or(&unpaired);
+        writeln!(writer, ">{}", seq.id)?;
+        writeln!(writer, "{}", seq.data())?;
+        writeln!(writer, "{structure}")?;
+    }
+    Ok(())
+}
+
+/// Write an alignment in Vienna format to a file (gzip-compressed for `.gz`
+/// paths).
+pub fn write_vienna(alignment: &Alignment, path: &std::path::Path) -> Result<()> {
+    with_writer(path, |writer| write_vienna_to(alignment, writer))
+}
+
+/// Columns per block in Clustal output.
+const CLUSTAL_BLOCK_WIDTH: usize = 60;
+
+/// Write an alignment in Clustal format.
+///
+/// Gap characters are written as `-`, and each block ends with a
+/// conservation line marking columns where every sequence has the same
+/// residue (case-insensitive) with `*`.
+pub fn write_clustal_to<W: Write>(
+    alignment: &Alignment,
+    mut writer: W,
+    gap_chars: &[char],
+) -> Result<()> {
+    writeln!(writer, "CLUSTAL W multiple sequence alignment")?;
+
+    let id_width = alignment.max_id_len().max(10);
+    let rows: Vec<Vec<char>> = alignment
+        .sequences
+        .iter()
+        .map(|seq| {
+            seq.chars()
+                .iter()
+                .map(|c| if gap_chars.contains(c) { '-' } else { *c })
+                .collect()
+        })
+        .collect();
+    let width = alignment.width();
+    for start in (0..width).step_by(CLUSTAL_BLOCK_WIDTH) {
+        let end = (start + CLUSTAL_BLOCK_WIDTH).min(width);
+        writeln!(writer)?;
+        for (seq, row) in alignment.sequences.iter().zip(&rows) {
+            let block: String = row.get(start..end).unwrap_or_default().iter().collect();
+            writeln!(writer, "{:<id_width$} {block}", seq.id)?;
+        }
+        let conservation: String = (start..end)
+            .map(|col| {
+                let mut residues = rows.iter().map(|row| row.get(col).copied().unwrap_or('-'));
+                let first = residues.next().unwrap_or('-');
+                if first != '-' && residues.all(|c| c.eq_ignore_ascii_case(&first)) {
+                    '*'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        writeln!(writer, "{:id_width$} {}", "", conservation.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Write an alignment to a file in Clustal format (gzip-compressed for `.gz`
+/// paths).
+pub fn write_clustal(
+    alignment: &Alignment,
+    path: &std::path::Path,
+    gap_chars: &[char],
+) -> Result<()> {
+    with_writer(path, |writer| {
+        write_clustal_to(alignment, writer, gap_chars)
+    })
+}
+
+/// Write an alignment as relaxed sequential PHYLIP: a `count width` header,
+/// then one line per sequence with the full ID padded to a common width.
+pub fn write_phylip_to<W: Write>(alignment: &Alignment, mut writer: W) -> Result<()> {
+    let id_width = alignment.max_id_len();
+    writeln!(
+        writer,
+        "{} {}",
+        alignment.num_sequences(),
+        alignment.width()
+    )?;
+    for seq in &alignment.sequences {
+        writeln!(writer, "{:<id_width$}  {}", seq.id, seq.data())?;
+    }
+    Ok(())
+}
+
+/// Write an alignment to a file as PHYLIP (gzip-compressed for `.gz` paths).
+pub fn write_phylip(alignment: &Alignment, path: &std::path::Path) -> Result<()> {
+    with_writer(path, |writer| write_phylip_to(alignment, writer))
+}
+
+/// Write a profile as a JASPAR matrix (one bracketed row per residue).
+pub fn write_jaspar_to<W: Write>(profile: &Profile, name: &str, mut writer: W) -> Result<()> {
+    writeln!(writer, ">{name} {name}")?;
+    for &residue in &profile.alphabet {
+        let values: Vec<String> = (0..profile.columns.len())
+            .map(|col| format!("{:.4}", profile.frequency(col, residue)))
+            .collect();
+        writeln!(writer, "{residue}  [ {} ]", values.join(" "))?;
+    }
+    Ok(())
+}
+
+/// Write a profile as a TRANSFAC matrix (one numbered row per column).
+pub fn write_transfac_to<W: Write>(profile: &Profile, name: &str, mut writer: W) -> Result<()> {
+    writeln!(writer, "AC  {name}")?;
+    writeln!(writer, "XX")?;
+    writeln!(writer, "ID  {name}")?;
+    writeln!(writer, "XX")?;
+    let header: Vec<String> = profile.alphabet.iter().map(|c| format!("{c:>8}")).collect();
+    writeln!(writer, "P0{}", header.concat())?;
+    for col in 0..profile.columns.len() {
+        let values: Vec<String> = profile
+            .alphabet
+            .iter()
+            .map(|&residue| format!("{:>8.4}", profile.frequency(col, residue)))
+            .collect();
+        writeln!(writer, "{:02}{}", col + 1, values.concat())?;
+    }
+    writeln!(writer, "XX")?;
+    writeln!(writer, "//")?;
+    Ok(())
+}
+
+/// Write a profile to a file in JASPAR format.
+pub fn write_jaspar(profile: &Profile, name: &str, path: &std::path::Path) -> Result<()> {
     let file = std::fs::File::create(path)?;
-    write(alignment, file)
+    write_jaspar_to(profile, name, file)
 }
 
-/// Write several Stockholm alignments to a file, in order.
-pub fn write_all_file(alignments: &[Alignment], path: &std::path::Path) -> Result<()> {
+/// Write a profile to a file in TRANSFAC format.
+pub fn write_transfac(profile: &Profile, name: &str, path: &std::path::Path) -> Result<()> {
     let file = std::fs::File::create(path)?;
-    write_all(alignments, file)
+    write_transfac_to(profile, name, file)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::stockholm::parser;
+    use crate::stockholm::{parser, vienna};
     use std::rc::Rc;
 
     #[test]
@@ -118,6 +436,36 @@ seq2/1-10  ACGU..ACGU
         assert_eq!(alignment.ss_cons(), reparsed.ss_cons());
     }
 
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_write_gzip_roundtrip() {
+        let alignment = parser::parse_str("# STOCKHOLM 1.0\nseq1 ACGU\n//\n").unwrap();
+        let mut path = std::env::temp_dir();
+        path.push(format!("aform_write_gzip_{}.sto.gz", std::process::id()));
+
+        write_file(&alignment, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[..2], [0x1f, 0x8b]);
+        let reparsed = parser::parse_file(&path).unwrap();
+        assert_eq!(reparsed.sequences[0].data(), "ACGU");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_write_gzip_unsupported_keeps_file() {
+        let alignment = parser::parse_str("# STOCKHOLM 1.0\nseq1 ACGU\n//\n").unwrap();
+        let mut path = std::env::temp_dir();
+        path.push(format!("aform_write_nogzip_{}.sto.gz", std::process::id()));
+        std::fs::write(&path, "existing").unwrap();
+
+        assert!(write_file(&alignment, &path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "existing");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[test]
     fn test_write_all_roundtrip() {
         let input = "# STOCKHOLM 1.0\n#=GF ID one\nseqA ACGU\n//\n\
@@ -127,7 +475,7 @@ seq2/1-10  ACGU..ACGU
         assert_eq!(alignments.len(), 2);
 
         let mut buffer = Vec::new();
-        write_all(&alignments, &mut buffer).unwrap();
+        write_all(&alignments, &mut buffer, false).unwrap();
 
         let reparsed = parser::parse_all(buffer.as_slice()).unwrap();
         assert_eq!(reparsed.len(), 2);
@@ -157,4 +505,141 @@ seq2/1-10  ACGU..ACGU
         assert!(output.contains("#=GC SS_cons"));
         assert!(output.contains("//"));
     }
+
+    #[test]
+    fn test_write_pretty() {
+        let input = "# STOCKHOLM 1.0\n#=GS seq1 DE first\n\
+                     seq1 ACGU\n#=GR seq1 PP 9999\nseq2 AC.U\n\
+                     #=GC SS_cons <..>\n//\n";
+        let alignment = parser::parse_str(input).unwrap();
+
+        let mut buffer = Vec::new();
+        write_with(&alignment, &mut buffer, true).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        // Data starts in the same column on every line
+        assert!(output.contains("#=GS seq1       DE first\n"));
+        assert!(output.contains("seq1               ACGU\n"));
+        assert!(output.contains("#=GR seq1       PP 9999\n"));
+        assert!(output.contains("#=GC SS_cons       <..>\n"));
+
+        let reparsed = parser::parse_str(&output).unwrap();
+        assert_eq!(reparsed.sequences[1].data(), "AC.U");
+        assert_eq!(reparsed.ss_cons(), Some("<..>"));
+        assert_eq!(reparsed.residue_annotations["seq1"][0].data, "9999");
+    }
+
+    #[test]
+    fn test_write_interleaved() {
+        let input = "# STOCKHOLM 1.0\n#=GF ID blocks\n\
+                     seq1 ACGUACGUAC\n#=GR seq1 PP 0123456789\n\
+                     seq2 ACGU..GUAC\n#=GC SS_cons <<<....>>>\n//\n";
+        let alignment = parser::parse_str(input).unwrap();
+
+        let mut buffer = Vec::new();
+        write_interleaved(&alignment, &mut buffer, 4).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        // 10 columns in blocks of 4: three blocks, each with every line type
+        assert_eq!(output.matches("#=GC SS_cons").count(), 3);
+        assert!(output.contains("seq2       ..GU\n"));
+        assert!(output.contains("#=GR seq1       PP 89\n"));
+        assert_eq!(output.matches("#=GF ID").count(), 1);
+
+        let reparsed = parser::parse_str(&output).unwrap();
+        assert_eq!(reparsed.sequences[1].data(), "ACGU..GUAC");
+        assert_eq!(reparsed.ss_cons(), Some("<<<....>>>"));
+        assert_eq!(reparsed.residue_annotations["seq1"][0].data, "0123456789");
+    }
+
+    #[test]
+    fn test_write_fasta() {
+        let records = vec![
+            ("short".to_string(), "ACGU".to_string()),
+            ("long".to_string(), "A".repeat(65)),
+        ];
+        let mut buffer = Vec::new();
+        write_fasta_to(&records, &mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[..2], [">short", "ACGU"]);
+        assert_eq!(lines[2], ">long");
+        assert_eq!(lines[3].len(), 60);
+        assert_eq!(lines[4], "AAAAA");
+    }
+
+    #[test]
+    fn test_write_vienna_roundtrip() {
+        let input = ">s1\nGGGAAACCC\n(((...))) (-1.20)\n>s2\nGGAAACC\n((...))\n";
+        let alignment = vienna::parse_vienna(input.as_bytes()).unwrap();
+        let mut buffer = Vec::new();
+        write_vienna_to(&alignment, &mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert_eq!(
+            output,
+            ">s1\nGGGAAACCC\n(((...)))\n>s2\nGGAAACC..\n((...))..\n"
+        );
+
+        let reparsed = vienna::parse_vienna(output.as_bytes()).unwrap();
+        assert_eq!(reparsed.sequences[1].data(), "GGAAACC..");
+        assert_eq!(reparsed.ss_cons(), Some("(((...)))"));
+    }
+
+    #[test]
+    fn test_write_phylip() {
+        let input = "# STOCKHOLM 1.0\nseq1 AC-GU\nlonger_id ACAGU\n//\n";
+        let alignment = parser::parse_str(input).unwrap();
+        let mut buffer = Vec::new();
+        write_phylip_to(&alignment, &mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        assert_eq!(output, "2 5\nseq1       AC-GU\nlonger_id  ACAGU\n");
+    }
+
+    #[test]
+    fn test_write_clustal() {
+        let input = "# STOCKHOLM 1.0\nseq1 AC.GU\nseq2 aCAGA\n//\n";
+        let alignment = parser::parse_str(input).unwrap();
+        let mut buffer = Vec::new();
+        write_clustal_to(&alignment, &mut buffer, &['.', '-']).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "CLUSTAL W multiple sequence alignment");
+        assert_eq!(lines[1], "");
+        assert_eq!(lines[2], "seq1       AC-GU");
+        assert_eq!(lines[3], "seq2       aCAGA");
+        assert_eq!(lines[4], "           ** *");
+    }
+
+    fn two_column_profile() -> Profile {
+        Profile {
+            alphabet: vec!['A', 'C'],
+            columns: vec![
+                [('A', 1.0), ('C', 0.0)].into_iter().collect(),
+                [('A', 0.25), ('C', 0.75)].into_iter().collect(),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_write_jaspar() {
+        let mut buffer = Vec::new();
+        write_jaspar_to(&two_column_profile(), "motif", &mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            ">motif motif\nA  [ 1.0000 0.2500 ]\nC  [ 0.0000 0.7500 ]\n"
+        );
+    }
+
+    #[test]
+    fn test_write_transfac() {
+        let mut buffer = Vec::new();
+        write_transfac_to(&two_column_profile(), "motif", &mut buffer).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "AC  motif");
+        assert_eq!(lines[4], "P0       A       C");
+        assert_eq!(lines[5], "01  1.0000  0.0000");
+        assert_eq!(lines[6], "02  0.2500  0.7500");
+        assert_eq!(&lines[7..], ["XX", "//"]);
+    }
 }
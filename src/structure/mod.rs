@@ -4,3 +4,4 @@ mod pairs;
 mod parser;
 
 pub use pairs::*;
+pub use parser::reverse_structure;
@@ -56,7 +56,6 @@ impl StructureCache {
     }
 
     /// Get all base pairs.
-    #[allow(dead_code)] // API for structure analysis
     pub fn pairs(&self) -> &[BasePair] {
         &self.pairs
     }
@@ -78,7 +77,6 @@ impl StructureCache {
     }
 
     /// Clear the cache.
-    #[allow(dead_code)] // API for cache management
     pub fn clear(&mut self) {
         self.cached_structure.clear();
         self.pairs.clear();
@@ -109,7 +107,7 @@ pub fn is_valid_pair(base1: char, base2: char) -> bool {
 }
 
 /// Analyze compensatory changes between two sequences at paired positions.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CompensatoryChange {
     /// Both positions unchanged, valid pair.
     Unchanged,
@@ -174,6 +172,77 @@ pub fn analyze_compensatory(
     }
 }
 
+/// Per-column agreement between a reference structure (SS_cons) and a
+/// per-sequence structure (`#=GR SS`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructDiffClass {
+    /// Both unpaired, or both paired to the same partner.
+    Agree,
+    /// Paired in the reference only.
+    RefPaired,
+    /// Paired in the sequence structure only.
+    SeqPaired,
+    /// Paired in both, but to different partners.
+    Conflict,
+}
+
+/// Compare a per-sequence structure against a reference structure column by column.
+///
+/// Columns beyond the end of either structure are treated as unpaired.
+pub fn compare_structures(
+    reference: &str,
+    query: &str,
+) -> Result<Vec<StructDiffClass>, StructureError> {
+    let mut ref_cache = StructureCache::new();
+    ref_cache.update(reference)?;
+    let mut query_cache = StructureCache::new();
+    query_cache.update(query)?;
+
+    let len = reference.chars().count().max(query.chars().count());
+    Ok((0..len)
+        .map(
+            |col| match (ref_cache.get_pair(col), query_cache.get_pair(col)) {
+                (None, None) => StructDiffClass::Agree,
+                (Some(_), None) => StructDiffClass::RefPaired,
+                (None, Some(_)) => StructDiffClass::SeqPaired,
+                (Some(a), Some(b)) if a == b => StructDiffClass::Agree,
+                (Some(_), Some(_)) => StructDiffClass::Conflict,
+            },
+        )
+        .collect())
+}
+
+/// Project an aligned sequence and structure onto the sequence's own residues.
+///
+/// Gap columns are removed from both strings, and any pair whose partner falls
+/// on a gap is turned into `.` so the result stays balanced. Returns
+/// `(ungapped_sequence, ungapped_structure)`.
+pub fn ungap_structure(
+    aligned: &[char],
+    structure: &str,
+    gap_chars: &[char],
+) -> Result<(String, String), StructureError> {
+    let mut cache = StructureCache::new();
+    cache.update(structure)?;
+
+    let ss: Vec<char> = structure.chars().collect();
+    let is_residue = |col: usize| aligned.get(col).is_some_and(|c| !gap_chars.contains(c));
+
+    let mut sequence = String::new();
+    let mut ungapped = String::new();
+    for (col, &ch) in aligned.iter().enumerate() {
+        if !is_residue(col) {
+            continue;
+        }
+        sequence.push(ch);
+        ungapped.push(match cache.get_pair(col) {
+            Some(partner) if is_residue(partner) => ss[col],
+            _ => '.',
+        });
+    }
+    Ok((sequence, ungapped))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,4 +303,30 @@ mod tests {
         let result = analyze_compensatory("AUUA", "GCGC", 0, &cache, &gap_chars);
         assert_eq!(result, CompensatoryChange::DoubleCompatible);
     }
+
+    #[test]
+    fn test_compare_structures() {
+        use StructDiffClass::*;
+
+        let diff = compare_structures("<<..>>", "<.<>.>").unwrap();
+        assert_eq!(
+            diff,
+            vec![Agree, RefPaired, SeqPaired, SeqPaired, RefPaired, Agree]
+        );
+
+        let diff = compare_structures("<<>>..", "<.>.<>").unwrap();
+        assert_eq!(diff[0], Conflict);
+        assert_eq!(diff[5], SeqPaired);
+
+        assert!(compare_structures("<<>>", "<<>").is_err());
+    }
+
+    #[test]
+    fn test_ungap_structure() {
+        let aligned: Vec<char> = "GG-AAACC.".chars().collect();
+        let (seq, ss) = ungap_structure(&aligned, "<<<...>>>", &['-', '.']).unwrap();
+        assert_eq!(seq, "GGAAACC");
+        // Columns 0 and 6 lose their partners (gaps at columns 8 and 2).
+        assert_eq!(ss, ".<....>");
+    }
 }
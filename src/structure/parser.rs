@@ -42,7 +42,6 @@ pub fn is_close_bracket(c: char) -> bool {
 }
 
 /// Get the matching closing bracket for an opening bracket.
-#[allow(dead_code)] // API utility for bracket matching
 pub fn matching_close(open: char) -> Option<char> {
     OPEN_BRACKETS
         .iter()
@@ -51,7 +50,6 @@ pub fn matching_close(open: char) -> Option<char> {
 }
 
 /// Get the matching opening bracket for a closing bracket.
-#[allow(dead_code)] // API utility for bracket matching
 pub fn matching_open(close: char) -> Option<char> {
     CLOSE_BRACKETS
         .iter()
@@ -59,6 +57,15 @@ pub fn matching_open(close: char) -> Option<char> {
         .map(|i| OPEN_BRACKETS[i])
 }
 
+/// Structure of the reverse strand: reverse the string and swap each
+/// bracket for its partner of the same type, so every pair stays balanced.
+pub fn reverse_structure(ss: &str) -> String {
+    ss.chars()
+        .rev()
+        .map(|c| matching_close(c).or_else(|| matching_open(c)).unwrap_or(c))
+        .collect()
+}
+
 /// Parse a secondary structure string into base pairs.
 ///
 /// Handles nested bracket notation with multiple bracket types.
@@ -171,6 +178,17 @@ pub fn is_valid_structure(ss: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_reverse_structure() {
+        assert_eq!(reverse_structure("<<..>.>"), "<.<..>>");
+        // Pseudoknot bracket types are mirrored independently
+        let ss = "<<[[..>>..]]";
+        let reversed = reverse_structure(ss);
+        assert_eq!(reversed, "[[..<<..]]>>");
+        assert_eq!(parse_structure(&reversed).unwrap().len(), 4);
+        assert_eq!(reverse_structure(&reversed), ss);
+    }
+
     #[test]
     fn test_simple_helix() {
         let pairs = parse_structure("<<<>>>").unwrap();
@@ -1,4 +1,4 @@
-//! SVG export for alignment visualization.
+//! SVG export for alignment visualization and structure arc diagrams.
 
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
@@ -8,10 +8,11 @@ use ratatui::style::Color;
 
 use crate::app::App;
 use crate::color::{
-    calculate_conservation, conservation_to_block, get_color, get_consensus_char_with_case,
-    pp_to_color,
+    HELIX_COLORS, calculate_conservation, conservation_to_block, get_color,
+    get_consensus_char_with_case, pp_to_color,
 };
 use crate::stockholm::short_id;
+use crate::structure::{StructureCache, is_valid_pair};
 
 /// Layout constants for the SVG grid.
 const CELL_WIDTH: f64 = 9.6;
@@ -20,7 +21,7 @@ const FONT_SIZE: f64 = 14.0;
 const FONT_FAMILY: &str = "monospace";
 
 /// Convert a ratatui Color to a CSS hex string.
-fn color_to_hex(color: Color) -> String {
+pub(crate) fn color_to_hex(color: Color) -> String {
     match color {
         Color::Rgb(r, g, b) => format!("#{r:02X}{g:02X}{b:02X}"),
         Color::Black => "#000000".to_string(),
@@ -44,7 +45,7 @@ fn color_to_hex(color: Color) -> String {
 }
 
 /// Escape special XML characters in text content.
-fn xml_escape(s: &str) -> String {
+pub(crate) fn xml_escape(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
@@ -68,7 +69,7 @@ fn write_svg<W: Write>(app: &App, w: &mut W) -> io::Result<()> {
     }
 
     // Determine columns to render
-    let cols: Vec<usize> = if app.hide_gap_columns && !app.visible_columns.is_empty() {
+    let cols: Vec<usize> = if app.columns_hidden() {
         app.visible_columns.clone()
     } else {
         (0..alignment.width()).collect()
@@ -215,6 +216,7 @@ fn write_svg<W: Write>(app: &App, w: &mut W) -> io::Result<()> {
     )?;
 
     // ─── Sequence IDs + Sequence grid ───
+    let reference_row = app.reference_row();
     for display_row in 0..num_display_rows {
         let actual_row = app.display_to_actual_row(display_row);
         let seq = &alignment.sequences[actual_row];
@@ -264,7 +266,7 @@ fn write_svg<W: Write>(app: &App, w: &mut W) -> io::Result<()> {
                 alignment,
                 &app.structure_cache,
                 &app.gap_chars,
-                app.reference_seq,
+                reference_row,
                 app.sequence_type,
                 app.terminal_theme,
             );
@@ -523,3 +525,115 @@ fn write_svg<W: Write>(app: &App, w: &mut W) -> io::Result<()> {
     writeln!(w, "</svg>")?;
     w.flush()
 }
+
+/// Horizontal spacing of positions in an arc diagram.
+const ARC_STEP: f64 = 12.0;
+/// Space around an arc diagram.
+const ARC_MARGIN: f64 = 20.0;
+
+/// Write an arc diagram of `structure` over `sequence` to `path`.
+///
+/// The sequence is drawn along a horizontal axis with a tick and number every
+/// 10 positions. Watson-Crick pairs arc above the axis and all other pairs
+/// (G-U wobble, mismatches, gaps) below, each colored by helix.
+pub fn write_arc_diagram(structure: &str, sequence: &str, path: &Path) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut w = BufWriter::new(file);
+    write_arcs(structure, sequence, &mut w)
+}
+
+fn write_arcs<W: Write>(structure: &str, sequence: &str, w: &mut W) -> io::Result<()> {
+    let mut cache = StructureCache::new();
+    cache
+        .update(structure)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let residues: Vec<char> = sequence.chars().collect();
+    let len = residues.len().max(structure.chars().count());
+
+    let is_watson_crick = |left: usize, right: usize| {
+        let a = residues.get(left).map_or(' ', char::to_ascii_uppercase);
+        let b = residues.get(right).map_or(' ', char::to_ascii_uppercase);
+        is_valid_pair(a, b) && !matches!((a, b), ('G', 'U' | 'T') | ('U' | 'T', 'G'))
+    };
+    let radius = |left: usize, right: usize| (right - left) as f64 * ARC_STEP / 2.0;
+    let (above, below): (Vec<_>, Vec<_>) = cache
+        .pairs()
+        .iter()
+        .copied()
+        .partition(|pair| is_watson_crick(pair.left, pair.right));
+    let max_above = above
+        .iter()
+        .map(|pair| radius(pair.left, pair.right))
+        .fold(0.0, f64::max);
+    let max_below = below
+        .iter()
+        .map(|pair| radius(pair.left, pair.right))
+        .fold(0.0, f64::max);
+
+    // Axis with letters below it, then tick labels; wobble arcs hang below those
+    let axis_y = ARC_MARGIN + max_above;
+    let letters_y = axis_y + CELL_HEIGHT / 2.0 + 2.0;
+    let ticks_y = letters_y + CELL_HEIGHT;
+    let lower_axis_y = ticks_y + CELL_HEIGHT / 2.0;
+    let width = 2.0 * ARC_MARGIN + len as f64 * ARC_STEP;
+    let height = lower_axis_y + max_below + ARC_MARGIN;
+    let x = |pos: usize| ARC_MARGIN + (pos as f64 + 0.5) * ARC_STEP;
+
+    writeln!(w, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(
+        w,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+    )?;
+    writeln!(
+        w,
+        "<rect width=\"{width}\" height=\"{height}\" fill=\"#FFFFFF\"/>"
+    )?;
+    writeln!(
+        w,
+        "<style>text {{ font-family: {FONT_FAMILY}; font-size: {FONT_SIZE}px; dominant-baseline: central; text-anchor: middle; }}</style>"
+    )?;
+
+    // Arcs: sweep flag 1 bends a left-to-right arc upwards, 0 downwards
+    for (pairs, y, sweep) in [(&above, axis_y, 1), (&below, lower_axis_y, 0)] {
+        for pair in pairs.iter() {
+            let r = radius(pair.left, pair.right);
+            let color = color_to_hex(HELIX_COLORS[pair.helix_id % HELIX_COLORS.len()]);
+            writeln!(
+                w,
+                "<path d=\"M {} {y} A {r} {r} 0 0 {sweep} {} {y}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"1.5\"/>",
+                x(pair.left),
+                x(pair.right)
+            )?;
+        }
+    }
+
+    // Axis, letters and ticks every 10 positions
+    writeln!(
+        w,
+        "<line x1=\"{ARC_MARGIN}\" y1=\"{axis_y}\" x2=\"{}\" y2=\"{axis_y}\" stroke=\"#000000\"/>",
+        width - ARC_MARGIN
+    )?;
+    for (pos, &ch) in residues.iter().enumerate() {
+        writeln!(
+            w,
+            "<text x=\"{}\" y=\"{letters_y}\" fill=\"#000000\">{}</text>",
+            x(pos),
+            xml_escape(&ch.to_string())
+        )?;
+    }
+    for pos in (10..=len).step_by(10) {
+        let tick_x = x(pos - 1);
+        writeln!(
+            w,
+            "<line x1=\"{tick_x}\" y1=\"{axis_y}\" x2=\"{tick_x}\" y2=\"{}\" stroke=\"#000000\"/>",
+            axis_y - 4.0
+        )?;
+        writeln!(
+            w,
+            "<text x=\"{tick_x}\" y=\"{ticks_y}\" fill=\"#808080\">{pos}</text>"
+        )?;
+    }
+
+    writeln!(w, "</svg>")?;
+    w.flush()
+}
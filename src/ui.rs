@@ -12,8 +12,20 @@ use ratatui::{
     },
 };
 
-use crate::app::{ActivePane, App, ColorScheme, Mode, SplitMode, TerminalTheme};
-use crate::color::{Rgb, get_color};
+use crate::app::{
+    ActivePane, AlignmentStats, AnnotationKind, App, BasePairStats, ColorScheme,
+    CompensatoryRecord, CovariationReport, DiagnosticMessage, GapStats, GsTable, IdentityMatrix,
+    MemoryStats, Mode, PidHistogram, Report, SequenceGrid, SplitMode, TerminalTheme,
+    sequence_strand,
+};
+use crate::color::{
+    COMP_DOUBLE_COMPATIBLE, COMP_DOUBLE_INCOMPATIBLE, COMP_GAP, COMP_SINGLE_COMPATIBLE,
+    COMP_SINGLE_INCOMPATIBLE, Rgb, differs_from_consensus, get_base_color, get_clade_color,
+    get_color, get_consensus_char, get_consensus_char_with_case, helix_covariation_color,
+    pinned_comparison_color, struct_diff_color,
+};
+use crate::editor::UndoTreeRow;
+use crate::structure::CompensatoryChange;
 
 /// Render the application UI.
 pub fn render(frame: &mut Frame, app: &App) {
@@ -102,6 +114,9 @@ pub fn render(frame: &mut Frame, app: &App) {
     render_status_bar(frame, app, chunks[1]);
     render_command_line(frame, app, chunks[2]);
 
+    // Full ID and #=GS annotations of the sequence whose ID is under the mouse
+    render_id_popup(frame, app);
+
     // Render help overlay if active
     if app.show_help {
         render_help(frame, app);
@@ -112,12 +127,79 @@ pub fn render(frame: &mut Frame, app: &App) {
         render_info(frame, app);
     }
 
+    // Render column profile popup if active
+    if app.show_column_profile {
+        render_column_profile_popup(frame, app);
+    }
+
+    // Render report popup if active
+    if let Some(report) = &app.report {
+        render_report(frame, app, report);
+    }
+
+    // Render command history popup if active
+    if app.show_history_popup {
+        render_command_history_popup(frame, app);
+    }
+
     // Render MSA selection overlay if active
     if app.show_msa_picker {
         render_msa_picker(frame, app);
     }
 }
 
+/// Popup beside the mouse with the full ID and `#=GS` annotations of the
+/// sequence whose ID is hovered, so IDs cut short by `:compact` stay readable.
+fn render_id_popup(frame: &mut Frame, app: &App) {
+    let (Some(row), Some((mouse_x, mouse_y))) = (app.mouse_id_row(), app.mouse_pos) else {
+        return;
+    };
+    let Some(seq) = app.alignment.sequences.get(app.display_to_actual_row(row)) else {
+        return;
+    };
+
+    let mut lines = vec![Line::from(Span::styled(
+        seq.id.clone(),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    for ann in app
+        .alignment
+        .sequence_annotations
+        .get(&seq.id)
+        .into_iter()
+        .flatten()
+    {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} ", ann.tag), Style::default().fg(Color::Yellow)),
+            Span::raw(ann.value.clone()),
+        ]));
+    }
+
+    // Just below and right of the pointer, kept on screen
+    let area = frame.area();
+    let content_width = lines.iter().map(Line::width).max().unwrap_or(0);
+    let width = u16::try_from(content_width + 2)
+        .unwrap_or(u16::MAX)
+        .min(area.width);
+    let height = u16::try_from(lines.len() + 2)
+        .unwrap_or(u16::MAX)
+        .min(area.height);
+    let x = (mouse_x + 1).min(area.width - width);
+    let y = if mouse_y + 1 + height <= area.height {
+        mouse_y + 1
+    } else {
+        mouse_y.saturating_sub(height)
+    };
+    let popup_area = Rect::new(x, y, width, height);
+
+    frame.render_widget(Clear, popup_area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
 /// Render the MSA (multiple-alignment) selection overlay.
 fn render_msa_picker(frame: &mut Frame, app: &App) {
     // Build one list item per alignment. The selection marker and highlight are
@@ -211,9 +293,28 @@ struct IdFormatter {
     id_width: usize,
     show_row_numbers: bool,
     show_short_ids: bool,
+    /// IDs longer than `id_width` are cut with `…` (`:compact`).
+    truncate: bool,
+    /// IDs longer than `id_width` continue on a second row (`:wrapids`).
+    wrap: bool,
+    /// Strand prefix before each ID (`:strand-display`).
+    show_strand: bool,
     collapse_width: usize,
 }
 
+/// Width of the `[+] ` strand prefix.
+const STRAND_PREFIX_WIDTH: usize = 4;
+
+/// Cut `id` to `width` characters, ending in `…` when anything was dropped.
+fn truncate_id(id: &str, width: usize) -> std::borrow::Cow<'_, str> {
+    if id.chars().count() <= width {
+        return id.into();
+    }
+    let mut cut: String = id.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut.into()
+}
+
 /// Format an annotation bar label with consistent styling.
 fn format_annotation_label(
     name: &str,
@@ -221,12 +322,17 @@ fn format_annotation_label(
     fg: Color,
     bg: Color,
 ) -> Line<'static> {
+    let name = if id_formatter.truncate {
+        truncate_id(name, id_formatter.id_width)
+    } else {
+        name.into()
+    };
     let label = format!(
         "{:>row_w$} {:id_w$}",
         "═",
         name,
         row_w = id_formatter.row_width,
-        id_w = id_formatter.id_width
+        id_w = id_formatter.strand_width() + id_formatter.id_width
     );
     Line::from(Span::styled(label, Style::reset().fg(fg).bg(bg)))
 }
@@ -238,6 +344,8 @@ impl IdFormatter {
         show_row_numbers: bool,
         max_collapse_count: usize,
         show_short_ids: bool,
+        compact_width: Option<usize>,
+        wrap: bool,
     ) -> Self {
         // Width for collapse count suffix: " (N)" where N is the max count
         let collapse_width = if max_collapse_count > 1 {
@@ -253,13 +361,40 @@ impl IdFormatter {
             } else {
                 0
             },
-            id_width: max_id_len,
+            id_width: compact_width.map_or(max_id_len, |width| max_id_len.min(width)),
             show_row_numbers,
             show_short_ids,
+            truncate: compact_width.is_some(),
+            wrap,
+            show_strand: false,
             collapse_width,
         }
     }
 
+    /// Reserve room for a strand prefix before each ID.
+    fn with_strand(mut self, show_strand: bool) -> Self {
+        self.show_strand = show_strand;
+        self
+    }
+
+    /// Width of the strand prefix (0 when not shown).
+    fn strand_width(&self) -> usize {
+        if self.show_strand {
+            STRAND_PREFIX_WIDTH
+        } else {
+            0
+        }
+    }
+
+    /// The displayed form of `id`: its short form with `:shortids`.
+    fn display_id<'a>(&self, id: &'a str) -> &'a str {
+        if self.show_short_ids {
+            crate::stockholm::short_id(id)
+        } else {
+            id
+        }
+    }
+
     /// Total width of the formatted ID column.
     fn width(&self) -> usize {
         let base = if self.show_row_numbers {
@@ -269,28 +404,61 @@ impl IdFormatter {
             // Format: "id " with trailing space
             self.id_width + 1
         };
-        base + self.collapse_width
+        base + self.strand_width() + self.collapse_width
     }
 
-    /// Format a row number and ID.
-    fn format(&self, row: usize, id: &str) -> String {
-        use crate::stockholm::short_id;
-        let display_id = if self.show_short_ids {
-            short_id(id)
+    /// Format a row number, strand prefix and ID.
+    fn format(&self, row: usize, id: &str, strand: Option<char>) -> String {
+        let display_id = self.display_id(id);
+        let display_id = if self.wrap {
+            // The rest goes on the continuation row
+            display_id
+                .chars()
+                .take(self.id_width)
+                .collect::<String>()
+                .into()
+        } else if self.truncate {
+            truncate_id(display_id, self.id_width)
         } else {
-            id
+            display_id.into()
+        };
+        let strand = match (self.show_strand, strand) {
+            (false, _) => String::new(),
+            (true, Some(strand)) => format!("[{strand}] "),
+            (true, None) => " ".repeat(STRAND_PREFIX_WIDTH),
         };
         if self.show_row_numbers {
             format!(
-                "{:>row_w$} {:id_w$} ",
+                "{:>row_w$} {strand}{:id_w$} ",
                 row + 1,
                 display_id,
                 row_w = self.row_width,
                 id_w = self.id_width
             )
         } else {
-            format!("{:id_w$} ", display_id, id_w = self.id_width)
+            format!("{strand}{:id_w$} ", display_id, id_w = self.id_width)
+        }
+    }
+
+    /// Second row of a wrapped ID: the characters past `id_width` after `↳`,
+    /// or `None` when wrapping is off or the ID fits.
+    fn continuation(&self, id: &str) -> Option<String> {
+        let rest: String = self.display_id(id).chars().skip(self.id_width).collect();
+        if !self.wrap || rest.is_empty() {
+            return None;
         }
+        let rest = truncate_id(&format!("↳{rest}"), self.id_width).into_owned();
+        let row_pad = if self.show_row_numbers {
+            self.row_width + 1
+        } else {
+            0
+        } + self.strand_width();
+        Some(format!(
+            "{:row_pad$}{:id_w$} ",
+            "",
+            rest,
+            id_w = self.id_width
+        ))
     }
 }
 
@@ -329,6 +497,7 @@ fn render_alignment_pane(
     );
 
     let title = match pane_label {
+        Some(_) if app.compare_mode => format!("{} [COMPARE]", file_info),
         Some(label) => format!("{} [{}]", file_info, label),
         None => file_info,
     };
@@ -360,8 +529,11 @@ fn render_alignment_pane(
     } else {
         alignment.max_id_len().max(10)
     };
-    // For secondary pane with its own alignment, collapse count is always 1
-    let max_collapse = if is_secondary && app.secondary_alignment.is_some() {
+    // For secondary pane with its own alignment, collapse count is always 1.
+    // When the tree shows group sizes, IDs carry no count suffix.
+    let max_collapse = if (is_secondary && app.secondary_alignment.is_some())
+        || (!is_secondary && app.tree_shows_group_counts())
+    {
         1
     } else {
         app.max_collapse_count()
@@ -372,13 +544,16 @@ fn render_alignment_pane(
         app.show_row_numbers,
         max_collapse,
         app.show_short_ids,
-    );
+        app.id_width_cap(),
+        app.wrap_ids && !is_secondary,
+    )
+    .with_strand(app.show_strand);
     let id_width = id_formatter.width();
 
     // Account for tree width if showing (separator + tree column)
     // Only show tree for primary pane
-    let tree_display_width = if !is_secondary && app.show_tree && app.cluster_tree.is_some() {
-        app.tree_width + 1
+    let tree_display_width = if !is_secondary {
+        app.tree_display_width()
     } else {
         0
     };
@@ -388,41 +563,49 @@ fn render_alignment_pane(
     let available_width = (inner.width as usize)
         .saturating_sub(id_width + 1) // +1 for separator after IDs
         .saturating_sub(tree_display_width);
-    let seq_width = alignment_width.min(available_width);
+    let stride = if is_secondary {
+        1
+    } else {
+        app.column_stride.max(1)
+    };
+    let seq_width = alignment_width.div_ceil(stride).min(available_width);
 
     // Vertical layout dimensions
-    let ruler_height = if app.show_ruler { RULER_HEIGHT } else { 0 };
-    let has_ss_cons = alignment.ss_cons().is_some();
-    let ss_cons_height: u16 = if has_ss_cons { 1 } else { 0 };
-    let has_rf = alignment.rf().is_some();
-    let rf_height: u16 = if app.show_rf_bar && has_rf { 1 } else { 0 };
-    let has_pp_cons = alignment.pp_cons().is_some();
-    let pp_cons_height: u16 = if app.show_pp_cons && has_pp_cons {
-        1
+    let ruler_height = ruler_height(app);
+    let annotations = app.visible_annotations(alignment);
+    // One row per annotation bar
+    let annotation_height = annotations.len() as u16;
+
+    // Sticky header (`:pin-header`) on the primary pane only
+    let header_row = if is_secondary {
+        None
     } else {
-        0
+        app.pinned_header_display_row()
     };
-    let consensus_height: u16 = if app.show_consensus { 1 } else { 0 };
-    let conservation_height: u16 = if app.show_conservation_bar { 1 } else { 0 };
+    let header_height = header_rows_height(header_row);
 
-    // Calculate visible rows (inner height minus ruler and annotation bars)
+    // Calculate visible rows (inner height minus ruler, header and annotation bars)
+    // A `:show-gr` track or wrapped IDs take a second screen row per sequence
+    let rows_per_seq = if is_secondary {
+        1
+    } else {
+        app.rows_per_sequence()
+    };
     let visible_rows = (inner.height as usize)
         .saturating_sub(ruler_height as usize)
-        .saturating_sub(ss_cons_height as usize)
-        .saturating_sub(rf_height as usize)
-        .saturating_sub(pp_cons_height as usize)
-        .saturating_sub(consensus_height as usize)
-        .saturating_sub(conservation_height as usize);
+        .saturating_sub(header_height as usize)
+        .saturating_sub(annotation_height as usize)
+        / rows_per_seq;
 
     // === Split horizontally: IDs | Alignment | Tree | Filler ===
     let h_constraints = if tree_display_width > 0 {
         vec![
-            Constraint::Length(id_width as u16),       // IDs column
-            Constraint::Length(1),                     // Separator
-            Constraint::Length(seq_width as u16),      // Alignment column (capped)
-            Constraint::Length(1),                     // Separator
-            Constraint::Length(app.tree_width as u16), // Tree column
-            Constraint::Min(0),                        // Filler (absorbs extra space)
+            Constraint::Length(id_width as u16),  // IDs column
+            Constraint::Length(1),                // Separator
+            Constraint::Length(seq_width as u16), // Alignment column (capped)
+            Constraint::Length(1),                // Separator
+            Constraint::Length(u16::try_from(tree_display_width - 1).unwrap_or(u16::MAX)), // Tree column
+            Constraint::Min(0), // Filler (absorbs extra space)
         ]
     } else {
         vec![
@@ -446,17 +629,14 @@ fn render_alignment_pane(
         None
     };
 
-    // Total annotation bar height
-    let annotation_height =
-        ss_cons_height + rf_height + pp_cons_height + consensus_height + conservation_height;
-
     // Calculate actual sequence rows to display (may be less than visible_rows)
     let visible_seq_count = if is_secondary && app.secondary_alignment.is_some() {
         alignment.num_sequences()
     } else {
         app.visible_sequence_count()
     };
-    let actual_seq_rows = (visible_seq_count.saturating_sub(viewport_row)).min(visible_rows) as u16;
+    let actual_seq_rows =
+        ((visible_seq_count.saturating_sub(viewport_row)).min(visible_rows) * rows_per_seq) as u16;
 
     // === Render IDs column (with vertical alignment to match sequences) ===
     render_ids_column(
@@ -468,18 +648,21 @@ fn render_alignment_pane(
         visible_rows,
         &id_formatter,
         ruler_height,
-        annotation_height,
-        actual_seq_rows,
+        &annotations,
+        header_row,
+        actual_seq_rows + header_height,
         is_secondary,
     );
 
     // === Render separator line ===
     render_separator(
         frame,
+        app,
         h_chunks[1],
         ruler_height,
         annotation_height,
-        actual_seq_rows,
+        actual_seq_rows + header_height,
+        is_active,
     );
 
     // === Render alignment column (with ruler above, annotation bars below) ===
@@ -493,32 +676,35 @@ fn render_alignment_pane(
         visible_rows,
         seq_width,
         ruler_height,
-        ss_cons_height,
-        rf_height,
-        pp_cons_height,
-        consensus_height,
-        conservation_height,
+        &annotations,
+        header_row,
         is_active,
         is_secondary,
     );
+    if !is_secondary && let Some(grid) = app.sequence_grid.borrow_mut().as_mut() {
+        grid.id_x = ids_area.x;
+    }
 
     // === Render tree column if present ===
     if let Some(tree_rect) = tree_area {
         // Render separator before tree
         render_separator(
             frame,
+            app,
             h_chunks[3],
             ruler_height,
             annotation_height,
-            actual_seq_rows,
+            actual_seq_rows + header_height,
+            is_active,
         );
+        // The tree is left blank beside the pinned header
         render_tree_column(
             frame,
             app,
             tree_rect,
             viewport_row,
             visible_rows,
-            ruler_height,
+            ruler_height + header_height,
             annotation_height,
             actual_seq_rows,
         );
@@ -538,7 +724,7 @@ fn render_alignment_pane(
     }
 
     // Horizontal scrollbar (bottom border) when the alignment is wider than fits.
-    if alignment_width > seq_width {
+    if alignment_width.div_ceil(stride) > seq_width {
         let mut hscroll_state = ScrollbarState::new(alignment_width).position(viewport_col);
         frame.render_stateful_widget(
             Scrollbar::new(ScrollbarOrientation::HorizontalBottom)
@@ -561,10 +747,13 @@ fn render_ids_column(
     visible_rows: usize,
     id_formatter: &IdFormatter,
     ruler_height: u16,
-    annotation_height: u16,
+    annotations: &[AnnotationKind],
+    header_row: Option<usize>,
     actual_seq_rows: u16,
     is_secondary: bool,
 ) {
+    let annotation_height = annotations.len() as u16;
+
     // Split to match alignment layout (blank space for ruler/annotation bars)
     let v_chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -580,9 +769,6 @@ fn render_ids_column(
     let ids_annotation_area = v_chunks[2];
 
     // Render sequence IDs (with collapse count if enabled)
-    // Get selection bounds for row highlighting in visual mode
-    let selection_bounds = app.get_selection_bounds();
-
     // For secondary pane with its own alignment, use simple row indexing
     let visible_seq_count = if is_secondary && app.secondary_alignment.is_some() {
         alignment.num_sequences()
@@ -591,7 +777,27 @@ fn render_ids_column(
     };
 
     let mut lines = Vec::new();
-    for display_row in viewport_row..(viewport_row + visible_rows).min(visible_seq_count) {
+    // The pinned header row (if any) comes first, above the scrolling rows
+    let rows = header_row
+        .into_iter()
+        .chain(viewport_row..(viewport_row + visible_rows).min(visible_seq_count));
+    // `:show-gr` track label under each scrolling row (primary pane only)
+    let gr_label = app
+        .show_gr_tag
+        .as_deref()
+        .filter(|_| !is_secondary)
+        .map(|tag| {
+            let colors = &app.theme.annotations;
+            format_annotation_label(
+                &format!("#=GR {tag}"),
+                id_formatter,
+                colors.label_ss_cons_fg.to_color(),
+                colors.ss_cons_bg.to_color(),
+            )
+        });
+    let pinned_comparison_row = app.pinned_comparison_row();
+    let reference_row = app.reference_row();
+    for (i, display_row) in rows.enumerate() {
         // For secondary pane with its own alignment, display_row == actual_row
         let actual_row = if is_secondary && app.secondary_alignment.is_some() {
             display_row
@@ -599,12 +805,17 @@ fn render_ids_column(
             app.display_to_actual_row(display_row)
         };
         let seq = &alignment.sequences[actual_row];
+        let track_label = gr_label.as_ref().map(|label| {
+            if app.gr_track(&seq.id).is_some() {
+                label.clone()
+            } else {
+                Line::default()
+            }
+        });
 
         // Check if this row is in the visual selection (only for active pane)
         let is_row_selected = if !is_secondary || app.secondary_alignment.is_none() {
-            selection_bounds
-                .map(|(min_row, _, max_row, _)| display_row >= min_row && display_row <= max_row)
-                .unwrap_or(false)
+            app.is_row_selected(display_row)
         } else {
             false
         };
@@ -614,77 +825,104 @@ fn render_ids_column(
             Style::reset()
                 .bg(app.theme.id_column.selected_bg.to_color())
                 .fg(app.theme.id_column.selected_fg.to_color())
+        } else if (!is_secondary || app.secondary_alignment.is_none())
+            && pinned_comparison_row == Some(actual_row)
+        {
+            // Pinned comparison sequence
+            Style::reset()
+                .bg(app.theme.selection.pair_highlight_bg.to_color())
+                .fg(app.theme.selection.pair_highlight_fg.to_color())
         } else if display_row == app.cursor_row {
             Style::reset().add_modifier(Modifier::BOLD)
         } else {
             Style::reset().fg(app.theme.id_column.text.to_color())
         };
+        // Mark the reference sequence for compensatory coloring
+        let id_style = if (!is_secondary || app.secondary_alignment.is_none())
+            && app.color_scheme == ColorScheme::Compensatory
+            && actual_row == reference_row
+        {
+            id_style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else {
+            id_style
+        };
+        // Clade background, unless a selection or pin already colors the ID
+        let id_style = if app.color_scheme == ColorScheme::Clade
+            && !is_row_selected
+            && pinned_comparison_row != Some(actual_row)
+            && let Some(color) = get_clade_color(&seq.id, alignment)
+        {
+            id_style.bg(color).fg(Color::Black)
+        } else {
+            id_style
+        };
 
         // Show collapse count if enabled and group has more than 1 member
         // (collapse only applies to primary pane)
-        let collapse_count = if is_secondary && app.secondary_alignment.is_some() {
+        let collapse_count = if (is_secondary && app.secondary_alignment.is_some())
+            || (!is_secondary && app.tree_shows_group_counts())
+        {
             1
         } else {
             app.get_collapse_count(display_row)
         };
+        let strand = sequence_strand(alignment, &seq.id);
         let id_display = if collapse_count > 1 {
             format!(
                 "{} ({})",
-                id_formatter.format(display_row, &seq.id),
+                id_formatter.format(display_row, &seq.id, strand),
                 collapse_count
             )
         } else {
-            id_formatter.format(display_row, &seq.id)
+            id_formatter.format(display_row, &seq.id, strand)
         };
         lines.push(Line::from(Span::styled(id_display, id_style)));
+
+        // Second row: a wrapped ID's remainder, else the `:show-gr` label
+        // (the pinned header row has neither)
+        if !is_secondary && app.rows_per_sequence() > 1 && (header_row.is_none() || i > 0) {
+            let continuation = id_formatter
+                .continuation(&seq.id)
+                .map(|rest| Line::from(Span::styled(rest, id_style)));
+            lines.push(continuation.or(track_label).unwrap_or_default());
+        }
+    }
+    if header_row.is_some() && !lines.is_empty() {
+        lines.insert(1, header_separator_line(id_formatter.width()));
     }
 
     let paragraph = Paragraph::new(lines);
     frame.render_widget(paragraph, ids_seq_area);
 
-    // Render annotation labels using helper
-    let mut annotation_lines = Vec::new();
-
-    if alignment.ss_cons().is_some() {
-        annotation_lines.push(format_annotation_label(
-            "#=GC SS_cons",
-            id_formatter,
-            app.theme.annotations.label_ss_cons_fg.to_color(),
-            app.theme.annotations.ss_cons_bg.to_color(),
-        ));
-    }
-    if app.show_rf_bar && alignment.rf().is_some() {
-        annotation_lines.push(format_annotation_label(
-            "#=GC RF",
-            id_formatter,
-            app.theme.annotations.label_rf_fg.to_color(),
-            app.theme.annotations.rf_conserved_bg.to_color(),
-        ));
-    }
-    if app.show_pp_cons && alignment.pp_cons().is_some() {
-        annotation_lines.push(format_annotation_label(
-            "#=GC PP_cons",
-            id_formatter,
-            app.theme.annotations.label_pp_cons_fg.to_color(),
-            app.theme.annotations.pp_cons_bg.to_color(),
-        ));
-    }
-    if app.show_consensus {
-        annotation_lines.push(format_annotation_label(
-            "Consensus",
-            id_formatter,
-            app.theme.annotations.label_consensus_fg.to_color(),
-            app.theme.annotations.consensus_bg.to_color(),
-        ));
-    }
-    if app.show_conservation_bar {
-        annotation_lines.push(format_annotation_label(
-            "Conservation",
-            id_formatter,
-            app.theme.annotations.label_conservation_fg.to_color(),
-            app.theme.annotations.conservation_bg.to_color(),
-        ));
-    }
+    // Render annotation labels using helper, in the configured bar order
+    let colors = &app.theme.annotations;
+    let annotation_lines: Vec<Line> = annotations
+        .iter()
+        .map(|kind| {
+            let (label, fg, bg) = match kind {
+                AnnotationKind::SsCons => {
+                    ("#=GC SS_cons", colors.label_ss_cons_fg, colors.ss_cons_bg)
+                }
+                AnnotationKind::Rf => ("#=GC RF", colors.label_rf_fg, colors.rf_conserved_bg),
+                AnnotationKind::PpCons => {
+                    ("#=GC PP_cons", colors.label_pp_cons_fg, colors.pp_cons_bg)
+                }
+                AnnotationKind::Consensus => {
+                    ("Consensus", colors.label_consensus_fg, colors.consensus_bg)
+                }
+                AnnotationKind::Conservation => (
+                    "Conservation",
+                    colors.label_conservation_fg,
+                    colors.conservation_bg,
+                ),
+                AnnotationKind::Helix => ("#=GC HELIX", colors.label_ss_cons_fg, colors.ss_cons_bg),
+                AnnotationKind::Anchors => {
+                    ("#=GC ANCHORS", colors.label_ss_cons_fg, colors.ss_cons_bg)
+                }
+            };
+            format_annotation_label(label, id_formatter, fg.to_color(), bg.to_color())
+        })
+        .collect();
 
     if !annotation_lines.is_empty() {
         let label_para = Paragraph::new(annotation_lines);
@@ -692,38 +930,68 @@ fn render_ids_column(
     }
 }
 
+/// Rows taken by a pinned header: the header row and its separator.
+fn header_rows_height(header_row: Option<usize>) -> u16 {
+    if header_row.is_some() { 2 } else { 0 }
+}
+
+/// Horizontal rule between the pinned header row and the scrolling rows.
+fn header_separator_line(width: usize) -> Line<'static> {
+    Line::from(Span::styled(
+        "─".repeat(width),
+        Style::reset().fg(Color::DarkGray),
+    ))
+}
+
 /// Render a vertical separator line.
 fn render_separator(
     frame: &mut Frame,
+    app: &App,
     area: Rect,
     ruler_height: u16,
     annotation_height: u16,
     actual_seq_rows: u16,
+    is_active: bool,
 ) {
+    let misc = &app.theme.misc;
+    let plain = Style::reset().fg(misc.separator.to_color());
+    let section = Style::reset().fg(misc.separator_section.to_color());
+    let active = Style::reset().fg(misc.separator_active.to_color());
+
+    // Glyph for row `i` of a section `len` rows tall: (top, middle, bottom)
+    let pick = |i: u16, len: u16, (top, middle, bottom): (char, char, char)| {
+        if i == 0 {
+            top
+        } else if i + 1 == len {
+            bottom
+        } else {
+            middle
+        }
+    };
+
     let mut lines = Vec::new();
 
-    // Blank space for ruler area
+    // Ruler rows
     for _ in 0..ruler_height {
-        lines.push(Line::from(Span::styled(
-            "│",
-            Style::reset().fg(Color::DarkGray),
-        )));
+        lines.push(Line::from(Span::styled("│", plain)));
     }
 
-    // Separator for sequence rows
-    for _ in 0..actual_seq_rows {
-        lines.push(Line::from(Span::styled(
-            "│",
-            Style::reset().fg(Color::DarkGray),
-        )));
+    // Sequence rows: double line framing the active pane of a split view,
+    // else section ticks
+    let (seq_glyphs, seq_style) = if is_active && app.split_mode.is_some() {
+        (('╔', '║', '╚'), active)
+    } else {
+        (('┬', '│', '┴'), section)
+    };
+    for i in 0..actual_seq_rows {
+        let glyph = pick(i, actual_seq_rows, seq_glyphs);
+        lines.push(Line::from(Span::styled(glyph.to_string(), seq_style)));
     }
 
-    // Separator for annotation bars
-    for _ in 0..annotation_height {
-        lines.push(Line::from(Span::styled(
-            "│",
-            Style::reset().fg(Color::DarkGray),
-        )));
+    // Annotation bar rows
+    for i in 0..annotation_height {
+        let glyph = pick(i, annotation_height, ('╡', '║', '╞'));
+        lines.push(Line::from(Span::styled(glyph.to_string(), section)));
     }
 
     let paragraph = Paragraph::new(lines);
@@ -742,17 +1010,13 @@ fn render_alignment_column(
     visible_rows: usize,
     seq_width: usize,
     ruler_height: u16,
-    ss_cons_height: u16,
-    rf_height: u16,
-    pp_cons_height: u16,
-    consensus_height: u16,
-    conservation_height: u16,
+    annotations: &[AnnotationKind],
+    header_row: Option<usize>,
     is_active: bool,
     is_secondary: bool,
 ) {
-    // Total annotation bar height
-    let annotation_height =
-        ss_cons_height + rf_height + pp_cons_height + consensus_height + conservation_height;
+    let annotation_height = annotations.len() as u16;
+    let header_height = header_rows_height(header_row);
 
     // Calculate actual sequence rows to display (may be less than visible_rows)
     let visible_seq_count = if is_secondary && app.secondary_alignment.is_some() {
@@ -760,7 +1024,15 @@ fn render_alignment_column(
     } else {
         app.visible_sequence_count()
     };
-    let actual_seq_rows = (visible_seq_count.saturating_sub(viewport_row)).min(visible_rows);
+    // A `:show-gr` track or wrapped IDs take a second screen row per sequence
+    let show_tracks = !is_secondary && app.show_gr_tag.is_some();
+    let rows_per_seq = if is_secondary {
+        1
+    } else {
+        app.rows_per_sequence()
+    };
+    let actual_seq_rows =
+        (visible_seq_count.saturating_sub(viewport_row)).min(visible_rows) * rows_per_seq;
 
     // Split alignment area vertically: ruler | sequences | annotations | filler
     // Use Length for sequences so annotations follow immediately after
@@ -768,7 +1040,7 @@ fn render_alignment_column(
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(ruler_height),
-            Constraint::Length(actual_seq_rows as u16),
+            Constraint::Length(actual_seq_rows as u16 + header_height),
             Constraint::Length(annotation_height),
             Constraint::Min(0), // Filler takes remaining space
         ])
@@ -778,68 +1050,171 @@ fn render_alignment_column(
     let seq_area = v_chunks[1];
     let annotation_area = v_chunks[2];
 
-    // Further split annotation area
+    // Further split annotation area, one row per bar
     let annotation_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(ss_cons_height),
-            Constraint::Length(rf_height),
-            Constraint::Length(pp_cons_height),
-            Constraint::Length(consensus_height),
-            Constraint::Length(conservation_height),
-        ])
+        .constraints(vec![Constraint::Length(1); annotations.len()])
         .split(annotation_area);
 
-    let ss_cons_area = annotation_chunks[0];
-    let rf_area = annotation_chunks[1];
-    let pp_cons_area = annotation_chunks[2];
-    let consensus_area = annotation_chunks[3];
-    let conservation_area = annotation_chunks[4];
+    // Compute columns to render (handles hiding gap columns and zoom)
+    // For secondary pane, don't use hidden gap column logic or zoom
+    let stride = if is_secondary {
+        1
+    } else {
+        app.column_stride.max(1)
+    };
+    let cols_to_render: Vec<usize> = if is_secondary {
+        (viewport_col..alignment.width()).take(seq_width).collect()
+    } else {
+        app.screen_columns(viewport_col, seq_width)
+    };
+
+    // Remember where the primary pane's sequences are for the mouse readout
+    if !is_secondary {
+        *app.sequence_grid.borrow_mut() = Some(SequenceGrid {
+            // Set by render_alignment_pane, which lays out the IDs
+            id_x: 0,
+            x: seq_area.x,
+            y: seq_area.y + header_height,
+            first_row: viewport_row,
+            rows: actual_seq_rows / rows_per_seq,
+            rows_per_seq,
+            columns: cols_to_render.clone(),
+            header_row,
+        });
+    }
 
     // Render ruler (no ID padding - ruler is only over alignment)
     if app.show_ruler {
-        // Get cursor and paired column for base-pair display (only if this pane is active)
-        let (cursor_col, paired_col) = if is_active {
+        // Get cursor and its base-pair/coaxial partners (only if this pane is active)
+        let (cursor_col, connectors) = if is_active {
             let paired = app.structure_cache.get_pair(app.cursor_col);
-            (Some(app.cursor_col), paired)
+            let stacked = app.coaxial_partner(app.cursor_col);
+            let connectors: Vec<(usize, char)> = paired
+                .map(|col| (col, '─'))
+                .into_iter()
+                .chain(stacked.map(|col| (col, '═')))
+                .collect();
+            (Some(app.cursor_col), connectors)
         } else {
-            (None, None)
+            (None, Vec::new())
         };
         let ruler_colors = (
             app.theme.ruler.numbers,
             app.theme.ruler.ticks,
             app.theme.ruler.pair_line,
+            app.theme.ruler.anchor,
         );
-        let ruler_lines = render_ruler(
-            0,
-            seq_width,
-            viewport_col,
-            cursor_col,
-            paired_col,
-            ruler_colors,
-        );
+        // Anchor columns (`#=GC ANCHORS`) get their own tick color
+        let anchor_chars: Vec<char> = alignment
+            .anchors()
+            .map(|data| data.chars().collect())
+            .unwrap_or_default();
+        let anchors = |cols: &[usize]| -> Vec<bool> {
+            cols.iter()
+                .map(|&col| anchor_chars.get(col).is_some_and(|&c| c != '.'))
+                .collect()
+        };
+        let sliced = !is_secondary && app.column_slice.is_some();
+        // Ruler numbers: slice-relative or RF reference positions on the primary pane
+        let positions = |cols: &[usize]| -> Vec<Option<usize>> {
+            if is_secondary {
+                cols.iter().map(|&col| Some(col + 1)).collect()
+            } else {
+                app.ruler_positions(cols)
+            }
+        };
+        let (mut ruler_lines, ruler_cols) =
+            if stride > 1 || (sliced && (app.hide_gap_columns || app.column_filter.is_some())) {
+                let lines = render_sampled_ruler(
+                    &cols_to_render,
+                    &positions(&cols_to_render),
+                    &anchors(&cols_to_render),
+                    cursor_col,
+                    ruler_colors,
+                );
+                (lines, cols_to_render.clone())
+            } else {
+                // A slice alone keeps columns contiguous, so number them by actual column
+                let ruler_viewport = if sliced {
+                    cols_to_render.first().copied().unwrap_or(viewport_col)
+                } else {
+                    viewport_col
+                };
+                let ruler_cols: Vec<usize> = (ruler_viewport..ruler_viewport + seq_width).collect();
+                let lines = render_ruler(
+                    0,
+                    seq_width,
+                    ruler_viewport,
+                    cursor_col,
+                    RulerColumns {
+                        connectors: &connectors,
+                        positions: &positions(&ruler_cols),
+                        anchors: &anchors(&ruler_cols),
+                    },
+                    ruler_colors,
+                );
+                (lines, ruler_cols)
+            };
+        // Reference sequence coordinates (`:ref-ruler`), primary alignment only
+        if app.ref_ungapped_ruler {
+            let coords = if is_secondary && app.secondary_alignment.is_some() {
+                vec![None; ruler_cols.len()]
+            } else {
+                app.reference_coordinates(&ruler_cols)
+            };
+            ruler_lines.push(reference_ruler_line(&coords, app.theme.ruler.numbers));
+        }
         let ruler_paragraph = Paragraph::new(ruler_lines);
         frame.render_widget(ruler_paragraph, ruler_area);
     }
 
-    // Compute columns to render (handles hiding gap columns)
-    // For secondary pane, don't use hidden gap column logic
-    let cols_to_render: Vec<usize> =
-        if !is_secondary && app.hide_gap_columns && !app.visible_columns.is_empty() {
-            // viewport_col is in display column space when hiding
-            app.visible_columns
-                .iter()
-                .skip(viewport_col)
-                .take(seq_width)
-                .copied()
-                .collect()
-        } else {
-            (viewport_col..(viewport_col + seq_width).min(alignment.width())).collect()
-        };
+    // Variable columns (more than one distinct residue), computed once per column
+    let variable_cols: Vec<bool> = if app.highlight_variable_cols {
+        use crate::color::calculate_conservation;
+        cols_to_render
+            .iter()
+            .map(|&col| {
+                !alignment.is_empty_column(col, &app.gap_chars)
+                    && calculate_conservation(col, alignment, &app.gap_chars) < 1.0
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // Column consensus for :diffcons, computed once per column
+    let consensus_chars: Vec<char> = if app.highlight_consensus_diff {
+        cols_to_render
+            .iter()
+            .map(|&col| get_consensus_char(col, alignment, &app.gap_chars))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // RF characters for :rf-gaps, looked up per column
+    let rf_chars: Option<Vec<char>> = alignment
+        .rf()
+        .filter(|_| app.highlight_rf_insertions)
+        .map(|rf| rf.chars().collect());
+
+    // Helix covariation overlay (`H`) for the helix under the cursor
+    let helix_covariation = if is_secondary && app.secondary_alignment.is_some() {
+        None
+    } else {
+        app.cursor_helix_covariation()
+    };
 
     // Render sequences
     let mut lines = Vec::new();
-    for display_row in viewport_row..(viewport_row + visible_rows).min(visible_seq_count) {
+    // The pinned header row (if any) comes first, above the scrolling rows
+    let rows = header_row
+        .into_iter()
+        .chain(viewport_row..(viewport_row + visible_rows).min(visible_seq_count));
+    let pinned_comparison_row = app.pinned_comparison_row();
+    let reference_row = app.reference_row();
+    for (row_index, display_row) in rows.enumerate() {
         // For secondary pane with its own alignment, display_row == actual_row
         let actual_row = if is_secondary && app.secondary_alignment.is_some() {
             display_row
@@ -849,15 +1224,50 @@ fn render_alignment_column(
         let seq = &alignment.sequences[actual_row];
         let mut spans = Vec::new();
 
+        // Structure comparison overrides the color scheme for the compared row
+        let struct_diff = if is_secondary && app.secondary_alignment.is_some() {
+            None
+        } else {
+            app.structure_diff
+                .as_ref()
+                .filter(|_| seq.id == app.structure_diff_id)
+        };
+
+        // Pairwise comparison overrides the color scheme for every other row
+        let pinned_seq = if is_secondary && app.secondary_alignment.is_some() {
+            None
+        } else {
+            pinned_comparison_row
+                .filter(|&row| row != actual_row)
+                .and_then(|row| alignment.sequences.get(row))
+        };
+
         let seq_chars: Vec<char> = seq.chars().to_vec();
-        for &col in &cols_to_render {
+        let motif_colors = app.motif_highlight_colors(&seq_chars);
+        for (i, &col) in cols_to_render.iter().enumerate() {
             let ch = seq_chars.get(col).copied().unwrap_or(' ');
             let is_cursor = is_active && display_row == app.cursor_row && col == app.cursor_col;
 
             let mut style = Style::reset();
 
+            // Tint variable columns underneath any color scheme
+            if variable_cols.get(i).copied().unwrap_or(false) {
+                style = style.bg(app.theme.selection.variable_col_bg.to_color());
+            }
+
             // Apply color scheme
-            if let Some(color) = get_color(
+            if let Some(diff) = struct_diff {
+                if let Some(color) = diff.get(col).copied().and_then(struct_diff_color) {
+                    style = style.bg(color).fg(Color::Black);
+                }
+            } else if let Some(pinned) = pinned_seq {
+                let pinned_ch = pinned.get(col).unwrap_or(app.gap_char);
+                if let Some(color) =
+                    pinned_comparison_color(ch, pinned_ch, &app.gap_chars, app.sequence_type)
+                {
+                    style = style.bg(color).fg(Color::Black);
+                }
+            } else if let Some(color) = get_color(
                 app.color_scheme,
                 ch,
                 col,
@@ -865,18 +1275,76 @@ fn render_alignment_column(
                 alignment,
                 &app.structure_cache,
                 &app.gap_chars,
-                app.reference_seq,
+                reference_row,
                 app.sequence_type,
                 app.terminal_theme,
             ) {
                 style = style.bg(color).fg(Color::Black);
             }
+            if let Some(cov) = &helix_covariation
+                && struct_diff.is_none()
+                && pinned_seq.is_none()
+                && app.structure_cache.get_helix(col) == Some(cov.helix_id)
+                && let Some(score) = cov.scores.get(actual_row).copied().flatten()
+            {
+                style = style.bg(helix_covariation_color(score)).fg(Color::Black);
+            }
+            // Custom column colors replace the scheme color
+            if !(is_secondary && app.secondary_alignment.is_some())
+                && let Some(color) = app.column_color_overrides.get(&col)
+            {
+                style = style.bg(color.to_color()).fg(Color::Black);
+            }
+            // :shade regions tint whatever background the cell has so far
+            if !(is_secondary && app.secondary_alignment.is_some())
+                && let Some(shade) = app.shade_color(col)
+            {
+                let bg = match style.bg {
+                    Some(Color::Rgb(r, g, b)) => Rgb::new(r, g, b).blend(shade),
+                    _ => shade,
+                };
+                style = style.bg(bg.to_color()).fg(Color::Black);
+            }
+
+            // Underline the cursor sequence's residues that differ from the
+            // consensus (additive)
+            if is_active
+                && display_row == app.cursor_row
+                && consensus_chars
+                    .get(i)
+                    .is_some_and(|&cons| differs_from_consensus(ch, cons, &app.gap_chars))
+            {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
 
             // Highlight empty (all-gap) columns if enabled
             if app.highlight_gap_columns && alignment.is_empty_column(col, &app.gap_chars) {
                 style = style.bg(app.theme.selection.gap_column_bg.to_color());
             }
 
+            // Tint insertions relative to RF
+            if rf_chars
+                .as_ref()
+                .and_then(|rf| rf.get(col))
+                .is_some_and(|c| app.gap_chars.contains(c))
+            {
+                style = style.bg(app.theme.selection.rf_insertion_bg.to_color());
+            }
+
+            // Faint band down the cursor column, under any colored cell
+            if app.highlight_cursor_column
+                && is_active
+                && col == app.cursor_col
+                && matches!(style.bg, None | Some(Color::Reset))
+            {
+                style = style.bg(app.theme.selection.cursor_column_bg.to_color());
+            }
+
+            // Highlight :highlight motif matches
+            if let Some(color) = motif_colors.get(col).copied().flatten() {
+                style = style.bg(color.to_color()).fg(Color::Black);
+            }
+
             // Highlight search matches
             if let Some(is_current) = app.is_search_match(actual_row, col) {
                 if is_current {
@@ -917,106 +1385,176 @@ fn render_alignment_column(
         }
 
         lines.push(Line::from(spans));
+
+        // `:show-gr` track (or a blank row beside a wrapped ID) below the
+        // row; the pinned header row has none
+        if rows_per_seq > 1 && (header_row.is_none() || row_index > 0) {
+            lines.push(if show_tracks {
+                gr_track_line(app, app.gr_track(&seq.id), &cols_to_render, is_active)
+            } else {
+                Line::default()
+            });
+        }
+    }
+    if header_row.is_some() && !lines.is_empty() {
+        lines.insert(1, header_separator_line(cols_to_render.len()));
     }
 
     let paragraph = Paragraph::new(lines);
     frame.render_widget(paragraph, seq_area);
 
-    // Render SS_cons
-    if let Some(ss) = alignment.ss_cons() {
-        let mut spans = Vec::new();
-
-        let ss_chars: Vec<char> = ss.chars().collect();
-        for &col in &cols_to_render {
-            let ch = ss_chars.get(col).copied().unwrap_or(' ');
-            let is_cursor_col = is_active && col == app.cursor_col;
+    // Render annotation bars in the configured order
+    for (kind, &bar_area) in annotations.iter().zip(annotation_chunks.iter()) {
+        match kind {
+            AnnotationKind::SsCons => {
+                if let Some(ss) = alignment.ss_cons() {
+                    render_ss_cons_bar(
+                        frame,
+                        app,
+                        alignment,
+                        ss,
+                        bar_area,
+                        &cols_to_render,
+                        is_active,
+                    );
+                }
+            }
+            AnnotationKind::Rf => {
+                if let Some(rf) = alignment.rf() {
+                    render_rf_bar(
+                        frame,
+                        app,
+                        alignment,
+                        rf,
+                        bar_area,
+                        &cols_to_render,
+                        is_active,
+                        app.cursor_col,
+                    );
+                }
+            }
+            AnnotationKind::PpCons => {
+                if let Some(pp) = alignment.pp_cons() {
+                    render_pp_cons_bar(
+                        frame,
+                        app,
+                        alignment,
+                        pp,
+                        bar_area,
+                        &cols_to_render,
+                        is_active,
+                        app.cursor_col,
+                    );
+                }
+            }
+            AnnotationKind::Consensus => {
+                render_consensus_bar(frame, app, alignment, bar_area, &cols_to_render, is_active)
+            }
+            AnnotationKind::Conservation => {
+                render_conservation_bar(frame, app, alignment, bar_area, &cols_to_render, is_active)
+            }
+            AnnotationKind::Helix => {
+                if let Some(helix) = alignment.helix() {
+                    render_helix_bar(frame, app, helix, bar_area, &cols_to_render, is_active);
+                }
+            }
+            AnnotationKind::Anchors => {
+                if let Some(anchors) = alignment.anchors() {
+                    render_helix_bar(frame, app, anchors, bar_area, &cols_to_render, is_active);
+                }
+            }
+        }
+    }
+}
 
+/// One `:show-gr` track row, styled like the SS_cons bar; blank without data.
+fn gr_track_line(
+    app: &App,
+    data: Option<&str>,
+    cols_to_render: &[usize],
+    is_active: bool,
+) -> Line<'static> {
+    let Some(data) = data else {
+        return Line::default();
+    };
+    let chars: Vec<char> = data.chars().collect();
+    let spans: Vec<Span> = cols_to_render
+        .iter()
+        .map(|&col| {
             let mut style = Style::reset()
                 .fg(app.theme.annotations.ss_cons_fg.to_color())
                 .bg(app.theme.annotations.ss_cons_bg.to_color());
-
-            // Highlight empty (all-gap) columns if enabled
-            if app.highlight_gap_columns && alignment.is_empty_column(col, &app.gap_chars) {
-                style = style.bg(app.theme.selection.gap_column_bg.to_color());
-            }
-
-            // Highlight paired bracket
-            if let Some(paired_col) = app.structure_cache.get_pair(app.cursor_col)
-                && col == paired_col
-            {
-                style = style
-                    .fg(app.theme.annotations.ss_cons_paired_fg.to_color())
-                    .bg(app.theme.annotations.ss_cons_paired_bg.to_color())
-                    .add_modifier(Modifier::BOLD);
-            }
-
             // Column indicator
-            if is_cursor_col {
+            if app.highlight_cursor_column && is_active && col == app.cursor_col {
                 style = style.add_modifier(Modifier::UNDERLINED);
             }
+            Span::styled(chars.get(col).copied().unwrap_or(' ').to_string(), style)
+        })
+        .collect();
+    Line::from(spans)
+}
 
-            spans.push(Span::styled(ch.to_string(), style));
-        }
+/// Render the SS_cons bar, highlighting the bracket paired with the cursor.
+fn render_ss_cons_bar(
+    frame: &mut Frame,
+    app: &App,
+    alignment: &crate::stockholm::Alignment,
+    ss: &str,
+    area: Rect,
+    cols_to_render: &[usize],
+    is_active: bool,
+) {
+    let mut spans = Vec::new();
 
-        let ss_line = Paragraph::new(Line::from(spans));
-        frame.render_widget(ss_line, ss_cons_area);
-    }
+    let ss_chars: Vec<char> = ss.chars().collect();
+    for &col in cols_to_render {
+        let ch = ss_chars.get(col).copied().unwrap_or(' ');
+        let is_cursor_col = app.highlight_cursor_column && is_active && col == app.cursor_col;
 
-    // Render RF bar
-    if app.show_rf_bar
-        && let Some(rf) = alignment.rf()
-    {
-        render_rf_bar(
-            frame,
-            app,
-            alignment,
-            rf,
-            rf_area,
-            &cols_to_render,
-            is_active,
-            app.cursor_col,
-        );
-    }
+        let mut style = Style::reset()
+            .fg(app.theme.annotations.ss_cons_fg.to_color())
+            .bg(app.theme.annotations.ss_cons_bg.to_color());
 
-    // Render PP_cons bar
-    if app.show_pp_cons
-        && let Some(pp) = alignment.pp_cons()
-    {
-        render_pp_cons_bar(
-            frame,
-            app,
-            alignment,
-            pp,
-            pp_cons_area,
-            &cols_to_render,
-            is_active,
-            app.cursor_col,
-        );
-    }
+        // Highlight empty (all-gap) columns if enabled
+        if app.highlight_gap_columns && alignment.is_empty_column(col, &app.gap_chars) {
+            style = style.bg(app.theme.selection.gap_column_bg.to_color());
+        }
 
-    // Render consensus bar
-    if app.show_consensus {
-        render_consensus_bar(
-            frame,
-            app,
-            alignment,
-            consensus_area,
-            &cols_to_render,
-            is_active,
-        );
-    }
+        // Highlight paired bracket
+        if let Some(paired_col) = app.structure_cache.get_pair(app.cursor_col)
+            && col == paired_col
+        {
+            style = style
+                .fg(app.theme.annotations.ss_cons_paired_fg.to_color())
+                .bg(app.theme.annotations.ss_cons_paired_bg.to_color())
+                .add_modifier(Modifier::BOLD);
+        }
 
-    // Render conservation bar
-    if app.show_conservation_bar {
-        render_conservation_bar(
-            frame,
-            app,
-            alignment,
-            conservation_area,
-            &cols_to_render,
-            is_active,
-        );
+        // Highlight structure search matches (`:/s`, primary alignment only)
+        if std::ptr::eq(alignment, &app.alignment)
+            && let Some(is_current) = app.search.is_structure_match(col)
+        {
+            style = if is_current {
+                style
+                    .bg(app.theme.selection.search_current_bg.to_color())
+                    .fg(app.theme.selection.search_current_fg.to_color())
+            } else {
+                style
+                    .bg(app.theme.selection.search_other_bg.to_color())
+                    .fg(app.theme.selection.search_other_fg.to_color())
+            };
+        }
+
+        // Column indicator
+        if is_cursor_col {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+
+        spans.push(Span::styled(ch.to_string(), style));
     }
+
+    let ss_line = Paragraph::new(Line::from(spans));
+    frame.render_widget(ss_line, area);
 }
 
 /// Render the consensus bar (showing the most common character at each position).
@@ -1028,14 +1566,22 @@ fn render_consensus_bar(
     cols_to_render: &[usize],
     is_active: bool,
 ) {
-    use crate::color::get_consensus_char_with_case;
-
+    // The cache only covers the primary alignment, not a `:compare` pane
+    let cached = std::ptr::eq(alignment, &app.alignment).then(|| app.consensus_chars());
     let mut spans = Vec::new();
 
     for &col in cols_to_render {
-        let ch =
-            get_consensus_char_with_case(col, alignment, &app.gap_chars, app.consensus_threshold);
-        let is_cursor_col = is_active && col == app.cursor_col;
+        let ch = match &cached {
+            Some(consensus) => consensus.get(col).copied().unwrap_or(' '),
+            None if col < alignment.width() => get_consensus_char_with_case(
+                col,
+                alignment,
+                &app.gap_chars,
+                app.consensus_threshold,
+            ),
+            None => ' ',
+        };
+        let is_cursor_col = app.highlight_cursor_column && is_active && col == app.cursor_col;
 
         let mut style = Style::reset()
             .fg(app.theme.annotations.consensus_fg.to_color())
@@ -1073,7 +1619,7 @@ fn render_conservation_bar(
     for &col in cols_to_render {
         let conservation = calculate_conservation(col, alignment, &app.gap_chars);
         let (ch, color) = conservation_to_block(conservation);
-        let is_cursor_col = is_active && col == app.cursor_col;
+        let is_cursor_col = app.highlight_cursor_column && is_active && col == app.cursor_col;
 
         let mut style =
             Style::reset()
@@ -1113,7 +1659,7 @@ fn render_rf_bar(
 
     for &col in cols_to_render {
         let ch = rf_chars.get(col).copied().unwrap_or(' ');
-        let is_cursor_col = is_active && col == cursor_col;
+        let is_cursor_col = app.highlight_cursor_column && is_active && col == cursor_col;
 
         // Uppercase or 'x'/'X' = conserved (green), lowercase/gaps = variable (gray)
         let mut style = if ch.is_uppercase() || ch == 'x' || ch == 'X' {
@@ -1154,16 +1700,21 @@ fn render_pp_cons_bar(
     is_active: bool,
     cursor_col: usize,
 ) {
-    use crate::color::pp_to_color;
+    use crate::color::{pp_to_color, pp_value};
 
     let pp_chars: Vec<char> = pp.chars().collect();
     let mut spans = Vec::new();
 
     for &col in cols_to_render {
         let ch = pp_chars.get(col).copied().unwrap_or(' ');
-        let is_cursor_col = is_active && col == cursor_col;
+        let is_cursor_col = app.highlight_cursor_column && is_active && col == cursor_col;
 
-        let color = pp_to_color(ch);
+        // Values under `:ppbar-threshold` are drawn like gaps
+        let (ch, color) = if pp_value(ch).is_some_and(|p| p < app.pp_cons_threshold) {
+            (app.gap_char, pp_to_color('.'))
+        } else {
+            (ch, pp_to_color(ch))
+        };
         let mut style = Style::reset()
             .fg(color)
             .bg(app.theme.annotations.pp_cons_bg.to_color());
@@ -1184,6 +1735,36 @@ fn render_pp_cons_bar(
     frame.render_widget(line, area);
 }
 
+/// Render the `#=GC HELIX` bar, styled like SS_cons with helix numbers bold.
+/// Also draws `#=GC ANCHORS`, whose labels are bold the same way.
+fn render_helix_bar(
+    frame: &mut Frame,
+    app: &App,
+    helix: &str,
+    area: Rect,
+    cols_to_render: &[usize],
+    is_active: bool,
+) {
+    let helix_chars: Vec<char> = helix.chars().collect();
+    let spans: Vec<Span> = cols_to_render
+        .iter()
+        .map(|&col| {
+            let ch = helix_chars.get(col).copied().unwrap_or(' ');
+            let mut style = Style::reset()
+                .fg(app.theme.annotations.ss_cons_fg.to_color())
+                .bg(app.theme.annotations.ss_cons_bg.to_color());
+            if ch.is_ascii_alphanumeric() {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            if app.highlight_cursor_column && is_active && col == app.cursor_col {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            Span::styled(ch.to_string(), style)
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
 /// Render the tree/dendrogram column.
 #[allow(clippy::too_many_arguments)]
 fn render_tree_column(
@@ -1218,6 +1799,9 @@ fn render_tree_column(
         app.cluster_tree.as_ref()
     };
 
+    // Group sizes ("3×") are right-aligned in a prefix before the tree.
+    let count_width = app.tree_count_width();
+
     if let Some(tree_lines) = tree_lines {
         for display_row in
             viewport_row..(viewport_row + visible_rows).min(app.visible_sequence_count())
@@ -1228,12 +1812,39 @@ fn render_tree_column(
                     TerminalTheme::Dark => app.theme.misc.tree_dark_theme.to_color(),
                     TerminalTheme::Light => app.theme.misc.tree_light_theme.to_color(),
                 };
-                lines.push(Line::from(Span::styled(
-                    tree_str.clone(),
-                    Style::reset().fg(tree_color),
-                )));
+                let count = app.get_collapse_count(display_row);
+                let prefix = if count > 1 {
+                    format!("{:>count_width$}", format!("{count}×"))
+                } else {
+                    " ".repeat(count_width)
+                };
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        prefix,
+                        Style::reset().fg(app.theme.id_column.text.to_color()),
+                    ),
+                    Span::styled(tree_str.clone(), Style::reset().fg(tree_color)),
+                ]));
+                // Carry the branches down past a `:show-gr` track or wrapped ID row
+                if app.rows_per_sequence() > 1 {
+                    let continuation: String = tree_str
+                        .chars()
+                        .map(|c| match c {
+                            // Glyphs with a downward stroke
+                            '│' | '╷' | '╭' | '╮' | '┬' | '├' | '┤' | '┼' => '│',
+                            _ => ' ',
+                        })
+                        .collect();
+                    lines.push(Line::from(vec![
+                        Span::raw(" ".repeat(count_width)),
+                        Span::styled(continuation, Style::reset().fg(tree_color)),
+                    ]));
+                }
             } else {
                 lines.push(Line::from(""));
+                if app.rows_per_sequence() > 1 {
+                    lines.push(Line::from(""));
+                }
             }
         }
     }
@@ -1242,16 +1853,106 @@ fn render_tree_column(
     frame.render_widget(paragraph, tree_seq_area);
 }
 
+/// Ruler row of reference sequence coordinates for `:ref-ruler`: every tenth
+/// column is labelled with the reference position it shows, right-aligned.
+fn reference_ruler_line(coords: &[Option<usize>], color: Rgb) -> Line<'static> {
+    let mut chars = vec![' '; coords.len()];
+    for (i, coord) in coords.iter().enumerate() {
+        if (i + 1).is_multiple_of(10)
+            && let Some(coord) = coord
+        {
+            let coord_str = coord.to_string();
+            let start = (i + 1).saturating_sub(coord_str.len());
+            for (j, ch) in coord_str.chars().enumerate() {
+                chars[start + j] = ch;
+            }
+        }
+    }
+    Line::from(Span::styled(
+        chars.into_iter().collect::<String>(),
+        Style::reset().fg(color.to_color()),
+    ))
+}
+
+/// Render a ruler over sampled (zoomed-out) columns.
+///
+/// Every tenth cell is labelled with the actual column number it shows; the
+/// base-pair overlay is omitted.
+fn render_sampled_ruler(
+    cols: &[usize],
+    positions: &[Option<usize>], // ruler number of each column
+    anchors: &[bool],            // whether each column is an anchor
+    cursor_col: Option<usize>,
+    ruler_colors: (Rgb, Rgb, Rgb, Rgb), // (numbers, ticks, pair_line, anchor)
+) -> Vec<Line<'static>> {
+    let (numbers_color, ticks_color, pair_color, anchor_color) = ruler_colors;
+
+    let mut number_chars = vec![' '; cols.len()];
+    let mut tick_spans = Vec::with_capacity(cols.len());
+    for (i, &col) in cols.iter().enumerate() {
+        let labelled = (i + 1).is_multiple_of(10);
+        if labelled && let Some(pos) = positions.get(i).copied().flatten() {
+            // Place the number so it ends at the marker position
+            let pos_str = pos.to_string();
+            let start = i.saturating_sub(pos_str.len() - 1);
+            for (j, ch) in pos_str.chars().enumerate() {
+                if start + j < number_chars.len() {
+                    number_chars[start + j] = ch;
+                }
+            }
+        }
+        let color = if anchors.get(i).copied().unwrap_or(false) {
+            anchor_color
+        } else {
+            ticks_color
+        };
+        let (tick, color) = if cursor_col == Some(col) {
+            ('▼', pair_color)
+        } else if labelled {
+            ('|', color)
+        } else {
+            ('·', color)
+        };
+        tick_spans.push(Span::styled(
+            tick.to_string(),
+            Style::reset().fg(color.to_color()),
+        ));
+    }
+
+    vec![
+        Line::from(Span::styled(
+            number_chars.into_iter().collect::<String>(),
+            Style::reset().fg(numbers_color.to_color()),
+        )),
+        Line::from(tick_spans),
+    ]
+}
+
+/// What the ruler draws over each column of the viewport.
+struct RulerColumns<'a> {
+    /// (partner column, line character) connections from the cursor.
+    connectors: &'a [(usize, char)],
+    /// Ruler number of each column; `None` is unnumbered.
+    positions: &'a [Option<usize>],
+    /// Whether each column is an anchor.
+    anchors: &'a [bool],
+}
+
 /// Render the position ruler (returns two lines: numbers and tick marks).
 fn render_ruler(
     id_width: usize,
     seq_width: usize,
     viewport_col: usize,
     cursor_col: Option<usize>,
-    paired_col: Option<usize>,
-    ruler_colors: (Rgb, Rgb, Rgb), // (numbers, ticks, pair_line)
+    columns: RulerColumns,
+    ruler_colors: (Rgb, Rgb, Rgb, Rgb), // (numbers, ticks, pair_line, anchor)
 ) -> Vec<Line<'static>> {
-    let (numbers_color, ticks_color, pair_color) = ruler_colors;
+    let (numbers_color, ticks_color, pair_color, anchor_color) = ruler_colors;
+    let RulerColumns {
+        connectors,
+        positions,
+        anchors,
+    } = columns;
     let mut lines = Vec::new();
 
     // First line: position numbers
@@ -1262,11 +1963,9 @@ fn render_ruler(
     ));
 
     let mut number_chars = vec![' '; seq_width];
-    for col in viewport_col..(viewport_col + seq_width) {
-        let pos = col + 1; // 1-based position
-        if pos % 10 == 0 {
+    for (local_col, pos) in positions.iter().enumerate().take(seq_width) {
+        if let Some(pos) = pos.filter(|pos| pos % 10 == 0) {
             let pos_str = pos.to_string();
-            let local_col = col - viewport_col;
             // Place the number so it ends at the marker position
             let start = local_col.saturating_sub(pos_str.len() - 1);
             for (i, ch) in pos_str.chars().enumerate() {
@@ -1291,22 +1990,24 @@ fn render_ruler(
 
     // Build tick characters
     let mut tick_chars: Vec<char> = Vec::with_capacity(seq_width);
-    for col in viewport_col..(viewport_col + seq_width) {
-        let pos = col + 1; // 1-based position
-        if pos % 10 == 0 {
-            tick_chars.push('|');
-        } else if pos % 5 == 0 {
-            tick_chars.push('+');
-        } else {
-            tick_chars.push('·');
-        }
+    for i in 0..seq_width {
+        tick_chars.push(match positions.get(i).copied().flatten() {
+            Some(pos) if pos % 10 == 0 => '|',
+            Some(pos) if pos % 5 == 0 => '+',
+            Some(_) => '·',
+            // Unnumbered (RF insert) column
+            None => ' ',
+        });
     }
 
     // Track which positions are part of base-pair display
     let mut is_pair_display: Vec<bool> = vec![false; seq_width];
 
-    // Overlay base-pair connection if both cursor and paired positions exist
-    if let (Some(cursor), Some(paired)) = (cursor_col, paired_col) {
+    // Overlay base-pair (─) and coaxial stack (═) connections from the cursor
+    for &(paired, line_char) in connectors {
+        let Some(cursor) = cursor_col else {
+            break;
+        };
         let viewport_end = viewport_col + seq_width;
         let (left, right) = if cursor < paired {
             (cursor, paired)
@@ -1329,7 +2030,7 @@ fn render_ruler(
 
             // Draw connecting line
             for i in display_start..=display_end {
-                tick_chars[i] = '─';
+                tick_chars[i] = line_char;
                 is_pair_display[i] = true;
             }
 
@@ -1345,19 +2046,28 @@ fn render_ruler(
         }
     }
 
-    // Build spans with different styles for normal ticks vs pair display
+    // Build spans with different styles for normal ticks, pair display and anchors
     let tick_style = Style::reset().fg(ticks_color.to_color());
     let pair_style = Style::reset().fg(pair_color.to_color());
+    let anchor_style = Style::reset().fg(anchor_color.to_color());
+    let style_at = |i: usize| {
+        if is_pair_display[i] {
+            pair_style
+        } else if anchors.get(i).copied().unwrap_or(false) {
+            anchor_style
+        } else {
+            tick_style
+        }
+    };
 
     let mut i = 0;
     while i < seq_width {
-        let is_pair = is_pair_display[i];
+        let style = style_at(i);
         let start = i;
-        while i < seq_width && is_pair_display[i] == is_pair {
+        while i < seq_width && style_at(i) == style {
             i += 1;
         }
         let segment: String = tick_chars[start..i].iter().collect();
-        let style = if is_pair { pair_style } else { tick_style };
         tick_spans.push(Span::styled(segment, style));
     }
 
@@ -1389,6 +2099,13 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
 
     let mode_span = Span::styled(format!(" {} ", app.mode.as_ref()), mode_style);
 
+    // Macro recording indicator
+    let recording_info = if app.macro_recording.is_some() {
+        format!(" recording @{} ", app.macro_register)
+    } else {
+        String::new()
+    };
+
     // Position info
     let pos_info = format!(" {}:{} ", app.cursor_row + 1, app.cursor_col + 1);
 
@@ -1423,12 +2140,40 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
     let type_info = format!(" {} ", app.sequence_type.as_str());
 
     // Color scheme
-    let color_info = if app.color_scheme != ColorScheme::None {
+    let color_info = if app.color_scheme == ColorScheme::Compensatory {
+        let ref_id = app
+            .alignment
+            .sequences
+            .get(app.reference_row())
+            .map(|seq| seq.id.as_str())
+            .unwrap_or("-");
+        format!(" [{} ref:{}] ", app.color_scheme.as_ref(), ref_id)
+    } else if app.color_scheme != ColorScheme::None {
         format!(" [{}] ", app.color_scheme.as_ref())
     } else {
         String::new()
     };
 
+    // Column filter indicator
+    let filter_info = if app.column_filter.is_some() {
+        " [col-filtered] "
+    } else {
+        ""
+    };
+
+    // Slice indicator
+    let slice_info = app
+        .column_slice
+        .map(|(start, end)| format!(" [slice {}-{}] ", start + 1, end))
+        .unwrap_or_default();
+
+    // Zoom indicator
+    let zoom_info = if app.column_stride > 1 {
+        format!(" [zoom: {}×] ", app.column_stride)
+    } else {
+        String::new()
+    };
+
     // Structure info
     let structure_info = if app.structure_cache.is_paired(app.cursor_col) {
         if let Some(paired) = app.structure_cache.get_pair(app.cursor_col) {
@@ -1454,6 +2199,12 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
 
     let spans = vec![
         mode_span,
+        Span::styled(
+            recording_info,
+            Style::default()
+                .fg(app.theme.status_bar.color_scheme.to_color())
+                .add_modifier(Modifier::BOLD),
+        ),
         Span::styled(
             pos_info,
             Style::default().fg(app.theme.status_bar.position.to_color()),
@@ -1476,6 +2227,18 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
             color_info,
             Style::default().fg(app.theme.status_bar.color_scheme.to_color()),
         ),
+        Span::styled(
+            filter_info,
+            Style::default().fg(app.theme.status_bar.color_scheme.to_color()),
+        ),
+        Span::styled(
+            slice_info,
+            Style::default().fg(app.theme.status_bar.color_scheme.to_color()),
+        ),
+        Span::styled(
+            zoom_info,
+            Style::default().fg(app.theme.status_bar.color_scheme.to_color()),
+        ),
         Span::styled(
             structure_info,
             Style::default().fg(app.theme.status_bar.structure_info.to_color()),
@@ -1491,6 +2254,16 @@ fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
         .style(Style::default().bg(app.theme.status_bar.background.to_color()));
 
     frame.render_widget(status, area);
+
+    // Alignment cell under the mouse, at the right end
+    if let Some((row, col)) = app.mouse_cell() {
+        let mouse_info = Paragraph::new(Span::styled(
+            format!(" mouse: seq{}:col{} ", row + 1, col + 1),
+            Style::default().fg(app.theme.status_bar.position.to_color()),
+        ))
+        .alignment(ratatui::layout::Alignment::Right);
+        frame.render_widget(mouse_info, area);
+    }
 }
 
 /// Render the command/message line.
@@ -1538,26 +2311,34 @@ fn render_command_line(frame: &mut Frame, app: &App, area: Rect) {
     }
 }
 
+/// Screen rows taken by the ruler: numbers and ticks, plus reference
+/// coordinates with `:ref-ruler`.
+pub fn ruler_height(app: &App) -> u16 {
+    match (app.show_ruler, app.ref_ungapped_ruler) {
+        (false, _) => 0,
+        (true, false) => RULER_HEIGHT,
+        (true, true) => RULER_HEIGHT + 1,
+    }
+}
+
 /// Calculate visible dimensions for the alignment area.
 #[allow(clippy::too_many_arguments)]
 pub fn visible_dimensions(
     area: Rect,
     num_sequences: usize,
     max_id_len: usize,
-    show_ruler: bool,
+    ruler_height: u16,
     show_row_numbers: bool,
     show_short_ids: bool,
+    show_strand: bool,
     split_mode: Option<SplitMode>,
-    has_ss_cons: bool,
-    has_rf: bool,
-    show_rf_bar: bool,
-    has_pp_cons: bool,
-    show_pp_cons: bool,
-    show_consensus: bool,
-    show_conservation_bar: bool,
+    annotation_height: u16,
+    header_height: u16,
     max_collapse_count: usize,
     tree_display_width: usize,
     alignment_width: usize,
+    rows_per_sequence: usize,
+    id_width_cap: Option<usize>,
 ) -> (usize, usize) {
     let id_formatter = IdFormatter::new(
         num_sequences,
@@ -1565,15 +2346,10 @@ pub fn visible_dimensions(
         show_row_numbers,
         max_collapse_count,
         show_short_ids,
-    );
-    let ruler_height = if show_ruler { RULER_HEIGHT } else { 0 };
-    let ss_cons_height: u16 = if has_ss_cons { 1 } else { 0 };
-    let rf_height: u16 = if show_rf_bar && has_rf { 1 } else { 0 };
-    let pp_cons_height: u16 = if show_pp_cons && has_pp_cons { 1 } else { 0 };
-    let consensus_height: u16 = if show_consensus { 1 } else { 0 };
-    let conservation_height: u16 = if show_conservation_bar { 1 } else { 0 };
-    let annotation_height =
-        ss_cons_height + rf_height + pp_cons_height + consensus_height + conservation_height;
+        id_width_cap,
+        false,
+    )
+    .with_strand(show_strand);
 
     // Calculate the alignment area (total - status - command)
     let alignment_area_height = area.height.saturating_sub(2); // status + command
@@ -1592,9 +2368,12 @@ pub fn visible_dimensions(
         }
     };
 
-    // Subtract borders (2), ruler height, annotation bar heights, and tree width
+    // Subtract borders (2), ruler height, pinned header, annotation bar heights, and tree width
     // Cap at alignment width (no excess space beyond alignment)
-    let inner_height = pane_height.saturating_sub(2 + ruler_height + annotation_height) as usize;
+    // Each sequence may take more than one screen row (`:show-gr` tracks)
+    let inner_height =
+        pane_height.saturating_sub(2 + ruler_height + header_height + annotation_height) as usize
+            / rows_per_sequence.max(1);
     let inner_width = (pane_width as usize)
         .saturating_sub(id_formatter.width() + 2)
         .saturating_sub(tree_display_width)
@@ -1752,6 +2531,7 @@ fn render_help(frame: &mut Frame, app: &App) {
         Line::from("  Ctrl-w v    Vertical split (:vs)"),
         Line::from("  Ctrl-w hjkl Switch pane (or arrows)"),
         Line::from("  Ctrl-w q    Close split (:q or :only)"),
+        Line::from("  :link-panes Scroll panes together (:unlink-panes)"),
         Line::from(""),
         Line::from(Span::styled(
             "Editing",
@@ -1761,12 +2541,17 @@ fn render_help(frame: &mut Frame, app: &App) {
         )),
         Line::from("  i           Insert mode (then . for gap)"),
         Line::from("  x           Delete gap at cursor"),
-        Line::from("  I           Insert gap column"),
+        Line::from("  [N]I        Insert N gap columns"),
         Line::from("  X           Delete gap column"),
         Line::from("  < / >       Shift sequence left/right"),
         Line::from("  { / }       Throw sequence left/right"),
+        Line::from("  C           Pin sequence for pairwise comparison"),
+        Line::from("  H           Helix covariation overlay (comp)"),
         Line::from("  u           Undo"),
         Line::from("  Ctrl-r      Redo"),
+        Line::from("  Alt-Left/Right  Previous/next undo branch"),
+        Line::from("  q<a-z> ... q Record a macro"),
+        Line::from("  [N]@<a-z>   Play a macro (@@ repeats last)"),
         Line::from(""),
         Line::from(Span::styled(
             "Commands",
@@ -1777,15 +2562,125 @@ fn render_help(frame: &mut Frame, app: &App) {
         Line::from("  :w          Save file"),
         Line::from("  :q          Quit (:q! to force)"),
         Line::from("  :wq         Save and quit"),
+        Line::from("  :watch      Reload the file when it changes"),
         Line::from("  :color X    Set color (ss/base/protein/cons)"),
+        Line::from("  :auto-color Pick color for the sequence type"),
         Line::from("  :type X     Set seq type (rna/dna/protein/auto)"),
         Line::from("  :collapse   Toggle collapse identical seqs"),
+        Line::from("  :compact    Narrow the ID column"),
+        Line::from("  :set-strand +|- Set cursor sequence's strand"),
+        Line::from("  :strand-display Show [+]/[-] before IDs"),
+        Line::from("  :wrapids    Wrap long IDs onto a second row"),
         Line::from("  :consensus  Toggle consensus bar"),
+        Line::from("  :consensus-threshold X  Uppercase cutoff"),
+        Line::from("  :recompute-consensus  Refresh consensus bar"),
         Line::from("  :conserv..  Toggle conservation bar"),
         Line::from("  :cluster    Cluster sequences by similarity"),
         Line::from("  :uncluster  Restore original order"),
+        Line::from("  :randomize [seed] Shuffle sequence order"),
+        Line::from("  :sort-gs[-r] TAG Sort by #=GS value"),
+        Line::from("  :delete-seqs <pat> Delete sequences by ID (~regex)"),
+        Line::from("  :dedup      Delete duplicate sequences"),
+        Line::from("  :dedup-ids  Delete sequences with repeated IDs"),
+        Line::from("  :reverse-cols Reverse column order"),
+        Line::from("  :complement-ss Reverse-strand SS_cons"),
         Line::from("  :tree       Toggle dendrogram tree"),
+        Line::from("  :show-pid   Identity heatmap in clustered order"),
+        Line::from("  :number-seqs! [d] Rename to groupN_memberM"),
+        Line::from("  :write-by-cluster <dir> [d] FASTA per cluster"),
+        Line::from("  :set tree_width=N|auto  Tree width cap"),
+        Line::from("  :write-tree <path> Export tree (Newick)"),
+        Line::from("  :write-dot <max> <path> Distance graph (DOT)"),
+        Line::from("  :write-md <path>  Visible columns as Markdown"),
+        Line::from("  :import-tree <path> Order by Newick tree"),
+        Line::from("  :import-features <path> <tag> BED/GFF3 as #=GC"),
+        Line::from("  :import-coaxial <path> Coaxial stacks as #=GC"),
+        Line::from("  :highlight N PAT #RGB Color motif (slot 1-9)"),
+        Line::from("  :clear-highlight [N] Remove motif highlight"),
+        Line::from("  :set-color-at COL #RGB Color a column"),
+        Line::from("  :clear-color-at COL / :clear-color-all"),
+        Line::from("  :shade N-M [#RGB] Shade a column range"),
+        Line::from("  :unshade N-M / :unshade-all"),
+        Line::from("  :varcols    Tint variable columns"),
+        Line::from("  :cursor-col-highlight Toggle cursor column band"),
+        Line::from("  :rf-gaps    Tint insertions relative to RF"),
+        Line::from("  :annotate-helices Number helices in #=GC HELIX"),
+        Line::from("  :helix-bar  Toggle the #=GC HELIX bar"),
+        Line::from("  :import-anchors <tsv> Anchors as #=GC ANCHORS"),
+        Line::from("  :anchorbar  Toggle the #=GC ANCHORS bar"),
+        Line::from("  :ppbar-threshold T Gap PP_cons values below T"),
+        Line::from("  :zoom N|off Show every Nth column"),
+        Line::from("  :/s PATTERN Search SS_cons (. = any column)"),
+        Line::from("  :pin-header Keep cursor sequence on top"),
+        Line::from("  :color-ref [N|ID] Compensatory reference (gr)"),
+        Line::from("  :diffcons   Underline cursor residues unlike consensus"),
+        Line::from("  :set-gf TAG V Set a #=GF annotation"),
+        Line::from("  :delete-gf TAG Remove a #=GF annotation"),
+        Line::from("  :show-gr TAG|off Show a #=GR track under rows"),
+        Line::from("  :col-filter TAG RE Show columns where #=GC matches"),
+        Line::from("  :slice N-M|off Show only columns N through M"),
+        Line::from("  :ruler-mode ref|aln Number ruler by RF position"),
+        Line::from("  :ref-ruler  Reference coordinates under ruler"),
+        Line::from("  :ungap-rf   Remove RF insertion columns"),
+        Line::from("  :insert-cols N Insert N gap columns at cursor"),
+        Line::from("  :trim-ends  Mask terminal gaps with trim_char (~)"),
+        Line::from("  :gap-mask [c] Fill gap-only columns with c (|)"),
+        Line::from("  :interp-gaps [C] Fill internal gaps (N/X)"),
+        Line::from("  :convert-gaps C Use C for every gap"),
+        Line::from("  :pp-filter T Gap residues with PP below T"),
+        Line::from("  :align-to-ref Line up sequence starts with ref"),
+        Line::from("  :compare-ss Compare seq #=GR SS to SS_cons"),
+        Line::from("  :compare <path> Side-by-side file view"),
+        Line::from("  :fetch RFxxxxx Open an Rfam seed alignment"),
+        Line::from("  :merge <path> Append another file's columns"),
+        Line::from("  :insert-aligned <path> [at N] Insert file's columns"),
+        Line::from("  :show-loops List SS_cons hairpin loops"),
+        Line::from("  :show-pairs List SS_cons base pairs (Enter jumps)"),
+        Line::from("  :show-comp  List pairs changed vs the reference"),
+        Line::from("  :show-gc    GC content per sequence"),
+        Line::from("  :show-gap-seqs Gap fraction per sequence"),
+        Line::from("  :show-lengths Ungapped length per sequence"),
+        Line::from("  :check-alphabet Find non-standard residues"),
+        Line::from("  :diagnostic Report inconsistent lengths/SS_cons"),
+        Line::from("  :show-memory Estimated memory use"),
+        Line::from("  :show-gc <tag> Show a #=GC line in full"),
+        Line::from("  :show-insertions Insertions relative to RF"),
+        Line::from("  :show-rf-cons Conservation at RF match states"),
+        Line::from("  :stats      Alignment statistics"),
+        Line::from("  :pid-hist   Pairwise identity histogram"),
+        Line::from("  :show-covariation Column pair MI heatmap"),
+        Line::from("  :gapstats   Gap pattern statistics"),
+        Line::from("  :gsview     Table of #=GS annotations"),
+        Line::from("  :history    Search and rerun past commands"),
+        Line::from("  :undo-tree  Show undo branches"),
+        Line::from("  :column-profile  Residue counts at cursor (gP)"),
+        Line::from("  :fold-region Fold selection/helix (RNAfold)"),
+        Line::from("  :add-ss <ss> Set SS_cons (:clear-ss removes)"),
+        Line::from("  :infer-ss [bits] Propose SS_cons from covariation"),
+        Line::from("  :add-gr [ID] TAG DATA Add a #=GR annotation"),
         Line::from("  :svg <path> Export as SVG image"),
+        Line::from("  :write-svg <path> SS_cons arc diagram"),
+        Line::from("  :write-html <path> Export as HTML"),
+        Line::from("  :write-r2r <path> Input for R2R figures"),
+        Line::from("  :write-ct <path> Export cursor seq as .ct"),
+        Line::from("  :write-bpseq <path> Export cursor seq as BPSEQ"),
+        Line::from("  :write-both <stem> Write stem.aln + stem.phy"),
+        Line::from("  :write-hmm <path> Profile HMM (hmmbuild)"),
+        Line::from("  :align-hmm <hmm> Realign to profile (hmmalign)"),
+        Line::from("  :consensus-seq Add/refresh a CONSENSUS row"),
+        Line::from("  :rf-to-seq  Add RF as an RF_REFERENCE row"),
+        Line::from("  :import-hmm-cons <hmm> Add HMM consensus + SS_cons"),
+        Line::from("  :extract-stemloop <path> Save helix stem-loop"),
+        Line::from("  :split-at [N] <l> <r> Save halves left/right of N"),
+        Line::from("  :wu <path>  Write ungapped sequences as FASTA"),
+        Line::from("  :write-fasta-coords <path> Shown columns, fixed /start-end"),
+        Line::from("  :write-cons-bed <t> <path> RF conservation as BED"),
+        Line::from("  :write-per-seq <dir> One FASTA file per sequence"),
+        Line::from("  :write-per-col <dir> One FASTA file per column"),
+        Line::from("  :write-pretty <path> Stockholm, data columns aligned"),
+        Line::from("  :w-interleaved[!] <path> [N] Blocked Stockholm"),
+        Line::from("  :write-profile-jaspar <path> JASPAR matrix"),
+        Line::from("  :write-profile-transfac <path> TRANSFAC matrix"),
         Line::from("  :msa [N]    Select alignment (multi-MSA files)"),
         Line::from("  :help       Show this help"),
         Line::from(""),
@@ -1893,6 +2788,10 @@ fn render_info(frame: &mut Frame, app: &App) {
         Span::styled("Columns: ", Style::default().fg(Color::Yellow)),
         Span::raw(app.alignment.width().to_string()),
     ]));
+    lines.push(Line::from(vec![
+        Span::styled("Content hash: ", Style::default().fg(Color::Yellow)),
+        Span::raw(app.compute_alignment_hash()[..8].to_string()),
+    ]));
 
     lines.push(Line::from(""));
     lines.push(Line::from(Span::styled(
@@ -1900,9 +2799,26 @@ fn render_info(frame: &mut Frame, app: &App) {
         Style::default().fg(Color::DarkGray),
     )));
 
+    render_popup(frame, app, lines, app.info_scroll);
+}
+
+/// Render a centered, scrollable text popup.
+fn render_popup(frame: &mut Frame, app: &App, lines: Vec<Line>, scroll: u16) {
+    render_popup_with_width(frame, app, lines, scroll, 60);
+}
+
+/// Render a centered, scrollable text popup `width` columns wide (including
+/// borders), narrowed to fit the terminal.
+fn render_popup_with_width(
+    frame: &mut Frame,
+    app: &App,
+    lines: Vec<Line>,
+    scroll: u16,
+    width: u16,
+) {
     // Calculate centered popup area
     let area = frame.area();
-    let popup_width = 60.min(area.width.saturating_sub(4));
+    let popup_width = width.min(area.width.saturating_sub(4));
     let popup_height = (lines.len() as u16 + 2).min(area.height.saturating_sub(4));
     let popup_x = (area.width.saturating_sub(popup_width)) / 2;
     let popup_y = (area.height.saturating_sub(popup_height)) / 2;
@@ -1914,7 +2830,7 @@ fn render_info(frame: &mut Frame, app: &App) {
     let popup_bg = app.theme.misc.popup_bg.to_color();
     let popup_border = app.theme.misc.popup_border.to_color();
 
-    let info_block = Block::default()
+    let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(popup_border))
         .style(Style::default().bg(popup_bg));
@@ -1922,12 +2838,1327 @@ fn render_info(frame: &mut Frame, app: &App) {
     // Clamp the scroll so the last line stays visible (can't scroll past content).
     let inner_height = popup_area.height.saturating_sub(2);
     let max_scroll = (lines.len() as u16).saturating_sub(inner_height);
-    let scroll = app.info_scroll.min(max_scroll);
+    let scroll = scroll.min(max_scroll);
 
-    let info_paragraph = Paragraph::new(lines)
-        .block(info_block)
+    let paragraph = Paragraph::new(lines)
+        .block(block)
         .scroll((scroll, 0))
         .style(Style::default().bg(popup_bg));
 
-    frame.render_widget(info_paragraph, popup_area);
+    frame.render_widget(paragraph, popup_area);
+}
+
+/// Render the `:history` popup: numbered commands, most recent first.
+fn render_command_history_popup(frame: &mut Frame, app: &App) {
+    let matches = app.history_matches();
+
+    let mut lines = vec![Line::from(Span::styled(
+        format!("Command History ({})", app.command_history.entries().len()),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))];
+    if app.history_filter_editing || !app.history_filter.is_empty() {
+        let cursor = if app.history_filter_editing { "_" } else { "" };
+        lines.push(Line::from(Span::styled(
+            format!("/{}{cursor}", app.history_filter),
+            Style::default().fg(Color::Yellow),
+        )));
+    } else {
+        lines.push(Line::from(""));
+    }
+    if matches.is_empty() {
+        lines.push(Line::from("  No matching commands"));
+    }
+    for (i, (number, entry)) in matches.iter().enumerate() {
+        let style = if i == app.history_selection {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{number:>4}  "),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(format!(":{entry}"), style),
+        ]));
+    }
+
+    let hint = if app.history_filter_editing {
+        "type to filter · Enter done · Esc clear"
+    } else {
+        "j/k move · Enter run · / filter · Esc close"
+    };
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        hint,
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    // Keep the highlighted entry on screen by scrolling with the selection
+    render_popup(frame, app, lines, app.history_selection as u16);
+}
+
+/// Render the residue distribution of the cursor column (`gP`).
+fn render_column_profile_popup(frame: &mut Frame, app: &App) {
+    const BAR_WIDTH: usize = 12;
+    const EIGHTHS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+    let profile = app.column_profile(app.cursor_col);
+    let total = app.alignment.num_sequences().max(1);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Column {}", app.cursor_col + 1),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    for &(ch, count) in &profile {
+        // Bar length in eighths of a cell, at least one so every residue shows
+        let eighths = (count * BAR_WIDTH * 8 / total).max(1);
+        let mut bar = "█".repeat(eighths / 8);
+        if !eighths.is_multiple_of(8) {
+            bar.push(EIGHTHS[eighths % 8 - 1]);
+        }
+        let color = get_base_color(ch, &app.gap_chars, app.sequence_type, app.terminal_theme)
+            .unwrap_or(Color::Gray);
+        lines.push(Line::from(vec![
+            Span::styled(format!("{ch} "), Style::default().fg(Color::Yellow)),
+            Span::styled(format!("{bar:<BAR_WIDTH$}"), Style::default().fg(color)),
+            Span::raw(format!(
+                " {count:>4} {:>5.1}%",
+                count as f64 * 100.0 / total as f64
+            )),
+        ]));
+    }
+
+    let area = frame.area();
+    let width = 30.min(area.width.saturating_sub(4));
+    let height = (lines.len() as u16 + 2).min(area.height.saturating_sub(4));
+    let popup_area = Rect::new(
+        area.width.saturating_sub(width) / 2,
+        area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(app.theme.misc.popup_border.to_color()))
+        .style(Style::default().bg(app.theme.misc.popup_bg.to_color()));
+    frame.render_widget(Paragraph::new(lines).block(block), popup_area);
+}
+
+/// Render the report popup selected by `app.report`.
+fn render_report(frame: &mut Frame, app: &App, report: &Report) {
+    let mut lines = match report {
+        Report::HairpinLoops => hairpin_loop_lines(app),
+        Report::Stats(stats, elapsed) => stats_lines(stats, *elapsed),
+        Report::PidHistogram(hist) => pid_histogram_lines(hist),
+        Report::GapStats(stats) => gap_stats_lines(stats),
+        Report::Covariation(report) => covariation_lines(app, report),
+        Report::SequenceAnnotations(table) => gs_table_lines(table, app.report_hscroll),
+        Report::Insertions(rows) => insertion_lines(rows),
+        Report::BasePairs(pairs) => base_pair_lines(pairs, app.report_scroll as usize),
+        Report::GcContent(rows) => gc_content_lines(app, rows),
+        Report::GapFractions(rows) => gap_fraction_lines(app, rows),
+        Report::SequenceLengths(rows) => sequence_length_lines(app, rows),
+        Report::AlphabetIssues(issues) => alphabet_issue_lines(app, issues),
+        Report::Memory(stats) => memory_lines(stats),
+        Report::Diagnostics(issues) => diagnostic_lines(issues, app.report_scroll as usize),
+        Report::Compensatory(records) => compensatory_lines(app, records),
+        Report::ColumnAnnotation {
+            tag,
+            data,
+            selected,
+        } => column_annotation_lines(tag, data, *selected),
+        Report::IdentityMatrix(matrix) => identity_matrix_lines(matrix),
+        Report::RfConservation(columns, insert_mean) => {
+            rf_conservation_lines(columns, *insert_mean)
+        }
+        Report::UndoTree(rows) => undo_tree_lines(rows),
+    };
+
+    let hint = match report {
+        Report::SequenceAnnotations(_) => "j/k/h/l scroll · any other key to close",
+        Report::BasePairs(_) => "j/k select · Enter jump to pair · any other key to close",
+        Report::Compensatory(_) => "j/k select · Enter jump to residue · any other key to close",
+        Report::Diagnostics(_) => "j/k select · Enter jump to issue · any other key to close",
+        Report::ColumnAnnotation { .. } => {
+            "h/l/w/b select column · Enter jump to column · any other key to close"
+        }
+        Report::GcContent(_) | Report::GapFractions(_) | Report::SequenceLengths(_) => {
+            "j/k select · Enter jump to sequence · any other key to close"
+        }
+        _ => "j/k scroll · any other key to close",
+    };
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        hint,
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    // The identity heatmap is wider than the usual popup
+    let width = match report {
+        Report::IdentityMatrix(_) => lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2,
+        _ => 60,
+    };
+    render_popup_with_width(frame, app, lines, app.report_scroll, width.max(60));
+}
+
+/// Undo history for `:undo-tree`: one line per state, later branches
+/// indented under the state they split from.
+fn undo_tree_lines(rows: &[UndoTreeRow]) -> Vec<Line<'static>> {
+    let branches = rows.iter().filter(|r| r.branch.0 > 0).count();
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Undo Tree ({} states, {branches} branches)", rows.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(Span::styled(
+            "Alt-Left/Alt-Right switch between branches",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(""),
+    ];
+    for row in rows {
+        let marker = if row.current { '●' } else { '○' };
+        let branch = if row.branch.1 > 1 {
+            format!("branch {}/{}", row.branch.0 + 1, row.branch.1)
+        } else {
+            String::new()
+        };
+        let text = format!(
+            "{:>4}  {}{marker} {branch:<12} cursor {}:{}",
+            row.depth,
+            "  ".repeat(row.level.min(10)),
+            row.cursor.0 + 1,
+            row.cursor.1 + 1
+        );
+        let style = if row.current {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(text, style)));
+    }
+    lines
+}
+
+/// Estimated memory use for `:show-memory`, largest category first.
+fn memory_lines(stats: &MemoryStats) -> Vec<Line<'static>> {
+    fn human(bytes: usize) -> String {
+        const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit + 1 < UNITS.len() {
+            value /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{bytes} B")
+        } else {
+            format!("{value:.1} {}", UNITS[unit])
+        }
+    }
+
+    let undo_label = format!("Undo history ({})", stats.undo_states);
+    let mut rows = [
+        ("Sequences", stats.sequences),
+        ("#=GC annotations", stats.column_annotations),
+        ("#=GR annotations", stats.residue_annotations),
+        ("#=GS annotations", stats.sequence_annotations),
+        (undo_label.as_str(), stats.undo_history),
+        ("Clustering", stats.clustering),
+        ("Visible columns", stats.visible_columns),
+    ];
+    rows.sort_by_key(|row| std::cmp::Reverse(row.1));
+
+    let total = stats.total().max(1);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Estimated Memory Use",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    for (label, bytes) in rows {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{label:<22}"), Style::default().fg(Color::Yellow)),
+            Span::raw(format!(
+                "{:>10} {:>5.1}%",
+                human(bytes),
+                bytes as f64 * 100.0 / total as f64
+            )),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled(
+            format!("{:<22}", "Total"),
+            Style::default().fg(Color::Yellow),
+        ),
+        Span::raw(format!("{:>10}", human(stats.total()))),
+    ]));
+    lines.push(Line::from(Span::styled(
+        "Undo states share unchanged sequences with the alignment",
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines
+}
+
+/// Alignment statistics table for `:stats`.
+fn stats_lines(stats: &AlignmentStats, elapsed: std::time::Duration) -> Vec<Line<'static>> {
+    let row = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{label:<22}"), Style::default().fg(Color::Yellow)),
+            Span::raw(value),
+        ])
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Alignment Statistics",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        row("Sequences", stats.num_sequences.to_string()),
+        row("Unique sequences", stats.unique_sequences.to_string()),
+        row("Columns", stats.width.to_string()),
+        row("Gap-only columns", stats.gap_only_columns.to_string()),
+        row("Conserved columns", stats.conserved_columns.to_string()),
+        row(
+            "Mean conservation",
+            format!("{:.3}", stats.mean_conservation),
+        ),
+        row("Mean entropy", format!("{:.3} bits", stats.mean_entropy)),
+        row("Gaps", format!("{:.1}%", stats.gap_percent)),
+    ];
+    if let Some(gc) = stats.gc_percent {
+        lines.push(row("GC content", format!("{gc:.1}%")));
+    }
+    lines.push(row("SS_cons base pairs", stats.base_pairs.to_string()));
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("Computed in {:.1} ms", elapsed.as_secs_f64() * 1000.0),
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines
+}
+
+/// Horizontal bar chart of pairwise identities for `:pid-hist`.
+fn pid_histogram_lines(hist: &PidHistogram) -> Vec<Line<'static>> {
+    const BAR_WIDTH: usize = 30;
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Pairwise Identity Distribution",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+
+    let max_count = hist.bins.iter().copied().max().unwrap_or(0).max(1);
+    for (i, &count) in hist.bins.iter().enumerate().rev() {
+        let len = (count * BAR_WIDTH).div_ceil(max_count);
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{:>3}-{:<3}% ", i * 10, (i + 1) * 10),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(
+                format!("{:<BAR_WIDTH$}", "█".repeat(len)),
+                Style::default().fg(Color::Cyan),
+            ),
+            Span::raw(format!(" {count}")),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(format!(
+        "Mean {:.1}%  Median {:.1}%  Min {:.1}%  Max {:.1}%",
+        hist.mean, hist.median, hist.min, hist.max
+    )));
+    let pairs = if hist.sampled {
+        format!("{} randomly sampled pairs", hist.pairs)
+    } else {
+        format!("{} pairs", hist.pairs)
+    };
+    lines.push(Line::from(Span::styled(
+        pairs,
+        Style::default().fg(Color::DarkGray),
+    )));
+    lines
+}
+
+/// Mutual information heatmap for `:show-covariation`.
+///
+/// Wide alignments are binned so the heatmap fits the popup; each cell shows
+/// the highest MI among the column pairs it covers.
+fn covariation_lines(app: &App, report: &CovariationReport) -> Vec<Line<'static>> {
+    const MAX_CELLS: usize = 48;
+    const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+    const SHADE_COLORS: [Color; 5] = [
+        Color::DarkGray,
+        Color::Blue,
+        Color::Cyan,
+        Color::Yellow,
+        Color::Red,
+    ];
+
+    let matrix = &report.matrix;
+    let width = matrix.len();
+    let max_mi = report.top_pairs.first().map_or(0.0, |p| p.0);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Column Covariation (mutual information)",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Top column pairs",
+            Style::default().fg(Color::Yellow),
+        )),
+    ];
+    if report.top_pairs.is_empty() {
+        lines.push(Line::from("  No covarying columns"));
+    }
+    for &(mi, i, j) in &report.top_pairs {
+        let paired = if app.structure_cache.get_pair(i) == Some(j) {
+            "  (SS_cons pair)"
+        } else {
+            ""
+        };
+        lines.push(Line::from(format!(
+            "  {:>5} - {:<5} {mi:.3} bits{paired}",
+            i + 1,
+            j + 1
+        )));
+    }
+
+    let bin = width.div_ceil(MAX_CELLS).max(1);
+    let cells = width.div_ceil(bin);
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!("Heatmap ({bin} column(s) per cell, ▣ = SS_cons pair)"),
+        Style::default().fg(Color::Yellow),
+    )));
+    for r in 0..cells {
+        let rows = r * bin..((r + 1) * bin).min(width);
+        let mut spans = vec![Span::styled(
+            format!("{:>5} ", rows.start + 1),
+            Style::default().fg(Color::DarkGray),
+        )];
+        for c in 0..cells {
+            if c < r {
+                spans.push(Span::raw(" "));
+                continue;
+            }
+            let cols = c * bin..((c + 1) * bin).min(width);
+            let mi = rows
+                .clone()
+                .flat_map(|i| cols.clone().filter(move |&j| j > i).map(move |j| (i, j)))
+                .map(|(i, j)| matrix[i][j])
+                .fold(0.0, f64::max);
+            let paired = rows.clone().any(|i| {
+                app.structure_cache
+                    .get_pair(i)
+                    .is_some_and(|j| j > i && cols.contains(&j))
+            });
+            let level = if max_mi > 0.0 {
+                ((mi / max_mi) * 4.0).ceil() as usize
+            } else {
+                0
+            };
+            let ch = if paired { '▣' } else { SHADES[level] };
+            spans.push(Span::styled(
+                ch.to_string(),
+                Style::default().fg(SHADE_COLORS[level]),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Insertions relative to RF for `:show-insertions`, one line per insertion.
+fn insertion_lines(rows: &[(String, Vec<(usize, usize)>)]) -> Vec<Line<'static>> {
+    let id_width = rows
+        .iter()
+        .map(|(id, _)| id.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    let total: usize = rows.iter().map(|(_, ins)| ins.len()).sum();
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "Insertions relative to RF ({total} in {} sequences)",
+                rows.len()
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("{:<id_width$}  {:>8}  {:>6}", "ID", "After RF", "Length"),
+            Style::default().fg(Color::Yellow),
+        )),
+    ];
+    for (id, insertions) in rows {
+        for (i, &(rf_pos, len)) in insertions.iter().enumerate() {
+            // Only the first insertion of each sequence repeats its ID
+            let label = if i == 0 { id.as_str() } else { "" };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{label:<id_width$}"),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(format!("  {rf_pos:>8}  {len:>6}")),
+            ]));
+        }
+    }
+    lines
+}
+
+/// Base pair table for `:show-pairs`, with the `selected` row highlighted.
+fn base_pair_lines(pairs: &[BasePairStats], selected: usize) -> Vec<Line<'static>> {
+    let pct = |f: f64| format!("{:>5.1}%", f * 100.0);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("SS_cons Base Pairs ({})", pairs.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "{:>5}  {:>5}  {:>5}  {:>6}  {:>6}  {:>8}  {:>6}",
+                "Helix", "Left", "Right", "WC", "GU", "Mismatch", "Gap"
+            ),
+            Style::default().fg(Color::Yellow),
+        )),
+    ];
+    for (i, pair) in pairs.iter().enumerate() {
+        let style = if i == selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{:>5}  {:>5}  {:>5}  {}  {}  {:>8}  {}",
+                pair.helix_id + 1,
+                pair.left + 1,
+                pair.right + 1,
+                pct(pair.watson_crick),
+                pct(pair.wobble),
+                pct(pair.mismatch),
+                pct(pair.gap)
+            ),
+            style,
+        )));
+    }
+    lines
+}
+
+/// Per-sequence GC content for `:show-gc`, with a bar per sequence.
+fn gc_content_lines(app: &App, rows: &[(String, f64)]) -> Vec<Line<'static>> {
+    let header = vec![
+        Line::from(Span::styled(
+            format!("GC Content ({} sequences)", rows.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    sequence_fraction_lines(app, header, rows, None)
+}
+
+/// Bar chart of a per-sequence fraction after the `header` lines: ID, bar
+/// and percentage for each of `rows`.
+///
+/// The selected row is reversed and the sequence under the cursor is yellow;
+/// fractions above `red_above` are drawn in red instead.
+fn sequence_fraction_lines(
+    app: &App,
+    header: Vec<Line<'static>>,
+    rows: &[(String, f64)],
+    red_above: Option<f64>,
+) -> Vec<Line<'static>> {
+    const BAR_WIDTH: usize = 20;
+
+    let id_width = rows
+        .iter()
+        .map(|(id, _)| id.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(2, 24);
+    let cursor_id = app
+        .alignment
+        .sequences
+        .get(app.display_to_actual_row(app.cursor_row))
+        .map(|seq| seq.id.as_str());
+    let selected = app.report_scroll as usize;
+
+    let mut lines = header;
+    for (i, (id, fraction)) in rows.iter().enumerate() {
+        let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+        let label: String = id.chars().take(id_width).collect();
+        let mut style = if red_above.is_some_and(|limit| *fraction > limit) {
+            Style::default().fg(Color::Red)
+        } else if cursor_id == Some(id.as_str()) {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        if i == selected {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{label:<id_width$}  {bar}  {:>5.1}%", fraction * 100.0),
+            style,
+        )));
+    }
+    lines
+}
+
+/// Conservation at RF match-state columns for `:show-rf-cons`, drawn with
+/// the conservation bar's block characters in rows of 50 RF positions.
+fn rf_conservation_lines(columns: &[(usize, f64)], insert_mean: Option<f64>) -> Vec<Line<'static>> {
+    use crate::color::conservation_to_block;
+
+    const ROW_WIDTH: usize = 50;
+
+    let mean = columns.iter().map(|&(_, c)| c).sum::<f64>() / columns.len().max(1) as f64;
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!(
+                "Conservation at Match States ({} RF positions)",
+                columns.len()
+            ),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(vec![
+            Span::styled("Match columns  ", Style::default().fg(Color::Yellow)),
+            Span::raw(format!("mean {mean:.3}")),
+        ]),
+        Line::from(vec![
+            Span::styled("Insert columns ", Style::default().fg(Color::Yellow)),
+            Span::raw(match insert_mean {
+                Some(mean) => format!("mean {mean:.3}"),
+                None => "none".to_string(),
+            }),
+        ]),
+        Line::from(""),
+    ];
+
+    for (row, chunk) in columns.chunks(ROW_WIDTH).enumerate() {
+        let mut spans = vec![Span::styled(
+            format!("{:>5} ", row * ROW_WIDTH + 1),
+            Style::default().fg(Color::DarkGray),
+        )];
+        spans.extend(chunk.iter().map(|&(_, cons)| {
+            let (ch, color) = conservation_to_block(cons);
+            Span::styled(ch.to_string(), Style::default().fg(color))
+        }));
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Gap fraction bar chart for `:show-gap-seqs`, most gapped first; sequences
+/// that are mostly gaps are drawn in red.
+fn gap_fraction_lines(app: &App, rows: &[(String, f64)]) -> Vec<Line<'static>> {
+    const HIGH_GAP_FRACTION: f64 = 0.5;
+
+    let mean = rows.iter().map(|(_, f)| f).sum::<f64>() / rows.len().max(1) as f64;
+    let summary = match rows.first() {
+        Some((id, max)) => format!(
+            "Mean gap: {:.1}%, Max: {:.1}% ({id})",
+            mean * 100.0,
+            max * 100.0
+        ),
+        None => "No sequences".to_string(),
+    };
+
+    let header = vec![
+        Line::from(Span::styled(
+            format!("Gaps per Sequence ({} sequences)", rows.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(summary),
+        Line::from(""),
+    ];
+    sequence_fraction_lines(app, header, rows, Some(HIGH_GAP_FRACTION))
+}
+
+/// Ungapped length bar chart for `:show-lengths`, longest first. Bars are
+/// scaled to the alignment width; lengths more than two standard deviations
+/// from the mean are drawn in red.
+fn sequence_length_lines(app: &App, rows: &[(String, usize)]) -> Vec<Line<'static>> {
+    const BAR_WIDTH: usize = 20;
+    const OUTLIER_SD: f64 = 2.0;
+
+    let width = app.alignment.width();
+    let id_width = rows
+        .iter()
+        .map(|(id, _)| id.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(2, 24);
+    let cursor_id = app
+        .alignment
+        .sequences
+        .get(app.display_to_actual_row(app.cursor_row))
+        .map(|seq| seq.id.as_str());
+    let selected = app.report_scroll as usize;
+
+    let n = rows.len().max(1) as f64;
+    let mean = rows.iter().map(|&(_, len)| len as f64).sum::<f64>() / n;
+    let sd = (rows
+        .iter()
+        .map(|&(_, len)| (len as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    let min = rows.iter().map(|&(_, len)| len).min().unwrap_or(0);
+    let max = rows.iter().map(|&(_, len)| len).max().unwrap_or(0);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Ungapped Lengths ({} sequences)", rows.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!("Min {min}  Max {max}  Mean {mean:.1}  SD {sd:.1}")),
+        Line::from(Span::styled(
+            format!("Alignment width {width} (a full bar)"),
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(""),
+    ];
+    for (i, (id, len)) in rows.iter().enumerate() {
+        let filled = (len * BAR_WIDTH)
+            .checked_div(width)
+            .unwrap_or(0)
+            .min(BAR_WIDTH);
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled));
+        let label: String = id.chars().take(id_width).collect();
+        let mut style = if (*len as f64 - mean).abs() > OUTLIER_SD * sd {
+            Style::default().fg(Color::Red)
+        } else if cursor_id == Some(id.as_str()) {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        if i == selected {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        lines.push(Line::from(Span::styled(
+            format!("{label:<id_width$}  {bar}  {len:>5}"),
+            style,
+        )));
+    }
+    lines
+}
+
+/// A `#=GC` annotation for `:show-gc <tag>`, 50 columns at a time under a
+/// column ruler, with the selected column highlighted.
+fn column_annotation_lines(tag: &str, data: &str, selected: usize) -> Vec<Line<'static>> {
+    const WINDOW: usize = 50;
+
+    let chars: Vec<char> = data.chars().collect();
+    let start = selected - selected % WINDOW;
+    let end = (start + WINDOW).min(chars.len());
+
+    let mut numbers = vec![' '; end - start];
+    for col in start..end {
+        if (col + 1).is_multiple_of(10) {
+            let label = (col + 1).to_string();
+            let first = (col - start + 1).saturating_sub(label.len());
+            for (i, ch) in label.chars().enumerate() {
+                numbers[first + i] = ch;
+            }
+        }
+    }
+    let ticks: String = (start..end)
+        .map(|col| {
+            if col == selected {
+                '▼'
+            } else if (col + 1).is_multiple_of(10) {
+                '|'
+            } else {
+                '·'
+            }
+        })
+        .collect();
+    let cells: Vec<Span> = (start..end)
+        .map(|col| {
+            let style = if col == selected {
+                Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Span::styled(chars[col].to_string(), style)
+        })
+        .collect();
+
+    vec![
+        Line::from(Span::styled(
+            format!("#=GC {tag} ({} columns)", chars.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!(
+            "Column {}: '{}'   (showing {}-{end})",
+            selected + 1,
+            chars.get(selected).copied().unwrap_or(' '),
+            start + 1
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            numbers.into_iter().collect::<String>(),
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(Span::styled(ticks, Style::default().fg(Color::DarkGray))),
+        Line::from(cells),
+    ]
+}
+
+/// Unexpected characters for `:check-alphabet`, one line per character with
+/// the character itself in red.
+fn alphabet_issue_lines(app: &App, issues: &[(String, usize, char)]) -> Vec<Line<'static>> {
+    let id_width = issues
+        .iter()
+        .map(|(id, _, _)| id.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(2, 24);
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Alphabet Check ({})", app.sequence_type.as_str()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!("{} unexpected characters found", issues.len())),
+        Line::from(""),
+    ];
+    for (id, col, ch) in issues {
+        let label: String = id.chars().take(id_width).collect();
+        lines.push(Line::from(vec![
+            Span::raw(format!("{label:<id_width$}  col {:>6}  ", col + 1)),
+            Span::styled(
+                ch.to_string(),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+        ]));
+    }
+    lines
+}
+
+/// Inconsistencies for `:diagnostic`, one per line with its location; the
+/// selected row is reversed.
+fn diagnostic_lines(issues: &[DiagnosticMessage], selected: usize) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Alignment Diagnostics",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!("{} issues found", issues.len())),
+        Line::from(""),
+    ];
+    for (idx, issue) in issues.iter().enumerate() {
+        let location = match (&issue.seq_id, issue.col) {
+            (Some(id), Some(col)) => format!("{id}:{}", col + 1),
+            (Some(id), None) => id.clone(),
+            (None, Some(col)) => format!("col {}", col + 1),
+            (None, None) => String::new(),
+        };
+        let style = if idx == selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{location:<16} "), style.fg(Color::Yellow)),
+            Span::styled(issue.message.clone(), style),
+        ]));
+    }
+    lines
+}
+
+/// Changed pairs for `:show-comp`, with a count per change type.
+///
+/// The selected row is reversed; each change type has its compensatory color.
+fn compensatory_lines(app: &App, records: &[CompensatoryRecord]) -> Vec<Line<'static>> {
+    const KINDS: [(CompensatoryChange, &str, Color); 5] = [
+        (
+            CompensatoryChange::SingleCompatible,
+            "single compatible",
+            COMP_SINGLE_COMPATIBLE,
+        ),
+        (
+            CompensatoryChange::DoubleCompatible,
+            "double compatible",
+            COMP_DOUBLE_COMPATIBLE,
+        ),
+        (
+            CompensatoryChange::SingleIncompatible,
+            "single incompatible",
+            COMP_SINGLE_INCOMPATIBLE,
+        ),
+        (
+            CompensatoryChange::DoubleIncompatible,
+            "double incompatible",
+            COMP_DOUBLE_INCOMPATIBLE,
+        ),
+        (CompensatoryChange::InvolvesGap, "gap", COMP_GAP),
+    ];
+    let kind = |change: CompensatoryChange| {
+        KINDS
+            .iter()
+            .find(|(c, _, _)| *c == change)
+            .map_or(("", Color::Reset), |&(_, label, color)| (label, color))
+    };
+
+    let id_width = records
+        .iter()
+        .map(|r| r.seq_id.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp(8, 24);
+    let reference = app
+        .alignment
+        .sequences
+        .get(app.reference_row())
+        .map_or("-", |seq| seq.id.as_str());
+    let selected = app.report_scroll as usize;
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Compensatory Changes vs {reference} ({})", records.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+    for (change, label, color) in KINDS {
+        let count = records.iter().filter(|r| r.change == change).count();
+        lines.push(Line::from(vec![
+            Span::styled(format!("{label:<20}"), Style::default().fg(color)),
+            Span::raw(format!("{count:>6}")),
+        ]));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{:<id_width$}  {:>5}  {:>5}  {}",
+            "Sequence", "Left", "Right", "Change"
+        ),
+        Style::default().fg(Color::Yellow),
+    )));
+    for (i, record) in records.iter().enumerate() {
+        let (label, color) = kind(record.change);
+        let id: String = record.seq_id.chars().take(id_width).collect();
+        let row_style = if i == selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(
+                    "{id:<id_width$}  {:>5}  {:>5}  ",
+                    record.left + 1,
+                    record.right + 1
+                ),
+                row_style,
+            ),
+            Span::styled(label, row_style.fg(color)),
+        ]));
+    }
+    lines
+}
+
+/// Pairwise identity heatmap for `:show-pid`, two half blocks per cell.
+fn identity_matrix_lines(matrix: &IdentityMatrix) -> Vec<Line<'static>> {
+    const ID_WIDTH: usize = 12;
+    const LEVELS: [(f64, Color); 5] = [
+        (90.0, Color::Red),
+        (70.0, Color::Yellow),
+        (50.0, Color::Cyan),
+        (30.0, Color::Blue),
+        (0.0, Color::DarkGray),
+    ];
+    let level_color = |pid: f64| {
+        LEVELS
+            .iter()
+            .find(|&&(min, _)| pid >= min)
+            .map_or(Color::DarkGray, |&(_, color)| color)
+    };
+
+    let title = if matrix.total > matrix.ids.len() {
+        format!(
+            "Pairwise Identity (first {} of {} sequences, clustered order)",
+            matrix.ids.len(),
+            matrix.total
+        )
+    } else {
+        format!(
+            "Pairwise Identity ({} sequences, clustered order)",
+            matrix.total
+        )
+    };
+    let mut legend = vec![Span::raw("  ")];
+    for (i, &(min, color)) in LEVELS.iter().enumerate().rev() {
+        legend.push(Span::styled("▐▌", Style::default().fg(color)));
+        let label = match i {
+            0 => format!(" ≥{min:.0}%"),
+            _ => format!(" ≥{min:.0}%  "),
+        };
+        legend.push(Span::raw(label));
+    }
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(legend),
+        Line::from(""),
+    ];
+    for (id, row) in matrix.ids.iter().zip(&matrix.pids) {
+        let label: String = id.chars().take(ID_WIDTH).collect();
+        let mut spans = vec![Span::styled(
+            format!("{label:<ID_WIDTH$} "),
+            Style::default().fg(Color::Yellow),
+        )];
+        for pid in row {
+            spans.push(match pid {
+                Some(pid) => Span::styled("▐▌", Style::default().fg(level_color(*pid))),
+                None => Span::raw("  "),
+            });
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// `#=GS` annotation table for `:gsview`.
+///
+/// The ID column stays put while the tag columns scroll by `hscroll` characters.
+fn gs_table_lines(table: &GsTable, hscroll: u16) -> Vec<Line<'static>> {
+    const MAX_CELL: usize = 30;
+
+    let clip = |s: &str| -> String {
+        if s.chars().count() > MAX_CELL {
+            let mut clipped: String = s.chars().take(MAX_CELL - 1).collect();
+            clipped.push('…');
+            clipped
+        } else {
+            s.to_string()
+        }
+    };
+    let cells: Vec<Vec<String>> = table
+        .rows
+        .iter()
+        .map(|(_, row)| row.iter().map(|c| clip(c)).collect())
+        .collect();
+    let id_width = table
+        .rows
+        .iter()
+        .map(|(id, _)| id.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    let widths: Vec<usize> = table
+        .tags
+        .iter()
+        .enumerate()
+        .map(|(i, tag)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([tag.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let scrolled = |fields: &[String]| -> String {
+        let row: String = fields
+            .iter()
+            .zip(&widths)
+            .map(|(field, &w)| format!("  {field:<w$}"))
+            .collect();
+        row.chars().skip(hscroll as usize).collect()
+    };
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "#=GS Annotations",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(
+                format!("{:<id_width$}", "ID"),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(scrolled(&table.tags), Style::default().fg(Color::Yellow)),
+        ]),
+    ];
+    for ((id, _), row) in table.rows.iter().zip(&cells) {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{id:<id_width$}"), Style::default().fg(Color::Cyan)),
+            Span::raw(scrolled(row)),
+        ]));
+    }
+    lines
+}
+
+/// Column class in the `:gapstats` table: label and gapped-fraction test.
+type GapFractionClass = (&'static str, fn(f64) -> bool);
+
+/// Gap pattern summary for `:gapstats`.
+fn gap_stats_lines(stats: &GapStats) -> Vec<Line<'static>> {
+    let heading = |text: &str| {
+        Line::from(Span::styled(
+            text.to_string(),
+            Style::default().fg(Color::Yellow),
+        ))
+    };
+    let (min, max, mean, median) = stats.seq_gap_fraction;
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Gap Statistics",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        heading("Gap fraction per sequence"),
+        Line::from(format!(
+            "  min {:.1}%  max {:.1}%  mean {:.1}%  median {:.1}%",
+            min * 100.0,
+            max * 100.0,
+            mean * 100.0,
+            median * 100.0
+        )),
+        Line::from(""),
+        heading("Gap runs"),
+        Line::from(format!(
+            "  mean length {:.1}  longest {}",
+            stats.mean_run_length, stats.max_run_length
+        )),
+        Line::from(""),
+        heading("Sequences"),
+        Line::from(format!("  no gaps          {}", stats.ungapped_seqs)),
+        Line::from(format!("  terminal only    {}", stats.terminal_only_seqs)),
+        Line::from(format!("  internal gaps    {}", stats.internal_gap_seqs)),
+        Line::from(""),
+        heading("Columns by gapped fraction"),
+    ];
+
+    let classes: [GapFractionClass; 6] = [
+        ("0%", |f| f == 0.0),
+        ("<25%", |f| f > 0.0 && f < 0.25),
+        ("25-50%", |f| (0.25..0.5).contains(&f)),
+        ("50-75%", |f| (0.5..0.75).contains(&f)),
+        ("75-<100%", |f| (0.75..1.0).contains(&f)),
+        ("100%", |f| f == 1.0),
+    ];
+    for (label, in_class) in classes {
+        let count = stats
+            .column_gap_fraction
+            .iter()
+            .filter(|&&f| in_class(f))
+            .count();
+        lines.push(Line::from(format!("  {label:<16} {count}")));
+    }
+    lines
+}
+
+/// Table of SS_cons hairpin loops for `:show-loops`.
+fn hairpin_loop_lines(app: &App) -> Vec<Line<'static>> {
+    let loops = app.extract_hairpin_loops();
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Hairpin Loops ({})", loops.len()),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+    ];
+
+    if loops.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "No hairpin loops in SS_cons",
+            Style::default().fg(Color::DarkGray),
+        )));
+        return lines;
+    }
+
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{:>3}  {:<11} {:>4}  {:<4}  Sequence",
+            "#", "Position", "Len", "Pair"
+        ),
+        Style::default().fg(Color::Yellow),
+    )));
+    for (i, (left, right, seq)) in loops.iter().enumerate() {
+        let pair = format!(
+            "{}-{}",
+            get_consensus_char(*left, &app.alignment, &app.gap_chars),
+            get_consensus_char(*right, &app.alignment, &app.gap_chars)
+        );
+        lines.push(Line::from(format!(
+            "{:>3}  {:<11} {:>4}  {:<4}  {}",
+            i + 1,
+            format!("{}-{}", left + 1, right + 1),
+            seq.len(),
+            pair,
+            seq
+        )));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{Terminal, backend::TestBackend};
+    use std::io::Write;
+
+    #[test]
+    fn test_tree_follows_wrapped_ids() {
+        let contents = "# STOCKHOLM 1.0\nseq_one_with_a_long_name ACGUACGU\n\
+                        seq_two_with_a_long_name ACGUACGA\nseq_six_with_a_long_name UUUUCCCC\n//\n";
+        let path = std::env::temp_dir().join(format!("aform_ui_wrap_{}.stk", std::process::id()));
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(contents.as_bytes())
+            .unwrap();
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.id_wrap_width = 14;
+        app.wrap_ids = true;
+        app.cluster_sequences();
+        app.show_tree = true;
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| render(frame, &app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect();
+
+        // Each sequence's tree line sits on the first row of its wrapped ID
+        let tree = app.cluster_tree.clone().unwrap();
+        for (display_row, tree_line) in tree.iter().enumerate() {
+            let seq = app.display_to_actual_row(display_row);
+            let id = &app.alignment.sequences[seq].id;
+            let y = rows.iter().position(|row| row.contains(&id[..8])).unwrap();
+            assert!(rows[y].contains(tree_line.trim_end()), "{rows:#?}");
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_diffcons_underlines_cursor_sequence() {
+        let path =
+            std::env::temp_dir().join(format!("aform_ui_diffcons_{}.stk", std::process::id()));
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(b"# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\ns3 ACGA\n//\n")
+            .unwrap();
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.highlight_consensus_diff = true;
+
+        let underlined = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+            terminal.draw(|frame| render(frame, app)).unwrap();
+            let buffer = terminal.backend().buffer();
+            buffer
+                .content()
+                .iter()
+                .filter(|cell| cell.modifier.contains(Modifier::UNDERLINED))
+                .count()
+        };
+        // Only s1's U differs, and only while the cursor is on s1
+        assert_eq!(underlined(&app), 1);
+        app.cursor_row = 1;
+        assert_eq!(underlined(&app), 0);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_id_popup_shows_full_id() {
+        let path = std::env::temp_dir().join(format!("aform_ui_hover_{}.stk", std::process::id()));
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(
+                b"# STOCKHOLM 1.0\n#=GS a_rather_long_sequence_id DE tRNA-Phe\n\
+                  a_rather_long_sequence_id ACGU\ns2 ACGA\n//\n",
+            )
+            .unwrap();
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+        app.compact_ids = true;
+
+        let screen = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+            terminal.draw(|frame| render(frame, app)).unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..buffer.area.height)
+                .map(|y| {
+                    (0..buffer.area.width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        // Draw once so the mouse can be mapped onto the ID column
+        assert!(!screen(&app).contains("a_rather_long_sequence_id"));
+        let grid = app.sequence_grid.borrow().clone().unwrap();
+        app.mouse_pos = Some((grid.id_x + 1, grid.y));
+        let hovered = screen(&app);
+        assert!(hovered.contains("a_rather_long_sequence_id"), "{hovered}");
+        assert!(hovered.contains("DE tRNA-Phe"), "{hovered}");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_active_separator_only_when_split() {
+        let path = std::env::temp_dir().join(format!("aform_ui_split_{}.stk", std::process::id()));
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(b"# STOCKHOLM 1.0\ns1 ACGU\ns2 ACGA\n//\n")
+            .unwrap();
+        let mut app = App::new();
+        app.load_file(&path).unwrap();
+
+        let draws_frame = |app: &App| {
+            let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+            terminal.draw(|frame| render(frame, app)).unwrap();
+            let buffer = terminal.backend().buffer();
+            buffer.content().iter().any(|cell| cell.symbol() == "╔")
+        };
+        assert!(!draws_frame(&app));
+        app.vertical_split();
+        assert!(draws_frame(&app));
+
+        let _ = std::fs::remove_file(&path);
+    }
 }